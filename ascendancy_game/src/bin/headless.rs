@@ -0,0 +1,27 @@
+//! Runs the game simulation without a window or renderer
+//!
+//! Usage: `cargo run --bin headless -- [frames]`, running forever when no frame count is given.
+
+use std::time::Duration;
+
+use ascendancy_lib::simulation::{HeadlessPlugin, SimulationPlugins};
+use bevy::app::ScheduleRunnerPlugin;
+use bevy::log::LogPlugin;
+use bevy::prelude::*;
+
+fn main() {
+    let frame_limit = std::env::args().nth(1).and_then(|arg| arg.parse().ok());
+
+    App::new()
+        .add_plugins(
+            MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::from_secs_f64(
+                1.0 / 60.0,
+            ))),
+        )
+        .add_plugins((
+            LogPlugin::default(),
+            SimulationPlugins,
+            HeadlessPlugin { frame_limit },
+        ))
+        .run();
+}
@@ -7,7 +7,6 @@ use bevy_inspector_egui::quick::WorldInspectorPlugin;
 use bevy_mod_picking::prelude::*;
 
 use bevy_screen_diagnostics::{ScreenDiagnosticsPlugin, ScreenFrameDiagnosticsPlugin};
-use big_brain::prelude::*;
 
 fn main() {
     App::new()
@@ -31,12 +30,7 @@ fn main() {
             ascendancy_lib::menu::menu::MenuPlugin,
             ascendancy_lib::player_interactions::InteractionPlugin,
             ascendancy_lib::graphics::GraphicsPlugin,
-            ascendancy_lib::world_gen::WorldGenPlugin,
-            ascendancy_lib::solar_system::SolarSystemPlugin,
-            ascendancy_lib::faction::FactionPlugin,
-            ascendancy_lib::agent::UnitPlugin,
-            ascendancy_lib::structures::StructurePlugin,
-            BigBrainPlugin::new(PreUpdate),
+            ascendancy_lib::simulation::SimulationPlugins,
         ))
         .run();
 }
@@ -1,6 +1,14 @@
 use crate::{
-    agent::agent::Agent,
+    agent::{agent::Agent, cargo::CargoHold, combat::Shield},
+    faction::{
+        attributes::{Attributes, FactionID},
+        claims::{SystemControl, MAX_CONTROL},
+        fleets::{Fleet, FleetMember},
+        taxes::TaxPolicy,
+    },
+    items::{inventory::Inventory, ItemRegistry},
     player_interactions::selection::UpdateSelectedItemEvent,
+    solar_system::SolarSystem,
     structures::{stargate::Stargate, station::Station},
 };
 use bevy::prelude::*;
@@ -69,11 +77,22 @@ pub fn selected_item_panel(mut commands: Commands) {
 }
 
 /// Updates the UI system
+#[allow(clippy::too_many_arguments)]
 pub fn update_ui_system(
     mut ev_selected_target: EventReader<UpdateSelectedItemEvent>,
-    agents: Query<&Agent>,
+    agents: Query<(
+        &Agent,
+        Option<&CargoHold>,
+        Option<&FleetMember>,
+        Option<&Shield>,
+    )>,
+    fleets: Query<&Fleet>,
     stargates: Query<&Stargate>,
     stations: Query<&Station>,
+    systems: Query<&SolarSystem>,
+    controls: Query<&SystemControl>,
+    factions: Query<(&Attributes, &TaxPolicy)>,
+    items: Res<ItemRegistry>,
     mut text_query: Query<&mut Text, With<SelectedItemText>>, // Update this line
 ) {
     for event in ev_selected_target.read() {
@@ -82,8 +101,7 @@ pub fn update_ui_system(
             text.sections.clear();
 
             // Check if the selected entity is a trader
-            if let Ok(agent) = agents.get(event.0) {
-                // Add cargo details
+            if let Ok((agent, hold, membership, shield)) = agents.get(event.0) {
                 text.sections.push(TextSection {
                     value: format!(
                         "Agent Name: {}\nHealth: {}\nHome System: {}",
@@ -91,6 +109,44 @@ pub fn update_ui_system(
                     ),
                     ..default()
                 });
+                if let Some(shield) = shield {
+                    text.sections.push(TextSection {
+                        value: format!("\nShield: {:.0} / {:.0}", shield.current, shield.max),
+                        ..default()
+                    });
+                }
+                if let Some((attributes, _)) = factions
+                    .iter()
+                    .find(|(attributes, _)| attributes.id == agent.faction)
+                {
+                    text.sections.push(TextSection {
+                        value: format!("\nFaction: {}", attributes.name),
+                        ..default()
+                    });
+                }
+                if let Some(fleet) =
+                    membership.and_then(|FleetMember(fleet)| fleets.get(*fleet).ok())
+                {
+                    text.sections.push(TextSection {
+                        value: format!("\nFleet: {} - {}", fleet.name, fleet.order.describe()),
+                        ..default()
+                    });
+                }
+                // Add cargo details
+                if let Some(hold) = hold {
+                    text.sections.push(TextSection {
+                        value: format!(
+                            "\nSpeed: {:.1} / {:.1}",
+                            agent.cruising_speed(hold),
+                            agent.speed
+                        ),
+                        ..default()
+                    });
+                    text.sections.push(TextSection {
+                        value: describe_inventory(hold.contents(), &items),
+                        ..default()
+                    });
+                }
             } else if let Ok(stargate) = stargates.get(event.0) {
                 text.sections.push(TextSection {
                     value: format!(
@@ -112,6 +168,35 @@ pub fn update_ui_system(
                     ),
                     ..default()
                 });
+                if let Some(owner) = systems
+                    .iter()
+                    .find(|system| system.attributes.id == station.system_id)
+                    .map(|system| system.attributes.owner)
+                {
+                    text.sections.push(TextSection {
+                        value: describe_owner(owner, &factions),
+                        ..default()
+                    });
+                }
+                text.sections.push(TextSection {
+                    value: describe_inventory(&station.resource_manager.inventory, &items),
+                    ..default()
+                });
+            } else if let Ok(system) = systems.get(event.0) {
+                text.sections.push(TextSection {
+                    value: format!("System Name: {}", system.attributes.name),
+                    ..default()
+                });
+                text.sections.push(TextSection {
+                    value: describe_owner(system.attributes.owner, &factions),
+                    ..default()
+                });
+                if let Ok(control) = controls.get(event.0) {
+                    text.sections.push(TextSection {
+                        value: describe_control(control, &factions),
+                        ..default()
+                    });
+                }
             }
             // Check if the selected entity is a station
 
@@ -119,3 +204,53 @@ pub fn update_ui_system(
         }
     }
 }
+
+/// Lists the goods in an inventory along with how full its storage is
+fn describe_inventory(inventory: &Inventory, items: &ItemRegistry) -> String {
+    let mut description = format!(
+        "\nCargo: Solid - {} / {}, Fluid - {} / {}",
+        inventory.used_volume(false),
+        inventory.capacity(false),
+        inventory.used_volume(true),
+        inventory.capacity(true)
+    );
+
+    for (id, stack) in inventory.iter() {
+        let name = items
+            .get(id)
+            .map_or_else(|| id.to_string(), |item| item.key.clone());
+        description.push_str(&format!("\n  {}: {}", name, stack.quantity));
+    }
+
+    description
+}
+
+/// Lists the control points each faction holds in a system
+fn describe_control(
+    control: &SystemControl,
+    factions: &Query<(&Attributes, &TaxPolicy)>,
+) -> String {
+    let mut description = String::from("\nControl:");
+    for (faction, points) in control.iter() {
+        let name = factions
+            .iter()
+            .find(|(attributes, _)| attributes.id == faction)
+            .map_or_else(
+                || format!("Faction {}", faction.id),
+                |(attributes, _)| attributes.name.clone(),
+            );
+        description.push_str(&format!("\n  {}: {:.0} / {:.0}", name, points, MAX_CONTROL));
+    }
+    description
+}
+
+/// Names the faction that owns a system and the taxes it levies there
+fn describe_owner(owner: FactionID, factions: &Query<(&Attributes, &TaxPolicy)>) -> String {
+    match factions
+        .iter()
+        .find(|(attributes, _)| attributes.id == owner)
+    {
+        Some((attributes, taxes)) => format!("\nOwner: {}\n{}", attributes.name, taxes.describe()),
+        None => "\nOwner: None\nTaxes: None".to_string(),
+    }
+}
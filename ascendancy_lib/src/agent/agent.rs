@@ -1,11 +1,19 @@
+use crate::agent::cargo::CargoHold;
+use crate::faction::attributes::FactionID;
 use crate::solar_system::attributes::SystemAttributes;
 use crate::solar_system::SolarSystem;
 use crate::structures::stargate::Stargate;
 
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// The volume of solid goods an agent can carry
+pub const AGENT_SOLID_CARGO: f32 = 100.0;
+/// The volume of fluid goods an agent can carry
+pub const AGENT_FLUID_CARGO: f32 = 50.0;
 
 /// Represents an agent in the game world. This is the most important component, and it should be added to all entities that represent agents.
-#[derive(Component, Default, Reflect, Debug, Clone, PartialEq)]
+#[derive(Component, Default, Reflect, Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[reflect(Component)]
 pub struct Agent {
     /// The unique ID of the agent.
@@ -18,6 +26,8 @@ pub struct Agent {
     pub current_goal: CurrentGoal,
     /// The agent's health.
     pub health: Health,
+    /// The faction the agent works for.
+    pub faction: FactionID,
     /// The agent's home system.
     pub home_system: SolarSystem,
     /// The agent's current system.
@@ -33,7 +43,7 @@ pub struct Agent {
 }
 
 impl Agent {
-    /// Creates a new agent with the given ID and name.
+    /// Creates a new agent with the given ID and name, working for the faction that owns its home system.
     pub fn new(id: u32, name: String, home_system: &SolarSystem) -> Self {
         Agent {
             id,
@@ -44,23 +54,32 @@ impl Agent {
                 current: 100.0,
                 max: 100.0,
             },
+            faction: home_system.attributes.owner,
             home_system: home_system.clone(),
             current_system: home_system.clone(),
             target_system: None,
             speed: 30.0,
-            stargate_path: StargatePath { path: Vec::new() },
+            stargate_path: StargatePath::default(),
             target_destination: None,
         }
     }
 
+    /// The agent's speed, slowed by the weight of the goods in its hold.
+    pub fn cruising_speed(&self, hold: &CargoHold) -> f32 {
+        self.speed * hold.speed_multiplier()
+    }
+
     /// Set the path to the target system.
     pub fn set_stargate_path(&mut self, path: Vec<Stargate>) {
-        self.stargate_path.path = path;
+        self.stargate_path = StargatePath {
+            path,
+            invalidated: false,
+        };
     }
 }
 
 /// Represents the financial assets of an agent.
-#[derive(Component, Default, Reflect, Debug, Clone, PartialEq)]
+#[derive(Component, Default, Reflect, Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[reflect(Component)]
 pub struct Wallet {
     /// The amount of money the agent has.
@@ -68,7 +87,7 @@ pub struct Wallet {
 }
 
 /// Represents the agent's current goal.
-#[derive(Component, Default, Reflect, Debug, Clone, PartialEq)]
+#[derive(Component, Default, Reflect, Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[reflect(Component)]
 pub enum Goal {
     #[default]
@@ -80,10 +99,14 @@ pub enum Goal {
     Defend,
     /// The agent is trading with another agent.
     Trade,
+    /// The agent is mining a resource field.
+    Mine,
+    /// The agent is hunting traders to rob.
+    Raid,
 }
 
 /// Represents the agent's current goal.
-#[derive(Component, Default, Reflect, Debug, Clone, PartialEq)]
+#[derive(Component, Default, Reflect, Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[reflect(Component)]
 pub struct CurrentGoal {
     /// The agent's current goal.
@@ -91,7 +114,7 @@ pub struct CurrentGoal {
 }
 
 /// Represents the health of the agent. This might be essential if there's any form of combat or danger in your game.
-#[derive(Component, Default, Reflect, Debug, Clone, PartialEq)]
+#[derive(Component, Default, Reflect, Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[reflect(Component)]
 pub struct Health {
     /// The agent's current health.
@@ -100,25 +123,42 @@ pub struct Health {
     pub max: f32,
 }
 
-/// Represents the faction or group the agent belongs to.
-#[derive(Component, Default, Reflect, Clone, PartialEq)]
+/// The line of work an agent follows, which decides the behaviours its thinker is built with.
+#[derive(
+    Component, Default, Reflect, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq,
+)]
 #[reflect(Component)]
-pub enum Faction {
+pub enum AgentRole {
     #[default]
-    /// The agent is neutral or independent.
-    Neutral,
-    /// The agent is part of the player's faction.
+    /// The agent buys goods cheaply and sells them where they fetch more.
     Trader,
-    /// The agent is part of the enemy faction.
-    Explorer,
-    /// The agent is part of the enemy faction.
+    /// The agent mines resource fields and sells what it extracts.
+    Miner,
+    /// The agent serves in one of its faction's fleets.
     Defender,
+    /// The agent lies in wait at chokepoints to rob laden traders.
+    Pirate,
 }
 
 /// vec list of stargates to travel through to reach target system
-#[derive(Component, Default, Reflect, Debug, Clone, PartialEq)]
+#[derive(Component, Default, Reflect, Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[reflect(Component)]
 pub struct StargatePath {
     /// The list of stargates to travel through to reach target system.
     pub path: Vec<Stargate>,
+    /// Whether a gate along the path has closed or gone since it was planned.
+    #[serde(default)]
+    pub invalidated: bool,
+}
+
+impl StargatePath {
+    /// Marks the path as running through a gate that is no longer open, so it must be planned again.
+    pub fn invalidate(&mut self) {
+        self.invalidated = true;
+    }
+
+    /// Check if the path must be planned again before it is followed.
+    pub fn is_invalidated(&self) -> bool {
+        self.invalidated
+    }
 }
@@ -0,0 +1,121 @@
+use std::fmt;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::items::inventory::{Inventory, InventoryError, InventoryTransaction, ItemStack};
+use crate::items::{ItemDefinition, ItemId};
+
+/// The fraction of its speed an agent loses when its hold is full
+pub const FULL_HOLD_SPEED_PENALTY: f32 = 0.5;
+
+/// The goods an agent is carrying, limited by the volume of its hold.
+///
+/// A fuller hold makes for a slower ship, see [`CargoHold::speed_multiplier`].
+#[derive(Component, Clone, Debug, Default, PartialEq, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
+pub struct CargoHold {
+    /// The goods in the hold
+    contents: Inventory,
+}
+
+/// Why goods couldn't be moved in or out of a hold
+#[derive(Debug, Clone, PartialEq)]
+pub enum CargoError {
+    /// The agent isn't docked at the station it is trading with
+    NotDocked,
+    /// The goods didn't fit, or weren't there to move
+    Inventory(InventoryError),
+}
+
+impl fmt::Display for CargoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotDocked => write!(f, "the agent is not docked at the station"),
+            Self::Inventory(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for CargoError {}
+
+impl From<InventoryError> for CargoError {
+    fn from(error: InventoryError) -> Self {
+        Self::Inventory(error)
+    }
+}
+
+impl CargoHold {
+    /// Creates an empty hold with the given solid and fluid volumes
+    pub fn new(solid_capacity: f32, fluid_capacity: f32) -> Self {
+        Self {
+            contents: Inventory::new(solid_capacity, fluid_capacity),
+        }
+    }
+
+    /// The goods in the hold
+    pub fn contents(&self) -> &Inventory {
+        &self.contents
+    }
+
+    /// The number of units of an item in the hold
+    pub fn quantity(&self, item: ItemId) -> u32 {
+        self.contents.quantity(item)
+    }
+
+    /// Whether the hold is empty
+    pub fn is_empty(&self) -> bool {
+        self.contents.is_empty()
+    }
+
+    /// Loads some units of an item, failing if they don't fit
+    pub fn load(&mut self, item: &ItemDefinition, quantity: u32) -> Result<(), InventoryError> {
+        self.contents.add(item, quantity)
+    }
+
+    /// Loads a stack of an item, failing if it doesn't fit
+    pub fn load_stack(&mut self, item: ItemId, stack: ItemStack) -> Result<(), InventoryError> {
+        self.contents
+            .apply(InventoryTransaction::new().add_stack(item, stack))
+    }
+
+    /// Unloads some units of an item, failing if not enough are aboard
+    pub fn unload(&mut self, item: ItemId, quantity: u32) -> Result<(), InventoryError> {
+        self.contents.remove(item, quantity)
+    }
+
+    /// Moves goods from another inventory into the hold, changing neither if it fails
+    pub fn load_from(
+        &mut self,
+        source: &mut Inventory,
+        item: &ItemDefinition,
+        quantity: u32,
+    ) -> Result<(), InventoryError> {
+        Inventory::transfer(source, &mut self.contents, item, quantity)
+    }
+
+    /// Moves goods out of the hold into another inventory, changing neither if it fails
+    pub fn unload_to(
+        &mut self,
+        destination: &mut Inventory,
+        item: &ItemDefinition,
+        quantity: u32,
+    ) -> Result<(), InventoryError> {
+        Inventory::transfer(&mut self.contents, destination, item, quantity)
+    }
+
+    /// How full the hold is, from 0 to 1, taking whichever of the solid and fluid storage is fuller
+    pub fn load_fraction(&self) -> f32 {
+        [false, true]
+            .into_iter()
+            .filter(|fluid| self.contents.capacity(*fluid) > 0.0)
+            .map(|fluid| self.contents.used_volume(fluid) / self.contents.capacity(fluid))
+            .fold(0.0, f32::max)
+            .min(1.0)
+    }
+
+    /// The fraction of its speed an agent keeps when carrying this hold
+    pub fn speed_multiplier(&self) -> f32 {
+        1.0 - FULL_HOLD_SPEED_PENALTY * self.load_fraction()
+    }
+}
@@ -0,0 +1,386 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::faction::attributes::FactionID;
+use crate::faction::diplomoacy::{
+    Diplomacy, DiplomaticIncidentEvent, DiplomaticState, IncidentKind,
+};
+use crate::items::inventory::Inventory;
+use crate::simulation::SimulationClock;
+use crate::solar_system::SolarSystem;
+use crate::structures::services::dock::DockedAt;
+use crate::structures::station::Station;
+
+use super::agent::{Agent, AgentRole};
+use super::cargo::CargoHold;
+
+/// How often damage is dealt, in seconds
+pub const COMBAT_TICK: f32 = 0.5;
+/// How far away a defender can pick out an enemy ship
+const SENSOR_RANGE: f32 = 150.0;
+/// How long a wreck drifts before it breaks up, in seconds of simulation time
+const WRECK_LIFETIME: f64 = 300.0;
+
+/// Times the rounds of combat
+#[derive(Resource)]
+pub struct CombatTimer(pub Timer);
+
+impl Default for CombatTimer {
+    fn default() -> Self {
+        Self(Timer::from_seconds(COMBAT_TICK, TimerMode::Repeating))
+    }
+}
+
+/// A ship's guns
+#[derive(Component, Reflect, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[reflect(Component)]
+pub struct Weapon {
+    /// The damage dealt each second
+    pub damage_per_second: f32,
+    /// How close the target needs to be to be hit
+    pub range: f32,
+}
+
+impl Weapon {
+    /// The guns fitted to ships in the given line of work
+    pub fn for_role(role: AgentRole) -> Self {
+        match role {
+            AgentRole::Trader | AgentRole::Miner => Self {
+                damage_per_second: 4.0,
+                range: 40.0,
+            },
+            AgentRole::Defender => Self {
+                damage_per_second: 15.0,
+                range: 80.0,
+            },
+            AgentRole::Pirate => Self {
+                damage_per_second: 10.0,
+                range: 60.0,
+            },
+        }
+    }
+}
+
+/// A ship's shield, which takes damage before the hull and recharges once the ship is left alone for a while
+#[derive(Component, Reflect, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[reflect(Component)]
+pub struct Shield {
+    /// The shield's current strength
+    pub current: f32,
+    /// The shield's full strength
+    pub max: f32,
+    /// The strength regained each second while recharging
+    pub recharge_per_second: f32,
+    /// How long after a hit the shield starts to recharge, in seconds
+    pub recharge_delay: f32,
+    /// The seconds since the shield was last hit
+    pub since_hit: f32,
+}
+
+impl Shield {
+    /// A fully charged shield
+    pub fn new(max: f32, recharge_per_second: f32, recharge_delay: f32) -> Self {
+        Self {
+            current: max,
+            max,
+            recharge_per_second,
+            recharge_delay,
+            since_hit: recharge_delay,
+        }
+    }
+
+    /// The shield fitted to ships in the given line of work
+    pub fn for_role(role: AgentRole) -> Self {
+        match role {
+            AgentRole::Trader | AgentRole::Miner => Self::new(40.0, 4.0, 5.0),
+            AgentRole::Defender => Self::new(80.0, 6.0, 4.0),
+            AgentRole::Pirate => Self::new(60.0, 5.0, 4.0),
+        }
+    }
+
+    /// Soaks up as much of a hit as the shield can, returning the damage it absorbed
+    pub fn absorb(&mut self, damage: f32) -> f32 {
+        let absorbed = damage.min(self.current);
+        self.current -= absorbed;
+        self.since_hit = 0.0;
+        absorbed
+    }
+
+    /// Recharges the shield if it hasn't been hit for long enough
+    pub fn recharge(&mut self, seconds: f32) {
+        self.since_hit += seconds;
+        if self.since_hit >= self.recharge_delay {
+            self.current = (self.current + self.recharge_per_second * seconds).min(self.max);
+        }
+    }
+}
+
+/// The ship an agent is shooting at
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Target(pub Entity);
+
+/// The goods left behind by a destroyed ship
+#[derive(Component, Reflect, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[reflect(Component)]
+pub struct Wreck {
+    /// The goods that were in the ship's hold
+    pub cargo: Inventory,
+    /// The solar system the wreck is in
+    pub system_id: u32,
+    /// When the wreck breaks up, in seconds of simulation time
+    pub expires_at: f64,
+}
+
+/// Sent when a ship picks out a new target
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ShipEngagedEvent {
+    /// The ship that will open fire
+    pub attacker: Entity,
+    /// The ship it is shooting at
+    pub target: Entity,
+}
+
+/// Sent when a ship is hit
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ShipDamagedEvent {
+    /// The ship that fired
+    pub attacker: Entity,
+    /// The ship that was hit
+    pub target: Entity,
+    /// The damage the target's shield absorbed
+    pub shield_damage: f32,
+    /// The damage done to the target's hull
+    pub hull_damage: f32,
+}
+
+/// Sent when a ship is destroyed
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ShipDestroyedEvent {
+    /// The destroyed ship, which is despawned once the event is handled
+    pub ship: Entity,
+    /// The faction the ship belonged to
+    pub faction: FactionID,
+    /// The ship that destroyed it
+    pub attacker: Entity,
+    /// The faction the attacker belongs to
+    pub attacker_faction: FactionID,
+}
+
+/// Whether ships of the two factions fire on each other
+fn are_enemies(diplomacy: &Diplomacy, a: FactionID, b: FactionID) -> bool {
+    a != b && diplomacy.state(a, b) == DiplomaticState::War
+}
+
+/// Keeps every armed ship's target up to date.
+///
+/// Targets that have been destroyed, left the system, docked or made peace are dropped.
+/// Defenders then pick the nearest enemy within sensor range; other ships only shoot back at whoever attacks them.
+pub fn acquire_targets(
+    mut commands: Commands,
+    diplomacy: Res<Diplomacy>,
+    ships: Query<
+        (
+            Entity,
+            &Agent,
+            &Transform,
+            Option<&AgentRole>,
+            Option<&Target>,
+        ),
+        With<Weapon>,
+    >,
+    targets: Query<(Entity, &Agent, &Transform), Without<DockedAt>>,
+    mut engaged: EventWriter<ShipEngagedEvent>,
+) {
+    for (entity, agent, transform, role, target) in ships.iter() {
+        let is_valid = |candidate: Entity| {
+            targets.get(candidate).is_ok_and(|(_, enemy, _)| {
+                enemy.current_system.attributes.id == agent.current_system.attributes.id
+                    && are_enemies(&diplomacy, agent.faction, enemy.faction)
+            })
+        };
+        if let Some(Target(current)) = target {
+            if is_valid(*current) {
+                continue;
+            }
+            commands.entity(entity).remove::<Target>();
+        }
+        if role != Some(&AgentRole::Defender) {
+            continue;
+        }
+
+        let position = transform.translation.truncate();
+        let nearest = targets
+            .iter()
+            .filter(|(candidate, _, _)| *candidate != entity && is_valid(*candidate))
+            .map(|(candidate, _, enemy_transform)| {
+                (
+                    candidate,
+                    position.distance(enemy_transform.translation.truncate()),
+                )
+            })
+            .filter(|(_, distance)| *distance <= SENSOR_RANGE)
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((enemy, _)) = nearest {
+            commands.entity(entity).insert(Target(enemy));
+            engaged.send(ShipEngagedEvent {
+                attacker: entity,
+                target: enemy,
+            });
+        }
+    }
+}
+
+/// Deals damage on each combat tick, from every ship whose target is in range of its guns.
+///
+/// Hits wear down the target's shield before its hull, and a ship that is hit while it has no target shoots back.
+/// Shields recharge on the same tick.
+#[allow(clippy::too_many_arguments)]
+pub fn resolve_combat(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut timer: ResMut<CombatTimer>,
+    attackers: Query<(Entity, &Weapon, &Target, &Transform)>,
+    mut ships: Query<(&mut Agent, &Transform, Option<&mut Shield>, Has<Target>)>,
+    mut damaged: EventWriter<ShipDamagedEvent>,
+    mut destroyed: EventWriter<ShipDestroyedEvent>,
+    mut engaged: EventWriter<ShipEngagedEvent>,
+) {
+    if !timer.0.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    for (_, _, shield, _) in ships.iter_mut() {
+        if let Some(mut shield) = shield {
+            shield.recharge(COMBAT_TICK);
+        }
+    }
+
+    let mut wrecked: HashSet<Entity> = HashSet::new();
+    for (attacker, weapon, Target(target), transform) in attackers.iter() {
+        if wrecked.contains(&attacker) || wrecked.contains(target) {
+            continue;
+        }
+        let Ok(attacker_faction) = ships.get(attacker).map(|(agent, ..)| agent.faction) else {
+            continue;
+        };
+        let Ok((mut victim, victim_transform, shield, has_target)) = ships.get_mut(*target) else {
+            continue;
+        };
+        if transform
+            .translation
+            .truncate()
+            .distance(victim_transform.translation.truncate())
+            > weapon.range
+        {
+            continue;
+        }
+
+        let damage = weapon.damage_per_second * COMBAT_TICK;
+        let shield_damage = shield.map_or(0.0, |mut shield| shield.absorb(damage));
+        let hull_damage = damage - shield_damage;
+        victim.health.current = (victim.health.current - hull_damage).max(0.0);
+        damaged.send(ShipDamagedEvent {
+            attacker,
+            target: *target,
+            shield_damage,
+            hull_damage,
+        });
+
+        if victim.health.current <= 0.0 {
+            wrecked.insert(*target);
+            destroyed.send(ShipDestroyedEvent {
+                ship: *target,
+                faction: victim.faction,
+                attacker,
+                attacker_faction,
+            });
+        } else if !has_target {
+            commands.entity(*target).insert(Target(attacker));
+            engaged.send(ShipEngagedEvent {
+                attacker: *target,
+                target: attacker,
+            });
+        }
+    }
+}
+
+/// Removes destroyed ships, leaving their cargo behind in a wreck.
+///
+/// The ship is taken off its system's entity list, out of any dock and off every market, and its faction
+/// holds the loss against the attacker's.
+#[allow(clippy::too_many_arguments)]
+pub fn wreck_destroyed_ships(
+    mut commands: Commands,
+    mut destroyed: EventReader<ShipDestroyedEvent>,
+    clock: Res<SimulationClock>,
+    ships: Query<(&Agent, &Transform, Option<&CargoHold>)>,
+    mut systems: Query<&mut SolarSystem>,
+    mut stations: Query<&mut Station>,
+    mut incidents: EventWriter<DiplomaticIncidentEvent>,
+) {
+    let mut handled: HashSet<Entity> = HashSet::new();
+    for event in destroyed.read() {
+        if !handled.insert(event.ship) {
+            continue;
+        }
+        let Ok((agent, transform, hold)) = ships.get(event.ship) else {
+            continue;
+        };
+        info!(
+            "{} was destroyed, leaving a wreck in {}",
+            agent.name, agent.current_system.attributes.name
+        );
+
+        if let Some(hold) = hold.filter(|hold| !hold.is_empty()) {
+            commands.spawn((
+                Wreck {
+                    cargo: hold.contents().clone(),
+                    system_id: agent.current_system.attributes.id,
+                    expires_at: clock.now() + WRECK_LIFETIME,
+                },
+                TransformBundle::from_transform(*transform),
+                Name::new(format!("Wreck of {}", agent.name)),
+            ));
+        }
+
+        // Only the ship's own system is touched, so the others aren't marked as changed
+        if let Some(mut system) = systems
+            .iter_mut()
+            .find(|system| system.attributes.id == agent.current_system.attributes.id)
+        {
+            system.remove_entity(event.ship);
+        }
+        for mut station in stations.iter_mut() {
+            if let Some(dock) = station.dock_mut() {
+                dock.release(event.ship);
+            }
+            if let Some(market) = station.market_mut() {
+                market.cancel_orders_for(event.ship);
+            }
+        }
+
+        if event.faction != event.attacker_faction {
+            incidents.send(DiplomaticIncidentEvent {
+                instigator: event.attacker_faction,
+                target: event.faction,
+                kind: IncidentKind::Attack,
+            });
+        }
+        commands.entity(event.ship).despawn_recursive();
+    }
+}
+
+/// Breaks up wrecks that have drifted for too long
+pub fn clear_wrecks(
+    mut commands: Commands,
+    clock: Res<SimulationClock>,
+    wrecks: Query<(Entity, &Wreck)>,
+) {
+    for (entity, wreck) in wrecks.iter() {
+        if wreck.expires_at <= clock.now() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
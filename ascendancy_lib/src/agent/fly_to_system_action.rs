@@ -1,10 +1,19 @@
+use std::collections::HashMap;
+
+use crate::faction::attributes::FactionID;
+use crate::faction::diplomoacy::Diplomacy;
 use crate::solar_system::SolarSystem;
 
 use crate::structures::stargate::Stargate;
 use bevy::prelude::*;
 use big_brain::prelude::*;
 
-use super::{agent::Agent, pathfinding::SystemGraph};
+use super::{
+    agent::Agent,
+    cargo::CargoHold,
+    pathfinding::{GraphError, PathRequest, SystemGraph},
+    piracy::{PiracyRisk, AVOIDED_RISK},
+};
 
 /// The distance required to jump a stargate
 const DISTANCE_REQUIRED_TO_JUMP_STARGATE: f32 = 0.1;
@@ -50,104 +59,57 @@ impl FlyToSystem {
 pub fn fly_to_system(
     time: Res<Time>,
     mut action_query: Query<(&Actor, &mut ActionState, &ActionSpan), With<FlyToSystem>>,
-    mut fly_to_system_query: Query<(&mut Agent, &mut FlyToSystem, &mut Transform)>,
-    system_graph: Res<SystemGraph>,
-    star_gates: Query<(&Stargate, &Transform), Without<FlyToSystem>>,
+    mut commands: Commands,
+    mut fly_to_system_query: Query<(
+        &mut Agent,
+        &mut FlyToSystem,
+        &mut Transform,
+        &CargoHold,
+        Has<PathRequest>,
+    )>,
+    star_gates: Query<(&Stargate, &Transform), Without<Agent>>,
     solar_systems: Query<(&SolarSystem, &Transform), Without<Agent>>,
 ) {
     for (actor, mut action_state, span) in &mut action_query {
         let _guard = span.span().enter();
         match *action_state {
             ActionState::Requested => {
-                let (mut agent, _, _) = fly_to_system_query.get_mut(actor.0).unwrap();
-                let current_system = &agent.current_system;
-
-                match system_graph.get_pathfinding_to_random_system(current_system) {
-                    Ok(path) => {
-                        if !path.is_empty() {
-                            agent.set_stargate_path(path);
-                        }
-                    }
-                    Err(_) => {
-                        *action_state = ActionState::Failure;
-                    }
-                }
+                let (mut agent, _, _, _, _) = fly_to_system_query.get_mut(actor.0).unwrap();
 
+                // The path is found along with every other agent's by `resolve_path_requests`
+                agent.set_stargate_path(Vec::new());
+                commands.entity(actor.0).insert(PathRequest::Anywhere);
                 *action_state = ActionState::Executing;
             }
             ActionState::Executing => {
-                let (mut agent, _, mut transform) = fly_to_system_query.get_mut(actor.0).unwrap();
-                let first_stargate_opt = agent.stargate_path.path.first().cloned(); // Clone the first stargate
-
-                if let Some(first_stargate) = first_stargate_opt {
-                    let matching_stargate_transform = star_gates
-                        .iter()
-                        .find(|(gate, _)| {
-                            gate.origin_system_id() == first_stargate.origin_system_id()
-                                && gate.destination_system_id()
-                                    == first_stargate.destination_system_id()
-                        })
-                        .map(|(_, transform)| transform)
-                        .unwrap();
-
-                    agent.target_destination = Some(matching_stargate_transform.translation);
-
-                    if let Some(target_destination) = agent.target_destination {
-                        let delta = target_destination - transform.translation;
-                        let distance = delta.length();
-
-                        if distance > DISTANCE_REQUIRED_TO_JUMP_STARGATE {
-                            let step_size = time.delta_seconds() * agent.speed;
-                            let step = delta.normalize() * step_size.min(distance);
-                            transform.translation += step;
-                        } else {
-                            // Find the destination stargate's transform (not the destination solar system's transform)
-                            let destination_stargate_transform = star_gates
-                                .iter()
-                                .find(|(gate, _)| {
-                                    gate.origin_system_id()
-                                        == first_stargate.destination_system_id()
-                                        && gate.destination_system_id()
-                                            == first_stargate.origin_system_id()
-                                })
-                                .map(|(_, transform)| transform)
-                                .unwrap();
-
-                            // Teleport the agent to the destination stargate
-                            transform.translation = destination_stargate_transform.translation;
-
-                            // Update the agent's current system
-                            agent.current_system = solar_systems
-                                .iter()
-                                .find(|(system, _)| {
-                                    system.attributes.id == first_stargate.destination_system_id()
-                                })
-                                .map(|(system, _)| system)
-                                .unwrap()
-                                .clone();
-
-                            // Remove the stargate from the path
-                            agent.stargate_path.path.remove(0);
-
-                            // Check if there are more stargates in the path. If not, set the action state to Success.
-                            if agent.stargate_path.path.is_empty() {
-                                if let Ok((_, mut fly_to_system, _)) =
-                                    fly_to_system_query.get_mut(actor.0)
-                                {
-                                    fly_to_system.reset_desire();
-                                }
-                                *action_state = ActionState::Success;
-                            }
-                        }
+                let (mut agent, mut fly_to_system, mut transform, hold, waiting) =
+                    fly_to_system_query.get_mut(actor.0).unwrap();
+                if waiting {
+                    continue;
+                }
+                let step_size = time.delta_seconds() * agent.cruising_speed(hold);
+
+                match follow_stargate_path(
+                    &mut agent,
+                    &mut transform,
+                    step_size,
+                    &star_gates,
+                    &solar_systems,
+                ) {
+                    // Check if there are more stargates in the path. If not, set the action state to Success.
+                    Some(true) => {
+                        fly_to_system.reset_desire();
+                        *action_state = ActionState::Success;
                     }
-                } else {
-                    if let Ok((_, mut fly_to_system, _)) = fly_to_system_query.get_mut(actor.0) {
+                    Some(false) => {}
+                    None => {
                         fly_to_system.reset_desire();
+                        *action_state = ActionState::Failure
                     }
-                    *action_state = ActionState::Failure
                 }
             }
             ActionState::Cancelled => {
+                commands.entity(actor.0).remove::<PathRequest>();
                 *action_state = ActionState::Failure;
             }
             _ => {}
@@ -155,6 +117,122 @@ pub fn fly_to_system(
     }
 }
 
+/// Moves an agent towards the first stargate in its path, jumping through it on arrival.
+///
+/// Returns `None` if the agent has no path to follow, or its path runs through a gate that has since closed,
+/// otherwise whether it has reached the end of its path.
+pub(crate) fn follow_stargate_path(
+    agent: &mut Agent,
+    transform: &mut Transform,
+    step_size: f32,
+    star_gates: &Query<(&Stargate, &Transform), Without<Agent>>,
+    solar_systems: &Query<(&SolarSystem, &Transform), Without<Agent>>,
+) -> Option<bool> {
+    if agent.stargate_path.is_invalidated() {
+        return None;
+    }
+    let first_stargate = agent.stargate_path.path.first().cloned()?; // Clone the first stargate
+
+    let (_, matching_stargate_transform) = star_gates.iter().find(|(gate, _)| {
+        gate.origin_system_id() == first_stargate.origin_system_id()
+            && gate.destination_system_id() == first_stargate.destination_system_id()
+    })?;
+    agent.target_destination = Some(matching_stargate_transform.translation);
+
+    let delta = matching_stargate_transform.translation - transform.translation;
+    let distance = delta.length();
+
+    if distance > DISTANCE_REQUIRED_TO_JUMP_STARGATE {
+        let step = delta.normalize() * step_size.min(distance);
+        transform.translation += step;
+        return Some(false);
+    }
+
+    // Find the destination stargate's transform (not the destination solar system's transform)
+    let (_, destination_stargate_transform) = star_gates.iter().find(|(gate, _)| {
+        gate.origin_system_id() == first_stargate.destination_system_id()
+            && gate.destination_system_id() == first_stargate.origin_system_id()
+    })?;
+
+    // Teleport the agent to the destination stargate
+    transform.translation = destination_stargate_transform.translation;
+
+    // Update the agent's current system
+    let (destination_system, _) = solar_systems
+        .iter()
+        .find(|(system, _)| system.attributes.id == first_stargate.destination_system_id())?;
+    agent.current_system = destination_system.clone();
+
+    // Remove the stargate from the path
+    agent.stargate_path.path.remove(0);
+
+    Some(agent.stargate_path.path.is_empty())
+}
+
+/// Finds a path for an agent that avoids the systems of factions at war with its own.
+///
+/// Owners are read from the live solar systems, as the copies in the `SystemGraph` are only synced once a frame.
+pub(crate) fn route_avoiding_wars(
+    system_graph: &SystemGraph,
+    diplomacy: &Diplomacy,
+    agent: &Agent,
+    target: &SolarSystem,
+    solar_systems: &Query<(&SolarSystem, &Transform), Without<Agent>>,
+) -> Result<Vec<Stargate>, GraphError> {
+    route_avoiding_wars_where(
+        system_graph,
+        diplomacy,
+        agent,
+        target,
+        solar_systems,
+        |_| true,
+    )
+}
+
+/// Finds a path for an agent that avoids wars and, where there is another way round, the systems pirates haunt.
+pub(crate) fn route_avoiding_pirates(
+    system_graph: &SystemGraph,
+    diplomacy: &Diplomacy,
+    risk: &PiracyRisk,
+    agent: &Agent,
+    target: &SolarSystem,
+    solar_systems: &Query<(&SolarSystem, &Transform), Without<Agent>>,
+) -> Result<Vec<Stargate>, GraphError> {
+    let target_id = target.attributes.id;
+    route_avoiding_wars_where(
+        system_graph,
+        diplomacy,
+        agent,
+        target,
+        solar_systems,
+        |system_id| system_id == target_id || risk.risk(system_id) < AVOIDED_RISK,
+    )
+    .or_else(|_| route_avoiding_wars(system_graph, diplomacy, agent, target, solar_systems))
+}
+
+/// Finds a path for an agent that avoids wars and only enters the systems `is_safe` allows, by system id
+fn route_avoiding_wars_where(
+    system_graph: &SystemGraph,
+    diplomacy: &Diplomacy,
+    agent: &Agent,
+    target: &SolarSystem,
+    solar_systems: &Query<(&SolarSystem, &Transform), Without<Agent>>,
+    is_safe: impl Fn(u32) -> bool,
+) -> Result<Vec<Stargate>, GraphError> {
+    let owners: HashMap<u32, FactionID> = solar_systems
+        .iter()
+        .map(|(system, _)| (system.attributes.id, system.attributes.owner))
+        .collect();
+    let faction = agent.faction;
+
+    system_graph.get_pathfinding_between_where(&agent.current_system, target, |system| {
+        is_safe(system.attributes.id)
+            && owners
+                .get(&system.attributes.id)
+                .map_or(true, |owner| diplomacy.can_use_stargates(faction, *owner))
+    })
+}
+
 /// Scorers are the same as in the thirst example.
 #[derive(Clone, Component, Debug, ScorerBuilder)]
 pub struct WantToFlyToSystem;
@@ -2,7 +2,10 @@ use crate::solar_system::SolarSystem;
 use bevy::prelude::*;
 use big_brain::prelude::*;
 
-use super::{agent::Agent, fly_to_system_action::FlyToSystem, utils::random_position_in_hex};
+use super::{
+    agent::Agent, cargo::CargoHold, fly_to_system_action::FlyToSystem,
+    utils::random_position_in_hex,
+};
 
 /// The maximum distance to the target before the action is considered a success.
 const MAX_DISTANCE: f32 = 0.1;
@@ -53,7 +56,7 @@ fn rotate_towards_target(agent: &mut Transform, target: &Transform, time: &Res<T
 pub fn idle_action_system(
     time: Res<Time>,
     solar_systems: Query<(Entity, &SolarSystem, &Transform), Without<Agent>>,
-    mut agent_query: Query<(&Agent, &mut Transform), With<Agent>>,
+    mut agent_query: Query<(&Agent, &mut Transform, &CargoHold), With<Agent>>,
     mut action_query: Query<(&Actor, &mut ActionState, &mut Idle, &ActionSpan)>,
     mut fly_to_system_query: Query<(&Agent, &mut FlyToSystem), Without<Actor>>,
 ) {
@@ -97,7 +100,8 @@ pub fn idle_action_system(
             }
             ActionState::Executing => {
                 if let Some(target) = &idle.target {
-                    move_towards_target(&mut agent.1, target, agent.0.speed, &time);
+                    let speed = agent.0.cruising_speed(agent.2);
+                    move_towards_target(&mut agent.1, target, speed, &time);
                     rotate_towards_target(&mut agent.1, target, &time);
 
                     let delta = target.translation - agent.1.translation;
@@ -0,0 +1,357 @@
+use bevy::prelude::*;
+use big_brain::prelude::*;
+
+use crate::faction::diplomoacy::Diplomacy;
+use crate::items::{ItemId, ItemRegistry};
+use crate::solar_system::resources::ResourceFields;
+use crate::solar_system::SolarSystem;
+use crate::structures::stargate::Stargate;
+
+use super::agent::{Agent, Goal};
+use super::cargo::CargoHold;
+use super::fly_to_system_action::{follow_stargate_path, route_avoiding_wars};
+use super::pathfinding::SystemGraph;
+use super::trade::{KnownPrices, MarketQuote, TradeCooldown, TradePlan};
+
+/// The units of resource an agent extracts each second
+const MINING_RATE: f32 = 2.0;
+/// The score an idle miner gives to going mining
+const MINING_DESIRE: f32 = 0.7;
+/// The score a miner part way through a run gives, so it sees the run through
+const COMMITTED_MINING_DESIRE: f32 = 0.9;
+/// How long a miner waits before looking again after failing to find a field, in seconds
+const MINING_SEARCH_COOLDOWN: f32 = 15.0;
+/// How close a miner needs to be to a field to start mining it
+const MINING_DISTANCE: f32 = 5.0;
+
+/// The resource field a miner has chosen to work
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct MiningSite {
+    /// The system the field is in
+    pub system_id: u32,
+    /// The index of the field in the system's [`ResourceFields`]
+    pub field: usize,
+    /// The item the field yields
+    pub item: ItemId,
+}
+
+/// Stops a miner looking for fields for a while after it failed to find one
+#[derive(Component, Debug, Clone)]
+pub struct MiningCooldown(pub Timer);
+
+/// This is the `WantToMine` scorer
+#[derive(Clone, Component, Debug, ScorerBuilder)]
+pub struct WantToMine;
+
+/// Scores how much a miner wants to go mining.
+///
+/// Miners part way through a run, whether mining or selling what they mined, want to finish it.
+/// Failing to find a field or a buyer holds a miner back for a while.
+pub fn want_to_mine_scorer_system(
+    time: Res<Time>,
+    mut agents: Query<(
+        Option<&MiningSite>,
+        Option<&TradePlan>,
+        Option<&mut MiningCooldown>,
+        Option<&mut TradeCooldown>,
+    )>,
+    mut query: Query<(&Actor, &mut Score, &ScorerSpan), With<WantToMine>>,
+) {
+    for (Actor(actor), mut score, span) in &mut query {
+        let Ok((site, plan, mining_cooldown, trade_cooldown)) = agents.get_mut(*actor) else {
+            continue;
+        };
+        let cooling_down = mining_cooldown
+            .is_some_and(|mut cooldown| !cooldown.0.tick(time.delta()).finished())
+            | trade_cooldown.is_some_and(|mut cooldown| !cooldown.0.tick(time.delta()).finished());
+
+        let desire = if site.is_some() || plan.is_some() {
+            COMMITTED_MINING_DESIRE
+        } else if cooling_down {
+            0.0
+        } else {
+            MINING_DESIRE
+        };
+
+        score.set(desire);
+        span.span()
+            .in_scope(|| debug!("Want to mine! Score: {}", desire));
+    }
+}
+
+/// The first step of a mining run, choosing which field to work
+#[derive(Clone, Component, Debug, ActionBuilder)]
+pub struct FindMiningSite;
+
+/// Picks the field whose yield would sell for the most per jump away.
+///
+/// Only fields whose resource some known market is buying are considered, so the miner can sell what it mines.
+/// A miner whose hold has no room for more is sent on to sell without a site.
+pub fn find_mining_site_action_system(
+    mut commands: Commands,
+    prices: Res<KnownPrices>,
+    items: Res<ItemRegistry>,
+    system_graph: Res<SystemGraph>,
+    mut agents: Query<(&mut Agent, &CargoHold)>,
+    systems: Query<(&SolarSystem, &ResourceFields)>,
+    mut action_query: Query<(&Actor, &mut ActionState, &ActionSpan), With<FindMiningSite>>,
+) {
+    for (Actor(actor), mut action_state, span) in &mut action_query {
+        let _guard = span.span().enter();
+        match *action_state {
+            ActionState::Requested => {
+                let Ok((mut agent, hold)) = agents.get_mut(*actor) else {
+                    *action_state = ActionState::Failure;
+                    continue;
+                };
+
+                let jumps = system_graph.jumps_from(&agent.current_system);
+                let mut best: Option<(f32, MiningSite)> = None;
+                for (system, fields) in systems.iter() {
+                    let Some(distance) = jumps.get(&system.attributes.id) else {
+                        continue;
+                    };
+                    for (index, field) in fields.0.iter().enumerate() {
+                        let Some(item) = items.get(field.item()) else {
+                            continue;
+                        };
+                        let Some(bid) = prices
+                            .quotes(item.id)
+                            .iter()
+                            .filter_map(MarketQuote::net_bid)
+                            .reduce(f32::max)
+                        else {
+                            continue;
+                        };
+
+                        let quantity = field.available().min(hold.contents().room_for(item));
+                        let value = quantity as f32 * bid / (1 + distance) as f32;
+                        if quantity > 0 && best.as_ref().map_or(true, |(best, _)| value > *best) {
+                            best = Some((
+                                value,
+                                MiningSite {
+                                    system_id: system.attributes.id,
+                                    field: index,
+                                    item: item.id,
+                                },
+                            ));
+                        }
+                    }
+                }
+
+                match best {
+                    Some((_, site)) => {
+                        debug!("Chose mining site: {:?}", site);
+                        agent.current_goal.goal = Some(Goal::Mine);
+                        commands.entity(*actor).insert(site);
+                        *action_state = ActionState::Success;
+                    }
+                    // Nothing worth mining, but the hold has goods to sell, so skip straight to selling them
+                    None if !hold.is_empty() => {
+                        *action_state = ActionState::Success;
+                    }
+                    None => {
+                        agent.current_goal.goal = None;
+                        commands
+                            .entity(*actor)
+                            .insert(MiningCooldown(Timer::from_seconds(
+                                MINING_SEARCH_COOLDOWN,
+                                TimerMode::Once,
+                            )));
+                        *action_state = ActionState::Failure;
+                    }
+                }
+            }
+            ActionState::Cancelled => {
+                *action_state = ActionState::Failure;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Flies the miner to the field it has chosen
+#[derive(Clone, Component, Debug, ActionBuilder)]
+pub struct FlyToMiningSite;
+
+/// Follows the stargates to the field's system, then flies out to the field itself.
+///
+/// The route avoids systems held by factions at war with the miner's own.
+pub fn fly_to_mining_site_action_system(
+    time: Res<Time>,
+    system_graph: Res<SystemGraph>,
+    diplomacy: Res<Diplomacy>,
+    mut agents: Query<(&mut Agent, &CargoHold, &mut Transform, Option<&MiningSite>)>,
+    fields: Query<(&SolarSystem, &Transform, &ResourceFields), Without<Agent>>,
+    star_gates: Query<(&Stargate, &Transform), Without<Agent>>,
+    solar_systems: Query<(&SolarSystem, &Transform), Without<Agent>>,
+    mut action_query: Query<(&Actor, &mut ActionState, &ActionSpan), With<FlyToMiningSite>>,
+) {
+    for (Actor(actor), mut action_state, span) in &mut action_query {
+        let _guard = span.span().enter();
+        let Ok((mut agent, hold, mut transform, site)) = agents.get_mut(*actor) else {
+            *action_state = ActionState::Failure;
+            continue;
+        };
+
+        match *action_state {
+            ActionState::Requested => {
+                let Some(site) = site else {
+                    // A miner without a site is on its way to sell what it already holds
+                    *action_state = ActionState::Success;
+                    continue;
+                };
+                if agent.current_system.attributes.id == site.system_id {
+                    agent.stargate_path.path.clear();
+                    *action_state = ActionState::Executing;
+                    continue;
+                }
+
+                let Some(target) = system_graph.system_by_id(&site.system_id) else {
+                    *action_state = ActionState::Failure;
+                    continue;
+                };
+                match route_avoiding_wars(&system_graph, &diplomacy, &agent, target, &solar_systems)
+                {
+                    Ok(path) => {
+                        agent.set_stargate_path(path);
+                        *action_state = ActionState::Executing;
+                    }
+                    Err(_) => *action_state = ActionState::Failure,
+                }
+            }
+            ActionState::Executing => {
+                let Some(site) = site else {
+                    *action_state = ActionState::Failure;
+                    continue;
+                };
+                let step_size = time.delta_seconds() * agent.cruising_speed(hold);
+
+                if !agent.stargate_path.path.is_empty() {
+                    if follow_stargate_path(
+                        &mut agent,
+                        &mut transform,
+                        step_size,
+                        &star_gates,
+                        &solar_systems,
+                    )
+                    .is_none()
+                    {
+                        *action_state = ActionState::Failure;
+                    }
+                    continue;
+                }
+
+                let Some(field_position) = fields
+                    .iter()
+                    .find(|(system, _, _)| system.attributes.id == site.system_id)
+                    .and_then(|(_, system_transform, fields)| {
+                        let field = fields.0.get(site.field)?;
+                        Some(system_transform.translation.truncate() + field.offset)
+                    })
+                else {
+                    *action_state = ActionState::Failure;
+                    continue;
+                };
+                let target = field_position.extend(transform.translation.z);
+                agent.target_destination = Some(target);
+
+                let delta = target - transform.translation;
+                let distance = delta.length();
+                if distance <= MINING_DISTANCE {
+                    *action_state = ActionState::Success;
+                } else {
+                    transform.translation += delta.normalize() * step_size.min(distance);
+                }
+            }
+            ActionState::Cancelled => {
+                agent.stargate_path.path.clear();
+                *action_state = ActionState::Failure;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Mines the chosen field into the miner's hold
+#[derive(Clone, Component, Debug, Default, ActionBuilder)]
+pub struct MineResources {
+    /// The part of a unit mined but not yet loaded
+    progress: f32,
+}
+
+/// Extracts units from the field into the hold until the hold is full or the field runs dry.
+///
+/// The miner then gives up its site, ready to sell what it mined.
+pub fn mine_resources_action_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    items: Res<ItemRegistry>,
+    mut agents: Query<(&mut CargoHold, Option<&MiningSite>)>,
+    mut systems: Query<(&SolarSystem, &mut ResourceFields)>,
+    mut action_query: Query<(&Actor, &mut ActionState, &mut MineResources, &ActionSpan)>,
+) {
+    for (Actor(actor), mut action_state, mut mine, span) in &mut action_query {
+        let _guard = span.span().enter();
+        let Ok((mut hold, site)) = agents.get_mut(*actor) else {
+            *action_state = ActionState::Failure;
+            continue;
+        };
+
+        match *action_state {
+            ActionState::Requested => {
+                mine.progress = 0.0;
+                *action_state = match site {
+                    Some(_) => ActionState::Executing,
+                    // A miner without a site is on its way to sell what it already holds
+                    None if !hold.is_empty() => ActionState::Success,
+                    None => ActionState::Failure,
+                };
+            }
+            ActionState::Executing => {
+                let Some(site) = site else {
+                    *action_state = ActionState::Failure;
+                    continue;
+                };
+                let fields = systems
+                    .iter_mut()
+                    .find(|(system, _)| system.attributes.id == site.system_id)
+                    .map(|(_, fields)| fields);
+                let (Some(item), Some(mut fields)) = (items.get(site.item), fields) else {
+                    commands.entity(*actor).remove::<MiningSite>();
+                    *action_state = ActionState::Failure;
+                    continue;
+                };
+                let Some(field) = fields.0.get_mut(site.field) else {
+                    commands.entity(*actor).remove::<MiningSite>();
+                    *action_state = ActionState::Failure;
+                    continue;
+                };
+
+                mine.progress += MINING_RATE * time.delta_seconds();
+                let wanted = mine.progress.floor() as u32;
+                mine.progress -= wanted as f32;
+
+                let extracted = field.extract(wanted.min(hold.contents().room_for(item)));
+                if extracted > 0 {
+                    hold.load(item, extracted)
+                        .expect("extraction is limited to the room in the hold");
+                }
+
+                if hold.contents().room_for(item) == 0 || field.available() == 0 {
+                    commands.entity(*actor).remove::<MiningSite>();
+                    *action_state = if hold.is_empty() {
+                        ActionState::Failure
+                    } else {
+                        ActionState::Success
+                    };
+                }
+            }
+            ActionState::Cancelled => {
+                commands.entity(*actor).remove::<MiningSite>();
+                *action_state = ActionState::Failure;
+            }
+            _ => {}
+        }
+    }
+}
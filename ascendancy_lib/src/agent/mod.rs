@@ -4,22 +4,49 @@ use big_brain::BigBrainSet;
 use crate::{agent::pathfinding::SystemGraph, GameState};
 
 use self::{
+    combat::{
+        acquire_targets, clear_wrecks, resolve_combat, wreck_destroyed_ships, CombatTimer,
+        ShipDamagedEvent, ShipDestroyedEvent, ShipEngagedEvent,
+    },
     fly_to_system_action::{fly_to_system, want_to_fly_to_system_scorer_system},
     idle::{idle_action_system, idle_scorer_system},
-    pathfinding::get_stargate_path_between_systems,
+    mining::{
+        find_mining_site_action_system, fly_to_mining_site_action_system,
+        mine_resources_action_system, want_to_mine_scorer_system,
+    },
+    pathfinding::{get_stargate_path_between_systems, resolve_path_requests, sync_system_graph},
+    piracy::{
+        flee_action_system, intercept_trader_action_system, scout_chokepoint_action_system,
+        update_piracy_risk, want_to_flee_scorer_system, want_to_raid_scorer_system, PiracyRisk,
+    },
     random_path::{get_random_path_between_two_systems, PathTimer},
+    trade::{
+        buy_goods_action_system, dock_at_station_action_system, find_trade_action_system,
+        fly_to_station_action_system, sell_goods_action_system, update_known_prices,
+        want_to_trade_scorer_system, KnownPrices, PriceBoardTimer, PRICE_BOARD_INTERVAL,
+    },
 };
 
 /// agent module
 pub mod agent;
+/// cargo holds
+pub mod cargo;
+/// ship weapons and shields
+pub mod combat;
 /// fly to system action
 pub mod fly_to_system_action;
 /// idleing Action
 pub mod idle;
+/// miner behaviour
+pub mod mining;
 /// pathfinding module
 pub mod pathfinding;
+/// pirate behaviour, and the risk traders see in it
+pub mod piracy;
 /// The plugin for the unit module.
 pub mod random_path;
+/// trader behaviour
+pub mod trade;
 /// utils
 pub mod utils;
 
@@ -30,12 +57,53 @@ impl Plugin for UnitPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(SystemGraph::default())
             .insert_resource(PathTimer(Timer::from_seconds(5.0, TimerMode::Repeating)))
+            .insert_resource(PriceBoardTimer(Timer::from_seconds(
+                PRICE_BOARD_INTERVAL,
+                TimerMode::Repeating,
+            )))
+            .init_resource::<KnownPrices>()
+            .init_resource::<CombatTimer>()
+            .init_resource::<PiracyRisk>()
+            .add_event::<ShipEngagedEvent>()
+            .add_event::<ShipDamagedEvent>()
+            .add_event::<ShipDestroyedEvent>()
+            .add_systems(Update, sync_system_graph)
             .add_systems(
                 FixedUpdate,
                 (
-                    (idle_action_system, fly_to_system).in_set(BigBrainSet::Actions),
+                    update_known_prices.run_if(in_state(GameState::Playing)),
+                    resolve_path_requests.before(fly_to_system),
+                    (
+                        acquire_targets,
+                        resolve_combat,
+                        wreck_destroyed_ships,
+                        clear_wrecks,
+                        update_piracy_risk,
+                    )
+                        .chain()
+                        .run_if(in_state(GameState::Playing)),
+                    (
+                        idle_action_system,
+                        fly_to_system,
+                        find_trade_action_system,
+                        fly_to_station_action_system,
+                        dock_at_station_action_system,
+                        buy_goods_action_system,
+                        sell_goods_action_system,
+                        find_mining_site_action_system,
+                        fly_to_mining_site_action_system,
+                        mine_resources_action_system,
+                        scout_chokepoint_action_system,
+                        intercept_trader_action_system,
+                        flee_action_system,
+                    )
+                        .in_set(BigBrainSet::Actions),
                     (
                         idle_scorer_system,
+                        want_to_trade_scorer_system,
+                        want_to_mine_scorer_system,
+                        want_to_raid_scorer_system,
+                        want_to_flee_scorer_system,
                         want_to_fly_to_system_scorer_system, //fly_to_system,
                                                              //want_to_fly_to_system_scorer_system,
                     )
@@ -43,6 +111,11 @@ impl Plugin for UnitPlugin {
                 ),
             )
             //.add_systems(FixedUpdate, get_random_path_between_two_systems.run_if(in_state(GameState::Playing)))
-            .register_type::<agent::Agent>();
+            .register_type::<agent::Agent>()
+            .register_type::<agent::AgentRole>()
+            .register_type::<cargo::CargoHold>()
+            .register_type::<combat::Weapon>()
+            .register_type::<combat::Shield>()
+            .register_type::<combat::Wreck>();
     }
 }
@@ -2,22 +2,108 @@ use crate::player_interactions::selection::Selection;
 use crate::solar_system::SolarSystem;
 use crate::structures::stargate::Stargate;
 use bevy::prelude::*;
-use petgraph::algo::astar;
-use petgraph::graph::{EdgeIndex, Graph, NodeIndex};
+use bevy::tasks::{ComputeTaskPool, TaskPool};
+use petgraph::algo::{astar, dijkstra};
+use petgraph::stable_graph::{EdgeIndex, NodeIndex, StableGraph};
+use petgraph::unionfind::UnionFind;
+use petgraph::visit::{EdgeFiltered, EdgeRef};
 use rand::prelude::IteratorRandom;
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::sync::{Arc, RwLock};
+
+use crate::faction::attributes::FactionID;
+
+use super::agent::Agent;
+use super::piracy::PiracyRisk;
+
+/// The extra distance, as a multiple of the jump, a safe route weighs entering a system pirates are certain to be in
+const PIRACY_DETOUR: f32 = 4.0;
+/// How much farther a route will go to save a credit in tolls
+const TOLL_DETOUR: f32 = 10.0;
 
 /// a Graph representing the solar systems and their connections
+///
+/// The graph is kept in step with the `SolarSystem` and `Stargate` components by [`sync_system_graph`].
+/// Its indices stay valid as systems and gates are removed, so a gate can always be found by its id.
 #[derive(Resource, Default)]
 pub struct SystemGraph {
     /// The graph of solar systems and their connections
-    graph: Graph<SolarSystem, Stargate>,
+    graph: StableGraph<SolarSystem, Stargate>,
     /// A mapping from a system id to a node index
     system_to_node: HashMap<u32, NodeIndex>, // mapping from SolarSystem id to NodeIndex
+    /// A mapping from a gate id to an edge index
+    gate_to_edge: HashMap<u32, EdgeIndex>,
+    /// Where each system with a known position is in the world, by system id
+    positions: HashMap<u32, Vec2>,
+    /// The shortest routes out of each system that has been routed from, forgotten whenever a gate changes
+    routes: RwLock<HashMap<NodeIndex, Arc<RouteTree>>>,
+    /// How many shortest routes pass through each system, forgotten along with the routes
+    through_traffic: RwLock<Option<Arc<HashMap<u32, usize>>>>,
+}
+
+/// The gate each system is reached through, on the shortest routes out of one system
+type RouteTree = HashMap<NodeIndex, EdgeIndex>;
+
+/// Weighs the cost of jumping through a gate into the system beyond it.
+///
+/// Returns `None` if a route must not enter that system.
+pub trait RouteCost {
+    /// The cost of jumping through `gate` into `destination`
+    fn cost(&self, gate: &Stargate, destination: &SolarSystem) -> Option<f32>;
+}
+
+impl<F> RouteCost for F
+where
+    F: Fn(&Stargate, &SolarSystem) -> Option<f32>,
+{
+    fn cost(&self, gate: &Stargate, destination: &SolarSystem) -> Option<f32> {
+        self(gate, destination)
+    }
+}
+
+/// The ways a route between two systems can be chosen
+#[derive(Clone, Copy, Debug)]
+pub enum RoutePolicy<'a> {
+    /// The route with the least distance to travel
+    Shortest,
+    /// The route that goes out of its way round the systems pirates haunt
+    Safest(&'a PiracyRisk),
+    /// The route that pays the least in tolls, from the toll each faction charges to enter its systems
+    CheapestTolls(&'a HashMap<FactionID, f32>),
+    /// The shortest route that never enters the systems of a faction
+    AvoidFaction(FactionID),
+}
+
+impl RouteCost for RoutePolicy<'_> {
+    fn cost(&self, gate: &Stargate, destination: &SolarSystem) -> Option<f32> {
+        let distance = gate.distance as f32;
+        let system = &destination.attributes;
+        match self {
+            RoutePolicy::Shortest => Some(distance),
+            RoutePolicy::Safest(risk) => {
+                Some(distance * (1.0 + PIRACY_DETOUR * risk.risk(system.id)))
+            }
+            RoutePolicy::CheapestTolls(tolls) => {
+                let toll = tolls.get(&system.owner).copied().unwrap_or_default();
+                Some(distance + TOLL_DETOUR * toll)
+            }
+            RoutePolicy::AvoidFaction(faction) => (system.owner != *faction).then_some(distance),
+        }
+    }
+}
+
+/// A route an agent has asked for, resolved along with every other agent's by [`resolve_path_requests`]
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PathRequest {
+    /// The shortest route to a system, by id
+    To(u32),
+    /// The shortest route to any other system that can be reached, picked at random
+    Anywhere,
 }
 
 /// Errors that can occur when using the `SystemGraph`
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum GraphError {
     /// The system was not found in the graph
     SystemNotFound,
@@ -29,49 +115,278 @@ impl SystemGraph {
     /// Create a new `SystemGraph`
     pub fn new() -> Self {
         Self {
-            graph: Graph::<SolarSystem, Stargate>::new(),
+            graph: StableGraph::<SolarSystem, Stargate>::new(),
             system_to_node: HashMap::new(),
+            gate_to_edge: HashMap::new(),
+            positions: HashMap::new(),
+            routes: RwLock::default(),
+            through_traffic: RwLock::default(),
+        }
+    }
+
+    /// Build a graph from a set of solar systems and the stargates connecting them
+    pub fn from_systems_and_gates<'a>(
+        systems: impl IntoIterator<Item = &'a SolarSystem>,
+        gates: impl IntoIterator<Item = &'a Stargate>,
+    ) -> Self {
+        let mut system_graph = Self::new();
+
+        for system in systems {
+            system_graph.add_node(system.clone());
+        }
+
+        for gate in gates {
+            if system_graph.add_gate(gate.clone()).is_none() {
+                warn!(
+                    "Could not find the systems stargate {} connects, {} to {}",
+                    gate.id, gate.origin_system_id, gate.destination_system_id
+                );
+            }
         }
+
+        system_graph
     }
 
     /// Add a node to the graph, i.e a solar system
+    ///
+    /// A system that is already in the graph is replaced, keeping its gates.
     pub fn add_node(&mut self, system: SolarSystem) -> NodeIndex {
-        let index = self.graph.add_node(system.clone());
-        self.system_to_node.insert(system.attributes.id, index); // save the mapping
+        if let Some(index) = self.system_to_node.get(&system.attributes.id) {
+            self.graph[*index] = system;
+            return *index;
+        }
+
+        let id = system.attributes.id;
+        let index = self.graph.add_node(system);
+        self.system_to_node.insert(id, index); // save the mapping
 
         index
     }
 
     /// Add an edge to the graph, i.e a connection between two systems
+    ///
+    /// A gate that is already in the graph is replaced.
     pub fn add_edge(
         &mut self,
         system_a: NodeIndex,
         system_b: NodeIndex,
         gate: Stargate,
     ) -> EdgeIndex {
-        self.graph.add_edge(system_a, system_b, gate)
+        self.remove_gate(gate.id);
+        self.forget_routes();
+        let id = gate.id;
+        let index = self.graph.add_edge(system_a, system_b, gate);
+        self.gate_to_edge.insert(id, index);
+
+        index
+    }
+
+    /// Add a gate between the systems it connects, replacing it if it is already in the graph.
+    ///
+    /// Returns `None` if either system is not in the graph.
+    pub fn add_gate(&mut self, gate: Stargate) -> Option<EdgeIndex> {
+        let source = *self.system_to_node.get(&gate.origin_system_id)?;
+        let destination = *self.system_to_node.get(&gate.destination_system_id)?;
+
+        Some(self.add_edge(source, destination, gate))
+    }
+
+    /// Record where systems are in the world, by system id, so routes can be searched toward their destination
+    pub fn locate_systems(&mut self, positions: impl IntoIterator<Item = (u32, Vec2)>) {
+        self.positions.extend(positions);
+        self.forget_routes();
     }
 
-    /// Get the path between two systems
+    /// The straight line distance between two systems, or zero if either position is unknown
+    fn straight_distance(&self, system_a: NodeIndex, system_b: NodeIndex) -> f32 {
+        let position = |node: NodeIndex| {
+            self.graph
+                .node_weight(node)
+                .and_then(|system| self.positions.get(&system.attributes.id))
+        };
+        match (position(system_a), position(system_b)) {
+            (Some(a), Some(b)) => a.distance(*b),
+            _ => 0.0,
+        }
+    }
+
+    /// The cost of a jump through an open gate, never less than the straight line distance it covers
+    fn weigh_jump(
+        &self,
+        gate: &Stargate,
+        from: NodeIndex,
+        to: NodeIndex,
+        cost: &impl RouteCost,
+    ) -> Option<f32> {
+        if !gate.is_active {
+            return None;
+        }
+        let cost = cost.cost(gate, &self.graph[to])?;
+        Some(cost.max(self.straight_distance(from, to)))
+    }
+
+    /// Forget every cached route, as the gates they run through have changed
+    fn forget_routes(&mut self) {
+        self.routes.get_mut().unwrap().clear();
+        *self.through_traffic.get_mut().unwrap() = None;
+    }
+
+    /// The shortest routes out of a system to every system reachable from it, from the cache if they are known
+    fn route_tree(&self, start: NodeIndex) -> Arc<RouteTree> {
+        if let Some(tree) = self.routes.read().unwrap().get(&start) {
+            return tree.clone();
+        }
+
+        // Search outward from the start, cheapest system first, remembering the gate each system was reached through
+        let mut tree = RouteTree::new();
+        let mut costs = HashMap::from([(start, 0)]);
+        let mut frontier = BinaryHeap::from([Reverse((0, start))]);
+        while let Some(Reverse((cost, node))) = frontier.pop() {
+            if costs.get(&node).is_some_and(|known| *known < cost) {
+                continue;
+            }
+            for edge in self.graph.edges(node) {
+                let Some(jump) =
+                    self.weigh_jump(edge.weight(), node, edge.target(), &RoutePolicy::Shortest)
+                else {
+                    continue;
+                };
+                let next_cost = cost + jump.ceil() as u32;
+                if costs
+                    .get(&edge.target())
+                    .map_or(true, |known| next_cost < *known)
+                {
+                    costs.insert(edge.target(), next_cost);
+                    tree.insert(edge.target(), edge.id());
+                    frontier.push(Reverse((next_cost, edge.target())));
+                }
+            }
+        }
+        tree.remove(&start);
+
+        let tree = Arc::new(tree);
+        self.routes.write().unwrap().insert(start, tree.clone());
+        tree
+    }
+
+    /// Follows a route tree back from a system to the start of the tree, collecting the gates along the way
+    fn path_in_tree(&self, tree: &RouteTree, end: NodeIndex) -> Vec<Stargate> {
+        let mut path = Vec::new();
+        let mut node = end;
+        while let Some(edge) = tree.get(&node) {
+            path.push(self.graph[*edge].clone());
+            node = self.graph.edge_endpoints(*edge).unwrap().0;
+        }
+        path.reverse();
+        path
+    }
+
+    /// Get the shortest path between two systems, from the route cache
+    ///
+    /// The first route asked for out of a system finds the routes to every other system at once, so later ones are quick.
+    pub fn shortest_path(
+        &self,
+        system_a: &SolarSystem,
+        system_b: &SolarSystem,
+    ) -> Result<Vec<Stargate>, GraphError> {
+        self.resolve_path(
+            system_a.attributes.id,
+            PathRequest::To(system_b.attributes.id),
+        )
+    }
+
+    /// Resolve a path request for an agent in a system, from the route cache
+    pub fn resolve_path(
+        &self,
+        from: u32,
+        request: PathRequest,
+    ) -> Result<Vec<Stargate>, GraphError> {
+        let start = *self
+            .system_to_node
+            .get(&from)
+            .ok_or(GraphError::SystemNotFound)?;
+        let tree = self.route_tree(start);
+
+        let end = match request {
+            PathRequest::To(to) => {
+                let end = *self
+                    .system_to_node
+                    .get(&to)
+                    .ok_or(GraphError::SystemNotFound)?;
+                if end != start && !tree.contains_key(&end) {
+                    return Err(GraphError::NoPath);
+                }
+                end
+            }
+            PathRequest::Anywhere => *tree
+                .keys()
+                .choose(&mut rand::thread_rng())
+                .ok_or(GraphError::NoPath)?,
+        };
+        Ok(self.path_in_tree(&tree, end))
+    }
+
+    /// Resolve many path requests at once, as `(from, request)` pairs, spread across the compute task pool.
+    ///
+    /// The paths are returned in the same order as the requests.
+    pub fn resolve_paths(
+        &self,
+        requests: &[(u32, PathRequest)],
+    ) -> Vec<Result<Vec<Stargate>, GraphError>> {
+        let pool = ComputeTaskPool::get_or_init(TaskPool::default);
+        let batch_size = requests.len().div_ceil(pool.thread_num().max(1)).max(1);
+        pool.scope(|scope| {
+            for batch in requests.chunks(batch_size) {
+                scope.spawn(async move {
+                    batch
+                        .iter()
+                        .map(|(from, request)| self.resolve_path(*from, *request))
+                        .collect::<Vec<_>>()
+                });
+            }
+        })
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    /// Get the cheapest path between two systems, as weighed by `cost`
+    ///
+    /// The straight line distance to the destination guides the search.
+    /// No jump is weighed as less than the distance between the systems it joins, so that guide never overestimates.
     fn get_path(
         &self,
         system_a: NodeIndex,
         system_b: NodeIndex,
+        cost: impl RouteCost,
     ) -> Result<Vec<Stargate>, GraphError> {
+        let weigh = |gate: &Stargate, from: NodeIndex, to: NodeIndex| {
+            self.weigh_jump(gate, from, to, &cost)
+        };
+        let graph = EdgeFiltered::from_fn(&self.graph, |edge| {
+            weigh(edge.weight(), edge.source(), edge.target()).is_some()
+        });
         match astar(
-            &self.graph,
+            &graph,
             system_a,
             |finish| finish == system_b,
-            |e| e.weight().distance,
-            |_| 0,
+            |edge| weigh(edge.weight(), edge.source(), edge.target()).unwrap_or(f32::INFINITY),
+            |node| self.straight_distance(node, system_b),
         ) {
             Some((_, path_nodes)) => {
-                // Convert path of NodeIndices to a path of Stargates
+                // Convert path of NodeIndices to the cheapest usable gate between each pair of systems
                 let mut path_gates = Vec::new();
-                for i in 0..(path_nodes.len() - 1) {
-                    if let Some(edge) = self.graph.find_edge(path_nodes[i], path_nodes[i + 1]) {
-                        let gate = self.graph.edge_weight(edge).unwrap().clone(); // Clone the Stargate object
-                        path_gates.push(gate);
+                for pair in path_nodes.windows(2) {
+                    if let Some((gate, _)) = self
+                        .graph
+                        .edges(pair[0])
+                        .filter(|edge| edge.target() == pair[1])
+                        .filter_map(|edge| {
+                            Some((edge.weight(), weigh(edge.weight(), pair[0], pair[1])?))
+                        })
+                        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                    {
+                        path_gates.push(gate.clone()); // Clone the Stargate object
                     }
                 }
                 Ok(path_gates)
@@ -80,11 +395,12 @@ impl SystemGraph {
         }
     }
 
-    /// Get the path between two systems
+    /// Get the path between two systems, following a [`RoutePolicy`] or any other way of weighing each jump
     pub fn get_pathfinding_between(
         &self,
         system_a: &SolarSystem,
         system_b: &SolarSystem,
+        cost: impl RouteCost,
     ) -> Result<Vec<Stargate>, GraphError> {
         let start_index = self
             .system_to_node
@@ -95,47 +411,56 @@ impl SystemGraph {
             .get(&system_b.attributes.id)
             .ok_or(GraphError::SystemNotFound)?;
 
-        self.get_path(*start_index, *end_index)
+        self.get_path(*start_index, *end_index, cost)
     }
 
-    /// get the path between a known starting system and a random system
-    /// retry up to a maximum of 10 times if a path is not found
-    pub fn get_pathfinding_to_random_system(
+    /// Get the shortest path between two systems that only passes through the systems `can_enter` allows
+    pub fn get_pathfinding_between_where(
         &self,
         system_a: &SolarSystem,
+        system_b: &SolarSystem,
+        can_enter: impl Fn(&SolarSystem) -> bool,
     ) -> Result<Vec<Stargate>, GraphError> {
-        let start_index = self
-            .system_to_node
-            .get(&system_a.attributes.id)
-            .ok_or(GraphError::SystemNotFound)?;
+        self.get_pathfinding_between(
+            system_a,
+            system_b,
+            |gate: &Stargate, system: &SolarSystem| {
+                can_enter(system).then(|| RoutePolicy::Shortest.cost(gate, system))?
+            },
+        )
+    }
 
-        let mut rng = rand::thread_rng();
-        let mut path = Err(GraphError::NoPath);
-        for _ in 0..10 {
-            let end_index = *self
-                .system_to_node
-                .values()
-                .choose(&mut rng)
-                .ok_or(GraphError::SystemNotFound)?;
-
-            path = self.get_path(*start_index, end_index);
-            if path.is_ok() {
-                break;
-            }
-        }
-        path
+    /// get the shortest path between a known starting system and a random system that can be reached from it
+    pub fn get_pathfinding_to_random_system(
+        &self,
+        system_a: &SolarSystem,
+    ) -> Result<Vec<Stargate>, GraphError> {
+        self.resolve_path(system_a.attributes.id, PathRequest::Anywhere)
     }
 
-    /// Remove a node from the graph, i.e a solar system
+    /// Remove a node from the graph, i.e a solar system, along with the gates into and out of it
     pub fn remove_node(&mut self, system: &SolarSystem) {
-        if let Some(index) = self.system_to_node.remove(&system.attributes.id) {
-            self.graph.remove_node(index);
-        }
+        self.remove_system(system.attributes.id);
+    }
+
+    /// Remove a system by its id, along with the gates into and out of it
+    pub fn remove_system(&mut self, id: u32) -> Option<SolarSystem> {
+        let index = self.system_to_node.remove(&id)?;
+        self.positions.remove(&id);
+        self.forget_routes();
+        let system = self.graph.remove_node(index);
+        let graph = &self.graph;
+        self.gate_to_edge
+            .retain(|_, edge| graph.edge_weight(*edge).is_some());
+
+        system
     }
 
-    /// Remove an edge from the graph, i.e a connection between two systems
-    pub fn remove_edge(&mut self, edge_index: EdgeIndex) {
-        self.graph.remove_edge(edge_index);
+    /// Remove a gate by its id, i.e a connection between two systems
+    pub fn remove_gate(&mut self, id: u32) -> Option<Stargate> {
+        let index = self.gate_to_edge.remove(&id)?;
+        self.forget_routes();
+        self.graph.remove_edge(index)
     }
 
     /// Check if the graph contains a system
@@ -143,9 +468,124 @@ impl SystemGraph {
         self.system_to_node.contains_key(&system.attributes.id)
     }
 
-    /// Check if the graph contains a gate
-    pub fn contains_gate(&self, edge_index: EdgeIndex) -> bool {
-        self.graph.edge_weight(edge_index).is_some()
+    /// Check if the graph contains a gate, by its id
+    pub fn contains_gate(&self, id: u32) -> bool {
+        self.gate_to_edge.contains_key(&id)
+    }
+
+    /// Every gate in the graph, open or closed
+    pub fn gates(&self) -> impl Iterator<Item = &Stargate> {
+        self.gate_to_edge
+            .values()
+            .filter_map(|index| self.graph.edge_weight(*index))
+    }
+
+    /// The groups of systems joined to one another by gates, open or closed, as system ids
+    pub fn components(&self) -> Vec<Vec<u32>> {
+        let mut joined = UnionFind::new(self.graph.node_bound());
+        for edge in self.graph.edge_indices() {
+            if let Some((a, b)) = self.graph.edge_endpoints(edge) {
+                joined.union(a.index(), b.index());
+            }
+        }
+
+        let mut components: HashMap<usize, Vec<u32>> = HashMap::new();
+        for node in self.graph.node_indices() {
+            components
+                .entry(joined.find(node.index()))
+                .or_default()
+                .push(self.graph[node].attributes.id);
+        }
+        components.into_values().collect()
+    }
+
+    /// Get a gate by its id
+    pub fn gate_by_id(&self, id: &u32) -> Option<&Stargate> {
+        self.gate_to_edge
+            .get(id)
+            .and_then(|index| self.graph.edge_weight(*index))
+    }
+
+    /// Check that every gate along a path is still in the graph, open, and joins the same systems it did
+    pub fn is_path_open(&self, path: &[Stargate]) -> bool {
+        path.iter().all(|gate| {
+            self.gate_by_id(&gate.id).is_some_and(|current| {
+                current.is_active
+                    && current.origin_system_id == gate.origin_system_id
+                    && current.destination_system_id == gate.destination_system_id
+            })
+        })
+    }
+
+    /// The number of jumps from a system to every system reachable from it through open gates, keyed by system id
+    pub fn jumps_from(&self, system: &SolarSystem) -> HashMap<u32, usize> {
+        let Some(start) = self.system_to_node.get(&system.attributes.id) else {
+            return HashMap::new();
+        };
+
+        let graph = EdgeFiltered::from_fn(&self.graph, |edge| edge.weight().is_active);
+        dijkstra(&graph, *start, None, |_| 1)
+            .into_iter()
+            .filter_map(|(node, jumps)| {
+                self.graph
+                    .node_weight(node)
+                    .map(|system| (system.attributes.id, jumps))
+            })
+            .collect()
+    }
+
+    /// How many of the shortest routes between every pair of systems pass through each system, keyed by system id.
+    ///
+    /// The systems the most routes pass through are the chokepoints of the gate network.
+    /// The counts are cached until the gates change.
+    pub fn through_traffic(&self) -> Arc<HashMap<u32, usize>> {
+        if let Some(traffic) = self.through_traffic.read().unwrap().as_ref() {
+            return traffic.clone();
+        }
+
+        let mut traffic: HashMap<NodeIndex, usize> = HashMap::new();
+        for start in self.graph.node_indices() {
+            // Search outward, remembering which system each one was first reached from
+            let mut reached_from: HashMap<NodeIndex, NodeIndex> = HashMap::new();
+            let mut order = Vec::new();
+            let mut queue = VecDeque::from([start]);
+            while let Some(node) = queue.pop_front() {
+                let open_gates = self
+                    .graph
+                    .edges(node)
+                    .filter(|edge| edge.weight().is_active);
+                for next in open_gates.map(|edge| edge.target()) {
+                    if next != start && !reached_from.contains_key(&next) {
+                        reached_from.insert(next, node);
+                        order.push(next);
+                        queue.push_back(next);
+                    }
+                }
+            }
+
+            // Every system is passed through on the way to the systems reached beyond it
+            let mut beyond: HashMap<NodeIndex, usize> = HashMap::new();
+            for node in order.iter().rev() {
+                let count = beyond.get(node).copied().unwrap_or_default();
+                *traffic.entry(*node).or_default() += count;
+                *beyond.entry(reached_from[node]).or_default() += count + 1;
+            }
+        }
+
+        let traffic: Arc<HashMap<u32, usize>> = Arc::new(
+            self.graph
+                .node_indices()
+                .filter_map(|node| {
+                    let system = self.graph.node_weight(node)?;
+                    Some((
+                        system.attributes.id,
+                        traffic.get(&node).copied().unwrap_or_default(),
+                    ))
+                })
+                .collect(),
+        );
+        *self.through_traffic.write().unwrap() = Some(traffic.clone());
+        traffic
     }
 
     /// Get a system by its id
@@ -166,6 +606,120 @@ impl SystemGraph {
     }
 }
 
+/// The solar system and stargate entities the `SystemGraph` was last synced with, and their ids
+#[derive(Default)]
+pub struct SyncedEntities {
+    /// The id of the system on each solar system entity
+    systems: HashMap<Entity, u32>,
+    /// The id of the gate on each stargate entity
+    gates: HashMap<Entity, u32>,
+}
+
+/// Keeps the `SystemGraph` in step with the `SolarSystem` and `Stargate` components as they are added, changed and removed.
+///
+/// When a gate closes or goes away, every agent whose path runs through it has its path invalidated, so it plans a new one.
+pub fn sync_system_graph(
+    mut system_graph: ResMut<SystemGraph>,
+    mut synced: Local<SyncedEntities>,
+    changed_systems: Query<(Entity, &SolarSystem), Changed<SolarSystem>>,
+    changed_gates: Query<(Entity, &Stargate), Changed<Stargate>>,
+    all_gates: Query<&Stargate>,
+    mut removed_systems: RemovedComponents<SolarSystem>,
+    mut removed_gates: RemovedComponents<Stargate>,
+    mut agents: Query<(Entity, &mut Agent)>,
+) {
+    let mut gates_changed = false;
+
+    // Removals come first, so an id that was despawned and spawned again this frame ends up in the graph
+    for entity in removed_gates.read() {
+        if let Some(id) = synced.gates.remove(&entity) {
+            if !synced.gates.values().any(|other| *other == id) {
+                system_graph.remove_gate(id);
+                gates_changed = true;
+            }
+        }
+    }
+    for entity in removed_systems.read() {
+        if let Some(id) = synced.systems.remove(&entity) {
+            if !synced.systems.values().any(|other| *other == id) {
+                system_graph.remove_system(id);
+                gates_changed = true;
+            }
+        }
+    }
+
+    for (entity, system) in changed_systems.iter() {
+        let id = system.attributes.id;
+        let is_new = system_graph.system_by_id(&id).is_none();
+        synced.systems.insert(entity, id);
+        system_graph.add_node(system.clone());
+
+        // Gates spawned before their system could not be added until now
+        if is_new {
+            for gate in all_gates
+                .iter()
+                .filter(|gate| gate.origin_system_id == id || gate.destination_system_id == id)
+            {
+                system_graph.add_gate(gate.clone());
+            }
+        }
+    }
+    for (entity, gate) in changed_gates.iter() {
+        let old_id = synced.gates.insert(entity, gate.id);
+        if system_graph.gate_by_id(&gate.id) == Some(gate) {
+            continue;
+        }
+        if let Some(old_id) = old_id.filter(|old_id| *old_id != gate.id) {
+            system_graph.remove_gate(old_id);
+        }
+        system_graph.remove_gate(gate.id);
+        system_graph.add_gate(gate.clone());
+        gates_changed = true;
+    }
+
+    if !gates_changed {
+        return;
+    }
+    for (entity, mut agent) in agents.iter_mut() {
+        if !agent.stargate_path.is_invalidated()
+            && !system_graph.is_path_open(&agent.stargate_path.path)
+        {
+            debug!(
+                "Path of agent {:?} runs through a gate that is no longer open",
+                entity
+            );
+            agent.stargate_path.invalidate();
+        }
+    }
+}
+
+/// Resolves the path requests of every agent waiting on one in a single batch, spread across the compute task pool.
+///
+/// Each agent is given its path, or an empty one if there is no way there, and its request is removed.
+pub fn resolve_path_requests(
+    mut commands: Commands,
+    system_graph: Res<SystemGraph>,
+    mut agents: Query<(Entity, &mut Agent, &PathRequest)>,
+) {
+    let (waiting, requests): (Vec<Entity>, Vec<(u32, PathRequest)>) = agents
+        .iter()
+        .map(|(entity, agent, request)| (entity, (agent.current_system.attributes.id, *request)))
+        .unzip();
+    if requests.is_empty() {
+        return;
+    }
+
+    for (entity, path) in waiting
+        .into_iter()
+        .zip(system_graph.resolve_paths(&requests))
+    {
+        if let Ok((_, mut agent, _)) = agents.get_mut(entity) {
+            agent.set_stargate_path(path.unwrap_or_default());
+        }
+        commands.entity(entity).remove::<PathRequest>();
+    }
+}
+
 /// get a path between two selected Systems
 pub fn get_stargate_path_between_systems(
     selected_systems: Res<Selection>,
@@ -183,7 +737,7 @@ pub fn get_stargate_path_between_systems(
     let system_a = selected_systems.get(0).unwrap();
     let system_b = selected_systems.get(1).unwrap();
 
-    let path = system_graph.get_pathfinding_between(&system_a, &system_b);
+    let path = system_graph.shortest_path(&system_a, &system_b);
 
     match path {
         Ok(gates) => {
@@ -0,0 +1,473 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use big_brain::prelude::*;
+use rand::seq::{IteratorRandom, SliceRandom};
+
+use crate::faction::diplomoacy::{Diplomacy, DiplomaticState};
+use crate::faction::pirates::PIRATES;
+use crate::simulation::SimulationClock;
+use crate::solar_system::SolarSystem;
+use crate::structures::services::dock::DockedAt;
+use crate::structures::stargate::Stargate;
+
+use super::agent::{Agent, AgentRole, Goal};
+use super::cargo::CargoHold;
+use super::combat::{Shield, ShipDestroyedEvent, Target, Weapon};
+use super::fly_to_system_action::follow_stargate_path;
+use super::pathfinding::SystemGraph;
+
+/// The risk at which traders go out of their way to avoid a system
+pub const AVOIDED_RISK: f32 = 0.5;
+/// How often pirate sightings are reported, in seconds of simulation time
+const RISK_INTERVAL: f64 = 10.0;
+/// The share of a system's risk that is forgotten each second
+const RISK_DECAY: f32 = 0.01;
+/// The risk a pirate adds to the system it is seen in, at every report
+const SIGHTING_RISK: f32 = 0.05;
+/// The risk a ship destroyed by pirates adds to the system it was lost in
+const ATTACK_RISK: f32 = 0.25;
+/// The score a pirate gives to going raiding
+const RAID_DESIRE: f32 = 0.6;
+/// The score an outmatched pirate gives to fleeing
+const FLEE_DESIRE: f32 = 1.0;
+/// The most jumps a pirate travels to lie in wait at a chokepoint
+const CHOKEPOINT_RANGE: usize = 3;
+/// How many of the busiest chokepoints in range a pirate picks between
+const CHOKEPOINT_CHOICES: usize = 3;
+/// How close to a stargate a pirate lies in wait
+const AMBUSH_DISTANCE: f32 = 20.0;
+/// How long a pirate lies in wait for prey before moving on, in seconds
+const AMBUSH_TIME: f32 = 60.0;
+/// How far away a pirate keeps watch for ships that could fight back
+const THREAT_RANGE: f32 = 200.0;
+/// How far inside its weapon's range a pirate closes on its prey
+const CLOSING_RANGE: f32 = 0.75;
+
+/// How dangerous traders believe each system to be, from pirate sightings and attacks
+#[derive(Resource, Default, Debug, Clone)]
+pub struct PiracyRisk {
+    /// The risk in each system with any, from zero for safe to one for certain trouble
+    systems: HashMap<u32, f32>,
+}
+
+impl PiracyRisk {
+    /// The risk in a system
+    pub fn risk(&self, system_id: u32) -> f32 {
+        self.systems.get(&system_id).copied().unwrap_or_default()
+    }
+
+    /// Adds to the risk in a system, up to a maximum of one
+    pub fn report(&mut self, system_id: u32, amount: f32) {
+        let risk = self.systems.entry(system_id).or_default();
+        *risk = (*risk + amount).clamp(0.0, 1.0);
+    }
+
+    /// Forgets some of the risk in every system, as the given number of seconds pass without news
+    pub fn fade(&mut self, seconds: f32) {
+        let decay = (1.0 - RISK_DECAY * seconds).max(0.0);
+        for risk in self.systems.values_mut() {
+            *risk *= decay;
+        }
+        self.systems.retain(|_, risk| *risk > f32::EPSILON);
+    }
+
+    /// The chance of running into pirates somewhere along a path, from the risk in each system it enters
+    pub fn route_risk<'a>(&self, path: impl IntoIterator<Item = &'a Stargate>) -> f32 {
+        let safe: f32 = path
+            .into_iter()
+            .map(|gate| 1.0 - self.risk(gate.destination_system_id))
+            .product();
+        1.0 - safe
+    }
+}
+
+/// Keeps [`PiracyRisk`] up to date, from the pirates seen in each system and the ships they destroy
+pub fn update_piracy_risk(
+    mut last_update: Local<f64>,
+    clock: Res<SimulationClock>,
+    mut risk: ResMut<PiracyRisk>,
+    mut destroyed: EventReader<ShipDestroyedEvent>,
+    agents: Query<(&Agent, Option<&AgentRole>)>,
+) {
+    for event in destroyed.read() {
+        if event.attacker_faction != PIRATES {
+            continue;
+        }
+        if let Ok((pirate, _)) = agents.get(event.attacker) {
+            risk.report(pirate.current_system.attributes.id, ATTACK_RISK);
+        }
+    }
+
+    let now = clock.now();
+    if now - *last_update < RISK_INTERVAL {
+        return;
+    }
+    let elapsed = (now - *last_update).min(RISK_INTERVAL * 2.0) as f32;
+    *last_update = now;
+
+    risk.fade(elapsed);
+    for (agent, role) in agents.iter() {
+        if role == Some(&AgentRole::Pirate) {
+            risk.report(agent.current_system.attributes.id, SIGHTING_RISK);
+        }
+    }
+}
+
+/// The fighting strength of a ship, its firepower scaled by how much of its hull and shield it has left
+fn strength(agent: &Agent, weapon: &Weapon, shield: Option<&Shield>) -> f32 {
+    let (shield, max_shield) = shield.map_or((0.0, 0.0), |shield| (shield.current, shield.max));
+    weapon.damage_per_second * (agent.health.current + shield) / (agent.health.max + max_shield)
+}
+
+/// This is the `WantToRaid` scorer
+#[derive(Clone, Component, Debug, ScorerBuilder)]
+pub struct WantToRaid;
+
+/// Scores how much a pirate wants to go raiding, which is always more than it wants to wander
+pub fn want_to_raid_scorer_system(
+    mut query: Query<(&Actor, &mut Score, &ScorerSpan), With<WantToRaid>>,
+) {
+    for (_, mut score, span) in &mut query {
+        score.set(RAID_DESIRE);
+        span.span()
+            .in_scope(|| debug!("Want to raid! Score: {}", RAID_DESIRE));
+    }
+}
+
+/// This is the `WantToFlee` scorer
+#[derive(Clone, Component, Debug, ScorerBuilder)]
+pub struct WantToFlee;
+
+/// Scores how much an agent wants to flee, which it does whenever the enemies nearby are stronger than its side.
+pub fn want_to_flee_scorer_system(
+    diplomacy: Res<Diplomacy>,
+    ships: Query<(&Agent, &Transform, &Weapon, Option<&Shield>), Without<DockedAt>>,
+    mut query: Query<(&Actor, &mut Score, &ScorerSpan), With<WantToFlee>>,
+) {
+    for (Actor(actor), mut score, span) in &mut query {
+        let Ok((agent, transform, _, _)) = ships.get(*actor) else {
+            continue;
+        };
+        let position = transform.translation.truncate();
+
+        let mut allies = 0.0;
+        let mut enemies = 0.0;
+        for (other, other_transform, weapon, shield) in ships.iter() {
+            if other.current_system.attributes.id != agent.current_system.attributes.id
+                || position.distance(other_transform.translation.truncate()) > THREAT_RANGE
+            {
+                continue;
+            }
+            if other.faction == agent.faction {
+                allies += strength(other, weapon, shield);
+            } else if diplomacy.state(agent.faction, other.faction) == DiplomaticState::War {
+                enemies += strength(other, weapon, shield);
+            }
+        }
+
+        let desire = if enemies > allies { FLEE_DESIRE } else { 0.0 };
+        score.set(desire);
+        span.span()
+            .in_scope(|| debug!("Want to flee! Score: {}", desire));
+    }
+}
+
+/// The first step of a raid, lying in wait at one of the busiest stargates nearby
+#[derive(Clone, Component, Debug, Default, ActionBuilder)]
+pub struct ScoutChokepoint {
+    /// Where the pirate will lie in wait, once it has reached the chokepoint's system
+    ambush_point: Option<Vec3>,
+}
+
+/// Picks a chokepoint within a few jumps, from the systems the most routes through the gate network pass through,
+/// then flies there and waits beside one of its stargates.
+pub fn scout_chokepoint_action_system(
+    time: Res<Time>,
+    system_graph: Res<SystemGraph>,
+    mut agents: Query<(&mut Agent, &mut Transform)>,
+    star_gates: Query<(&Stargate, &Transform), Without<Agent>>,
+    solar_systems: Query<(&SolarSystem, &Transform), Without<Agent>>,
+    mut action_query: Query<(&Actor, &mut ActionState, &mut ScoutChokepoint, &ActionSpan)>,
+) {
+    for (Actor(actor), mut action_state, mut scout, span) in &mut action_query {
+        let _guard = span.span().enter();
+        let Ok((mut agent, mut transform)) = agents.get_mut(*actor) else {
+            *action_state = ActionState::Failure;
+            continue;
+        };
+
+        match *action_state {
+            ActionState::Requested => {
+                let traffic = system_graph.through_traffic();
+                let mut chokepoints: Vec<(usize, u32)> = system_graph
+                    .jumps_from(&agent.current_system)
+                    .into_iter()
+                    .filter(|(_, jumps)| *jumps <= CHOKEPOINT_RANGE)
+                    .map(|(system_id, _)| {
+                        (
+                            traffic.get(&system_id).copied().unwrap_or_default(),
+                            system_id,
+                        )
+                    })
+                    .collect();
+                chokepoints.sort_by(|a, b| b.cmp(a));
+                chokepoints.truncate(CHOKEPOINT_CHOICES);
+
+                let Some(path) = chokepoints
+                    .choose(&mut rand::thread_rng())
+                    .and_then(|(_, system_id)| system_graph.system_by_id(system_id))
+                    .and_then(|target| {
+                        system_graph
+                            .shortest_path(&agent.current_system, target)
+                            .ok()
+                    })
+                else {
+                    *action_state = ActionState::Failure;
+                    continue;
+                };
+                debug!("Scouting chokepoint {} jumps away", path.len());
+                agent.current_goal.goal = Some(Goal::Raid);
+                agent.set_stargate_path(path);
+                scout.ambush_point = None;
+                *action_state = ActionState::Executing;
+            }
+            ActionState::Executing => {
+                let step_size = time.delta_seconds() * agent.speed;
+
+                if !agent.stargate_path.path.is_empty() {
+                    if follow_stargate_path(
+                        &mut agent,
+                        &mut transform,
+                        step_size,
+                        &star_gates,
+                        &solar_systems,
+                    )
+                    .is_none()
+                    {
+                        *action_state = ActionState::Failure;
+                    }
+                    continue;
+                }
+
+                let here = agent.current_system.attributes.id;
+                let Some(target) = scout.ambush_point.or_else(|| {
+                    star_gates
+                        .iter()
+                        .filter(|(gate, _)| gate.origin_system_id == here)
+                        .choose(&mut rand::thread_rng())
+                        .map(|(_, gate_transform)| gate_transform.translation)
+                }) else {
+                    *action_state = ActionState::Failure;
+                    continue;
+                };
+                scout.ambush_point = Some(target);
+                let target = target.truncate().extend(transform.translation.z);
+                agent.target_destination = Some(target);
+
+                let delta = target - transform.translation;
+                let distance = delta.length();
+                if distance <= AMBUSH_DISTANCE {
+                    *action_state = ActionState::Success;
+                } else {
+                    transform.translation += delta.normalize() * step_size.min(distance);
+                }
+            }
+            ActionState::Cancelled => {
+                agent.stargate_path.path.clear();
+                *action_state = ActionState::Failure;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The last step of a raid, running down a laden trader that comes through the chokepoint
+#[derive(Clone, Component, Debug, Default, ActionBuilder)]
+pub struct InterceptTrader {
+    /// How long the pirate has waited for prey, in seconds
+    waited: f32,
+    /// The trader being run down
+    prey: Option<Entity>,
+}
+
+/// Waits for a trader with goods in its hold to turn up in the system, then closes on it and opens fire.
+///
+/// The raid succeeds once the trader is destroyed, and fails if it gets away by docking or leaving the system,
+/// or if no prey turns up for a while.
+pub fn intercept_trader_action_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut ships: Query<(
+        Entity,
+        &mut Agent,
+        &mut Transform,
+        Option<&AgentRole>,
+        Option<&CargoHold>,
+        Option<&Weapon>,
+        Has<DockedAt>,
+    )>,
+    mut action_query: Query<(&Actor, &mut ActionState, &mut InterceptTrader, &ActionSpan)>,
+) {
+    // Where every trader worth robbing is, as (trader, system, position)
+    let laden: Vec<(Entity, u32, Vec3)> = ships
+        .iter()
+        .filter(|(_, _, _, role, hold, _, docked)| {
+            role == &Some(&AgentRole::Trader)
+                && hold.is_some_and(|hold| !hold.is_empty())
+                && !docked
+        })
+        .map(|(entity, agent, transform, ..)| {
+            (
+                entity,
+                agent.current_system.attributes.id,
+                transform.translation,
+            )
+        })
+        .collect();
+
+    for (Actor(actor), mut action_state, mut intercept, span) in &mut action_query {
+        let _guard = span.span().enter();
+
+        match *action_state {
+            ActionState::Requested => {
+                intercept.waited = 0.0;
+                intercept.prey = None;
+                *action_state = ActionState::Executing;
+            }
+            ActionState::Executing => {
+                // Where the prey is now, or `None` if it has been destroyed
+                let prey = intercept.prey.map(|prey| {
+                    ships
+                        .get(prey)
+                        .ok()
+                        .map(|(_, agent, transform, .., docked)| {
+                            (
+                                agent.current_system.attributes.id,
+                                transform.translation,
+                                docked,
+                            )
+                        })
+                });
+                let Ok((_, mut agent, mut transform, _, _, weapon, _)) = ships.get_mut(*actor)
+                else {
+                    *action_state = ActionState::Failure;
+                    continue;
+                };
+                let here = agent.current_system.attributes.id;
+
+                match prey {
+                    None => {
+                        intercept.waited += time.delta_seconds();
+                        let position = transform.translation;
+                        let nearest = laden
+                            .iter()
+                            .filter(|(_, system_id, _)| *system_id == here)
+                            .min_by(|a, b| {
+                                a.2.distance(position).total_cmp(&b.2.distance(position))
+                            });
+                        if let Some((trader, _, _)) = nearest {
+                            debug!("Intercepting trader {:?}", trader);
+                            intercept.prey = Some(*trader);
+                            commands.entity(*actor).insert(Target(*trader));
+                        } else if intercept.waited >= AMBUSH_TIME {
+                            *action_state = ActionState::Failure;
+                        }
+                    }
+                    // The prey was destroyed
+                    Some(None) => {
+                        intercept.prey = None;
+                        *action_state = ActionState::Success;
+                    }
+                    Some(Some((system_id, position, docked))) => {
+                        if system_id != here || docked {
+                            commands.entity(*actor).remove::<Target>();
+                            *action_state = ActionState::Failure;
+                            continue;
+                        }
+
+                        let range = weapon.map_or(0.0, |weapon| weapon.range) * CLOSING_RANGE;
+                        let target = position.truncate().extend(transform.translation.z);
+                        agent.target_destination = Some(target);
+                        let delta = target - transform.translation;
+                        let distance = delta.length();
+                        if distance > range {
+                            let step_size = time.delta_seconds() * agent.speed;
+                            transform.translation +=
+                                delta.normalize() * step_size.min(distance - range);
+                        }
+                    }
+                }
+            }
+            ActionState::Cancelled => {
+                commands.entity(*actor).remove::<Target>();
+                *action_state = ActionState::Failure;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Flees through the nearest stargate
+#[derive(Clone, Component, Debug, ActionBuilder)]
+pub struct Flee;
+
+/// Breaks off any fight and makes for the nearest stargate, jumping into the next system to get away.
+pub fn flee_action_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut agents: Query<(&mut Agent, &mut Transform)>,
+    star_gates: Query<(&Stargate, &Transform), Without<Agent>>,
+    solar_systems: Query<(&SolarSystem, &Transform), Without<Agent>>,
+    mut action_query: Query<(&Actor, &mut ActionState, &ActionSpan), With<Flee>>,
+) {
+    for (Actor(actor), mut action_state, span) in &mut action_query {
+        let _guard = span.span().enter();
+        let Ok((mut agent, mut transform)) = agents.get_mut(*actor) else {
+            *action_state = ActionState::Failure;
+            continue;
+        };
+
+        match *action_state {
+            ActionState::Requested => {
+                let here = agent.current_system.attributes.id;
+                let position = transform.translation;
+                let Some((gate, _)) = star_gates
+                    .iter()
+                    .filter(|(gate, _)| gate.origin_system_id == here)
+                    .min_by(|(_, a), (_, b)| {
+                        a.translation
+                            .distance(position)
+                            .total_cmp(&b.translation.distance(position))
+                    })
+                else {
+                    *action_state = ActionState::Failure;
+                    continue;
+                };
+                debug!("Fleeing to system {}", gate.destination_system_id);
+                commands.entity(*actor).remove::<Target>();
+                agent.set_stargate_path(vec![gate.clone()]);
+                *action_state = ActionState::Executing;
+            }
+            ActionState::Executing => {
+                let step_size = time.delta_seconds() * agent.speed;
+                match follow_stargate_path(
+                    &mut agent,
+                    &mut transform,
+                    step_size,
+                    &star_gates,
+                    &solar_systems,
+                ) {
+                    Some(true) => *action_state = ActionState::Success,
+                    Some(false) => {}
+                    None => *action_state = ActionState::Failure,
+                }
+            }
+            ActionState::Cancelled => {
+                agent.stargate_path.path.clear();
+                *action_state = ActionState::Failure;
+            }
+            _ => {}
+        }
+    }
+}
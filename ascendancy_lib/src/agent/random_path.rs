@@ -28,7 +28,7 @@ pub fn get_random_path_between_two_systems(
         let system_b = solar_systems_vec.choose(&mut rng).unwrap();
 
         // get the path between the two systems
-        let path = system_graph.get_pathfinding_between(&system_a, &system_b);
+        let path = system_graph.shortest_path(&system_a, &system_b);
 
         println!(
             "Path between {} and {}:",
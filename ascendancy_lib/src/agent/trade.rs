@@ -0,0 +1,869 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use big_brain::prelude::*;
+
+use crate::faction::diplomoacy::Diplomacy;
+use crate::items::{ItemDefinition, ItemId, ItemRegistry};
+use crate::solar_system::SolarSystem;
+use crate::structures::services::dock::{
+    DockDeniedEvent, DockRequestEvent, DockedAt, UndockRequestEvent,
+};
+use crate::structures::services::market::{Market, OrderId, Quote};
+use crate::structures::stargate::Stargate;
+use crate::structures::station::Station;
+
+use super::agent::{Agent, Goal};
+use super::cargo::CargoHold;
+use super::fly_to_system_action::{follow_stargate_path, route_avoiding_pirates};
+use super::pathfinding::SystemGraph;
+use super::piracy::PiracyRisk;
+
+/// How often traders refresh the prices they know about, in seconds
+pub const PRICE_BOARD_INTERVAL: f32 = 5.0;
+/// The fewest credits an agent needs before it will look for a trade
+const MIN_TRADE_FUNDS: f32 = 50.0;
+/// The profit at which the desire to trade reaches half of its maximum
+const PROFIT_FOR_HALF_DESIRE: f32 = 100.0;
+/// The highest score an agent looking for a trade gives
+const MAX_TRADE_DESIRE: f32 = 0.8;
+/// The score an agent part way through a trade gives, so it sees the trade through
+const COMMITTED_TRADE_DESIRE: f32 = 0.9;
+/// How many of the cheapest sellers and best buyers of an item are paired up when planning a trade
+const TRADE_CANDIDATES: usize = 5;
+/// How far past the quoted price an agent will go, so small price moves don't leave its order unfilled
+const PRICE_SLIPPAGE: f32 = 0.05;
+/// How long an agent waits for an order to fill before cancelling it, in seconds
+const ORDER_TIMEOUT: f32 = 10.0;
+/// How long an agent waits before looking again after failing to find a trade, in seconds
+const TRADE_SEARCH_COOLDOWN: f32 = 15.0;
+/// How close an agent needs to be to a station to ask to dock
+const DOCKING_DISTANCE: f32 = 5.0;
+
+/// Times the refreshes of [`KnownPrices`]
+#[derive(Resource)]
+pub struct PriceBoardTimer(pub Timer);
+
+/// The best prices for an item on one station's market
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MarketQuote {
+    /// The station the market is on
+    pub station: Entity,
+    /// The system the station is in
+    pub system_id: u32,
+    /// The best price the market will pay
+    pub bid: Option<Quote>,
+    /// The best price the market will sell at
+    pub ask: Option<Quote>,
+    /// The share of a sale withheld as tax
+    pub tax_rate: f32,
+}
+
+impl MarketQuote {
+    /// The price a seller keeps per unit after tax, if the market is buying
+    pub fn net_bid(&self) -> Option<f32> {
+        self.bid.map(|bid| bid.price * (1.0 - self.tax_rate))
+    }
+}
+
+/// The prices on every market as traders last saw them
+#[derive(Resource, Default, Debug)]
+pub struct KnownPrices {
+    /// The quotes for each item, one per market
+    quotes: HashMap<ItemId, Vec<MarketQuote>>,
+}
+
+impl KnownPrices {
+    /// Records the quotes from a market, replacing any seen before
+    pub fn record(&mut self, station: Entity, system_id: u32, market: &Market) {
+        for (item, _) in market.listings() {
+            let quotes = self.quotes.entry(item).or_default();
+            quotes.retain(|quote| quote.station != station);
+            quotes.push(MarketQuote {
+                station,
+                system_id,
+                bid: market.best_bid(item),
+                ask: market.best_ask(item),
+                tax_rate: market.tax_rate,
+            });
+        }
+    }
+
+    /// Forgets every quote
+    pub fn clear(&mut self) {
+        self.quotes.clear();
+    }
+
+    /// The quotes known for an item
+    pub fn quotes(&self, item: ItemId) -> &[MarketQuote] {
+        self.quotes.get(&item).map_or(&[], Vec::as_slice)
+    }
+
+    /// Iterate over the items with known quotes
+    pub fn items(&self) -> impl Iterator<Item = ItemId> + '_ {
+        self.quotes.keys().copied()
+    }
+
+    /// The cheapest ask and the highest bid for an item, wherever they are
+    pub fn best_spread(&self, item: ItemId) -> Option<(Quote, Quote)> {
+        let quotes = self.quotes(item);
+        let ask = quotes
+            .iter()
+            .filter_map(|quote| quote.ask)
+            .min_by(|a, b| a.price.total_cmp(&b.price))?;
+        let bid = quotes
+            .iter()
+            .filter_map(|quote| quote.bid)
+            .max_by(|a, b| a.price.total_cmp(&b.price))?;
+        Some((ask, bid))
+    }
+
+    /// The sellers of an item, cheapest first
+    fn sellers(&self, item: ItemId) -> Vec<MarketQuote> {
+        let mut sellers: Vec<_> = self
+            .quotes(item)
+            .iter()
+            .filter(|quote| quote.ask.is_some())
+            .copied()
+            .collect();
+        sellers.sort_by(|a, b| ask_price(a).total_cmp(&ask_price(b)));
+        sellers
+    }
+
+    /// The buyers of an item, best paying first
+    fn buyers(&self, item: ItemId) -> Vec<MarketQuote> {
+        let mut buyers: Vec<_> = self
+            .quotes(item)
+            .iter()
+            .filter(|quote| quote.bid.is_some())
+            .copied()
+            .collect();
+        buyers.sort_by(|a, b| bid_price(b).total_cmp(&bid_price(a)));
+        buyers
+    }
+}
+
+/// The asking price of a quote, or infinity if nothing is for sale
+fn ask_price(quote: &MarketQuote) -> f32 {
+    quote.ask.map_or(f32::INFINITY, |ask| ask.price)
+}
+
+/// The bid price of a quote after tax, or zero if nobody is buying
+fn bid_price(quote: &MarketQuote) -> f32 {
+    quote.net_bid().unwrap_or(0.0)
+}
+
+/// Refreshes [`KnownPrices`] from every market when the price board timer runs out
+pub fn update_known_prices(
+    time: Res<Time>,
+    mut timer: ResMut<PriceBoardTimer>,
+    mut prices: ResMut<KnownPrices>,
+    stations: Query<(Entity, &Station)>,
+) {
+    if !timer.0.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    prices.clear();
+    for (entity, station) in stations.iter() {
+        if let Some(market) = station.market() {
+            if market.is_active {
+                prices.record(entity, station.system_id, market);
+            }
+        }
+    }
+}
+
+/// Whether a trade run is on its way to buy or to sell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TradeStage {
+    /// Heading to the seller to buy the goods
+    Buying,
+    /// Hauling the goods to the buyer
+    Selling,
+}
+
+/// A station a trade run stops at
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TradeStop {
+    /// The station to trade at
+    pub station: Entity,
+    /// The system the station is in
+    pub system_id: u32,
+    /// The price the agent expects to trade at, after any tax
+    pub price: f32,
+}
+
+/// The trade an agent has decided to make
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct TradePlan {
+    /// The item being traded
+    pub item: ItemId,
+    /// The number of units to trade
+    pub quantity: u32,
+    /// Where the goods are bought, if the agent doesn't already hold them
+    pub buy_from: Option<TradeStop>,
+    /// Where the goods are sold
+    pub sell_to: TradeStop,
+    /// How far through the run the agent is
+    pub stage: TradeStage,
+}
+
+impl TradePlan {
+    /// The station the agent is heading for at this stage of the run
+    pub fn current_stop(&self) -> &TradeStop {
+        match (self.stage, &self.buy_from) {
+            (TradeStage::Buying, Some(stop)) => stop,
+            _ => &self.sell_to,
+        }
+    }
+
+    /// The profit the plan expects to make
+    pub fn expected_profit(&self) -> f32 {
+        let cost = self.buy_from.map_or(0.0, |stop| stop.price);
+        (self.sell_to.price - cost) * self.quantity as f32
+    }
+}
+
+/// Stops an agent looking for trades for a while after it failed to find one
+#[derive(Component, Debug, Clone)]
+pub struct TradeCooldown(pub Timer);
+
+/// The number of units an agent can buy, limited by the quote, its cargo space and its wallet
+fn affordable_quantity(
+    item: &ItemDefinition,
+    ask: &Quote,
+    bid: &Quote,
+    wallet: f32,
+    hold: &CargoHold,
+) -> u32 {
+    let limit = ask.price * (1.0 + PRICE_SLIPPAGE);
+    ask.quantity
+        .min(bid.quantity)
+        .min(hold.contents().room_for(item))
+        .min((wallet / limit).floor() as u32)
+}
+
+/// This is the `WantToTrade` scorer
+#[derive(Clone, Component, Debug, ScorerBuilder)]
+pub struct WantToTrade;
+
+/// Scores how much an agent wants to trade, from the profit it could make on the spreads it knows about.
+///
+/// Agents holding goods always want to sell them, and agents part way through a trade want to finish it.
+pub fn want_to_trade_scorer_system(
+    time: Res<Time>,
+    prices: Res<KnownPrices>,
+    items: Res<ItemRegistry>,
+    mut agents: Query<(
+        &Agent,
+        &CargoHold,
+        Option<&TradePlan>,
+        Option<&mut TradeCooldown>,
+    )>,
+    mut query: Query<(&Actor, &mut Score, &ScorerSpan), With<WantToTrade>>,
+) {
+    for (Actor(actor), mut score, span) in &mut query {
+        let Ok((agent, hold, plan, cooldown)) = agents.get_mut(*actor) else {
+            continue;
+        };
+
+        let desire = if plan.is_some() {
+            COMMITTED_TRADE_DESIRE
+        } else if cooldown.is_some_and(|mut cooldown| !cooldown.0.tick(time.delta()).finished()) {
+            0.0
+        } else if !hold.is_empty() {
+            MAX_TRADE_DESIRE
+        } else if agent.wallet.money < MIN_TRADE_FUNDS {
+            0.0
+        } else {
+            let profit = prices
+                .items()
+                .filter_map(|item| {
+                    let definition = items.get(item)?;
+                    let (ask, bid) = prices.best_spread(item)?;
+                    let quantity =
+                        affordable_quantity(definition, &ask, &bid, agent.wallet.money, hold);
+                    Some((bid.price - ask.price) * quantity as f32)
+                })
+                .fold(0.0, f32::max);
+            MAX_TRADE_DESIRE * profit / (profit + PROFIT_FOR_HALF_DESIRE)
+        };
+
+        score.set(desire);
+        span.span()
+            .in_scope(|| debug!("Want to trade! Score: {}", desire));
+    }
+}
+
+/// The number of jumps between two systems and the risk of meeting pirates on the way, remembering the answers
+struct JumpCounter<'a> {
+    /// The graph to search
+    graph: &'a SystemGraph,
+    /// The risk in each system
+    risk: &'a PiracyRisk,
+    /// The routes already worked out, as jumps and risk
+    known: HashMap<(u32, u32), Option<(usize, f32)>>,
+}
+
+impl<'a> JumpCounter<'a> {
+    /// Creates a counter over the given graph
+    fn new(graph: &'a SystemGraph, risk: &'a PiracyRisk) -> Self {
+        Self {
+            graph,
+            risk,
+            known: HashMap::new(),
+        }
+    }
+
+    /// The number of jumps from one system to another and the risk of the route, or `None` if there is no route
+    fn jumps(&mut self, from: u32, to: u32) -> Option<(usize, f32)> {
+        if from == to {
+            return Some((0, 0.0));
+        }
+        let graph = self.graph;
+        let risk = self.risk;
+        *self.known.entry((from, to)).or_insert_with(|| {
+            let from = graph.system_by_id(&from)?;
+            let to = graph.system_by_id(&to)?;
+            graph
+                .shortest_path(from, to)
+                .ok()
+                .map(|path| (path.len(), risk.route_risk(&path)))
+        })
+    }
+}
+
+/// Finds the most profitable trade per jump an agent can reach and afford.
+///
+/// Profits are discounted by the risk of losing the goods to pirates on the way.
+fn plan_trade(
+    agent: &Agent,
+    hold: &CargoHold,
+    items: &ItemRegistry,
+    prices: &KnownPrices,
+    jumps: &mut JumpCounter,
+) -> Option<TradePlan> {
+    let here = agent.current_system.attributes.id;
+    let mut best: Option<(f32, TradePlan)> = None;
+
+    for item in prices.items() {
+        let Some(definition) = items.get(item) else {
+            continue;
+        };
+        let sellers = prices.sellers(item);
+        let buyers = prices.buyers(item);
+
+        for seller in sellers.iter().take(TRADE_CANDIDATES) {
+            for buyer in buyers.iter().take(TRADE_CANDIDATES) {
+                let (Some(ask), Some(bid), Some(net_bid)) =
+                    (seller.ask, buyer.bid, buyer.net_bid())
+                else {
+                    continue;
+                };
+                if seller.station == buyer.station || net_bid <= ask.price {
+                    continue;
+                }
+                let quantity =
+                    affordable_quantity(definition, &ask, &bid, agent.wallet.money, hold);
+                if quantity == 0 {
+                    continue;
+                }
+                let Some((outbound, outbound_risk)) = jumps.jumps(here, seller.system_id) else {
+                    continue;
+                };
+                let Some((haul, haul_risk)) = jumps.jumps(seller.system_id, buyer.system_id) else {
+                    continue;
+                };
+
+                let plan = TradePlan {
+                    item,
+                    quantity,
+                    buy_from: Some(TradeStop {
+                        station: seller.station,
+                        system_id: seller.system_id,
+                        price: ask.price,
+                    }),
+                    sell_to: TradeStop {
+                        station: buyer.station,
+                        system_id: buyer.system_id,
+                        price: net_bid,
+                    },
+                    stage: TradeStage::Buying,
+                };
+                let safety = (1.0 - outbound_risk) * (1.0 - haul_risk);
+                let value = plan.expected_profit() * safety / (1 + outbound + haul) as f32;
+                if best.as_ref().map_or(true, |(best, _)| value > *best) {
+                    best = Some((value, plan));
+                }
+            }
+        }
+    }
+
+    best.map(|(_, plan)| plan)
+}
+
+/// Finds the best paying market an agent can reach for goods it already holds, allowing for the risk of the route
+fn plan_sale(
+    agent: &Agent,
+    item: ItemId,
+    quantity: u32,
+    prices: &KnownPrices,
+    jumps: &mut JumpCounter,
+) -> Option<TradePlan> {
+    let here = agent.current_system.attributes.id;
+    prices
+        .buyers(item)
+        .into_iter()
+        .filter_map(|buyer| {
+            let net_bid = buyer.net_bid()?;
+            let (distance, risk) = jumps.jumps(here, buyer.system_id)?;
+            Some((
+                net_bid * (1.0 - risk) / (1 + distance) as f32,
+                buyer,
+                net_bid,
+            ))
+        })
+        .max_by(|(a, _, _), (b, _, _)| a.total_cmp(b))
+        .map(|(_, buyer, net_bid)| TradePlan {
+            item,
+            quantity,
+            buy_from: None,
+            sell_to: TradeStop {
+                station: buyer.station,
+                system_id: buyer.system_id,
+                price: net_bid,
+            },
+            stage: TradeStage::Selling,
+        })
+}
+
+/// The first step of a trade run, choosing what to trade and where
+#[derive(Clone, Component, Debug, ActionBuilder)]
+pub struct FindTrade;
+
+/// Plans a trade for the agent, selling anything it already holds before buying more.
+pub fn find_trade_action_system(
+    mut commands: Commands,
+    prices: Res<KnownPrices>,
+    items: Res<ItemRegistry>,
+    system_graph: Res<SystemGraph>,
+    risk: Res<PiracyRisk>,
+    mut agents: Query<(&mut Agent, &CargoHold)>,
+    mut action_query: Query<(&Actor, &mut ActionState, &ActionSpan), With<FindTrade>>,
+) {
+    let mut jumps = JumpCounter::new(&system_graph, &risk);
+
+    for (Actor(actor), mut action_state, span) in &mut action_query {
+        let _guard = span.span().enter();
+        match *action_state {
+            ActionState::Requested => {
+                let Ok((mut agent, hold)) = agents.get_mut(*actor) else {
+                    *action_state = ActionState::Failure;
+                    continue;
+                };
+
+                let plan = match hold.contents().iter().next() {
+                    Some((item, stack)) => {
+                        plan_sale(&agent, item, stack.quantity, &prices, &mut jumps)
+                    }
+                    None => plan_trade(&agent, hold, &items, &prices, &mut jumps),
+                };
+
+                match plan {
+                    Some(plan) => {
+                        debug!("Planned trade: {:?}", plan);
+                        agent.current_goal.goal = Some(Goal::Trade);
+                        commands.entity(*actor).insert(plan);
+                        *action_state = ActionState::Success;
+                    }
+                    None => {
+                        agent.current_goal.goal = None;
+                        commands
+                            .entity(*actor)
+                            .remove::<TradePlan>()
+                            .insert(TradeCooldown(Timer::from_seconds(
+                                TRADE_SEARCH_COOLDOWN,
+                                TimerMode::Once,
+                            )));
+                        *action_state = ActionState::Failure;
+                    }
+                }
+            }
+            ActionState::Cancelled => {
+                *action_state = ActionState::Failure;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Flies the agent to the station at the current stop of its trade plan
+#[derive(Clone, Component, Debug, ActionBuilder)]
+pub struct FlyToStation;
+
+/// Follows the stargates to the station's system, then flies to the station itself.
+///
+/// The route avoids systems held by factions at war with the agent's own,
+/// and goes round the systems pirates haunt when there is another way.
+#[allow(clippy::too_many_arguments)]
+pub fn fly_to_station_action_system(
+    time: Res<Time>,
+    system_graph: Res<SystemGraph>,
+    diplomacy: Res<Diplomacy>,
+    risk: Res<PiracyRisk>,
+    mut agents: Query<(&mut Agent, &CargoHold, &mut Transform, &TradePlan)>,
+    stations: Query<&Transform, (With<Station>, Without<Agent>)>,
+    star_gates: Query<(&Stargate, &Transform), Without<Agent>>,
+    solar_systems: Query<(&SolarSystem, &Transform), Without<Agent>>,
+    mut action_query: Query<(&Actor, &mut ActionState, &ActionSpan), With<FlyToStation>>,
+) {
+    for (Actor(actor), mut action_state, span) in &mut action_query {
+        let _guard = span.span().enter();
+        let Ok((mut agent, hold, mut transform, plan)) = agents.get_mut(*actor) else {
+            *action_state = ActionState::Failure;
+            continue;
+        };
+        let stop = *plan.current_stop();
+
+        match *action_state {
+            ActionState::Requested => {
+                if agent.current_system.attributes.id == stop.system_id {
+                    agent.stargate_path.path.clear();
+                    *action_state = ActionState::Executing;
+                    continue;
+                }
+
+                let Some(target) = system_graph.system_by_id(&stop.system_id) else {
+                    *action_state = ActionState::Failure;
+                    continue;
+                };
+                match route_avoiding_pirates(
+                    &system_graph,
+                    &diplomacy,
+                    &risk,
+                    &agent,
+                    target,
+                    &solar_systems,
+                ) {
+                    Ok(path) => {
+                        agent.set_stargate_path(path);
+                        *action_state = ActionState::Executing;
+                    }
+                    Err(_) => *action_state = ActionState::Failure,
+                }
+            }
+            ActionState::Executing => {
+                let step_size = time.delta_seconds() * agent.cruising_speed(hold);
+
+                if !agent.stargate_path.path.is_empty() {
+                    if follow_stargate_path(
+                        &mut agent,
+                        &mut transform,
+                        step_size,
+                        &star_gates,
+                        &solar_systems,
+                    )
+                    .is_none()
+                    {
+                        *action_state = ActionState::Failure;
+                    }
+                    continue;
+                }
+
+                let Ok(station_transform) = stations.get(stop.station) else {
+                    *action_state = ActionState::Failure;
+                    continue;
+                };
+                let target = station_transform
+                    .translation
+                    .truncate()
+                    .extend(transform.translation.z);
+                agent.target_destination = Some(target);
+
+                let delta = target - transform.translation;
+                let distance = delta.length();
+                if distance <= DOCKING_DISTANCE {
+                    *action_state = ActionState::Success;
+                } else {
+                    transform.translation += delta.normalize() * step_size.min(distance);
+                }
+            }
+            ActionState::Cancelled => {
+                agent.stargate_path.path.clear();
+                *action_state = ActionState::Failure;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Docks the agent at the station at the current stop of its trade plan
+#[derive(Clone, Component, Debug, ActionBuilder)]
+pub struct DockAtStation;
+
+/// Asks the station for a berth and waits in its queue until given one.
+pub fn dock_at_station_action_system(
+    agents: Query<(&TradePlan, Option<&DockedAt>)>,
+    mut denials: EventReader<DockDeniedEvent>,
+    mut dock_requests: EventWriter<DockRequestEvent>,
+    mut undock_requests: EventWriter<UndockRequestEvent>,
+    mut action_query: Query<(&Actor, &mut ActionState, &ActionSpan), With<DockAtStation>>,
+) {
+    let denied: Vec<(Entity, Entity)> = denials
+        .read()
+        .map(|denial| (denial.agent, denial.station))
+        .collect();
+
+    for (Actor(actor), mut action_state, span) in &mut action_query {
+        let _guard = span.span().enter();
+        let Ok((plan, docked_at)) = agents.get(*actor) else {
+            *action_state = ActionState::Failure;
+            continue;
+        };
+        let station = plan.current_stop().station;
+        let docked = docked_at.is_some_and(|docked_at| docked_at.0 == station);
+
+        match *action_state {
+            ActionState::Requested => {
+                if docked {
+                    *action_state = ActionState::Success;
+                } else {
+                    dock_requests.send(DockRequestEvent {
+                        agent: *actor,
+                        station,
+                    });
+                    *action_state = ActionState::Executing;
+                }
+            }
+            ActionState::Executing => {
+                if docked {
+                    *action_state = ActionState::Success;
+                } else if denied.contains(&(*actor, station)) {
+                    *action_state = ActionState::Failure;
+                }
+            }
+            ActionState::Cancelled => {
+                undock_requests.send(UndockRequestEvent {
+                    agent: *actor,
+                    station,
+                });
+                *action_state = ActionState::Failure;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Buys the goods in the agent's trade plan from the market it is docked at
+#[derive(Clone, Component, Debug, Default, ActionBuilder)]
+pub struct BuyGoods {
+    /// The order placed on the market
+    order: Option<OrderId>,
+    /// How long the order has been waiting to fill
+    waited: f32,
+}
+
+/// Places a buy order at the current asking price and waits for the goods to be delivered.
+///
+/// Once the goods are aboard the agent undocks and its plan moves on to selling.
+pub fn buy_goods_action_system(
+    time: Res<Time>,
+    items: Res<ItemRegistry>,
+    mut agents: Query<(&mut Agent, &CargoHold, &mut TradePlan, Option<&DockedAt>)>,
+    mut stations: Query<&mut Station>,
+    mut undock_requests: EventWriter<UndockRequestEvent>,
+    mut action_query: Query<(&Actor, &mut ActionState, &mut BuyGoods, &ActionSpan)>,
+) {
+    for (Actor(actor), mut action_state, mut buy, span) in &mut action_query {
+        let _guard = span.span().enter();
+        let Ok((mut agent, hold, mut plan, docked_at)) = agents.get_mut(*actor) else {
+            *action_state = ActionState::Failure;
+            continue;
+        };
+        let station_entity = plan.current_stop().station;
+        let Ok(mut station) = stations.get_mut(station_entity) else {
+            *action_state = ActionState::Failure;
+            continue;
+        };
+        let Some(market) = station.market_mut() else {
+            *action_state = ActionState::Failure;
+            continue;
+        };
+        let mut undock = || {
+            undock_requests.send(UndockRequestEvent {
+                agent: *actor,
+                station: station_entity,
+            })
+        };
+
+        match *action_state {
+            ActionState::Requested => {
+                if plan.stage != TradeStage::Buying {
+                    *action_state = ActionState::Success;
+                    continue;
+                }
+                if docked_at.map(|docked_at| docked_at.0) != Some(station_entity) {
+                    *action_state = ActionState::Failure;
+                    continue;
+                }
+
+                let (Some(item), Some(ask)) = (items.get(plan.item), market.best_ask(plan.item))
+                else {
+                    undock();
+                    *action_state = ActionState::Failure;
+                    continue;
+                };
+                let limit = ask.price * (1.0 + PRICE_SLIPPAGE);
+                let quantity = plan
+                    .quantity
+                    .min(ask.quantity)
+                    .min(hold.contents().room_for(item))
+                    .min((agent.wallet.money / limit).floor() as u32);
+
+                match market.place_buy_order(*actor, plan.item, quantity, limit, &mut agent.wallet)
+                {
+                    Ok(order) => {
+                        buy.order = Some(order);
+                        buy.waited = 0.0;
+                        *action_state = ActionState::Executing;
+                    }
+                    Err(error) => {
+                        debug!("Could not place buy order: {}", error);
+                        undock();
+                        *action_state = ActionState::Failure;
+                    }
+                }
+            }
+            ActionState::Executing => {
+                buy.waited += time.delta_seconds();
+                let open = buy
+                    .order
+                    .is_some_and(|order| market.orders().any(|open| open.id == order));
+                let held = hold.quantity(plan.item);
+
+                if !open && held > 0 {
+                    plan.stage = TradeStage::Selling;
+                    plan.quantity = held;
+                    undock();
+                    *action_state = ActionState::Success;
+                } else if buy.waited > ORDER_TIMEOUT {
+                    if let Some(order) = buy.order.take() {
+                        market.cancel_order(order);
+                    }
+                    undock();
+                    *action_state = ActionState::Failure;
+                }
+            }
+            ActionState::Cancelled => {
+                if let Some(order) = buy.order.take() {
+                    market.cancel_order(order);
+                }
+                undock();
+                *action_state = ActionState::Failure;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Sells the goods in the agent's trade plan to the market it is docked at
+#[derive(Clone, Component, Debug, Default, ActionBuilder)]
+pub struct SellGoods {
+    /// The order placed on the market
+    order: Option<OrderId>,
+    /// How long the order has been waiting to fill
+    waited: f32,
+}
+
+/// Places a sell order at the current bid for everything held and waits for it to fill.
+///
+/// The trade run is over once the order fills, and the agent undocks and drops its plan.
+pub fn sell_goods_action_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut agents: Query<(&mut Agent, &mut CargoHold, &TradePlan, Option<&DockedAt>)>,
+    mut stations: Query<&mut Station>,
+    mut undock_requests: EventWriter<UndockRequestEvent>,
+    mut action_query: Query<(&Actor, &mut ActionState, &mut SellGoods, &ActionSpan)>,
+) {
+    for (Actor(actor), mut action_state, mut sell, span) in &mut action_query {
+        let _guard = span.span().enter();
+        let Ok((mut agent, mut hold, plan, docked_at)) = agents.get_mut(*actor) else {
+            *action_state = ActionState::Failure;
+            continue;
+        };
+        let station_entity = plan.sell_to.station;
+        let Ok(mut station) = stations.get_mut(station_entity) else {
+            *action_state = ActionState::Failure;
+            continue;
+        };
+        let Some(market) = station.market_mut() else {
+            *action_state = ActionState::Failure;
+            continue;
+        };
+        let mut undock = || {
+            undock_requests.send(UndockRequestEvent {
+                agent: *actor,
+                station: station_entity,
+            })
+        };
+
+        match *action_state {
+            ActionState::Requested => {
+                if docked_at.map(|docked_at| docked_at.0) != Some(station_entity) {
+                    *action_state = ActionState::Failure;
+                    continue;
+                }
+
+                let quantity = hold.quantity(plan.item);
+                let Some(bid) = market.best_bid(plan.item).filter(|_| quantity > 0) else {
+                    undock();
+                    *action_state = ActionState::Failure;
+                    continue;
+                };
+                let price = bid.price * (1.0 - PRICE_SLIPPAGE);
+
+                match market.place_sell_order(*actor, plan.item, quantity, price, &mut hold) {
+                    Ok(order) => {
+                        sell.order = Some(order);
+                        sell.waited = 0.0;
+                        *action_state = ActionState::Executing;
+                    }
+                    Err(error) => {
+                        debug!("Could not place sell order: {}", error);
+                        undock();
+                        *action_state = ActionState::Failure;
+                    }
+                }
+            }
+            ActionState::Executing => {
+                sell.waited += time.delta_seconds();
+                let open = sell
+                    .order
+                    .is_some_and(|order| market.orders().any(|open| open.id == order));
+
+                if !open {
+                    agent.current_goal.goal = None;
+                    commands.entity(*actor).remove::<TradePlan>();
+                    undock();
+                    *action_state = ActionState::Success;
+                } else if sell.waited > ORDER_TIMEOUT {
+                    if let Some(order) = sell.order.take() {
+                        market.cancel_order(order);
+                    }
+                    undock();
+                    *action_state = ActionState::Failure;
+                }
+            }
+            ActionState::Cancelled => {
+                if let Some(order) = sell.order.take() {
+                    market.cancel_order(order);
+                }
+                undock();
+                *action_state = ActionState::Failure;
+            }
+            _ => {}
+        }
+    }
+}
@@ -1,7 +1,188 @@
+use std::collections::VecDeque;
+use std::fmt;
+
 use bevy::prelude::*;
-use rand::Rng;
 use serde::{Deserialize, Serialize};
 
+use crate::simulation::SimulationClock;
+
+use super::attributes::{Attributes, FactionID};
+
+/// The most transactions a bank's ledger remembers, older ones are forgotten first
+pub const LEDGER_CAPACITY: usize = 4096;
+/// The number of repayments in a row a faction can miss before its loan is in default
+pub const MAX_MISSED_REPAYMENTS: u32 = 3;
+/// How long a faction is refused new loans after defaulting on one, in seconds
+pub const DEFAULT_CREDIT_BAN: f64 = 600.0;
+
+/// What a transaction in a bank's ledger was for
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TransactionKind {
+    /// Money paid in with no particular purpose
+    Deposit,
+    /// Money taken out with no particular purpose
+    Withdrawal,
+    /// Money moved between the bank and the faction's markets
+    Trade,
+    /// A fee paid for a service, such as docking
+    Fee,
+    /// A tax levied by the faction
+    Tax,
+    /// Money lent to the faction
+    Loan,
+    /// Money paid back against a loan
+    Repayment,
+    /// Money spent building stations
+    Construction,
+}
+
+/// A single movement of money in or out of a bank
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Transaction {
+    /// What the money was for
+    pub kind: TransactionKind,
+    /// The money moved, positive when paid in and negative when paid out
+    pub amount: i64,
+    /// When the transaction happened, in seconds of simulation time
+    pub timestamp: f64,
+}
+
+impl Transaction {
+    /// Whether the transaction paid money into the bank
+    pub fn is_income(&self) -> bool {
+        self.amount > 0
+    }
+}
+
+/// Identifies a loan within a bank
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct LoanId(pub u32);
+
+/// The terms a loan is offered on
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LoanTerms {
+    /// The interest added to the outstanding debt each period, as a fraction
+    pub interest_rate: f32,
+    /// The number of repayments the loan is paid back over
+    pub repayments: u32,
+    /// The time between repayments, in seconds
+    pub period: f64,
+}
+
+impl LoanTerms {
+    /// The repayment that pays off a loan of the given size over the term, interest included
+    pub fn repayment_for(&self, principal: u32) -> u32 {
+        let repayments = self.repayments.max(1) as f64;
+        let rate = self.interest_rate as f64;
+        let repayment = if rate > 0.0 {
+            principal as f64 * rate / (1.0 - (1.0 + rate).powf(-repayments))
+        } else {
+            principal as f64 / repayments
+        };
+        repayment.ceil() as u32
+    }
+}
+
+/// A loan the faction is paying back
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Loan {
+    /// The ID of the loan
+    pub id: LoanId,
+    /// The money originally lent
+    pub principal: u32,
+    /// The money still owed, including interest added so far
+    pub outstanding: u32,
+    /// The terms the loan was taken on
+    pub terms: LoanTerms,
+    /// The money due at each repayment
+    pub repayment: u32,
+    /// When the next repayment is due, in seconds of simulation time
+    pub next_due: f64,
+    /// The number of repayments missed in a row
+    pub missed_repayments: u32,
+}
+
+/// What happened to a loan when it was serviced
+#[derive(Clone, Debug, PartialEq)]
+pub enum LoanOutcome {
+    /// A repayment was made and the loan is paid off
+    Repaid(LoanId),
+    /// A repayment couldn't be made
+    Missed {
+        /// The loan that wasn't paid
+        loan: LoanId,
+        /// The number of repayments missed in a row
+        missed: u32,
+    },
+    /// Too many repayments were missed and the lender seized what it could
+    Defaulted {
+        /// The loan in default
+        loan: LoanId,
+        /// The money seized from the bank
+        seized: u32,
+        /// The debt written off after the seizure
+        written_off: u32,
+    },
+}
+
+/// Errors that can occur when moving money through a bank
+#[derive(Clone, Debug, PartialEq)]
+pub enum BankError {
+    /// The bank doesn't hold enough money
+    InsufficientFunds {
+        /// The money needed
+        required: u32,
+        /// The money held
+        available: u32,
+    },
+    /// The faction defaulted on a loan recently, so it can't borrow
+    CreditRefused {
+        /// When the faction can borrow again, in seconds of simulation time
+        until: f64,
+    },
+    /// The bank has no loan with the given ID
+    UnknownLoan(LoanId),
+    /// The loan terms have no time between repayments
+    InvalidTerms,
+    /// The amount moved was zero
+    ZeroAmount,
+    /// The balance can't hold that much money
+    Overflow {
+        /// The money paid in
+        amount: u32,
+        /// The money held
+        balance: u32,
+    },
+}
+
+impl fmt::Display for BankError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InsufficientFunds {
+                required,
+                available,
+            } => write!(
+                f,
+                "insufficient funds, {} needed but only {} held",
+                required, available
+            ),
+            Self::CreditRefused { until } => {
+                write!(f, "credit refused after a default until {:.0}s", until)
+            }
+            Self::UnknownLoan(id) => write!(f, "unknown loan {}", id.0),
+            Self::InvalidTerms => write!(f, "loan terms must have a period greater than zero"),
+            Self::ZeroAmount => write!(f, "amount must be greater than zero"),
+            Self::Overflow { amount, balance } => write!(
+                f,
+                "a balance of {} can't take another {} without overflowing",
+                balance, amount
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BankError {}
+
 /// The factions bank balance
 #[derive(Component, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Bank {
@@ -19,17 +200,37 @@ pub struct Bank {
 
     /// The total loans repaid by the faction
     pub total_loans_repaid: u32,
+
+    /// The most recent transactions, oldest first
+    #[serde(default)]
+    ledger: VecDeque<Transaction>,
+
+    /// The loans still being paid back
+    #[serde(default)]
+    loans: Vec<Loan>,
+
+    /// The ID the next loan will be given
+    #[serde(default)]
+    next_loan_id: u32,
+
+    /// When the faction can borrow again after a default, in seconds of simulation time
+    #[serde(default)]
+    credit_refused_until: Option<f64>,
 }
 
 impl Bank {
-    /// create a new bank with a random balance between 1000000 and 2000000
-    pub fn randomized() -> Self {
+    /// Creates a bank holding the given balance, with no history
+    pub fn new(balance: u32) -> Self {
         Self {
-            balance: rand::thread_rng().gen_range(1000000..2000000),
+            balance,
             total_deposits: 0,
             total_withdrawals: 0,
             total_loans: 0,
             total_loans_repaid: 0,
+            ledger: VecDeque::new(),
+            loans: Vec::new(),
+            next_loan_id: 0,
+            credit_refused_until: None,
         }
     }
 
@@ -38,15 +239,261 @@ impl Bank {
         self.balance
     }
 
-    /// Deposit money into the bank
-    pub fn deposit(&mut self, amount: u32) {
-        self.balance += amount;
-        self.total_deposits += amount;
+    /// Deposit money into the bank, failing without changing anything if the balance would overflow
+    pub fn deposit(
+        &mut self,
+        kind: TransactionKind,
+        amount: u32,
+        now: f64,
+    ) -> Result<(), BankError> {
+        self.credit(kind, amount, now)?;
+        self.total_deposits = self.total_deposits.saturating_add(amount);
+        Ok(())
+    }
+
+    /// Withdraw money from the bank, failing without changing anything if there isn't enough
+    pub fn withdraw(
+        &mut self,
+        kind: TransactionKind,
+        amount: u32,
+        now: f64,
+    ) -> Result<(), BankError> {
+        self.debit(kind, amount, now)?;
+        self.total_withdrawals = self.total_withdrawals.saturating_add(amount);
+        Ok(())
+    }
+
+    /// Borrows money on the given terms, with the first repayment due a period from now
+    pub fn take_loan(
+        &mut self,
+        principal: u32,
+        terms: LoanTerms,
+        now: f64,
+    ) -> Result<LoanId, BankError> {
+        if principal == 0 {
+            return Err(BankError::ZeroAmount);
+        }
+        if !(terms.period.is_finite() && terms.period > 0.0) {
+            return Err(BankError::InvalidTerms);
+        }
+        if let Some(until) = self.credit_refused_until.filter(|until| now < *until) {
+            return Err(BankError::CreditRefused { until });
+        }
+
+        self.credit(TransactionKind::Loan, principal, now)?;
+        self.total_loans = self.total_loans.saturating_add(principal);
+        let id = LoanId(self.next_loan_id);
+        self.next_loan_id += 1;
+        self.loans.push(Loan {
+            id,
+            principal,
+            outstanding: principal,
+            terms,
+            repayment: terms.repayment_for(principal),
+            next_due: now + terms.period,
+            missed_repayments: 0,
+        });
+        Ok(id)
     }
 
-    /// Withdraw money from the bank
-    pub fn withdraw(&mut self, amount: u32) {
+    /// Pays money off a loan ahead of schedule, returning the money actually paid.
+    ///
+    /// No more than the outstanding debt is taken, and a loan paid off in full is closed.
+    pub fn repay(&mut self, loan: LoanId, amount: u32, now: f64) -> Result<u32, BankError> {
+        let index = self
+            .loans
+            .iter()
+            .position(|candidate| candidate.id == loan)
+            .ok_or(BankError::UnknownLoan(loan))?;
+        let amount = amount.min(self.loans[index].outstanding);
+        if amount == 0 {
+            return Err(BankError::ZeroAmount);
+        }
+
+        self.debit(TransactionKind::Repayment, amount, now)?;
+        self.total_loans_repaid = self.total_loans_repaid.saturating_add(amount);
+        self.loans[index].outstanding -= amount;
+        if self.loans[index].outstanding == 0 {
+            self.loans.remove(index);
+        }
+        Ok(amount)
+    }
+
+    /// Makes every repayment that has fallen due, adding interest first.
+    ///
+    /// A missed repayment still has its interest added, and a loan with too many missed repayments in a row is in default:
+    /// the lender seizes what it can, writes off the rest and refuses the faction credit for a while.
+    pub fn service_loans(&mut self, now: f64) -> Vec<LoanOutcome> {
+        let mut outcomes = Vec::new();
+        let mut index = 0;
+
+        while index < self.loans.len() {
+            let loan = &mut self.loans[index];
+            if loan.next_due > now {
+                index += 1;
+                continue;
+            }
+            let interest = (loan.outstanding as f64 * loan.terms.interest_rate as f64).round();
+            loan.outstanding = loan.outstanding.saturating_add(interest as u32);
+            loan.next_due += loan.terms.period;
+            let id = loan.id;
+            let due = loan.repayment.min(loan.outstanding);
+
+            if self.debit(TransactionKind::Repayment, due, now).is_ok() {
+                self.total_loans_repaid = self.total_loans_repaid.saturating_add(due);
+                let loan = &mut self.loans[index];
+                loan.outstanding -= due;
+                loan.missed_repayments = 0;
+                if loan.outstanding == 0 {
+                    self.loans.remove(index);
+                    outcomes.push(LoanOutcome::Repaid(id));
+                }
+                // A loan that isn't closed may have more repayments due, so look at it again
+                continue;
+            }
+
+            let loan = &mut self.loans[index];
+            loan.missed_repayments += 1;
+            if loan.missed_repayments < MAX_MISSED_REPAYMENTS {
+                outcomes.push(LoanOutcome::Missed {
+                    loan: id,
+                    missed: loan.missed_repayments,
+                });
+                continue;
+            }
+
+            let outstanding = loan.outstanding;
+            self.loans.remove(index);
+            let seized = self.balance.min(outstanding);
+            if seized > 0 {
+                self.debit(TransactionKind::Repayment, seized, now)
+                    .expect("no more than the balance is seized");
+                self.total_loans_repaid = self.total_loans_repaid.saturating_add(seized);
+            }
+            self.credit_refused_until = Some(now + DEFAULT_CREDIT_BAN);
+            outcomes.push(LoanOutcome::Defaulted {
+                loan: id,
+                seized,
+                written_off: outstanding - seized,
+            });
+        }
+
+        outcomes
+    }
+
+    /// The loans still being paid back
+    pub fn loans(&self) -> &[Loan] {
+        &self.loans
+    }
+
+    /// The money owed across every loan
+    pub fn outstanding_debt(&self) -> u64 {
+        self.loans.iter().map(|loan| loan.outstanding as u64).sum()
+    }
+
+    /// The transactions the ledger remembers, oldest first
+    pub fn transactions(&self) -> impl Iterator<Item = &Transaction> {
+        self.ledger.iter()
+    }
+
+    /// The transactions made from `start` up to but not including `end`, in seconds of simulation time
+    pub fn transactions_between(&self, start: f64, end: f64) -> impl Iterator<Item = &Transaction> {
+        self.ledger
+            .iter()
+            .filter(move |transaction| (start..end).contains(&transaction.timestamp))
+    }
+
+    /// The money paid in from `start` up to but not including `end`
+    pub fn income_between(&self, start: f64, end: f64) -> u64 {
+        self.transactions_between(start, end)
+            .filter(|transaction| transaction.is_income())
+            .map(|transaction| transaction.amount.unsigned_abs())
+            .sum()
+    }
+
+    /// The money paid out from `start` up to but not including `end`
+    pub fn expenses_between(&self, start: f64, end: f64) -> u64 {
+        self.transactions_between(start, end)
+            .filter(|transaction| !transaction.is_income())
+            .map(|transaction| transaction.amount.unsigned_abs())
+            .sum()
+    }
+
+    /// The money of one kind moved from `start` up to but not including `end`, paid in minus paid out
+    pub fn net_between(&self, kind: TransactionKind, start: f64, end: f64) -> i64 {
+        self.transactions_between(start, end)
+            .filter(|transaction| transaction.kind == kind)
+            .map(|transaction| transaction.amount)
+            .sum()
+    }
+
+    /// Pays money in and records it in the ledger, if the balance can hold it
+    fn credit(&mut self, kind: TransactionKind, amount: u32, now: f64) -> Result<(), BankError> {
+        self.balance = self
+            .balance
+            .checked_add(amount)
+            .ok_or(BankError::Overflow {
+                amount,
+                balance: self.balance,
+            })?;
+        self.record(kind, amount as i64, now);
+        Ok(())
+    }
+
+    /// Takes money out and records it in the ledger, if there is enough
+    fn debit(&mut self, kind: TransactionKind, amount: u32, now: f64) -> Result<(), BankError> {
+        if amount > self.balance {
+            return Err(BankError::InsufficientFunds {
+                required: amount,
+                available: self.balance,
+            });
+        }
         self.balance -= amount;
-        self.total_withdrawals += amount;
+        self.record(kind, -(amount as i64), now);
+        Ok(())
+    }
+
+    /// Adds a transaction to the ledger, forgetting the oldest if it is full
+    fn record(&mut self, kind: TransactionKind, amount: i64, timestamp: f64) {
+        if amount == 0 {
+            return;
+        }
+        if self.ledger.len() == LEDGER_CAPACITY {
+            self.ledger.pop_front();
+        }
+        self.ledger.push_back(Transaction {
+            kind,
+            amount,
+            timestamp,
+        });
+    }
+}
+
+/// Sent when servicing a faction's loans pays one off, misses a repayment or puts one in default
+#[derive(Event, Debug, Clone)]
+pub struct LoanServicedEvent {
+    /// The faction that took the loan
+    pub faction: FactionID,
+    /// What happened to the loan
+    pub outcome: LoanOutcome,
+}
+
+/// Makes the loan repayments that have fallen due in every faction's bank
+pub fn service_faction_loans(
+    clock: Res<SimulationClock>,
+    mut factions: Query<(&Attributes, &mut Bank)>,
+    mut events: EventWriter<LoanServicedEvent>,
+) {
+    for (attributes, mut bank) in factions.iter_mut() {
+        if bank.loans.is_empty() {
+            continue;
+        }
+        for outcome in bank.service_loans(clock.now()) {
+            debug!("{} loan serviced: {:?}", attributes.name, outcome);
+            events.send(LoanServicedEvent {
+                faction: attributes.id,
+                outcome,
+            });
+        }
     }
 }
@@ -1,8 +1,247 @@
+use std::collections::HashMap;
+
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
+use crate::agent::agent::{Agent, AgentRole};
+use crate::simulation::SimulationClock;
 use crate::solar_system::SolarSystem;
+use crate::structures::station::Station;
+
+use super::attributes::{Attributes, FactionID};
+use super::diplomoacy::{Diplomacy, DiplomaticState};
+use super::fleets::FleetMember;
+use super::infrastructure::ConstructionSite;
+
+/// The most control points a faction can hold in a system
+pub const MAX_CONTROL: f32 = 100.0;
+/// The control points a challenger needs, and more than the owner has, to take a system
+pub const CAPTURE_THRESHOLD: f32 = 60.0;
+/// How often control is updated, in seconds of simulation time
+const CONTROL_INTERVAL: f64 = 10.0;
+/// The control points each unit of presence is worth per second
+const CONTROL_RATE: f32 = 0.05;
+/// The presence a station lends the faction that owns its system
+const STATION_PRESENCE: f32 = 3.0;
+/// The presence of a construction site
+const CONSTRUCTION_PRESENCE: f32 = 1.0;
+/// The presence of a defender or fleet member
+const DEFENDER_PRESENCE: f32 = 1.0;
+/// The presence of any other agent
+const CIVILIAN_PRESENCE: f32 = 0.1;
+/// How much presence a challenger loses each second once it has left a system
+const ABANDONED_DECAY: f32 = 1.0;
+
+/// The control points each faction has built up in a solar system
+#[derive(Component, Reflect, Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[reflect(Component)]
+pub struct SystemControl {
+    /// Points for each faction with any, in the order they first gained them
+    points: Vec<(FactionID, f32)>,
+}
+
+impl SystemControl {
+    /// Control of a system held entirely by its owner
+    pub fn held_by(owner: FactionID) -> Self {
+        Self {
+            points: vec![(owner, MAX_CONTROL)],
+        }
+    }
+
+    /// The points a faction holds
+    pub fn points(&self, faction: FactionID) -> f32 {
+        self.points
+            .iter()
+            .find(|(id, _)| *id == faction)
+            .map_or(0.0, |(_, points)| *points)
+    }
+
+    /// Every faction with points, and how many they hold
+    pub fn iter(&self) -> impl Iterator<Item = (FactionID, f32)> + '_ {
+        self.points.iter().copied()
+    }
+
+    /// Whether any faction but the owner holds points
+    pub fn is_contested(&self, owner: FactionID) -> bool {
+        self.points.iter().any(|(id, _)| *id != owner)
+    }
+
+    /// Adds to a faction's points, keeping them between zero and [`MAX_CONTROL`]
+    pub fn add(&mut self, faction: FactionID, amount: f32) {
+        match self.points.iter_mut().find(|(id, _)| *id == faction) {
+            Some((_, points)) => *points = (*points + amount).clamp(0.0, MAX_CONTROL),
+            None => self.points.push((faction, amount.clamp(0.0, MAX_CONTROL))),
+        }
+    }
+
+    /// The challenger that has taken the system from its owner, if one has
+    pub fn captor(&self, owner: FactionID) -> Option<FactionID> {
+        let defence = self.points(owner);
+        self.points
+            .iter()
+            .filter(|(id, points)| {
+                *id != owner && *points >= CAPTURE_THRESHOLD && *points > defence
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(id, _)| *id)
+    }
+
+    /// Moves each faction's points on by its presence in the system, over the given number of seconds.
+    ///
+    /// Presence builds up points and hostile presence wears them down. A challenger that has left loses its points,
+    /// but the owner keeps its hold on a system until someone hostile turns up.
+    pub fn update(
+        &mut self,
+        owner: FactionID,
+        presence: &HashMap<FactionID, f32>,
+        diplomacy: &Diplomacy,
+        seconds: f32,
+    ) {
+        let mut factions: Vec<FactionID> = self.points.iter().map(|(id, _)| *id).collect();
+        for faction in presence.keys() {
+            if !factions.contains(faction) {
+                factions.push(*faction);
+            }
+        }
+
+        for faction in factions {
+            let own = presence.get(&faction).copied().unwrap_or_default();
+            let opposition: f32 = presence
+                .iter()
+                .filter(|(other, _)| {
+                    **other != faction
+                        && diplomacy.state(faction, **other) <= DiplomaticState::Hostile
+                })
+                .map(|(_, presence)| presence)
+                .sum();
+            let abandoned = if faction != owner && own == 0.0 {
+                ABANDONED_DECAY
+            } else {
+                0.0
+            };
+            self.add(
+                faction,
+                (own - opposition - abandoned) * CONTROL_RATE * seconds,
+            );
+        }
 
-use super::attributes::Attributes;
+        self.points
+            .retain(|(id, points)| *id == owner || *points > 0.0);
+    }
+}
+
+/// Sent when a faction takes a solar system from another
+#[derive(Event, Debug, Clone, Copy)]
+pub struct OwnershipChangedEvent {
+    /// The system's entity
+    pub system: Entity,
+    /// The system's ID
+    pub system_id: u32,
+    /// The faction that lost the system
+    pub old: FactionID,
+    /// The faction that took it
+    pub new: FactionID,
+}
+
+/// The presence each faction has in each system, keyed by system ID.
+///
+/// Stations count for the system's owner, construction sites for the faction building them,
+/// and agents for the faction they work for, with defenders and fleet members counting the most.
+/// Pirates raid systems but never hold them, so they have no presence.
+pub fn measure_presence<'a>(
+    owners: &HashMap<u32, FactionID>,
+    stations: impl Iterator<Item = &'a Station>,
+    sites: impl Iterator<Item = &'a ConstructionSite>,
+    agents: impl Iterator<Item = (&'a Agent, Option<&'a AgentRole>, Option<&'a FleetMember>)>,
+) -> HashMap<u32, HashMap<FactionID, f32>> {
+    let mut presence: HashMap<u32, HashMap<FactionID, f32>> = HashMap::new();
+    let mut add_presence = |system_id: u32, faction: FactionID, amount: f32| {
+        *presence
+            .entry(system_id)
+            .or_default()
+            .entry(faction)
+            .or_default() += amount;
+    };
+    for station in stations {
+        if let Some(owner) = owners.get(&station.system_id) {
+            add_presence(station.system_id, *owner, STATION_PRESENCE);
+        }
+    }
+    for site in sites {
+        add_presence(site.system_id, site.owner, CONSTRUCTION_PRESENCE);
+    }
+    for (agent, role, membership) in agents {
+        let amount = match role {
+            Some(AgentRole::Pirate) => continue,
+            Some(AgentRole::Defender) => DEFENDER_PRESENCE,
+            _ if membership.is_some() => DEFENDER_PRESENCE,
+            _ => CIVILIAN_PRESENCE,
+        };
+        add_presence(agent.current_system.attributes.id, agent.faction, amount);
+    }
+    presence
+}
+
+/// Gives every solar system without control points, whether just generated or loaded from an older save, to its owner
+pub fn establish_system_control(
+    mut commands: Commands,
+    systems: Query<(Entity, &SolarSystem), Without<SystemControl>>,
+) {
+    for (entity, system) in systems.iter() {
+        commands
+            .entity(entity)
+            .insert(SystemControl::held_by(system.attributes.owner));
+    }
+}
+
+/// Builds up each faction's control of the systems it has a presence in, and hands systems over when they are captured.
+///
+/// See [`measure_presence`] for what counts toward a faction's presence.
+pub fn update_system_control(
+    mut last_update: Local<f64>,
+    clock: Res<SimulationClock>,
+    diplomacy: Res<Diplomacy>,
+    mut systems: Query<(Entity, &mut SolarSystem, &mut SystemControl)>,
+    stations: Query<&Station>,
+    sites: Query<&ConstructionSite>,
+    agents: Query<(&Agent, Option<&AgentRole>, Option<&FleetMember>)>,
+    mut changes: EventWriter<OwnershipChangedEvent>,
+) {
+    let now = clock.now();
+    if now - *last_update < CONTROL_INTERVAL {
+        return;
+    }
+    // The first update of a session doesn't count the time played before it started
+    let elapsed = (now - *last_update).min(CONTROL_INTERVAL * 2.0) as f32;
+    *last_update = now;
+
+    let owners: HashMap<u32, FactionID> = systems
+        .iter()
+        .map(|(_, system, _)| (system.attributes.id, system.attributes.owner))
+        .collect();
+    let presence = measure_presence(&owners, stations.iter(), sites.iter(), agents.iter());
+
+    let empty = HashMap::new();
+    for (entity, mut system, mut control) in systems.iter_mut() {
+        let old = system.attributes.owner;
+        let presence = presence.get(&system.attributes.id).unwrap_or(&empty);
+        control.update(old, presence, &diplomacy, elapsed);
+
+        if let Some(new) = control.captor(old) {
+            info!(
+                "System {} was captured by faction {} from faction {}",
+                system.attributes.name, new.id, old.id
+            );
+            system.update_system_owner(new);
+            changes.send(OwnershipChangedEvent {
+                system: entity,
+                system_id: system.attributes.id,
+                old,
+                new,
+            });
+        }
+    }
+}
 
 /// Detects when a system has changed owner and updates the color of the material on the entity to the color of the faction
 pub fn owner_changed_system(
@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use super::attributes::FactionID;
+use super::goals::FactionGoals;
+use super::pirates::PIRATES;
+use super::taxes::{TaxPolicy, MAX_TAX_RATE};
+use super::{FactionBundle, FactionResourse};
+
+/// The faction definitions shipped with the game, used when the asset pipeline is not running.
+const DEFAULT_FACTIONS: &str = include_str!("../../../ascendancy_game/assets/ron/factions.ron");
+
+/// The factions defined in a `*.factions.ron` file.
+#[derive(Asset, TypePath, Deserialize, Debug, Clone)]
+pub struct FactionDefinitions(pub Vec<FactionBundle>);
+
+/// A problem with one of the faction definitions
+#[derive(Debug, Clone, PartialEq)]
+pub enum FactionDefinitionError {
+    /// The file could not be parsed
+    Malformed(String),
+    /// The file does not define any factions
+    NoFactions,
+    /// Two factions share the same ID
+    DuplicateId {
+        /// The shared ID
+        id: FactionID,
+        /// The faction that was kept
+        first: String,
+        /// The faction that was skipped
+        second: String,
+    },
+    /// A faction uses the ID reserved for the pirates
+    ReservedId {
+        /// The faction using the reserved ID
+        name: String,
+    },
+    /// A faction's color has a channel that is not a number between 0 and 1
+    InvalidColor {
+        /// The faction with the invalid color
+        name: String,
+        /// The offending channels, as red, green, blue and alpha
+        channels: [f32; 4],
+    },
+    /// Two factions share the same color, so they can't be told apart on the map
+    DuplicateColor {
+        /// The faction that was kept
+        first: String,
+        /// The faction that was skipped
+        second: String,
+    },
+    /// A faction's tax rate is negative or above the maximum
+    InvalidTaxRate {
+        /// The faction with the invalid taxes
+        name: String,
+        /// The offending taxes
+        taxes: TaxPolicy,
+    },
+    /// A faction's goal weight is negative or not a number
+    InvalidGoalWeights {
+        /// The faction with the invalid weights
+        name: String,
+        /// The offending weights
+        goals: FactionGoals,
+    },
+}
+
+impl fmt::Display for FactionDefinitionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Malformed(error) => write!(f, "malformed faction definitions: {}", error),
+            Self::NoFactions => write!(f, "no factions are defined"),
+            Self::DuplicateId { id, first, second } => write!(
+                f,
+                "faction '{}' uses id {} which is already used by '{}'",
+                second, id.id, first
+            ),
+            Self::ReservedId { name } => write!(
+                f,
+                "faction '{}' uses id {} which is reserved for the pirates",
+                name, PIRATES.id
+            ),
+            Self::InvalidColor { name, channels } => write!(
+                f,
+                "faction '{}' has color {:?}, every channel must be between 0.0 and 1.0",
+                name, channels
+            ),
+            Self::DuplicateColor { first, second } => {
+                write!(f, "faction '{}' uses the same color as '{}'", second, first)
+            }
+            Self::InvalidTaxRate { name, taxes } => write!(
+                f,
+                "faction '{}' has taxes {:?}, every rate must be between 0.0 and {}",
+                name, taxes, MAX_TAX_RATE
+            ),
+            Self::InvalidGoalWeights { name, goals } => write!(
+                f,
+                "faction '{}' has goals {:?}, every weight must be a number no less than 0.0",
+                name, goals
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FactionDefinitionError {}
+
+impl FactionDefinitions {
+    /// Parses faction definitions from the contents of a RON file.
+    pub fn from_ron(source: &str) -> Result<Self, FactionDefinitionError> {
+        ron::from_str(source).map_err(|error| FactionDefinitionError::Malformed(error.to_string()))
+    }
+
+    /// Checks every faction, returning the ones that are valid and an error for each one that is not.
+    ///
+    /// Factions are checked in file order, so when two factions clash the first one is kept.
+    pub fn validate(self) -> (Vec<FactionBundle>, Vec<FactionDefinitionError>) {
+        let mut valid: Vec<FactionBundle> = Vec::new();
+        let mut errors = Vec::new();
+        let mut ids: HashMap<FactionID, String> = HashMap::new();
+
+        for faction in self.0 {
+            let attributes = &faction.faction_attributes;
+            let channels = attributes.colors.as_rgba_f32();
+
+            if let Some(first) = ids.get(&attributes.id) {
+                errors.push(FactionDefinitionError::DuplicateId {
+                    id: attributes.id,
+                    first: first.clone(),
+                    second: attributes.name.clone(),
+                });
+            } else if attributes.id == PIRATES {
+                errors.push(FactionDefinitionError::ReservedId {
+                    name: attributes.name.clone(),
+                });
+            } else if channels
+                .iter()
+                .any(|channel| !(0.0..=1.0).contains(channel))
+            {
+                errors.push(FactionDefinitionError::InvalidColor {
+                    name: attributes.name.clone(),
+                    channels,
+                });
+            } else if let Some(first) = valid
+                .iter()
+                .find(|other| other.faction_attributes.colors.as_rgba_f32() == channels)
+            {
+                errors.push(FactionDefinitionError::DuplicateColor {
+                    first: first.faction_attributes.name.clone(),
+                    second: attributes.name.clone(),
+                });
+            } else if !faction.faction_taxes.is_valid() {
+                errors.push(FactionDefinitionError::InvalidTaxRate {
+                    name: attributes.name.clone(),
+                    taxes: faction.faction_taxes,
+                });
+            } else if !faction.faction_goals.is_valid() {
+                errors.push(FactionDefinitionError::InvalidGoalWeights {
+                    name: attributes.name.clone(),
+                    goals: faction.faction_goals,
+                });
+            } else {
+                ids.insert(attributes.id, attributes.name.clone());
+                valid.push(faction);
+            }
+        }
+
+        if valid.is_empty() {
+            errors.push(FactionDefinitionError::NoFactions);
+        }
+
+        (valid, errors)
+    }
+
+    /// Validates the definitions and builds the faction resource, logging every invalid faction.
+    ///
+    /// The galaxy can't be generated without factions, so the built in ones are used if none are valid.
+    pub fn into_resource(self, source: &str) -> FactionResourse {
+        let (mut factions, errors) = self.validate();
+        for error in errors.iter() {
+            error!("Skipping faction in {}: {}", source, error);
+        }
+
+        if factions.is_empty() {
+            error!(
+                "{} does not define any valid factions, using the built in factions",
+                source
+            );
+            factions = Self::built_in().validate().0;
+        }
+
+        FactionResourse { factions }
+    }
+
+    /// The faction definitions that ship with the game.
+    pub fn built_in() -> Self {
+        Self::from_ron(DEFAULT_FACTIONS).expect("the built in faction definitions are valid")
+    }
+}
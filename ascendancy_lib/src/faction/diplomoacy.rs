@@ -0,0 +1,622 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::agent::agent::Agent;
+use crate::simulation::SimulationClock;
+use crate::solar_system::SolarSystem;
+use crate::structures::services::market::{MarketTradeEvent, Trader};
+use crate::structures::stargate::Stargate;
+use crate::structures::station::Station;
+
+use super::attributes::FactionID;
+use super::claims::OwnershipChangedEvent;
+
+/// The lowest a relation score can fall
+pub const MIN_RELATION: f32 = -100.0;
+/// The highest a relation score can rise
+pub const MAX_RELATION: f32 = 100.0;
+/// Factions whose relations fall this low go to war
+pub const WAR_THRESHOLD: f32 = -75.0;
+/// Factions at war make peace once their relations recover past this
+pub const PEACE_THRESHOLD: f32 = -40.0;
+/// Factions whose relations fall this low are hostile
+pub const HOSTILE_THRESHOLD: f32 = -25.0;
+/// The relations needed to sign a trade pact
+pub const TRADE_PACT_THRESHOLD: f32 = 10.0;
+/// The relations needed to sign an alliance
+pub const ALLIANCE_THRESHOLD: f32 = 50.0;
+
+/// The share of a relation score that fades back toward neutral each second
+const RELATION_DECAY: f32 = 0.002;
+/// The most seconds relations fade by in a single update
+const MAX_DECAY_STEP: f64 = 1.0;
+/// How often borders are checked for tension, in seconds
+const BORDER_CHECK_INTERVAL: f64 = 30.0;
+/// The relations lost for each gate linking two factions' systems, at every border check
+const BORDER_TENSION: f32 = 0.1;
+/// The relations gained for every thousand credits traded between two factions
+const TRADE_GOODWILL: f32 = 0.5;
+/// The relations lost when one faction attacks another
+const ATTACK_PENALTY: f32 = 20.0;
+/// The relations lost when one faction takes a system from another
+const CAPTURE_PENALTY: f32 = 15.0;
+
+/// How two factions stand with each other, from worst to best
+#[derive(
+    Reflect, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+pub enum DiplomaticState {
+    /// The factions are fighting, and close their gates and markets to each other
+    War,
+    /// The factions distrust each other
+    Hostile,
+    /// The factions have no strong feelings either way
+    Neutral,
+    /// The factions have signed a trade pact
+    TradePact,
+    /// The factions have signed an alliance
+    Alliance,
+}
+
+impl DiplomaticState {
+    /// Whether ships of one faction may use the stargates in the other's systems
+    pub fn allows_stargate_access(&self) -> bool {
+        *self != Self::War
+    }
+
+    /// Whether ships of one faction may dock and trade at the other's stations
+    pub fn allows_market_access(&self) -> bool {
+        *self != Self::War
+    }
+}
+
+impl fmt::Display for DiplomaticState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::War => write!(f, "War"),
+            Self::Hostile => write!(f, "Hostile"),
+            Self::Neutral => write!(f, "Neutral"),
+            Self::TradePact => write!(f, "Trade Pact"),
+            Self::Alliance => write!(f, "Alliance"),
+        }
+    }
+}
+
+/// The kinds of treaty factions can sign
+#[derive(Reflect, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TreatyKind {
+    /// Opens trade between the factions
+    TradePact,
+    /// Binds the factions together
+    Alliance,
+}
+
+impl TreatyKind {
+    /// The relations needed to sign, and keep, the treaty
+    pub fn threshold(&self) -> f32 {
+        match self {
+            Self::TradePact => TRADE_PACT_THRESHOLD,
+            Self::Alliance => ALLIANCE_THRESHOLD,
+        }
+    }
+
+    /// The state the factions are in while the treaty holds
+    pub fn state(&self) -> DiplomaticState {
+        match self {
+            Self::TradePact => DiplomaticState::TradePact,
+            Self::Alliance => DiplomaticState::Alliance,
+        }
+    }
+}
+
+/// A treaty signed between two factions
+#[derive(Reflect, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Treaty {
+    /// The kind of treaty
+    pub kind: TreatyKind,
+    /// When the treaty was signed, in simulation seconds
+    pub signed_at: f64,
+    /// When the treaty runs out, in simulation seconds
+    pub expires_at: f64,
+}
+
+/// How one pair of factions feel about each other
+#[derive(Reflect, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Relation {
+    /// The two factions, lowest ID first
+    factions: (FactionID, FactionID),
+    /// The relation score, between [`MIN_RELATION`] and [`MAX_RELATION`]
+    pub score: f32,
+    /// Whether the factions are at war
+    pub at_war: bool,
+    /// The treaty the factions have signed, if any
+    pub treaty: Option<Treaty>,
+}
+
+impl Relation {
+    /// Creates a neutral relation between two factions
+    pub fn new(a: FactionID, b: FactionID) -> Self {
+        Self {
+            factions: ordered(a, b),
+            score: 0.0,
+            at_war: false,
+            treaty: None,
+        }
+    }
+
+    /// The two factions, lowest ID first
+    pub fn factions(&self) -> (FactionID, FactionID) {
+        self.factions
+    }
+
+    /// How the factions stand with each other.
+    ///
+    /// War overrides any treaty, and a treaty overrides the score.
+    pub fn state(&self) -> DiplomaticState {
+        if self.at_war {
+            DiplomaticState::War
+        } else if let Some(treaty) = self.treaty {
+            treaty.kind.state()
+        } else if self.score <= HOSTILE_THRESHOLD {
+            DiplomaticState::Hostile
+        } else {
+            DiplomaticState::Neutral
+        }
+    }
+
+    /// Declares war or makes peace as the score crosses the thresholds, and breaks any treaty the score no longer supports
+    fn settle(&mut self) {
+        if !self.at_war && self.score <= WAR_THRESHOLD {
+            self.at_war = true;
+        } else if self.at_war && self.score > PEACE_THRESHOLD {
+            self.at_war = false;
+        }
+
+        if self.at_war
+            || self
+                .treaty
+                .is_some_and(|treaty| self.score < treaty.kind.threshold())
+        {
+            self.treaty = None;
+        }
+    }
+}
+
+/// Orders a pair of factions so each pair has one relation
+fn ordered(a: FactionID, b: FactionID) -> (FactionID, FactionID) {
+    if a.id <= b.id {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+/// Reasons a treaty can't be signed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiplomacyError {
+    /// A faction can't sign a treaty with itself
+    SameFaction,
+    /// The factions are at war
+    AtWar,
+    /// The factions don't get on well enough
+    RelationsTooLow {
+        /// The score the treaty needs
+        required: f32,
+        /// The factions' current score
+        score: f32,
+    },
+    /// Treaties must last for some time
+    InvalidDuration,
+}
+
+impl fmt::Display for DiplomacyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SameFaction => write!(f, "a faction can't sign a treaty with itself"),
+            Self::AtWar => write!(f, "the factions are at war"),
+            Self::RelationsTooLow { required, score } => write!(
+                f,
+                "the treaty needs relations of {:.0}, but they are {:.0}",
+                required, score
+            ),
+            Self::InvalidDuration => write!(f, "treaties must last a positive time"),
+        }
+    }
+}
+
+impl std::error::Error for DiplomacyError {}
+
+/// The relations between every pair of factions.
+///
+/// Pairs that have never had any dealings are neutral, and a faction is always allied with itself.
+/// Outlaws are at war with every other faction, whatever their relations.
+#[derive(Resource, Reflect, Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+#[reflect(Resource)]
+pub struct Diplomacy {
+    /// The relations between pairs of factions that have had dealings
+    relations: Vec<Relation>,
+    /// The factions every other faction is at war with
+    #[serde(default)]
+    outlaws: Vec<FactionID>,
+}
+
+impl Diplomacy {
+    /// Every relation between factions that have had dealings
+    pub fn relations(&self) -> &[Relation] {
+        &self.relations
+    }
+
+    /// The relation between two factions
+    pub fn relation(&self, a: FactionID, b: FactionID) -> Relation {
+        let factions = ordered(a, b);
+        self.relations
+            .iter()
+            .find(|relation| relation.factions == factions)
+            .copied()
+            .unwrap_or_else(|| Relation::new(a, b))
+    }
+
+    /// The relation between two factions, creating it if they have had no dealings
+    fn relation_mut(&mut self, a: FactionID, b: FactionID) -> &mut Relation {
+        let factions = ordered(a, b);
+        let index = match self
+            .relations
+            .iter()
+            .position(|relation| relation.factions == factions)
+        {
+            Some(index) => index,
+            None => {
+                self.relations.push(Relation::new(a, b));
+                self.relations.len() - 1
+            }
+        };
+        &mut self.relations[index]
+    }
+
+    /// The relation score between two factions
+    pub fn score(&self, a: FactionID, b: FactionID) -> f32 {
+        self.relation(a, b).score
+    }
+
+    /// How two factions stand with each other
+    pub fn state(&self, a: FactionID, b: FactionID) -> DiplomaticState {
+        if a == b {
+            return DiplomaticState::Alliance;
+        }
+        if self.is_outlaw(a) || self.is_outlaw(b) {
+            return DiplomaticState::War;
+        }
+        self.relation(a, b).state()
+    }
+
+    /// Puts a faction at war with every other faction for good
+    pub fn declare_outlaw(&mut self, faction: FactionID) {
+        if !self.is_outlaw(faction) {
+            self.outlaws.push(faction);
+        }
+    }
+
+    /// Whether a faction is at war with every other faction
+    pub fn is_outlaw(&self, faction: FactionID) -> bool {
+        self.outlaws.contains(&faction)
+    }
+
+    /// Whether ships of a faction may use the stargates in systems the owner holds
+    pub fn can_use_stargates(&self, traveller: FactionID, owner: FactionID) -> bool {
+        self.state(traveller, owner).allows_stargate_access()
+    }
+
+    /// Whether ships of a faction may dock and trade at stations the owner holds
+    pub fn can_trade(&self, trader: FactionID, owner: FactionID) -> bool {
+        self.state(trader, owner).allows_market_access()
+    }
+
+    /// Moves the relations between two factions by the given amount, returning the old and new state if it changed
+    pub fn shift(
+        &mut self,
+        a: FactionID,
+        b: FactionID,
+        amount: f32,
+    ) -> Option<(DiplomaticState, DiplomaticState)> {
+        if a == b {
+            return None;
+        }
+        let relation = self.relation_mut(a, b);
+        let old = relation.state();
+        relation.score = (relation.score + amount).clamp(MIN_RELATION, MAX_RELATION);
+        relation.settle();
+        let new = relation.state();
+        (old != new).then_some((old, new))
+    }
+
+    /// Signs a treaty between two factions that lasts for the given number of seconds.
+    ///
+    /// Any treaty the factions already had is replaced.
+    pub fn sign_treaty(
+        &mut self,
+        a: FactionID,
+        b: FactionID,
+        kind: TreatyKind,
+        duration: f64,
+        now: f64,
+    ) -> Result<(), DiplomacyError> {
+        if a == b {
+            return Err(DiplomacyError::SameFaction);
+        }
+        if duration <= 0.0 {
+            return Err(DiplomacyError::InvalidDuration);
+        }
+        if self.is_outlaw(a) || self.is_outlaw(b) {
+            return Err(DiplomacyError::AtWar);
+        }
+        let relation = self.relation_mut(a, b);
+        if relation.at_war {
+            return Err(DiplomacyError::AtWar);
+        }
+        if relation.score < kind.threshold() {
+            return Err(DiplomacyError::RelationsTooLow {
+                required: kind.threshold(),
+                score: relation.score,
+            });
+        }
+
+        relation.treaty = Some(Treaty {
+            kind,
+            signed_at: now,
+            expires_at: now + duration,
+        });
+        Ok(())
+    }
+
+    /// Ends any treaty between two factions
+    pub fn break_treaty(&mut self, a: FactionID, b: FactionID) {
+        if a != b {
+            self.relation_mut(a, b).treaty = None;
+        }
+    }
+
+    /// Puts two factions at war, breaking any treaty between them
+    pub fn declare_war(&mut self, a: FactionID, b: FactionID) {
+        if a != b {
+            let relation = self.relation_mut(a, b);
+            relation.at_war = true;
+            relation.treaty = None;
+        }
+    }
+
+    /// Ends a war between two factions, leaving their score as it is
+    pub fn make_peace(&mut self, a: FactionID, b: FactionID) {
+        if a != b {
+            self.relation_mut(a, b).at_war = false;
+        }
+    }
+
+    /// Fades every score back toward neutral, and ends the treaties that have run out.
+    ///
+    /// Returns each pair whose state changed, along with the old and new state.
+    pub fn update(
+        &mut self,
+        elapsed: f32,
+        now: f64,
+    ) -> Vec<(FactionID, FactionID, DiplomaticState, DiplomaticState)> {
+        let decay = (1.0 - RELATION_DECAY * elapsed).max(0.0);
+        let mut changes = Vec::new();
+        for relation in self.relations.iter_mut() {
+            let old = relation.state();
+            relation.score *= decay;
+            if relation
+                .treaty
+                .is_some_and(|treaty| treaty.expires_at <= now)
+            {
+                relation.treaty = None;
+            }
+            relation.settle();
+
+            let new = relation.state();
+            if old != new {
+                changes.push((relation.factions.0, relation.factions.1, old, new));
+            }
+        }
+        changes
+    }
+}
+
+/// Something that happened between two factions that changes how they feel about each other
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IncidentKind {
+    /// The factions' systems are linked by this many stargates
+    BorderTension {
+        /// The number of gates linking the factions' systems
+        shared_borders: u32,
+    },
+    /// The factions traded this many credits' worth of goods
+    Trade {
+        /// The value of the goods traded
+        credits: f32,
+    },
+    /// One faction attacked the other
+    Attack,
+    /// One faction took a system from the other
+    Capture,
+}
+
+impl IncidentKind {
+    /// How much the incident moves the factions' relations
+    pub fn shift(&self) -> f32 {
+        match self {
+            Self::BorderTension { shared_borders } => -BORDER_TENSION * *shared_borders as f32,
+            Self::Trade { credits } => TRADE_GOODWILL * credits / 1000.0,
+            Self::Attack => -ATTACK_PENALTY,
+            Self::Capture => -CAPTURE_PENALTY,
+        }
+    }
+}
+
+/// Sent when something happens between two factions that changes their relations
+#[derive(Event, Debug, Clone, Copy)]
+pub struct DiplomaticIncidentEvent {
+    /// The faction that caused the incident
+    pub instigator: FactionID,
+    /// The faction it happened to
+    pub target: FactionID,
+    /// What happened
+    pub kind: IncidentKind,
+}
+
+/// Sent when two factions' diplomatic state changes
+#[derive(Event, Debug, Clone, Copy)]
+pub struct DiplomaticStateChangedEvent {
+    /// One of the factions
+    pub a: FactionID,
+    /// The other faction
+    pub b: FactionID,
+    /// How they stood before
+    pub old: DiplomaticState,
+    /// How they stand now
+    pub new: DiplomaticState,
+}
+
+/// Builds tension between factions whose systems are linked by stargates.
+///
+/// Borders are checked every [`BORDER_CHECK_INTERVAL`] seconds of simulation time.
+pub fn measure_border_tension(
+    clock: Res<SimulationClock>,
+    mut last_check: Local<f64>,
+    systems: Query<&SolarSystem>,
+    gates: Query<&Stargate>,
+    mut incidents: EventWriter<DiplomaticIncidentEvent>,
+) {
+    if clock.now() - *last_check < BORDER_CHECK_INTERVAL {
+        return;
+    }
+    *last_check = clock.now();
+
+    let owners: HashMap<u32, FactionID> = systems
+        .iter()
+        .map(|system| (system.attributes.id, system.attributes.owner))
+        .collect();
+
+    // Gates come in pairs, so each link between two systems is only counted once
+    let mut links = HashSet::new();
+    let mut borders: HashMap<(FactionID, FactionID), u32> = HashMap::new();
+    for gate in gates.iter() {
+        let (from, to) = (gate.origin_system_id, gate.destination_system_id);
+        if !links.insert((from.min(to), from.max(to))) {
+            continue;
+        }
+        let (Some(a), Some(b)) = (owners.get(&from), owners.get(&to)) else {
+            continue;
+        };
+        if a != b {
+            *borders.entry(ordered(*a, *b)).or_default() += 1;
+        }
+    }
+
+    for ((a, b), shared_borders) in borders {
+        incidents.send(DiplomaticIncidentEvent {
+            instigator: a,
+            target: b,
+            kind: IncidentKind::BorderTension { shared_borders },
+        });
+    }
+}
+
+/// Builds goodwill between factions whose ships trade at each other's markets.
+///
+/// Trades between an agent and a station owned by another faction count toward their relations.
+pub fn record_trade_relations(
+    mut trades: EventReader<MarketTradeEvent>,
+    stations: Query<&Station>,
+    agents: Query<&Agent>,
+    systems: Query<&SolarSystem>,
+    mut incidents: EventWriter<DiplomaticIncidentEvent>,
+) {
+    for event in trades.read() {
+        let Some(owner) = stations.get(event.station).ok().and_then(|station| {
+            systems
+                .iter()
+                .find(|system| system.attributes.id == station.system_id)
+                .map(|system| system.attributes.owner)
+        }) else {
+            continue;
+        };
+
+        let credits = event.trade.price * event.trade.quantity as f32;
+        for trader in [event.trade.buyer, event.trade.seller] {
+            let Trader::Agent(entity) = trader else {
+                continue;
+            };
+            let Ok(agent) = agents.get(entity) else {
+                continue;
+            };
+            if agent.faction != owner {
+                incidents.send(DiplomaticIncidentEvent {
+                    instigator: agent.faction,
+                    target: owner,
+                    kind: IncidentKind::Trade { credits },
+                });
+            }
+        }
+    }
+}
+
+/// Sours relations between factions when one takes a system from the other
+pub fn record_system_captures(
+    mut captures: EventReader<OwnershipChangedEvent>,
+    mut incidents: EventWriter<DiplomaticIncidentEvent>,
+) {
+    for capture in captures.read() {
+        incidents.send(DiplomaticIncidentEvent {
+            instigator: capture.new,
+            target: capture.old,
+            kind: IncidentKind::Capture,
+        });
+    }
+}
+
+/// Applies diplomatic incidents to the factions' relations
+pub fn apply_diplomatic_incidents(
+    mut incidents: EventReader<DiplomaticIncidentEvent>,
+    mut diplomacy: ResMut<Diplomacy>,
+    mut changes: EventWriter<DiplomaticStateChangedEvent>,
+) {
+    for incident in incidents.read() {
+        if let Some((old, new)) =
+            diplomacy.shift(incident.instigator, incident.target, incident.kind.shift())
+        {
+            info!(
+                "Relations between factions {} and {} changed from {} to {}",
+                incident.instigator.id, incident.target.id, old, new
+            );
+            changes.send(DiplomaticStateChangedEvent {
+                a: incident.instigator,
+                b: incident.target,
+                old,
+                new,
+            });
+        }
+    }
+}
+
+/// Fades relations back toward neutral and ends treaties that have run out
+pub fn update_relations(
+    clock: Res<SimulationClock>,
+    mut last_update: Local<f64>,
+    mut diplomacy: ResMut<Diplomacy>,
+    mut changes: EventWriter<DiplomaticStateChangedEvent>,
+) {
+    // The first update of a session doesn't count the time played before it started
+    let elapsed = (clock.now() - *last_update).clamp(0.0, MAX_DECAY_STEP) as f32;
+    *last_update = clock.now();
+
+    for (a, b, old, new) in diplomacy.update(elapsed, clock.now()) {
+        info!(
+            "Relations between factions {} and {} changed from {} to {}",
+            a.id, b.id, old, new
+        );
+        changes.send(DiplomaticStateChangedEvent { a, b, old, new });
+    }
+}
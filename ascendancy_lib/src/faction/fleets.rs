@@ -0,0 +1,610 @@
+use std::collections::HashMap;
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use big_brain::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::agent::agent::Agent;
+use crate::agent::cargo::CargoHold;
+use crate::agent::fly_to_system_action::{follow_stargate_path, route_avoiding_wars};
+use crate::agent::pathfinding::SystemGraph;
+use crate::solar_system::SolarSystem;
+use crate::structures::stargate::Stargate;
+
+use super::attributes::FactionID;
+use super::diplomoacy::Diplomacy;
+
+/// The most agents a fleet can hold
+pub const MAX_FLEET_SIZE: usize = 12;
+/// The distance between neighbouring slots in a formation
+const FORMATION_SPACING: f32 = 6.0;
+/// How much faster than the fleet members fly when catching up with their slot
+const CATCH_UP_SPEED: f32 = 1.5;
+/// The number of slots in each ring of a ring formation
+const RING_SLOTS: usize = 8;
+
+/// The orders a fleet carries out
+#[derive(Reflect, Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub enum FleetOrder {
+    #[default]
+    /// Stay where it is
+    Hold,
+    /// Fly to a system and stay there
+    Move {
+        /// The system to fly to
+        system_id: u32,
+    },
+    /// Fly between systems in turn, starting again from the first after the last
+    Patrol {
+        /// The systems to visit
+        route: Vec<u32>,
+        /// The index in the route of the next system to visit
+        next: usize,
+    },
+    /// Stay with an agent wherever it goes
+    Escort {
+        /// The agent to escort
+        target: Entity,
+    },
+    /// Hold a system's stargates
+    Blockade {
+        /// The system to blockade
+        system_id: u32,
+    },
+}
+
+impl FleetOrder {
+    /// Describes the order for the selection panel
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Hold => "Holding position".to_string(),
+            Self::Move { system_id } => format!("Moving to system {}", system_id),
+            Self::Patrol { route, .. } => format!("Patrolling {} systems", route.len()),
+            Self::Escort { target } => format!("Escorting {:?}", target),
+            Self::Blockade { system_id } => format!("Blockading system {}", system_id),
+        }
+    }
+}
+
+/// The shape a fleet's members hold around their leader
+#[derive(Reflect, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Formation {
+    /// Members trail behind the leader in single file
+    Line,
+    #[default]
+    /// Members fan out behind the leader on either side
+    Wedge,
+    /// Members surround the leader
+    Ring,
+}
+
+impl Formation {
+    /// Where a slot sits relative to the leader, who holds slot 0
+    pub fn slot_offset(&self, slot: usize, spacing: f32) -> Vec2 {
+        if slot == 0 {
+            return Vec2::ZERO;
+        }
+        match self {
+            Self::Line => Vec2::new(-spacing * slot as f32, 0.0),
+            Self::Wedge => {
+                let rank = ((slot + 1) / 2) as f32;
+                let side = if slot % 2 == 1 { 1.0 } else { -1.0 };
+                Vec2::new(-spacing * rank, side * spacing * rank)
+            }
+            Self::Ring => {
+                let ring = (slot - 1) / RING_SLOTS + 1;
+                let angle = TAU * ((slot - 1) % RING_SLOTS) as f32 / RING_SLOTS as f32;
+                Vec2::from_angle(angle) * spacing * ring as f32
+            }
+        }
+    }
+}
+
+/// Why an agent couldn't join a fleet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FleetJoinDenial {
+    /// The fleet doesn't exist
+    NoFleet,
+    /// The agent doesn't exist
+    NoAgent,
+    /// The agent works for a different faction to the fleet's owner
+    WrongFaction,
+    /// The fleet has no room for more agents
+    FleetFull,
+}
+
+/// A group of agents owned by a faction that fly in formation and follow shared orders.
+///
+/// The first member leads the fleet, when it leaves the next member takes over.
+#[derive(Component, Reflect, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[reflect(Component)]
+pub struct Fleet {
+    /// The ID of the fleet
+    pub id: u32,
+    /// The name of the fleet
+    pub name: String,
+    /// The faction the fleet belongs to
+    pub owner: FactionID,
+    /// The orders the fleet is carrying out
+    pub order: FleetOrder,
+    /// The formation the members hold
+    pub formation: Formation,
+    /// The agents in the fleet, leader first
+    members: Vec<Entity>,
+}
+
+impl Fleet {
+    /// Creates an empty fleet holding position
+    pub fn new(id: u32, name: String, owner: FactionID) -> Self {
+        Self {
+            id,
+            name,
+            owner,
+            order: FleetOrder::Hold,
+            formation: Formation::default(),
+            members: Vec::new(),
+        }
+    }
+
+    /// The agent leading the fleet
+    pub fn leader(&self) -> Option<Entity> {
+        self.members.first().copied()
+    }
+
+    /// The agents in the fleet, leader first
+    pub fn members(&self) -> &[Entity] {
+        &self.members
+    }
+
+    /// Whether the agent is in the fleet
+    pub fn contains(&self, agent: Entity) -> bool {
+        self.members.contains(&agent)
+    }
+
+    /// Whether the fleet has no room for more agents
+    pub fn is_full(&self) -> bool {
+        self.members.len() >= MAX_FLEET_SIZE
+    }
+
+    /// Adds an agent to the back of the fleet, returning whether it joined
+    pub fn add_member(&mut self, agent: Entity) -> bool {
+        if self.is_full() || self.contains(agent) {
+            return false;
+        }
+        self.members.push(agent);
+        true
+    }
+
+    /// Takes an agent out of the fleet, returning whether it was a member
+    pub fn remove_member(&mut self, agent: Entity) -> bool {
+        let before = self.members.len();
+        self.members.retain(|member| *member != agent);
+        self.members.len() != before
+    }
+
+    /// Replaces the fleet's orders
+    pub fn set_order(&mut self, order: FleetOrder) {
+        self.order = order;
+    }
+
+    /// Maps the agents in the fleet to the entities they were restored as, used when loading a save.
+    pub fn map_entities(&mut self, entity_map: &HashMap<Entity, Entity>) {
+        self.members = self
+            .members
+            .iter()
+            .filter_map(|member| entity_map.get(member).copied())
+            .collect();
+        if let FleetOrder::Escort { target } = self.order {
+            self.order = match entity_map.get(&target) {
+                Some(target) => FleetOrder::Escort { target: *target },
+                None => FleetOrder::Hold,
+            };
+        }
+    }
+}
+
+/// Marks an agent as serving in a fleet
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FleetMember(pub Entity);
+
+/// Sent to add an agent to a fleet, taking it out of any fleet it is already in
+#[derive(Event, Debug, Clone, Copy)]
+pub struct JoinFleetRequestEvent {
+    /// The fleet to join
+    pub fleet: Entity,
+    /// The agent joining
+    pub agent: Entity,
+}
+
+/// Sent to take an agent out of a fleet
+#[derive(Event, Debug, Clone, Copy)]
+pub struct LeaveFleetRequestEvent {
+    /// The fleet to leave
+    pub fleet: Entity,
+    /// The agent leaving
+    pub agent: Entity,
+}
+
+/// Sent when an agent joins a fleet
+#[derive(Event, Debug, Clone, Copy)]
+pub struct FleetJoinedEvent {
+    /// The fleet joined
+    pub fleet: Entity,
+    /// The agent that joined
+    pub agent: Entity,
+}
+
+/// Sent when an agent is turned away from a fleet
+#[derive(Event, Debug, Clone, Copy)]
+pub struct FleetJoinDeniedEvent {
+    /// The fleet it asked to join
+    pub fleet: Entity,
+    /// The agent that asked to join
+    pub agent: Entity,
+    /// Why it was turned away
+    pub reason: FleetJoinDenial,
+}
+
+/// Sent when an agent leaves a fleet, whether it asked to or was lost
+#[derive(Event, Debug, Clone, Copy)]
+pub struct FleetLeftEvent {
+    /// The fleet left
+    pub fleet: Entity,
+    /// The agent that left
+    pub agent: Entity,
+}
+
+/// Sent when a fleet gets a new leader
+#[derive(Event, Debug, Clone, Copy)]
+pub struct FleetLeaderChangedEvent {
+    /// The fleet
+    pub fleet: Entity,
+    /// The agent now leading it
+    pub leader: Entity,
+}
+
+/// Sent when a fleet loses its last member and is disbanded
+#[derive(Event, Debug, Clone, Copy)]
+pub struct FleetDisbandedEvent {
+    /// The fleet that was disbanded
+    pub fleet: Entity,
+}
+
+/// The events fleet membership systems send
+#[derive(bevy::ecs::system::SystemParam)]
+pub struct FleetEventWriters<'w> {
+    /// Agents joining fleets
+    joined: EventWriter<'w, FleetJoinedEvent>,
+    /// Agents turned away from fleets
+    denied: EventWriter<'w, FleetJoinDeniedEvent>,
+    /// Agents leaving fleets
+    left: EventWriter<'w, FleetLeftEvent>,
+    /// Fleets getting new leaders
+    leader_changed: EventWriter<'w, FleetLeaderChangedEvent>,
+    /// Fleets disbanded
+    disbanded: EventWriter<'w, FleetDisbandedEvent>,
+}
+
+/// Handles requests to join and leave fleets, and drops members that no longer exist.
+///
+/// Fleets that lose their last member are disbanded, fleets that were created empty are kept until they have had one.
+pub fn process_fleet_membership(
+    mut commands: Commands,
+    mut join_requests: EventReader<JoinFleetRequestEvent>,
+    mut leave_requests: EventReader<LeaveFleetRequestEvent>,
+    mut events: FleetEventWriters,
+    mut fleets: Query<(Entity, &mut Fleet)>,
+    agents: Query<(Entity, &Agent, Option<&FleetMember>)>,
+) {
+    let leaders: HashMap<Entity, Option<Entity>> = fleets
+        .iter()
+        .map(|(entity, fleet)| (entity, fleet.leader()))
+        .collect();
+
+    for request in leave_requests.read() {
+        let Ok((_, mut fleet)) = fleets.get_mut(request.fleet) else {
+            continue;
+        };
+        if fleet.remove_member(request.agent) {
+            if let Some(mut agent) = commands.get_entity(request.agent) {
+                agent.remove::<FleetMember>();
+            }
+            events.left.send(FleetLeftEvent {
+                fleet: request.fleet,
+                agent: request.agent,
+            });
+        }
+    }
+
+    for request in join_requests.read() {
+        let Ok((_, agent, membership)) = agents.get(request.agent) else {
+            events.denied.send(FleetJoinDeniedEvent {
+                fleet: request.fleet,
+                agent: request.agent,
+                reason: FleetJoinDenial::NoAgent,
+            });
+            continue;
+        };
+        let denial = match fleets.get(request.fleet) {
+            Err(_) => Some(FleetJoinDenial::NoFleet),
+            Ok((_, fleet)) if fleet.contains(request.agent) => continue,
+            Ok((_, fleet)) if fleet.owner != agent.faction => Some(FleetJoinDenial::WrongFaction),
+            Ok((_, fleet)) if fleet.is_full() => Some(FleetJoinDenial::FleetFull),
+            Ok(_) => None,
+        };
+        if let Some(reason) = denial {
+            events.denied.send(FleetJoinDeniedEvent {
+                fleet: request.fleet,
+                agent: request.agent,
+                reason,
+            });
+            continue;
+        }
+
+        if let Some(FleetMember(old)) = membership {
+            if let Ok((_, mut old_fleet)) = fleets.get_mut(*old) {
+                if old_fleet.remove_member(request.agent) {
+                    events.left.send(FleetLeftEvent {
+                        fleet: *old,
+                        agent: request.agent,
+                    });
+                }
+            }
+        }
+
+        let (_, mut fleet) = fleets
+            .get_mut(request.fleet)
+            .expect("the fleet was checked above");
+        fleet.add_member(request.agent);
+        commands
+            .entity(request.agent)
+            .insert(FleetMember(request.fleet));
+        events.joined.send(FleetJoinedEvent {
+            fleet: request.fleet,
+            agent: request.agent,
+        });
+    }
+
+    for (fleet_entity, mut fleet) in fleets.iter_mut() {
+        let lost: Vec<Entity> = fleet
+            .members()
+            .iter()
+            .filter(|member| !agents.contains(**member))
+            .copied()
+            .collect();
+        for agent in lost {
+            fleet.remove_member(agent);
+            events.left.send(FleetLeftEvent {
+                fleet: fleet_entity,
+                agent,
+            });
+        }
+
+        let before = leaders.get(&fleet_entity).copied().flatten();
+        match (before, fleet.leader()) {
+            (Some(_), None) => {
+                info!("Fleet {} has been disbanded", fleet.name);
+                commands.entity(fleet_entity).despawn_recursive();
+                events.disbanded.send(FleetDisbandedEvent {
+                    fleet: fleet_entity,
+                });
+            }
+            (before, Some(leader)) if before != Some(leader) => {
+                events.leader_changed.send(FleetLeaderChangedEvent {
+                    fleet: fleet_entity,
+                    leader,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    // Agents can be left marked as members of a fleet that was despawned
+    for (agent, _, membership) in agents.iter() {
+        if let Some(FleetMember(fleet)) = membership {
+            if !fleets.contains(*fleet) {
+                commands.entity(agent).remove::<FleetMember>();
+            }
+        }
+    }
+}
+
+/// Flies every fleet toward what its orders want, its members holding formation around the leader.
+///
+/// The leader follows the stargates toward the fleet's destination at the speed of the slowest member,
+/// avoiding the systems of factions at war with the fleet's owner.
+/// Members follow the leader through the same stargates, then close up on their slots.
+/// A path through a stargate that has since closed is planned again.
+#[allow(clippy::too_many_arguments)]
+pub fn move_fleets(
+    time: Res<Time>,
+    system_graph: Res<SystemGraph>,
+    diplomacy: Res<Diplomacy>,
+    mut fleets: Query<&mut Fleet>,
+    mut agents: Query<(&mut Agent, &mut Transform, &CargoHold)>,
+    star_gates: Query<(&Stargate, &Transform), Without<Agent>>,
+    solar_systems: Query<(&SolarSystem, &Transform), Without<Agent>>,
+) {
+    for mut fleet in fleets.iter_mut() {
+        let Some(leader) = fleet.leader() else {
+            continue;
+        };
+        let Some(speed) = fleet
+            .members()
+            .iter()
+            .filter_map(|member| agents.get(*member).ok())
+            .map(|(agent, _, hold)| agent.cruising_speed(hold))
+            .reduce(f32::min)
+        else {
+            continue;
+        };
+        let step_size = speed * time.delta_seconds();
+
+        // The system the fleet is headed for, and where in it the leader should wait
+        let (destination, anchor) = match &fleet.order {
+            FleetOrder::Hold => (None, None),
+            FleetOrder::Move { system_id } => (Some(*system_id), None),
+            FleetOrder::Patrol { route, next } => (route.get(*next).copied(), None),
+            FleetOrder::Blockade { system_id } => (
+                Some(*system_id),
+                star_gates
+                    .iter()
+                    .find(|(gate, _)| gate.origin_system_id == *system_id)
+                    .map(|(_, transform)| transform.translation),
+            ),
+            FleetOrder::Escort { target } => match agents.get(*target) {
+                Ok((agent, transform, _)) => (
+                    Some(agent.current_system.attributes.id),
+                    Some(transform.translation),
+                ),
+                Err(_) => (None, None),
+            },
+        };
+
+        let Ok((mut agent, mut transform, _)) = agents.get_mut(leader) else {
+            continue;
+        };
+        let mut arrived = false;
+        if let Some(destination) = destination {
+            if agent.current_system.attributes.id != destination {
+                let heading = agent
+                    .stargate_path
+                    .path
+                    .last()
+                    .map(|gate| gate.destination_system_id);
+                if heading != Some(destination) || agent.stargate_path.is_invalidated() {
+                    let path = system_graph.system_by_id(&destination).and_then(|target| {
+                        route_avoiding_wars(
+                            &system_graph,
+                            &diplomacy,
+                            &agent,
+                            target,
+                            &solar_systems,
+                        )
+                        .ok()
+                    });
+                    agent.set_stargate_path(path.unwrap_or_default());
+                }
+                follow_stargate_path(
+                    &mut agent,
+                    &mut transform,
+                    step_size,
+                    &star_gates,
+                    &solar_systems,
+                );
+            } else {
+                agent.stargate_path.path.clear();
+                arrived = true;
+                if let Some(anchor) = anchor {
+                    let target = anchor.truncate().extend(transform.translation.z);
+                    agent.target_destination = Some(target);
+                    let delta = target - transform.translation;
+                    if delta.length() > 0.0 {
+                        transform.translation += delta.normalize() * step_size.min(delta.length());
+                    }
+                }
+            }
+        }
+        let leader_system = agent.current_system.clone();
+        let leader_position = transform.translation;
+
+        if arrived {
+            if let FleetOrder::Patrol { route, next } = &mut fleet.order {
+                *next = (*next + 1) % route.len();
+            }
+        }
+
+        for (slot, member) in fleet.members().iter().enumerate().skip(1) {
+            let Ok((mut agent, mut transform, _)) = agents.get_mut(*member) else {
+                continue;
+            };
+            let step_size = step_size * CATCH_UP_SPEED;
+
+            if agent.current_system.attributes.id != leader_system.attributes.id {
+                let heading = agent
+                    .stargate_path
+                    .path
+                    .last()
+                    .map(|gate| gate.destination_system_id);
+                if heading != Some(leader_system.attributes.id)
+                    || agent.stargate_path.is_invalidated()
+                {
+                    let path = route_avoiding_wars(
+                        &system_graph,
+                        &diplomacy,
+                        &agent,
+                        &leader_system,
+                        &solar_systems,
+                    );
+                    agent.set_stargate_path(path.unwrap_or_default());
+                }
+                follow_stargate_path(
+                    &mut agent,
+                    &mut transform,
+                    step_size,
+                    &star_gates,
+                    &solar_systems,
+                );
+                continue;
+            }
+
+            agent.stargate_path.path.clear();
+            let target = (leader_position.truncate()
+                + fleet.formation.slot_offset(slot, FORMATION_SPACING))
+            .extend(transform.translation.z);
+            agent.target_destination = Some(target);
+            let delta = target - transform.translation;
+            if delta.length() > 0.0 {
+                transform.translation += delta.normalize() * step_size.min(delta.length());
+            }
+        }
+    }
+}
+
+/// This is the `WantToFollowFleet` scorer
+#[derive(Clone, Component, Debug, ScorerBuilder)]
+pub struct WantToFollowFleet;
+
+/// Fleet members always want to follow their fleet, above anything else they might do
+pub fn want_to_follow_fleet_scorer_system(
+    members: Query<(), With<FleetMember>>,
+    mut query: Query<(&Actor, &mut Score, &ScorerSpan), With<WantToFollowFleet>>,
+) {
+    for (Actor(actor), mut score, span) in &mut query {
+        let desire = if members.contains(*actor) { 1.0 } else { 0.0 };
+        score.set(desire);
+        span.span()
+            .in_scope(|| debug!("Want to follow fleet! Score: {}", desire));
+    }
+}
+
+/// Hands an agent over to its fleet, which flies it until it leaves
+#[derive(Clone, Component, Debug, ActionBuilder)]
+pub struct FollowFleet;
+
+/// Keeps the agent's thinker busy while it is in a fleet, so only [`move_fleets`] flies it
+pub fn follow_fleet_action_system(
+    members: Query<(), With<FleetMember>>,
+    mut action_query: Query<(&Actor, &mut ActionState, &ActionSpan), With<FollowFleet>>,
+) {
+    for (Actor(actor), mut action_state, span) in &mut action_query {
+        let _guard = span.span().enter();
+        match *action_state {
+            ActionState::Requested => {
+                *action_state = if members.contains(*actor) {
+                    ActionState::Executing
+                } else {
+                    ActionState::Failure
+                };
+            }
+            ActionState::Executing if !members.contains(*actor) => {
+                *action_state = ActionState::Success;
+            }
+            ActionState::Cancelled => {
+                *action_state = ActionState::Failure;
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,720 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use big_brain::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::simulation::SimulationClock;
+use crate::solar_system::SolarSystem;
+use crate::structures::stargate::Stargate;
+use crate::structures::station::Station;
+
+use super::attributes::{Attributes, FactionID};
+use super::bank::Bank;
+use super::diplomoacy::{
+    Diplomacy, DiplomaticState, DiplomaticStateChangedEvent, TreatyKind, ALLIANCE_THRESHOLD,
+    TRADE_PACT_THRESHOLD,
+};
+use super::fleets::{Fleet, FleetOrder};
+use super::infrastructure::{ConstructionProject, ConstructionRequestEvent, ConstructionSite};
+use super::taxes::{TaxPolicy, MAX_TAX_RATE};
+
+/// How often a faction reviews its goals, in seconds of simulation time
+pub const REVIEW_INTERVAL: f64 = 60.0;
+/// The most stations a faction builds in one of its systems
+const MAX_STATIONS_PER_SYSTEM: usize = 2;
+/// The balance a faction is comfortable holding
+const TARGET_BALANCE: u32 = 1000000;
+/// How much a faction changes its trade tax by when it sets taxes, docking taxes change twice as fast
+const TAX_STEP: f32 = 0.01;
+/// How long the treaties factions propose last, in seconds of simulation time
+const TREATY_DURATION: f64 = 1800.0;
+/// The most systems on a border patrol
+const BORDER_PATROL_LENGTH: usize = 4;
+
+/// How much weight a faction gives to each of its goals, so factions can be given different characters.
+///
+/// A weight of zero means the faction never pursues the goal.
+#[derive(Component, Reflect, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component)]
+#[serde(default)]
+pub struct FactionGoals {
+    /// Building stations to grow the faction
+    pub expansion: f32,
+    /// Raising taxes when the faction is short of money, and lowering them once it has money to spare
+    pub wealth: f32,
+    /// Signing treaties with friendly factions
+    pub trade: f32,
+    /// Sending fleets to borders with unfriendly factions
+    pub defence: f32,
+}
+
+impl Default for FactionGoals {
+    fn default() -> Self {
+        Self {
+            expansion: 1.0,
+            wealth: 1.0,
+            trade: 1.0,
+            defence: 1.0,
+        }
+    }
+}
+
+impl FactionGoals {
+    /// Whether every weight is a number no less than zero
+    pub fn is_valid(&self) -> bool {
+        [self.expansion, self.wealth, self.trade, self.defence]
+            .iter()
+            .all(|weight| weight.is_finite() && *weight >= 0.0)
+    }
+}
+
+/// The unfriendliest faction on a faction's borders
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Threat {
+    /// The unfriendly faction
+    pub faction: FactionID,
+    /// How the factions stand with each other
+    pub state: DiplomaticState,
+    /// The faction's own system on the border with it
+    pub border_system: u32,
+}
+
+/// What a faction knows about its position when it reviews its goals
+#[derive(Debug, Clone, PartialEq)]
+pub struct StrategicAssessment {
+    /// The faction's bank balance
+    pub balance: u32,
+    /// The money the faction made over the last review interval, less what it spent
+    pub net_income: i64,
+    /// The faction's taxes
+    pub taxes: TaxPolicy,
+    /// The number of systems the faction holds
+    pub owned_systems: usize,
+    /// The faction's system with the fewest stations, if it has room for another
+    pub build_site: Option<u32>,
+    /// The faction's systems linked by a stargate to another faction's
+    pub border_systems: Vec<u32>,
+    /// The unfriendliest faction on the borders
+    pub threat: Option<Threat>,
+    /// The friendliest faction the faction could sign a treaty with, and the treaty it would sign
+    pub treaty_candidate: Option<(FactionID, TreatyKind)>,
+    /// The faction's fleets and their orders
+    pub fleets: Vec<(Entity, FleetOrder)>,
+}
+
+/// When a faction next reviews its goals, and what it found when it last did
+#[derive(Component, Debug, Clone, Default)]
+pub struct FactionStrategy {
+    /// When the next review is due, in seconds of simulation time
+    pub next_review: f64,
+    /// The assessment from the last review, until a goal acts on it
+    pub assessment: Option<StrategicAssessment>,
+}
+
+/// Builds the thinker that weighs up a faction's goals
+pub fn faction_thinker() -> ThinkerBuilder {
+    Thinker::build()
+        .label("FactionThinker")
+        .picker(Highest {})
+        .when(WantToExpand, ExpandTerritory)
+        .when(WantToGrowTreasury, GrowTreasury)
+        .when(WantToEaseTaxes, EaseTaxes)
+        .when(WantToSecureTradeRoutes, SecureTradeRoutes)
+        .when(WantToDefendBorders, DefendBorders)
+}
+
+/// Gives every faction that doesn't have one a thinker, whether it was just generated or loaded from a save
+pub fn give_factions_thinkers(
+    mut commands: Commands,
+    diplomacy: Res<Diplomacy>,
+    factions: Query<(Entity, &Attributes), Without<FactionStrategy>>,
+) {
+    for (faction, attributes) in factions.iter() {
+        // Outlaws don't hold territory or make treaties, so they have no strategy to plan
+        if diplomacy.is_outlaw(attributes.id) {
+            continue;
+        }
+        commands
+            .entity(faction)
+            .insert((faction_thinker(), FactionStrategy::default()));
+    }
+}
+
+/// Sizes up each faction's position when its review is due, for its goals to score
+pub fn assess_faction_strategy(
+    clock: Res<SimulationClock>,
+    diplomacy: Res<Diplomacy>,
+    mut factions: Query<(&Attributes, &Bank, Option<&TaxPolicy>, &mut FactionStrategy)>,
+    systems: Query<&SolarSystem>,
+    gates: Query<&Stargate>,
+    stations: Query<&Station>,
+    sites: Query<&ConstructionSite>,
+    fleets: Query<(Entity, &Fleet)>,
+) {
+    let now = clock.now();
+    if factions
+        .iter()
+        .all(|(_, _, _, strategy)| strategy.next_review > now)
+    {
+        return;
+    }
+
+    let owners: HashMap<u32, FactionID> = systems
+        .iter()
+        .map(|system| (system.attributes.id, system.attributes.owner))
+        .collect();
+    let mut station_counts: HashMap<u32, usize> = HashMap::new();
+    for station in stations.iter() {
+        *station_counts.entry(station.system_id).or_default() += 1;
+    }
+    // Stations still being built count, so a faction doesn't commission the same one twice
+    for site in sites.iter() {
+        if matches!(site.project, ConstructionProject::Station { .. }) {
+            *station_counts.entry(site.system_id).or_default() += 1;
+        }
+    }
+    let faction_ids: Vec<FactionID> = factions
+        .iter()
+        .map(|(attributes, _, _, _)| attributes.id)
+        .collect();
+
+    for (attributes, bank, taxes, mut strategy) in factions.iter_mut() {
+        if strategy.next_review > now {
+            continue;
+        }
+        let faction = attributes.id;
+
+        let mut owned: Vec<u32> = owners
+            .iter()
+            .filter(|(_, owner)| **owner == faction)
+            .map(|(system_id, _)| *system_id)
+            .collect();
+        owned.sort();
+
+        // Every link from one of the faction's systems into another faction's
+        let mut border_systems = Vec::new();
+        let mut threat: Option<Threat> = None;
+        for gate in gates.iter() {
+            let (Some(origin), Some(destination)) = (
+                owners.get(&gate.origin_system_id),
+                owners.get(&gate.destination_system_id),
+            ) else {
+                continue;
+            };
+            if *origin != faction || *destination == faction {
+                continue;
+            }
+            if !border_systems.contains(&gate.origin_system_id) {
+                border_systems.push(gate.origin_system_id);
+            }
+            let state = diplomacy.state(faction, *destination);
+            if threat.map_or(true, |threat| state < threat.state) {
+                threat = Some(Threat {
+                    faction: *destination,
+                    state,
+                    border_system: gate.origin_system_id,
+                });
+            }
+        }
+        border_systems.sort();
+
+        let build_site = owned
+            .iter()
+            .map(|system_id| {
+                let count = station_counts.get(system_id).copied().unwrap_or_default();
+                // Border systems are built up first
+                (count, !border_systems.contains(system_id), *system_id)
+            })
+            .filter(|(count, _, _)| *count < MAX_STATIONS_PER_SYSTEM)
+            .min()
+            .map(|(_, _, system_id)| system_id);
+
+        let treaty_candidate = faction_ids
+            .iter()
+            .filter(|other| **other != faction)
+            .filter_map(|other| {
+                let relation = diplomacy.relation(faction, *other);
+                let signed = relation.treaty.map(|treaty| treaty.kind);
+                let kind = if relation.score >= ALLIANCE_THRESHOLD {
+                    TreatyKind::Alliance
+                } else if relation.score >= TRADE_PACT_THRESHOLD {
+                    TreatyKind::TradePact
+                } else {
+                    return None;
+                };
+                (!relation.at_war && signed != Some(kind) && signed != Some(TreatyKind::Alliance))
+                    .then_some((relation.score, *other, kind))
+            })
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, other, kind)| (other, kind));
+
+        let window = (now - REVIEW_INTERVAL).max(0.0);
+        let income = bank.income_between(window, now + f64::EPSILON) as i64;
+        let expenses = bank.expenses_between(window, now + f64::EPSILON) as i64;
+
+        strategy.assessment = Some(StrategicAssessment {
+            balance: bank.balance,
+            net_income: income - expenses,
+            taxes: taxes.copied().unwrap_or_default(),
+            owned_systems: owned.len(),
+            build_site,
+            border_systems,
+            threat,
+            treaty_candidate,
+            fleets: fleets
+                .iter()
+                .filter(|(_, fleet)| fleet.owner == faction)
+                .map(|(entity, fleet)| (entity, fleet.order.clone()))
+                .collect(),
+        });
+        strategy.next_review = now + REVIEW_INTERVAL;
+    }
+}
+
+/// Scores a goal from the faction's latest assessment, scaled by the weight the faction gives the goal
+fn score_goal<T: Component>(
+    factions: &Query<(&FactionStrategy, Option<&FactionGoals>)>,
+    query: &mut Query<(&Actor, &mut Score, &ScorerSpan), With<T>>,
+    goal: &str,
+    weight: impl Fn(&FactionGoals) -> f32,
+    desire: impl Fn(&StrategicAssessment) -> f32,
+) {
+    for (Actor(actor), mut score, span) in query.iter_mut() {
+        let Ok((strategy, goals)) = factions.get(*actor) else {
+            continue;
+        };
+        let value = strategy.assessment.as_ref().map_or(0.0, |assessment| {
+            (desire(assessment) * weight(&goals.copied().unwrap_or_default())).clamp(0.0, 1.0)
+        });
+        score.set(value);
+        span.span()
+            .in_scope(|| debug!("Want to {}! Score: {}", goal, value));
+    }
+}
+
+/// Acts on a faction's latest assessment, issuing the order the goal decides on
+fn pursue_goal<T: Component>(
+    strategies: &mut Query<&mut FactionStrategy>,
+    orders: &mut EventWriter<FactionOrderEvent>,
+    action_query: &mut Query<(&Actor, &mut ActionState, &ActionSpan), With<T>>,
+    plan: impl Fn(&StrategicAssessment) -> Option<FactionOrder>,
+) {
+    for (Actor(actor), mut action_state, span) in action_query.iter_mut() {
+        let _guard = span.span().enter();
+        match *action_state {
+            ActionState::Requested => {
+                // Each review is acted on once, by whichever goal scored highest
+                let order = strategies
+                    .get_mut(*actor)
+                    .ok()
+                    .and_then(|mut strategy| strategy.assessment.take())
+                    .and_then(|assessment| plan(&assessment));
+                *action_state = match order {
+                    Some(order) => {
+                        debug!("Faction order: {:?}", order);
+                        orders.send(FactionOrderEvent {
+                            faction: *actor,
+                            order,
+                        });
+                        ActionState::Success
+                    }
+                    None => ActionState::Failure,
+                };
+            }
+            ActionState::Cancelled => {
+                *action_state = ActionState::Failure;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// This is the `WantToExpand` scorer
+#[derive(Clone, Component, Debug, ScorerBuilder)]
+pub struct WantToExpand;
+
+/// Factions want to build more stations the more money they have to spare
+pub fn want_to_expand_scorer_system(
+    factions: Query<(&FactionStrategy, Option<&FactionGoals>)>,
+    mut query: Query<(&Actor, &mut Score, &ScorerSpan), With<WantToExpand>>,
+) {
+    score_goal(
+        &factions,
+        &mut query,
+        "expand",
+        |goals| goals.expansion,
+        |assessment| match assessment.build_site {
+            Some(_) => {
+                let cost = ConstructionProject::outpost(String::new()).cost().credits;
+                assessment.balance.saturating_sub(cost) as f32 / (cost * 4) as f32
+            }
+            None => 0.0,
+        },
+    );
+}
+
+/// Grows a faction by building a station in its least developed system
+#[derive(Clone, Component, Debug, ActionBuilder)]
+pub struct ExpandTerritory;
+
+/// Orders a station built at the assessment's build site
+pub fn expand_territory_action_system(
+    mut strategies: Query<&mut FactionStrategy>,
+    mut orders: EventWriter<FactionOrderEvent>,
+    mut action_query: Query<(&Actor, &mut ActionState, &ActionSpan), With<ExpandTerritory>>,
+) {
+    pursue_goal(
+        &mut strategies,
+        &mut orders,
+        &mut action_query,
+        |assessment| {
+            assessment
+                .build_site
+                .map(|system_id| FactionOrder::BuildStation { system_id })
+        },
+    );
+}
+
+/// This is the `WantToGrowTreasury` scorer
+#[derive(Clone, Component, Debug, ScorerBuilder)]
+pub struct WantToGrowTreasury;
+
+/// Factions short of money, or losing it, want to raise their taxes while they still can
+pub fn want_to_grow_treasury_scorer_system(
+    factions: Query<(&FactionStrategy, Option<&FactionGoals>)>,
+    mut query: Query<(&Actor, &mut Score, &ScorerSpan), With<WantToGrowTreasury>>,
+) {
+    score_goal(
+        &factions,
+        &mut query,
+        "grow the treasury",
+        |goals| goals.wealth,
+        |assessment| {
+            if raised_taxes(&assessment.taxes) == assessment.taxes {
+                return 0.0;
+            }
+            let shortfall = 1.0 - assessment.balance as f32 / TARGET_BALANCE as f32;
+            let losing_money = if assessment.net_income < 0 { 0.25 } else { 0.0 };
+            shortfall + losing_money
+        },
+    );
+}
+
+/// Raises a faction's taxes to bring in more money
+#[derive(Clone, Component, Debug, ActionBuilder)]
+pub struct GrowTreasury;
+
+/// Orders the faction's taxes raised by a step
+pub fn grow_treasury_action_system(
+    mut strategies: Query<&mut FactionStrategy>,
+    mut orders: EventWriter<FactionOrderEvent>,
+    mut action_query: Query<(&Actor, &mut ActionState, &ActionSpan), With<GrowTreasury>>,
+) {
+    pursue_goal(
+        &mut strategies,
+        &mut orders,
+        &mut action_query,
+        |assessment| Some(FactionOrder::SetTaxes(raised_taxes(&assessment.taxes))),
+    );
+}
+
+/// The taxes a step higher than the given ones, capped at [`MAX_TAX_RATE`]
+fn raised_taxes(taxes: &TaxPolicy) -> TaxPolicy {
+    TaxPolicy {
+        trade_rate: (taxes.trade_rate + TAX_STEP).min(MAX_TAX_RATE),
+        docking_rate: (taxes.docking_rate + TAX_STEP * 2.0).min(MAX_TAX_RATE),
+    }
+}
+
+/// This is the `WantToEaseTaxes` scorer
+#[derive(Clone, Component, Debug, ScorerBuilder)]
+pub struct WantToEaseTaxes;
+
+/// Factions with more money than they need, and still making it, want to lower their taxes
+pub fn want_to_ease_taxes_scorer_system(
+    factions: Query<(&FactionStrategy, Option<&FactionGoals>)>,
+    mut query: Query<(&Actor, &mut Score, &ScorerSpan), With<WantToEaseTaxes>>,
+) {
+    score_goal(
+        &factions,
+        &mut query,
+        "ease taxes",
+        |goals| goals.wealth,
+        |assessment| {
+            if assessment.net_income < 0 || lowered_taxes(&assessment.taxes) == assessment.taxes {
+                return 0.0;
+            }
+            assessment.balance as f32 / TARGET_BALANCE as f32 - 1.0
+        },
+    );
+}
+
+/// Lowers a faction's taxes once its treasury has recovered
+#[derive(Clone, Component, Debug, ActionBuilder)]
+pub struct EaseTaxes;
+
+/// Orders the faction's taxes lowered by a step
+pub fn ease_taxes_action_system(
+    mut strategies: Query<&mut FactionStrategy>,
+    mut orders: EventWriter<FactionOrderEvent>,
+    mut action_query: Query<(&Actor, &mut ActionState, &ActionSpan), With<EaseTaxes>>,
+) {
+    pursue_goal(
+        &mut strategies,
+        &mut orders,
+        &mut action_query,
+        |assessment| Some(FactionOrder::SetTaxes(lowered_taxes(&assessment.taxes))),
+    );
+}
+
+/// The taxes a step lower than the given ones, stopping at zero
+fn lowered_taxes(taxes: &TaxPolicy) -> TaxPolicy {
+    TaxPolicy {
+        trade_rate: (taxes.trade_rate - TAX_STEP).max(0.0),
+        docking_rate: (taxes.docking_rate - TAX_STEP * 2.0).max(0.0),
+    }
+}
+
+/// This is the `WantToSecureTradeRoutes` scorer
+#[derive(Clone, Component, Debug, ScorerBuilder)]
+pub struct WantToSecureTradeRoutes;
+
+/// Factions want to sign treaties with the factions they get on with
+pub fn want_to_secure_trade_routes_scorer_system(
+    factions: Query<(&FactionStrategy, Option<&FactionGoals>)>,
+    mut query: Query<(&Actor, &mut Score, &ScorerSpan), With<WantToSecureTradeRoutes>>,
+) {
+    score_goal(
+        &factions,
+        &mut query,
+        "secure trade routes",
+        |goals| goals.trade,
+        |assessment| match assessment.treaty_candidate {
+            Some((_, TreatyKind::Alliance)) => 0.8,
+            Some((_, TreatyKind::TradePact)) => 0.6,
+            None => 0.0,
+        },
+    );
+}
+
+/// Opens trade with a friendly faction by proposing a treaty
+#[derive(Clone, Component, Debug, ActionBuilder)]
+pub struct SecureTradeRoutes;
+
+/// Orders a treaty proposed to the assessment's treaty candidate
+pub fn secure_trade_routes_action_system(
+    mut strategies: Query<&mut FactionStrategy>,
+    mut orders: EventWriter<FactionOrderEvent>,
+    mut action_query: Query<(&Actor, &mut ActionState, &ActionSpan), With<SecureTradeRoutes>>,
+) {
+    pursue_goal(
+        &mut strategies,
+        &mut orders,
+        &mut action_query,
+        |assessment| {
+            assessment
+                .treaty_candidate
+                .map(|(with, kind)| FactionOrder::ProposeTreaty { with, kind })
+        },
+    );
+}
+
+/// This is the `WantToDefendBorders` scorer
+#[derive(Clone, Component, Debug, ScorerBuilder)]
+pub struct WantToDefendBorders;
+
+/// Factions with fleets want to defend the borders they share with unfriendly factions
+pub fn want_to_defend_borders_scorer_system(
+    factions: Query<(&FactionStrategy, Option<&FactionGoals>)>,
+    mut query: Query<(&Actor, &mut Score, &ScorerSpan), With<WantToDefendBorders>>,
+) {
+    score_goal(
+        &factions,
+        &mut query,
+        "defend borders",
+        |goals| goals.defence,
+        |assessment| {
+            if defence_order(assessment).is_none() {
+                return 0.0;
+            }
+            match assessment.threat.map(|threat| threat.state) {
+                Some(DiplomaticState::War) => 1.0,
+                Some(DiplomaticState::Hostile) => 0.6,
+                _ => 0.0,
+            }
+        },
+    );
+}
+
+/// Sends a fleet to the border with the unfriendliest neighbouring faction
+#[derive(Clone, Component, Debug, ActionBuilder)]
+pub struct DefendBorders;
+
+/// Orders a fleet to blockade the border with a faction at war, or patrol the borders with a hostile one
+pub fn defend_borders_action_system(
+    mut strategies: Query<&mut FactionStrategy>,
+    mut orders: EventWriter<FactionOrderEvent>,
+    mut action_query: Query<(&Actor, &mut ActionState, &ActionSpan), With<DefendBorders>>,
+) {
+    pursue_goal(
+        &mut strategies,
+        &mut orders,
+        &mut action_query,
+        defence_order,
+    );
+}
+
+/// The fleet order that would defend against the assessment's threat, if a fleet isn't already carrying it out
+fn defence_order(assessment: &StrategicAssessment) -> Option<FactionOrder> {
+    let threat = assessment.threat?;
+    let order = match threat.state {
+        DiplomaticState::War => FleetOrder::Blockade {
+            system_id: threat.border_system,
+        },
+        DiplomaticState::Hostile => FleetOrder::Patrol {
+            route: assessment
+                .border_systems
+                .iter()
+                .take(BORDER_PATROL_LENGTH)
+                .copied()
+                .collect(),
+            next: 0,
+        },
+        _ => return None,
+    };
+
+    if assessment
+        .fleets
+        .iter()
+        .any(|(_, current)| same_duty(current, &order))
+    {
+        return None;
+    }
+    // Fleets already on a blockade are left there
+    let (fleet, _) = assessment
+        .fleets
+        .iter()
+        .find(|(_, current)| !matches!(current, FleetOrder::Blockade { .. }))?;
+    Some(FactionOrder::DispatchFleet {
+        fleet: *fleet,
+        order,
+    })
+}
+
+/// Whether two orders send a fleet to do the same job, ignoring how far through a patrol it is
+fn same_duty(a: &FleetOrder, b: &FleetOrder) -> bool {
+    match (a, b) {
+        (FleetOrder::Patrol { route: a, .. }, FleetOrder::Patrol { route: b, .. }) => a == b,
+        _ => a == b,
+    }
+}
+
+/// A concrete order a faction gives as it pursues its goals
+#[derive(Debug, Clone, PartialEq)]
+pub enum FactionOrder {
+    /// Commission an outpost in one of the faction's systems
+    BuildStation {
+        /// The system to build in
+        system_id: u32,
+    },
+    /// Give one of the faction's fleets new orders
+    DispatchFleet {
+        /// The fleet
+        fleet: Entity,
+        /// Its new orders
+        order: FleetOrder,
+    },
+    /// Change the faction's taxes
+    SetTaxes(TaxPolicy),
+    /// Offer another faction a treaty, which it accepts if their relations are good enough
+    ProposeTreaty {
+        /// The faction offered the treaty
+        with: FactionID,
+        /// The treaty offered
+        kind: TreatyKind,
+    },
+}
+
+/// Sent when a faction gives an order
+#[derive(Event, Debug, Clone)]
+pub struct FactionOrderEvent {
+    /// The faction giving the order
+    pub faction: Entity,
+    /// The order
+    pub order: FactionOrder,
+}
+
+/// Carries out the orders factions give.
+///
+/// Stations are commissioned as construction sites, and other orders a faction can't carry out are dropped.
+pub fn carry_out_faction_orders(
+    mut commands: Commands,
+    mut orders: EventReader<FactionOrderEvent>,
+    mut factions: Query<(&Attributes, Option<&mut TaxPolicy>)>,
+    mut fleets: Query<&mut Fleet>,
+    clock: Res<SimulationClock>,
+    mut diplomacy: ResMut<Diplomacy>,
+    mut changes: EventWriter<DiplomaticStateChangedEvent>,
+    mut construction: EventWriter<ConstructionRequestEvent>,
+) {
+    for event in orders.read() {
+        let Ok((attributes, taxes)) = factions.get_mut(event.faction) else {
+            continue;
+        };
+
+        match &event.order {
+            FactionOrder::BuildStation { system_id } => {
+                construction.send(ConstructionRequestEvent {
+                    faction: attributes.id,
+                    system_id: *system_id,
+                    project: ConstructionProject::outpost(format!(
+                        "{} Outpost {}",
+                        attributes.name, system_id
+                    )),
+                });
+            }
+            FactionOrder::DispatchFleet { fleet, order } => {
+                if let Ok(mut fleet) = fleets.get_mut(*fleet) {
+                    if fleet.owner == attributes.id {
+                        fleet.set_order(order.clone());
+                    }
+                }
+            }
+            FactionOrder::SetTaxes(policy) => {
+                if !policy.is_valid() {
+                    continue;
+                }
+                match taxes {
+                    Some(mut taxes) => *taxes = *policy,
+                    None => {
+                        commands.entity(event.faction).insert(*policy);
+                    }
+                }
+            }
+            FactionOrder::ProposeTreaty { with, kind } => {
+                let old = diplomacy.state(attributes.id, *with);
+                match diplomacy.sign_treaty(
+                    attributes.id,
+                    *with,
+                    *kind,
+                    TREATY_DURATION,
+                    clock.now(),
+                ) {
+                    Ok(()) => {
+                        let new = diplomacy.state(attributes.id, *with);
+                        if old != new {
+                            changes.send(DiplomaticStateChangedEvent {
+                                a: attributes.id,
+                                b: *with,
+                                old,
+                                new,
+                            });
+                        }
+                    }
+                    Err(error) => info!("{}'s treaty was refused: {}", attributes.name, error),
+                }
+            }
+        }
+    }
+}
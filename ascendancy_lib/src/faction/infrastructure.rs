@@ -0,0 +1,693 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::items::{ItemId, ItemRegistry};
+use crate::simulation::SimulationClock;
+use crate::solar_system::resources::ResourceKind;
+use crate::solar_system::SolarSystem;
+use crate::structures::services::dock::Dock;
+use crate::structures::services::factory::Factory;
+use crate::structures::services::market::Market;
+use crate::structures::services::mining::Mining;
+use crate::structures::services::solar_generator::SolarGenerator;
+use crate::structures::services::{StationServiceTrait, StationServices};
+use crate::structures::station::{Station, MAX_SERVICES};
+
+use super::attributes::{Attributes, FactionID};
+use super::bank::{Bank, TransactionKind};
+
+/// The credits it costs to build a station's hull, before any services
+pub const HULL_CREDITS: u32 = 100000;
+/// The refined metal that goes into a station's hull
+const HULL_METAL: u32 = 200;
+/// How long a station's hull takes to build, in seconds
+const HULL_SECONDS: f32 = 120.0;
+/// How far apart the stations in a system are placed
+const STATION_SPACING: f32 = 12.0;
+
+/// A service a construction site can build onto a station
+#[derive(Reflect, Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ServicePlan {
+    /// A dock for ten ships
+    Dock,
+    /// A market trading every known item
+    Market,
+    /// A solar generator
+    SolarGenerator,
+    /// A factory making the recipe with the given key
+    Factory {
+        /// The recipe's key
+        recipe: String,
+    },
+    /// A mining rig extracting the given resource
+    Mining {
+        /// The resource extracted
+        resource: ResourceKind,
+    },
+}
+
+impl ServicePlan {
+    /// The plan that would build the given service
+    pub fn of(service: &StationServices) -> Self {
+        match service {
+            StationServices::Dock(_) => Self::Dock,
+            StationServices::Market(_) => Self::Market,
+            StationServices::SolarGenerator(_) => Self::SolarGenerator,
+            StationServices::Factory(factory) => Self::Factory {
+                recipe: factory.recipe.key.clone(),
+            },
+            StationServices::Mining(mining) => Self::Mining {
+                resource: mining.resource,
+            },
+        }
+    }
+
+    /// What the service costs to build
+    pub fn cost(&self) -> ConstructionCost {
+        let (credits, metal, energy_cells, seconds) = match self {
+            Self::Dock => (20000, 60, 0, 30.0),
+            Self::Market => (30000, 40, 20, 30.0),
+            Self::SolarGenerator => (15000, 50, 0, 20.0),
+            Self::Factory { .. } => (50000, 120, 40, 60.0),
+            Self::Mining { .. } => (40000, 100, 20, 45.0),
+        };
+        let mut materials = vec![(ItemId::REFINED_METAL, metal)];
+        if energy_cells > 0 {
+            materials.push((ItemId::ENERGY_CELLS, energy_cells));
+        }
+        ConstructionCost {
+            credits,
+            materials,
+            seconds,
+        }
+    }
+
+    /// Builds the service with the given ID, or returns `None` if it makes a recipe that isn't known
+    pub fn build(&self, id: u32, items: &ItemRegistry) -> Option<StationServices> {
+        Some(match self {
+            Self::Dock => StationServices::Dock(Dock::new(id, String::from("Docking Bay"), 10)),
+            Self::Market => StationServices::Market(Market::new(id, items)),
+            Self::SolarGenerator => StationServices::SolarGenerator(SolarGenerator::new(
+                id,
+                String::from("Solar Generator"),
+            )),
+            Self::Factory { recipe } => StationServices::Factory(Factory::new(
+                id,
+                format!("{} Factory", recipe),
+                items.recipe(recipe)?,
+            )),
+            Self::Mining { resource } => StationServices::Mining(Mining::new(
+                id,
+                format!("{:?} Mining Rig", resource),
+                *resource,
+            )),
+        })
+    }
+}
+
+/// The credits, materials and time a construction project needs
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ConstructionCost {
+    /// The credits paid from the faction's bank as work goes on
+    pub credits: u32,
+    /// The units of each item used up as work goes on
+    pub materials: Vec<(ItemId, u32)>,
+    /// How long the work takes when nothing holds it up, in seconds
+    pub seconds: f32,
+}
+
+impl ConstructionCost {
+    fn add(&mut self, other: ConstructionCost) {
+        self.credits += other.credits;
+        self.seconds += other.seconds;
+        for (item, quantity) in other.materials {
+            match self.materials.iter_mut().find(|(id, _)| *id == item) {
+                Some((_, total)) => *total += quantity,
+                None => self.materials.push((item, quantity)),
+            }
+        }
+    }
+}
+
+/// What a construction site is building
+#[derive(Reflect, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum ConstructionProject {
+    /// A new station running the given services
+    Station {
+        /// The new station's name
+        name: String,
+        /// The services it starts with
+        services: Vec<ServicePlan>,
+    },
+    /// New services for an existing station
+    Upgrade {
+        /// The ID of the station being upgraded
+        station_id: u32,
+        /// The services added to it
+        services: Vec<ServicePlan>,
+    },
+}
+
+impl ConstructionProject {
+    /// A trading outpost, with a market, a dock and a solar generator to power them
+    pub fn outpost(name: String) -> Self {
+        Self::Station {
+            name,
+            services: vec![
+                ServicePlan::Market,
+                ServicePlan::Dock,
+                ServicePlan::SolarGenerator,
+            ],
+        }
+    }
+
+    /// The services the project builds
+    pub fn services(&self) -> &[ServicePlan] {
+        match self {
+            Self::Station { services, .. } | Self::Upgrade { services, .. } => services,
+        }
+    }
+
+    /// What the whole project costs, including the hull of a new station
+    pub fn cost(&self) -> ConstructionCost {
+        let mut cost = match self {
+            Self::Station { .. } => ConstructionCost {
+                credits: HULL_CREDITS,
+                materials: vec![(ItemId::REFINED_METAL, HULL_METAL)],
+                seconds: HULL_SECONDS,
+            },
+            Self::Upgrade { .. } => ConstructionCost::default(),
+        };
+        for service in self.services() {
+            cost.add(service.cost());
+        }
+        cost
+    }
+}
+
+/// Materials a construction site needs, and how many have been delivered and used up
+#[derive(Reflect, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct MaterialRequirement {
+    /// The item needed
+    pub item: ItemId,
+    /// The units the whole project uses up
+    pub required: u32,
+    /// The units delivered to the site so far
+    pub delivered: u32,
+    /// The units used up so far
+    pub consumed: u32,
+}
+
+impl MaterialRequirement {
+    /// The units delivered but not yet used up
+    pub fn stock(&self) -> u32 {
+        self.delivered - self.consumed
+    }
+
+    /// The units still to be delivered
+    pub fn outstanding(&self) -> u32 {
+        self.required - self.delivered
+    }
+}
+
+/// Why a construction site isn't making progress
+#[derive(Reflect, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum ConstructionStall {
+    /// The site is waiting for deliveries of an item
+    AwaitingMaterials(ItemId),
+    /// The owning faction can't pay for the next stage of work
+    AwaitingCredits,
+}
+
+/// A station, or new services for one, being built for a faction.
+///
+/// Work uses up materials and credits in step with its progress, so a site that runs short stalls until more arrive.
+#[derive(Component, Reflect, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[reflect(Component)]
+pub struct ConstructionSite {
+    /// The faction paying for the work
+    pub owner: FactionID,
+    /// The system the site is in
+    pub system_id: u32,
+    /// What is being built
+    pub project: ConstructionProject,
+    /// The materials the project needs
+    pub materials: Vec<MaterialRequirement>,
+    /// The credits the project costs
+    pub credits_required: u32,
+    /// The credits paid so far
+    pub credits_paid: u32,
+    /// How long the work takes, in seconds
+    pub build_seconds: f32,
+    /// How far the work has got, from 0 to 1
+    pub progress: f32,
+    /// Why the work is held up, if it is
+    pub stall: Option<ConstructionStall>,
+}
+
+impl ConstructionSite {
+    /// Sets up a site for the given project, with nothing delivered or paid yet
+    pub fn new(owner: FactionID, system_id: u32, project: ConstructionProject) -> Self {
+        let cost = project.cost();
+        Self {
+            owner,
+            system_id,
+            materials: cost
+                .materials
+                .into_iter()
+                .map(|(item, required)| MaterialRequirement {
+                    item,
+                    required,
+                    delivered: 0,
+                    consumed: 0,
+                })
+                .collect(),
+            credits_required: cost.credits,
+            credits_paid: 0,
+            build_seconds: cost.seconds,
+            progress: 0.0,
+            stall: None,
+            project,
+        }
+    }
+
+    /// The name the site goes by until the work is finished
+    pub fn name(&self) -> String {
+        match &self.project {
+            ConstructionProject::Station { name, .. } => format!("{} (under construction)", name),
+            ConstructionProject::Upgrade { station_id, .. } => {
+                format!("Station {} upgrade", station_id)
+            }
+        }
+    }
+
+    /// Whether the work is finished
+    pub fn is_complete(&self) -> bool {
+        self.progress >= 1.0
+    }
+
+    /// The units of an item the site still needs delivered
+    pub fn outstanding(&self, item: ItemId) -> u32 {
+        self.materials
+            .iter()
+            .find(|material| material.item == item)
+            .map_or(0, MaterialRequirement::outstanding)
+    }
+
+    /// Accepts a delivery, returning how many units were taken.
+    ///
+    /// The site only takes the units it still needs.
+    pub fn deliver(&mut self, item: ItemId, quantity: u32) -> u32 {
+        let Some(material) = self
+            .materials
+            .iter_mut()
+            .find(|material| material.item == item)
+        else {
+            return 0;
+        };
+        let accepted = quantity.min(material.outstanding());
+        material.delivered += accepted;
+        accepted
+    }
+
+    /// Works on the project for the given number of seconds, using up materials and credits as it goes.
+    ///
+    /// Progress is held back by whichever material runs out first, and stops altogether if the bank can't pay for it.
+    pub fn work(&mut self, seconds: f32, bank: &mut Bank, now: f64) {
+        if self.is_complete() {
+            return;
+        }
+        let mut target = (self.progress + seconds / self.build_seconds.max(f32::EPSILON)).min(1.0);
+        self.stall = None;
+
+        for material in self.materials.iter() {
+            let available = material.delivered as f32 / material.required as f32;
+            if available < target {
+                target = available;
+                self.stall = Some(ConstructionStall::AwaitingMaterials(material.item));
+            }
+        }
+        if target <= self.progress {
+            return;
+        }
+
+        let owed =
+            ((target * self.credits_required as f32).ceil() as u32).min(self.credits_required);
+        let payment = owed.saturating_sub(self.credits_paid);
+        if payment > 0
+            && bank
+                .withdraw(TransactionKind::Construction, payment, now)
+                .is_err()
+        {
+            self.stall = Some(ConstructionStall::AwaitingCredits);
+            return;
+        }
+        self.credits_paid += payment;
+
+        for material in self.materials.iter_mut() {
+            let used = ((target * material.required as f32).ceil() as u32).min(material.delivered);
+            material.consumed = material.consumed.max(used);
+        }
+        self.progress = target;
+    }
+}
+
+/// Why a construction request was turned down
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConstructionDenial {
+    /// The faction doesn't own the system
+    NotOwner,
+    /// There is no station with the given ID in the system
+    UnknownStation,
+    /// The project doesn't build any services
+    NoServices,
+    /// The station would end up with more than [`MAX_SERVICES`] services
+    TooManyServices,
+    /// The station would end up with two of the same service
+    DuplicateService(ServicePlan),
+    /// A factory would make a recipe that isn't known
+    UnknownRecipe(String),
+}
+
+/// Sent to commission a construction project
+#[derive(Event, Debug, Clone)]
+pub struct ConstructionRequestEvent {
+    /// The faction commissioning the work, which pays for it
+    pub faction: FactionID,
+    /// The system to build in
+    pub system_id: u32,
+    /// What to build
+    pub project: ConstructionProject,
+}
+
+/// Sent when a construction site is set up
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ConstructionStartedEvent {
+    /// The faction that commissioned the work
+    pub faction: FactionID,
+    /// The new site
+    pub site: Entity,
+}
+
+/// Sent when a construction request is turned down
+#[derive(Event, Debug, Clone)]
+pub struct ConstructionDeniedEvent {
+    /// The faction that commissioned the work
+    pub faction: FactionID,
+    /// Why it was turned down
+    pub reason: ConstructionDenial,
+}
+
+/// Sent when a construction site finishes its work
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ConstructionCompletedEvent {
+    /// The faction that commissioned the work
+    pub faction: FactionID,
+    /// The station that was built or upgraded
+    pub station: Entity,
+}
+
+/// Sets up construction sites for the projects factions commission.
+///
+/// Upgrades are checked against the station's services and any upgrades already under way, so no station goes over [`MAX_SERVICES`].
+pub fn commission_construction(
+    mut commands: Commands,
+    mut requests: EventReader<ConstructionRequestEvent>,
+    mut started: EventWriter<ConstructionStartedEvent>,
+    mut denied: EventWriter<ConstructionDeniedEvent>,
+    systems: Query<(&SolarSystem, &Transform)>,
+    stations: Query<(&Station, &Transform)>,
+    sites: Query<&ConstructionSite>,
+    items: Res<ItemRegistry>,
+) {
+    // Sites set up this tick aren't in the query yet
+    let mut pending: Vec<ConstructionSite> = Vec::new();
+
+    for request in requests.read() {
+        let result = check_request(request, &systems, &stations, &sites, &pending, &items);
+        let transform = match result {
+            Ok(transform) => transform,
+            Err(reason) => {
+                denied.send(ConstructionDeniedEvent {
+                    faction: request.faction,
+                    reason,
+                });
+                continue;
+            }
+        };
+
+        let site =
+            ConstructionSite::new(request.faction, request.system_id, request.project.clone());
+        let entity = commands
+            .spawn((
+                site.clone(),
+                TransformBundle::from_transform(transform),
+                Name::new(site.name()),
+            ))
+            .id();
+        pending.push(site);
+        started.send(ConstructionStartedEvent {
+            faction: request.faction,
+            site: entity,
+        });
+    }
+}
+
+/// Checks a construction request, returning where the site goes if it can go ahead
+fn check_request(
+    request: &ConstructionRequestEvent,
+    systems: &Query<(&SolarSystem, &Transform)>,
+    stations: &Query<(&Station, &Transform)>,
+    sites: &Query<&ConstructionSite>,
+    pending: &[ConstructionSite],
+    items: &ItemRegistry,
+) -> Result<Transform, ConstructionDenial> {
+    let Some((_, system_transform)) = systems.iter().find(|(system, _)| {
+        system.attributes.id == request.system_id && system.attributes.owner == request.faction
+    }) else {
+        return Err(ConstructionDenial::NotOwner);
+    };
+
+    let services = request.project.services();
+    if services.is_empty() {
+        return Err(ConstructionDenial::NoServices);
+    }
+    for service in services {
+        if let ServicePlan::Factory { recipe } = service {
+            if items.recipe(recipe).is_none() {
+                return Err(ConstructionDenial::UnknownRecipe(recipe.clone()));
+            }
+        }
+    }
+
+    let (mut planned, transform) = match &request.project {
+        ConstructionProject::Station { .. } => {
+            let count = stations
+                .iter()
+                .filter(|(station, _)| station.system_id == request.system_id)
+                .count()
+                + sites
+                    .iter()
+                    .chain(pending.iter())
+                    .filter(|site| {
+                        site.system_id == request.system_id
+                            && matches!(site.project, ConstructionProject::Station { .. })
+                    })
+                    .count();
+            let transform = Transform::from_xyz(
+                system_transform.translation.x + STATION_SPACING * count as f32,
+                system_transform.translation.y,
+                system_transform.translation.z.max(1.0),
+            );
+            (Vec::new(), transform)
+        }
+        ConstructionProject::Upgrade { station_id, .. } => {
+            let Some((station, transform)) = stations.iter().find(|(station, _)| {
+                station.id == *station_id && station.system_id == request.system_id
+            }) else {
+                return Err(ConstructionDenial::UnknownStation);
+            };
+            let mut planned: Vec<ServicePlan> =
+                station.services.iter().map(ServicePlan::of).collect();
+            for site in sites.iter().chain(pending.iter()) {
+                if let ConstructionProject::Upgrade {
+                    station_id: other,
+                    services,
+                } = &site.project
+                {
+                    if other == station_id {
+                        planned.extend(services.iter().cloned());
+                    }
+                }
+            }
+            (planned, *transform)
+        }
+    };
+
+    for service in services {
+        if planned.contains(service) {
+            return Err(ConstructionDenial::DuplicateService(service.clone()));
+        }
+        planned.push(service.clone());
+    }
+    if planned.len() > MAX_SERVICES {
+        return Err(ConstructionDenial::TooManyServices);
+    }
+    Ok(transform)
+}
+
+/// Buys the materials construction sites still need from the stations in their systems, paid for by the site's owner.
+///
+/// Goods are bought at the station market's price, so a site in a system without them waits for them to arrive.
+pub fn supply_construction_sites(
+    mut sites: Query<&mut ConstructionSite>,
+    mut stations: Query<&mut Station>,
+    mut factions: Query<(&Attributes, &mut Bank)>,
+    clock: Res<SimulationClock>,
+) {
+    for mut site in sites.iter_mut() {
+        let Some((_, mut bank)) = factions
+            .iter_mut()
+            .find(|(attributes, _)| attributes.id == site.owner)
+        else {
+            continue;
+        };
+        let wanted: Vec<(ItemId, u32)> = site
+            .materials
+            .iter()
+            .filter(|material| material.outstanding() > 0)
+            .map(|material| (material.item, material.outstanding()))
+            .collect();
+
+        for (item, mut outstanding) in wanted {
+            for mut station in stations.iter_mut() {
+                if outstanding == 0 {
+                    break;
+                }
+                if station.system_id != site.system_id {
+                    continue;
+                }
+                let Some(price) = station.market().and_then(|market| market.price(item)) else {
+                    continue;
+                };
+                let quantity = outstanding.min(station.resource_manager.inventory.quantity(item));
+                let affordable = (bank.balance as f32 / price.max(f32::EPSILON)) as u32;
+                let quantity = quantity.min(affordable);
+                if quantity == 0 {
+                    continue;
+                }
+
+                let cost = (quantity as f32 * price).ceil() as u32;
+                // The goods come out of a copy of the station's stock, so nothing changes unless they are paid for
+                let mut inventory = station.resource_manager.inventory.clone();
+                if inventory.remove(item, quantity).is_err()
+                    || bank
+                        .withdraw(TransactionKind::Construction, cost, clock.now())
+                        .is_err()
+                {
+                    continue;
+                }
+                station.resource_manager.inventory = inventory;
+                if let Some(market) = station.market_mut() {
+                    market.funds += cost as f32;
+                }
+                site.deliver(item, quantity);
+                outstanding -= quantity;
+            }
+        }
+    }
+}
+
+/// Works on every construction site, and hands over the finished work.
+///
+/// A finished new station is spawned where its site was; a finished upgrade adds its services to the station.
+#[allow(clippy::too_many_arguments)]
+pub fn advance_construction(
+    mut commands: Commands,
+    time: Res<Time>,
+    clock: Res<SimulationClock>,
+    items: Res<ItemRegistry>,
+    mut sites: Query<(Entity, &mut ConstructionSite, &Transform)>,
+    mut stations: Query<(Entity, &mut Station)>,
+    mut factions: Query<(&Attributes, &mut Bank)>,
+    mut completed: EventWriter<ConstructionCompletedEvent>,
+) {
+    let mut next_station_id = stations
+        .iter()
+        .map(|(_, station)| station.id + 1)
+        .max()
+        .unwrap_or_default();
+    let mut next_service_id = stations
+        .iter()
+        .flat_map(|(_, station)| station.services.iter())
+        .map(|service| service.id() + 1)
+        .max()
+        .unwrap_or_default();
+
+    for (site_entity, mut site, transform) in sites.iter_mut() {
+        if let Some((_, mut bank)) = factions
+            .iter_mut()
+            .find(|(attributes, _)| attributes.id == site.owner)
+        {
+            site.work(time.delta_seconds(), &mut bank, clock.now());
+        }
+        if !site.is_complete() {
+            continue;
+        }
+
+        let services: Vec<StationServices> = site
+            .project
+            .services()
+            .iter()
+            .filter_map(|plan| {
+                let service = plan.build(next_service_id, &items)?;
+                next_service_id += 1;
+                Some(service)
+            })
+            .collect();
+        let station = match &site.project {
+            ConstructionProject::Station { name, .. } => {
+                let mut station = Station::new(next_station_id, name.clone(), site.system_id);
+                next_station_id += 1;
+                add_services(&mut station, services);
+                commands
+                    .spawn((
+                        station,
+                        TransformBundle::from_transform(*transform),
+                        Name::new(name.clone()),
+                    ))
+                    .id()
+            }
+            ConstructionProject::Upgrade { station_id, .. } => {
+                let Some((entity, mut station)) = stations
+                    .iter_mut()
+                    .find(|(_, station)| station.id == *station_id)
+                else {
+                    warn!(
+                        "Station {} was gone before its upgrade finished",
+                        station_id
+                    );
+                    commands.entity(site_entity).despawn_recursive();
+                    continue;
+                };
+                add_services(&mut station, services);
+                entity
+            }
+        };
+
+        commands.entity(site_entity).despawn_recursive();
+        completed.send(ConstructionCompletedEvent {
+            faction: site.owner,
+            station,
+        });
+    }
+}
+
+/// Adds finished services to a station, logging any the station has no room for
+fn add_services(station: &mut Station, services: Vec<StationServices>) {
+    for service in services {
+        if let Err(error) = station.add_service(service) {
+            warn!("Couldn't add a service to {}: {}", station.name, error);
+        }
+    }
+}
@@ -1,13 +1,119 @@
-use self::{attributes::Attributes, bank::Bank, claims::owner_changed_system};
+use self::{
+    attributes::Attributes,
+    bank::{service_faction_loans, Bank, LoanServicedEvent},
+    claims::{
+        establish_system_control, update_system_control, OwnershipChangedEvent, SystemControl,
+    },
+    definitions::FactionDefinitions,
+    diplomoacy::{
+        apply_diplomatic_incidents, measure_border_tension, record_system_captures,
+        record_trade_relations, update_relations, Diplomacy, DiplomaticIncidentEvent,
+        DiplomaticStateChangedEvent,
+    },
+    fleets::{
+        follow_fleet_action_system, move_fleets, process_fleet_membership,
+        want_to_follow_fleet_scorer_system, Fleet, FleetDisbandedEvent, FleetJoinDeniedEvent,
+        FleetJoinedEvent, FleetLeaderChangedEvent, FleetLeftEvent, JoinFleetRequestEvent,
+        LeaveFleetRequestEvent,
+    },
+    goals::{
+        assess_faction_strategy, carry_out_faction_orders, defend_borders_action_system,
+        ease_taxes_action_system, expand_territory_action_system, give_factions_thinkers,
+        grow_treasury_action_system, secure_trade_routes_action_system,
+        want_to_defend_borders_scorer_system, want_to_ease_taxes_scorer_system,
+        want_to_expand_scorer_system, want_to_grow_treasury_scorer_system,
+        want_to_secure_trade_routes_scorer_system, FactionGoals, FactionOrderEvent,
+    },
+    infrastructure::{
+        advance_construction, commission_construction, supply_construction_sites,
+        ConstructionCompletedEvent, ConstructionDeniedEvent, ConstructionRequestEvent,
+        ConstructionSite, ConstructionStartedEvent,
+    },
+    taxes::TaxPolicy,
+};
+use crate::GameState;
 use bevy::prelude::*;
+use big_brain::BigBrainSet;
+use serde::Deserialize;
 
 /// Set the game state to align systems with their respective runtimes
 pub struct FactionPlugin;
 
 impl Plugin for FactionPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, (create_faction_resourse, apply_deferred).chain())
-            .add_systems(Update, owner_changed_system);
+        app.register_type::<TaxPolicy>()
+            .register_type::<Diplomacy>()
+            .register_type::<Fleet>()
+            .register_type::<FactionGoals>()
+            .register_type::<ConstructionSite>()
+            .register_type::<SystemControl>()
+            .init_resource::<Diplomacy>()
+            .add_event::<LoanServicedEvent>()
+            .add_event::<OwnershipChangedEvent>()
+            .add_event::<DiplomaticIncidentEvent>()
+            .add_event::<DiplomaticStateChangedEvent>()
+            .add_event::<JoinFleetRequestEvent>()
+            .add_event::<LeaveFleetRequestEvent>()
+            .add_event::<FleetJoinedEvent>()
+            .add_event::<FleetJoinDeniedEvent>()
+            .add_event::<FleetLeftEvent>()
+            .add_event::<FleetLeaderChangedEvent>()
+            .add_event::<FleetDisbandedEvent>()
+            .add_event::<FactionOrderEvent>()
+            .add_event::<ConstructionRequestEvent>()
+            .add_event::<ConstructionStartedEvent>()
+            .add_event::<ConstructionDeniedEvent>()
+            .add_event::<ConstructionCompletedEvent>()
+            .add_systems(Startup, (create_faction_resourse, apply_deferred).chain())
+            .add_systems(
+                FixedUpdate,
+                (
+                    service_faction_loans,
+                    (establish_system_control, update_system_control).chain(),
+                    (
+                        measure_border_tension,
+                        record_trade_relations,
+                        record_system_captures,
+                        apply_diplomatic_incidents,
+                        update_relations,
+                    )
+                        .chain(),
+                    (process_fleet_membership, move_fleets).chain(),
+                    (
+                        give_factions_thinkers,
+                        assess_faction_strategy,
+                        carry_out_faction_orders,
+                        commission_construction,
+                        supply_construction_sites,
+                        advance_construction,
+                    )
+                        .chain(),
+                )
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    follow_fleet_action_system.in_set(BigBrainSet::Actions),
+                    want_to_follow_fleet_scorer_system.in_set(BigBrainSet::Scorers),
+                    (
+                        expand_territory_action_system,
+                        grow_treasury_action_system,
+                        ease_taxes_action_system,
+                        secure_trade_routes_action_system,
+                        defend_borders_action_system,
+                    )
+                        .in_set(BigBrainSet::Actions),
+                    (
+                        want_to_expand_scorer_system,
+                        want_to_grow_treasury_scorer_system,
+                        want_to_ease_taxes_scorer_system,
+                        want_to_secure_trade_routes_scorer_system,
+                        want_to_defend_borders_scorer_system,
+                    )
+                        .in_set(BigBrainSet::Scorers),
+                ),
+            );
     }
 }
 
@@ -15,16 +121,36 @@ impl Plugin for FactionPlugin {
 pub mod attributes;
 /// The factions bank balance
 pub mod bank;
-/// the factions claims
+/// The factions claims on solar systems, and how they win and lose them
 pub mod claims;
+/// Loading factions from data files
+pub mod definitions;
+/// Relations, treaties and wars between factions
+pub mod diplomoacy;
+/// Groups of agents flying under a faction's orders
+pub mod fleets;
+/// The factions strategic goals and the orders it gives to pursue them
+pub mod goals;
+/// Stations and upgrades the factions have under construction
+pub mod infrastructure;
+/// The pirates that prey on the other factions traders
+pub mod pirates;
+/// The factions tax rates
+pub mod taxes;
 
 /// The factions bundle
-#[derive(Bundle, Clone)]
+#[derive(Bundle, Clone, Debug, Deserialize)]
 pub struct FactionBundle {
     /// Basic faction attributes
     pub faction_attributes: Attributes,
     /// The factions bank balance
     pub faction_bank: Bank,
+    /// The taxes the faction levies in its systems
+    #[serde(default)]
+    pub faction_taxes: TaxPolicy,
+    /// How much weight the faction gives to each of its goals
+    #[serde(default)]
+    pub faction_goals: FactionGoals,
 }
 
 /// The factions resourse
@@ -34,38 +160,9 @@ pub struct FactionResourse {
     pub factions: Vec<FactionBundle>,
 }
 
-/// Creates the faction resourse
+/// Creates the faction resourse from the built in faction definitions.
+///
+/// When assets are loaded this is replaced by the factions in `ron/factions.ron`, see the `LoadingPlugin`.
 fn create_faction_resourse(mut commands: Commands) {
-    let factions_vec = vec![
-        FactionBundle {
-            faction_attributes: Attributes {
-                id: attributes::FactionID { id: 0 },
-                name: "Galactic Empire".to_string(),
-                colors: Color::rgb(0.1, 0.2, 0.5),
-            },
-            faction_bank: bank::Bank::randomized(),
-        },
-        FactionBundle {
-            faction_attributes: Attributes {
-                id: attributes::FactionID { id: 1 },
-                name: "Sith".to_string(),
-                colors: Color::rgb(0.9, 0.6, 0.2),
-            },
-            faction_bank: bank::Bank::randomized(),
-        },
-        FactionBundle {
-            faction_attributes: Attributes {
-                id: attributes::FactionID { id: 3 },
-                name: "FlimFlams".to_string(),
-                colors: Color::rgb(0.5, 0.8, 0.2),
-            },
-            faction_bank: bank::Bank::randomized(),
-        },
-    ];
-
-    let faction_resource = FactionResourse {
-        factions: factions_vec,
-    };
-
-    commands.insert_resource(faction_resource);
+    commands.insert_resource(FactionDefinitions::built_in().into_resource("built in factions"));
 }
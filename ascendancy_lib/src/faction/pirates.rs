@@ -0,0 +1,24 @@
+use bevy::prelude::*;
+
+use super::attributes::{Attributes, FactionID};
+use super::bank::Bank;
+use super::goals::FactionGoals;
+use super::taxes::TaxPolicy;
+use super::FactionBundle;
+
+/// The faction ID reserved for the pirates, which faction definitions may not use
+pub const PIRATES: FactionID = FactionID { id: u8::MAX };
+
+/// The pirate faction, which holds no systems and is at war with everyone
+pub fn pirate_faction() -> FactionBundle {
+    FactionBundle {
+        faction_attributes: Attributes {
+            id: PIRATES,
+            name: "Pirates".to_string(),
+            colors: Color::rgb(0.6, 0.05, 0.05),
+        },
+        faction_bank: Bank::new(0),
+        faction_taxes: TaxPolicy::untaxed(),
+        faction_goals: FactionGoals::default(),
+    }
+}
@@ -0,0 +1,56 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// The highest tax rate a faction can set
+pub const MAX_TAX_RATE: f32 = 0.5;
+
+/// The taxes a faction levies in the systems it owns
+#[derive(Component, Reflect, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component)]
+#[serde(default)]
+pub struct TaxPolicy {
+    /// The share of every market sale withheld from the seller
+    pub trade_rate: f32,
+    /// The share of every docking fee added on top of the fee
+    pub docking_rate: f32,
+}
+
+impl Default for TaxPolicy {
+    fn default() -> Self {
+        Self {
+            trade_rate: 0.05,
+            docking_rate: 0.1,
+        }
+    }
+}
+
+impl TaxPolicy {
+    /// A policy that levies no taxes
+    pub fn untaxed() -> Self {
+        Self {
+            trade_rate: 0.0,
+            docking_rate: 0.0,
+        }
+    }
+
+    /// Whether every rate is between zero and [`MAX_TAX_RATE`]
+    pub fn is_valid(&self) -> bool {
+        [self.trade_rate, self.docking_rate]
+            .iter()
+            .all(|rate| (0.0..=MAX_TAX_RATE).contains(rate))
+    }
+
+    /// The tax due on a docking fee, rounded to whole credits
+    pub fn docking_tax(&self, fee: f32) -> f32 {
+        (fee * self.docking_rate).round()
+    }
+
+    /// Describes the rates for the selection panel
+    pub fn describe(&self) -> String {
+        format!(
+            "Taxes: Trade - {:.0}%, Docking - {:.0}%",
+            self.trade_rate * 100.0,
+            self.docking_rate * 100.0
+        )
+    }
+}
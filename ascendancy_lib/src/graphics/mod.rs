@@ -2,8 +2,10 @@
 use bevy::prelude::{App, Plugin};
 
 use self::lighting::LightingPlugin;
+use self::presentation::PresentationPlugin;
 
 mod lighting;
+mod presentation;
 
 /// Adds game logic for rendering the game world.
 ///
@@ -12,7 +14,7 @@ pub struct GraphicsPlugin;
 
 impl Plugin for GraphicsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(LightingPlugin);
+        app.add_plugins((LightingPlugin, PresentationPlugin));
     }
 }
 
@@ -0,0 +1,230 @@
+//! Attaches sprites, meshes and picking to simulation entities.
+//!
+//! World generation only spawns simulation components, so that it can run headless.
+//! The systems in this module watch for newly added simulation entities and give them a visual representation.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::{mesh::Indices, render_resource::PrimitiveTopology};
+use bevy_mod_picking::prelude::*;
+use hexx::*;
+
+use crate::agent::agent::Agent;
+use crate::faction::claims::owner_changed_system;
+use crate::loading::loading::TextureAssets;
+use crate::player_interactions::selection::UpdateSelectedItemEvent;
+use crate::solar_system::SolarSystem;
+use crate::structures::stargate::Stargate;
+use crate::structures::station::Station;
+use crate::world_gen::Map;
+
+/// Handles attaching visuals to simulation entities
+pub(super) struct PresentationPlugin;
+
+impl Plugin for PresentationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                (
+                    attach_solar_system_meshes.run_if(resource_exists::<Map>),
+                    owner_changed_system,
+                )
+                    .chain(),
+                attach_stargate_sprites,
+                attach_station_sprites,
+                attach_agent_sprites.run_if(resource_exists::<TextureAssets>),
+            ),
+        );
+    }
+}
+
+/// Gives newly spawned solar systems a hex mesh and a coordinate label.
+fn attach_solar_system_meshes(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    map: Res<Map>,
+    solar_systems: Query<(Entity, &Transform), Added<SolarSystem>>,
+) {
+    if solar_systems.is_empty() {
+        return;
+    }
+
+    let mesh_handle = meshes.add(hexagonal_plane(&map.layout));
+    let hexes: HashMap<Entity, Hex> = map
+        .entities
+        .iter()
+        .map(|(hex, entity)| (*entity, *hex))
+        .collect();
+
+    for (entity, transform) in solar_systems.iter() {
+        let mut system = commands.entity(entity);
+        system.insert((
+            ColorMesh2dBundle {
+                transform: *transform,
+                mesh: mesh_handle.clone().into(),
+                ..default()
+            },
+            PickableBundle::default(),
+            On::<Pointer<Down>>::send_event::<UpdateSelectedItemEvent>(),
+        ));
+
+        if let Some(hex) = hexes.get(&entity) {
+            system.with_children(|parent| {
+                parent.spawn(Text2dBundle {
+                    text: Text::from_section(
+                        format!("{},{}", hex.x, hex.y),
+                        TextStyle {
+                            font_size: 64.0,
+                            color: Color::BLACK,
+                            ..Default::default()
+                        },
+                    ),
+                    transform: Transform::from_xyz(0.0, 390.0, 10.0),
+                    ..Default::default()
+                });
+            });
+        }
+    }
+}
+
+/// Gives newly spawned stargates a sprite and draws a line to their destination gate.
+fn attach_stargate_sprites(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    added_gates: Query<(Entity, &Stargate, &Transform), Added<Stargate>>,
+    all_gates: Query<(&Stargate, &Transform)>,
+) {
+    for (entity, stargate, transform) in added_gates.iter() {
+        commands.entity(entity).insert((
+            SpriteBundle {
+                texture: asset_server.load("sprites/icons/systemGate.png"),
+                transform: *transform,
+                ..Default::default()
+            },
+            PickableBundle::default(), // Optional, for interactivity.
+        ));
+
+        // Only one gate of each pair draws the connection line
+        if stargate.id > stargate.destination_gate_id {
+            continue;
+        }
+
+        if let Some((_, destination_transform)) = all_gates
+            .iter()
+            .find(|(gate, _)| gate.id == stargate.destination_gate_id)
+        {
+            create_line_between_stargates(
+                &mut commands,
+                &asset_server,
+                transform,
+                destination_transform,
+                entity,
+            );
+        }
+    }
+}
+
+/// Gives newly spawned stations a sprite.
+fn attach_station_sprites(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    stations: Query<(Entity, &Transform), Added<Station>>,
+) {
+    for (entity, transform) in stations.iter() {
+        commands.entity(entity).insert((
+            SpriteBundle {
+                texture: asset_server.load("sprites/icons/structures/station_1.png"),
+                transform: *transform,
+                ..Default::default()
+            },
+            PickableBundle::default(),
+            On::<Pointer<Down>>::send_event::<UpdateSelectedItemEvent>(),
+        ));
+    }
+}
+
+/// Gives newly spawned agents a sprite.
+fn attach_agent_sprites(
+    mut commands: Commands,
+    textures: Res<TextureAssets>,
+    agents: Query<(Entity, &Transform), Added<Agent>>,
+) {
+    for (entity, transform) in agents.iter() {
+        commands.entity(entity).insert((
+            SpriteBundle {
+                texture: textures.small_trader.clone(),
+                transform: *transform,
+                ..Default::default()
+            },
+            PickableBundle::default(),
+            On::<Pointer<Down>>::send_event::<UpdateSelectedItemEvent>(),
+        ));
+    }
+}
+
+/// Returns a mesh for a hexagonal plane.
+fn hexagonal_plane(hex_layout: &HexLayout) -> Mesh {
+    let mesh_info = PlaneMeshBuilder::new(hex_layout).facing(Vec3::Z).build();
+    let mut mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, mesh_info.vertices);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, mesh_info.normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, mesh_info.uvs);
+    mesh.insert_indices(Indices::U16(mesh_info.indices));
+    mesh
+}
+
+/// Creates a visual line between two stargates.
+fn create_line_between_stargates(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    origin_transform: &Transform,
+    destination_transform: &Transform,
+    parent_entity: Entity,
+) {
+    // Draw a line between the origin stargate and the destination stargate
+    let midpoint = (origin_transform.translation + destination_transform.translation) * 0.5;
+    let distance = origin_transform
+        .translation
+        .distance(destination_transform.translation);
+    let angle = (destination_transform.translation.y - origin_transform.translation.y)
+        .atan2(destination_transform.translation.x - origin_transform.translation.x);
+
+    let child_intended_global_transform = Transform {
+        translation: midpoint,
+        rotation: Quat::from_rotation_z(angle),
+        scale: Vec3::new(distance, 1.0, 1.0),
+    };
+    // Inverse rotation
+    let inverse_rotation = origin_transform.rotation.conjugate();
+
+    // Inverse translation rotated by the inverse rotation
+    let inverse_translation = inverse_rotation * -origin_transform.translation;
+
+    let parent_inverse_transform = Transform {
+        translation: inverse_translation,
+        rotation: inverse_rotation,
+        ..Default::default() // Assuming the scale is just 1, 1, 1
+    };
+
+    let local_transform = parent_inverse_transform * child_intended_global_transform;
+
+    commands.entity(parent_entity).with_children(|parent| {
+        parent
+            .spawn(SpriteBundle {
+                texture: asset_server.load("sprites/icons/line.png"),
+                transform: Transform {
+                    translation: local_transform.translation,
+                    rotation: Quat::from_rotation_z(angle),
+                    scale: Vec3::new(distance, 1.0, 1.0),
+                },
+                ..Default::default()
+            })
+            .insert(Name::new("Stargate Connection Line")); // Optional, for debugging.
+    });
+}
@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{ItemDefinition, ItemId};
+
+/// A store of items with separate volume limits for solid and fluid goods.
+///
+/// Every change goes through [`Inventory::apply`], which checks the whole change before making it,
+/// so a failed change never leaves the inventory half updated.
+#[derive(Component, Clone, Debug, Default, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct Inventory {
+    /// The items held, keyed by their ID
+    stock: HashMap<ItemId, ItemStack>,
+    /// The total volume available for solid goods
+    solid_capacity: f32,
+    /// The total volume available for fluid goods
+    fluid_capacity: f32,
+}
+
+/// A quantity of a single item, along with the properties needed to store it.
+#[derive(Clone, Copy, Debug, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct ItemStack {
+    /// The number of units held
+    pub quantity: u32,
+    /// The volume taken up by a single unit
+    pub volume_per_unit: f32,
+    /// Whether the item is kept in fluid storage
+    pub fluid: bool,
+}
+
+impl ItemStack {
+    /// The total volume taken up by the stack
+    pub fn volume(&self) -> f32 {
+        self.quantity as f32 * self.volume_per_unit
+    }
+}
+
+/// Why an inventory change was rejected
+#[derive(Debug, Clone, PartialEq)]
+pub enum InventoryError {
+    /// There is not enough room in the solid or fluid storage
+    InsufficientSpace {
+        /// Whether the fluid storage ran out of room
+        fluid: bool,
+        /// The volume the goods would take up
+        required: f32,
+        /// The capacity of the storage
+        available: f32,
+    },
+    /// There are not enough units of an item to remove
+    InsufficientStock {
+        /// The item that ran out
+        item: ItemId,
+        /// The number of units that were needed
+        required: u32,
+        /// The number of units that were held
+        available: u32,
+    },
+    /// A stack would hold more units of an item than can be counted
+    QuantityOverflow {
+        /// The item being added
+        item: ItemId,
+        /// The number of units already held
+        held: u32,
+        /// The number of units being added
+        added: u32,
+    },
+}
+
+impl fmt::Display for InventoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InsufficientSpace {
+                fluid,
+                required,
+                available,
+            } => write!(
+                f,
+                "not enough {} storage, {} needed but the capacity is {}",
+                if *fluid { "fluid" } else { "solid" },
+                required,
+                available
+            ),
+            Self::InsufficientStock {
+                item,
+                required,
+                available,
+            } => write!(
+                f,
+                "not enough {}, {} needed but only {} held",
+                item, required, available
+            ),
+            Self::QuantityOverflow { item, held, added } => write!(
+                f,
+                "can't add {} {} to the {} held without overflowing",
+                added, item, held
+            ),
+        }
+    }
+}
+
+impl std::error::Error for InventoryError {}
+
+/// A set of additions and removals that are applied to an [`Inventory`] all at once.
+#[derive(Clone, Debug, Default)]
+pub struct InventoryTransaction {
+    /// The items to add
+    additions: Vec<(ItemId, ItemStack)>,
+    /// The items to remove
+    removals: Vec<(ItemId, u32)>,
+}
+
+impl InventoryTransaction {
+    /// Creates an empty transaction
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds some units of an item
+    pub fn add(self, item: &ItemDefinition, quantity: u32) -> Self {
+        self.add_stack(
+            item.id,
+            ItemStack {
+                quantity,
+                volume_per_unit: item.volume_per_unit,
+                fluid: item.fluid,
+            },
+        )
+    }
+
+    /// Adds a stack of an item
+    pub fn add_stack(mut self, item: ItemId, stack: ItemStack) -> Self {
+        self.additions.push((item, stack));
+        self
+    }
+
+    /// Removes some units of an item
+    pub fn remove(mut self, item: ItemId, quantity: u32) -> Self {
+        self.removals.push((item, quantity));
+        self
+    }
+}
+
+impl Inventory {
+    /// Creates an empty inventory with the given solid and fluid capacities
+    pub fn new(solid_capacity: f32, fluid_capacity: f32) -> Self {
+        Self {
+            stock: HashMap::new(),
+            solid_capacity,
+            fluid_capacity,
+        }
+    }
+
+    /// The number of units of an item held
+    pub fn quantity(&self, item: ItemId) -> u32 {
+        self.stock.get(&item).map_or(0, |stack| stack.quantity)
+    }
+
+    /// The stack of an item held, if there is one
+    pub fn stack(&self, item: ItemId) -> Option<&ItemStack> {
+        self.stock.get(&item)
+    }
+
+    /// Iterate over every item held
+    pub fn iter(&self) -> impl Iterator<Item = (ItemId, &ItemStack)> {
+        self.stock.iter().map(|(id, stack)| (*id, stack))
+    }
+
+    /// Whether nothing is held
+    pub fn is_empty(&self) -> bool {
+        self.stock.is_empty()
+    }
+
+    /// The total volume of the solid or fluid storage
+    pub fn capacity(&self, fluid: bool) -> f32 {
+        if fluid {
+            self.fluid_capacity
+        } else {
+            self.solid_capacity
+        }
+    }
+
+    /// The volume used in the solid or fluid storage
+    pub fn used_volume(&self, fluid: bool) -> f32 {
+        self.stock
+            .values()
+            .filter(|stack| stack.fluid == fluid)
+            .map(ItemStack::volume)
+            .sum()
+    }
+
+    /// The volume still free in the solid or fluid storage
+    pub fn free_volume(&self, fluid: bool) -> f32 {
+        (self.capacity(fluid) - self.used_volume(fluid)).max(0.0)
+    }
+
+    /// The number of units of an item that would still fit
+    pub fn room_for(&self, item: &ItemDefinition) -> u32 {
+        (self.free_volume(item.fluid) / item.volume_per_unit).floor() as u32
+    }
+
+    /// Adds some units of an item, failing if they don't fit
+    pub fn add(&mut self, item: &ItemDefinition, quantity: u32) -> Result<(), InventoryError> {
+        self.apply(InventoryTransaction::new().add(item, quantity))
+    }
+
+    /// Removes some units of an item, failing if not enough are held
+    pub fn remove(&mut self, item: ItemId, quantity: u32) -> Result<(), InventoryError> {
+        self.apply(InventoryTransaction::new().remove(item, quantity))
+    }
+
+    /// Applies every change in the transaction, or none of them if any would fail.
+    ///
+    /// Removals are applied before additions, so goods can be swapped in a full inventory.
+    pub fn apply(&mut self, transaction: InventoryTransaction) -> Result<(), InventoryError> {
+        let mut result = self.stock.clone();
+
+        for (item, quantity) in transaction.removals {
+            let available = result.get(&item).map_or(0, |stack| stack.quantity);
+            if available < quantity {
+                return Err(InventoryError::InsufficientStock {
+                    item,
+                    required: quantity,
+                    available,
+                });
+            }
+            if available == quantity {
+                result.remove(&item);
+            } else if let Some(stack) = result.get_mut(&item) {
+                stack.quantity -= quantity;
+            }
+        }
+
+        for (item, added) in transaction.additions {
+            if added.quantity == 0 {
+                continue;
+            }
+            match result.get_mut(&item) {
+                Some(stack) => {
+                    stack.quantity = stack.quantity.checked_add(added.quantity).ok_or(
+                        InventoryError::QuantityOverflow {
+                            item,
+                            held: stack.quantity,
+                            added: added.quantity,
+                        },
+                    )?;
+                }
+                None => {
+                    result.insert(item, added);
+                }
+            }
+        }
+
+        for fluid in [false, true] {
+            let required: f32 = result
+                .values()
+                .filter(|stack| stack.fluid == fluid)
+                .map(ItemStack::volume)
+                .sum();
+            if required > self.capacity(fluid) {
+                return Err(InventoryError::InsufficientSpace {
+                    fluid,
+                    required,
+                    available: self.capacity(fluid),
+                });
+            }
+        }
+
+        self.stock = result;
+        Ok(())
+    }
+
+    /// Moves some units of an item from one inventory to another, changing neither if it fails.
+    pub fn transfer(
+        from: &mut Inventory,
+        to: &mut Inventory,
+        item: &ItemDefinition,
+        quantity: u32,
+    ) -> Result<(), InventoryError> {
+        let mut source = from.clone();
+        source.remove(item.id, quantity)?;
+        to.add(item, quantity)?;
+        *from = source;
+        Ok(())
+    }
+}
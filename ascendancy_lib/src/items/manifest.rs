@@ -0,0 +1,305 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use super::recipe::RecipeManifestEntry;
+use super::{ItemDefinition, ItemId, ItemRegistry};
+
+/// The file extension shared by every item manifest.
+pub const ITEM_MANIFEST_EXTENSION: &str = ".item_manifest.json";
+
+/// The manifest that ships with the game, used when no manifest directory can be found.
+const BASE_GAME_MANIFEST: &str =
+    include_str!("../../../ascendancy_game/manifests/base_game.item_manifest.json");
+
+/// The directory the item manifests are loaded from.
+///
+/// Defaults to `manifests` next to the game's `Cargo.toml` when run through cargo, or next to the executable otherwise.
+#[derive(Resource, Debug, Clone)]
+pub struct ManifestDirectory(pub PathBuf);
+
+impl Default for ManifestDirectory {
+    fn default() -> Self {
+        let base = std::env::var_os("CARGO_MANIFEST_DIR")
+            .map(PathBuf::from)
+            .or_else(|| {
+                std::env::current_exe()
+                    .ok()
+                    .and_then(|exe| exe.parent().map(Path::to_path_buf))
+            })
+            .unwrap_or_default();
+        Self(base.join("manifests"))
+    }
+}
+
+/// The contents of an item manifest file, see `manifests/schema/items.schema.json`.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ItemManifest {
+    /// The schema the manifest was written against, only used by editors
+    #[serde(rename = "$schema", default)]
+    pub schema: Option<String>,
+    /// The items, keyed by their item key
+    pub items: BTreeMap<String, ItemManifestEntry>,
+    /// The factory recipes, keyed by their recipe key
+    #[serde(default)]
+    pub recipes: BTreeMap<String, RecipeManifestEntry>,
+}
+
+/// A single item in a manifest
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ItemManifestEntry {
+    /// The cargo volume taken up by a single unit
+    pub volume_per_unit: f32,
+    /// Whether the item must be kept in fluid storage
+    pub fluid: bool,
+    /// The category the item is listed under
+    pub category: String,
+    /// The price markets start trading the item at
+    #[serde(default = "default_base_price")]
+    pub base_price: f32,
+}
+
+/// The base price of items that don't set one
+fn default_base_price() -> f32 {
+    10.0
+}
+
+/// A problem found while loading an item manifest
+#[derive(Debug)]
+pub enum ManifestError {
+    /// The manifest could not be read
+    Io(PathBuf, std::io::Error),
+    /// The manifest is not valid JSON, or does not match the schema's structure
+    Malformed(PathBuf, serde_json::Error),
+    /// An item breaks one of the schema's rules
+    InvalidItem {
+        /// The manifest the item is in
+        path: PathBuf,
+        /// The item's key
+        key: String,
+        /// The rule that was broken
+        reason: String,
+    },
+    /// A recipe breaks one of the schema's rules, or uses an item that isn't defined
+    InvalidRecipe {
+        /// The manifest the recipe is in
+        path: PathBuf,
+        /// The recipe's key
+        key: String,
+        /// The rule that was broken
+        reason: String,
+    },
+    /// Two different item keys hash to the same ID
+    IdCollision {
+        /// The item that was registered first
+        first: String,
+        /// The item that was skipped
+        second: String,
+    },
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(path, error) => write!(f, "could not read {}: {}", path.display(), error),
+            Self::Malformed(path, error) => {
+                write!(
+                    f,
+                    "{} does not match the item schema: {}",
+                    path.display(),
+                    error
+                )
+            }
+            Self::InvalidItem { path, key, reason } => {
+                write!(f, "item '{}' in {} {}", key, path.display(), reason)
+            }
+            Self::InvalidRecipe { path, key, reason } => {
+                write!(f, "recipe '{}' in {} {}", key, path.display(), reason)
+            }
+            Self::IdCollision { first, second } => write!(
+                f,
+                "item '{}' has the same id as '{}', rename one of them",
+                second, first
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ManifestError {}
+
+impl ItemManifest {
+    /// Parses a manifest from JSON, `path` is only used for error messages.
+    pub fn from_json(path: &Path, source: &str) -> Result<Self, ManifestError> {
+        serde_json::from_str(source).map_err(|error| ManifestError::Malformed(path.into(), error))
+    }
+
+    /// Reads and parses the manifest at the given path.
+    pub fn read(path: &Path) -> Result<Self, ManifestError> {
+        let source =
+            fs::read_to_string(path).map_err(|error| ManifestError::Io(path.into(), error))?;
+        Self::from_json(path, &source)
+    }
+
+    /// Checks every item against the schema's rules, returning the valid items and an error for each invalid one.
+    ///
+    /// These checks, not the schema file, decide what the game accepts; the schema repeats the same rules for editors.
+    /// `tests/item_manifests.rs` fails if the two disagree, so change both together.
+    pub fn validate(self, path: &Path) -> (Vec<ItemDefinition>, Vec<ManifestError>) {
+        let mut items = Vec::new();
+        let mut errors = Vec::new();
+
+        for (key, entry) in self.items {
+            let reason = if key.is_empty()
+                || !key
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+            {
+                Some("must have a key made of lowercase letters, digits and underscores")
+            } else if !(entry.volume_per_unit.is_finite() && entry.volume_per_unit > 0.0) {
+                Some("must have a volume_per_unit greater than 0")
+            } else if !(entry.base_price.is_finite() && entry.base_price > 0.0) {
+                Some("must have a base_price greater than 0")
+            } else if entry.category.trim().is_empty() {
+                Some("must have a category")
+            } else {
+                None
+            };
+
+            match reason {
+                Some(reason) => errors.push(ManifestError::InvalidItem {
+                    path: path.into(),
+                    key,
+                    reason: reason.to_string(),
+                }),
+                None => items.push(ItemDefinition {
+                    id: ItemId::from_key(&key),
+                    key,
+                    volume_per_unit: entry.volume_per_unit,
+                    fluid: entry.fluid,
+                    category: entry.category,
+                    base_price: entry.base_price,
+                }),
+            }
+        }
+
+        (items, errors)
+    }
+}
+
+impl ItemRegistry {
+    /// Builds a registry from manifests, applied in order so later manifests extend or override earlier ones.
+    ///
+    /// Recipes are checked once every item is known, so a recipe can use items from any manifest.
+    /// Invalid manifests, items and recipes are skipped, and an error is returned for each of them.
+    pub fn from_manifests(
+        manifests: impl IntoIterator<Item = (PathBuf, Result<ItemManifest, ManifestError>)>,
+    ) -> (Self, Vec<ManifestError>) {
+        let mut registry = ItemRegistry::default();
+        let mut errors = Vec::new();
+        let mut recipes = BTreeMap::new();
+
+        for (path, manifest) in manifests {
+            let mut manifest = match manifest {
+                Ok(manifest) => manifest,
+                Err(error) => {
+                    errors.push(error);
+                    continue;
+                }
+            };
+
+            for (key, recipe) in std::mem::take(&mut manifest.recipes) {
+                recipes.insert(key, (path.clone(), recipe));
+            }
+
+            let (items, item_errors) = manifest.validate(&path);
+            errors.extend(item_errors);
+            for item in items {
+                if let Err(error) = registry.insert(item) {
+                    errors.push(error);
+                }
+            }
+        }
+
+        for (key, (path, recipe)) in recipes {
+            match recipe.resolve(&path, key, &registry) {
+                Ok(recipe) => {
+                    registry.recipes.insert(recipe.key.clone(), recipe);
+                }
+                Err(error) => errors.push(error),
+            }
+        }
+
+        (registry, errors)
+    }
+
+    /// Builds a registry from the `*.item_manifest.json` files in a directory.
+    ///
+    /// `base_game.item_manifest.json` is applied first, followed by the other manifests in alphabetical order.
+    pub fn from_directory(directory: &Path) -> Result<(Self, Vec<ManifestError>), ManifestError> {
+        let mut paths: Vec<PathBuf> = fs::read_dir(directory)
+            .map_err(|error| ManifestError::Io(directory.into(), error))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.ends_with(ITEM_MANIFEST_EXTENSION))
+            })
+            .collect();
+        paths.sort_by_key(|path| {
+            let is_base_game = path
+                .file_name()
+                .is_some_and(|name| name == "base_game.item_manifest.json");
+            (!is_base_game, path.clone())
+        });
+
+        Ok(Self::from_manifests(paths.into_iter().map(|path| {
+            let manifest = ItemManifest::read(&path);
+            (path, manifest)
+        })))
+    }
+
+    /// Builds a registry from the manifest that ships with the game.
+    pub fn built_in() -> Self {
+        let path = PathBuf::from("base_game.item_manifest.json");
+        let manifest = ItemManifest::from_json(&path, BASE_GAME_MANIFEST);
+        let (registry, errors) = Self::from_manifests([(path, manifest)]);
+        assert!(errors.is_empty(), "the built in item manifest is valid");
+        registry
+    }
+}
+
+/// Builds the [`ItemRegistry`] from the manifest directory, logging every problem that is found.
+pub(super) fn load_item_registry(
+    mut commands: Commands,
+    directory: Option<Res<ManifestDirectory>>,
+) {
+    let directory = directory
+        .map(|directory| directory.clone())
+        .unwrap_or_default();
+
+    let registry = match ItemRegistry::from_directory(&directory.0) {
+        Ok((registry, errors)) => {
+            for error in errors.iter() {
+                error!("Skipping item manifest entry: {}", error);
+            }
+            registry
+        }
+        Err(error) => {
+            warn!("{}, using the built in items", error);
+            ItemRegistry::built_in()
+        }
+    };
+
+    info!(
+        "Loaded {} items and {} recipes",
+        registry.len(),
+        registry.recipes().count()
+    );
+    commands.insert_resource(registry);
+}
@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use self::manifest::{load_item_registry, ManifestError};
+use self::recipe::Recipe;
+
+/// Storing quantities of items
+pub mod inventory;
+/// Reading item manifests from disk
+pub mod manifest;
+/// Factory recipes
+pub mod recipe;
+
+/// Loads the item catalogue that every part of the economy refers to.
+pub struct ItemPlugin;
+
+impl Plugin for ItemPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, (load_item_registry, apply_deferred).chain());
+    }
+}
+
+/// A handle to an item in the [`ItemRegistry`].
+///
+/// The ID is a hash of the item's key, so it is the same in every session and can be saved.
+#[derive(
+    Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug, Reflect, Serialize, Deserialize,
+)]
+pub struct ItemId(u32);
+
+impl ItemId {
+    /// Energy cells, produced by solar generators and consumed by most services.
+    pub const ENERGY_CELLS: ItemId = ItemId::from_key("energy_cells");
+    /// Unrefined ore, mined from resource fields.
+    pub const RAW_ORE: ItemId = ItemId::from_key("raw_ore");
+    /// Gas collected from resource fields.
+    pub const HYDROGEN: ItemId = ItemId::from_key("hydrogen");
+    /// Metal refined from ore by factories.
+    pub const REFINED_METAL: ItemId = ItemId::from_key("refined_metal");
+
+    /// Get the ID for the item with the given key, using the 32 bit FNV-1a hash.
+    pub const fn from_key(key: &str) -> Self {
+        let bytes = key.as_bytes();
+        let mut hash: u32 = 0x811c_9dc5;
+        let mut i = 0;
+        while i < bytes.len() {
+            hash ^= bytes[i] as u32;
+            hash = hash.wrapping_mul(0x0100_0193);
+            i += 1;
+        }
+        ItemId(hash)
+    }
+}
+
+impl fmt::Display for ItemId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "item#{:08x}", self.0)
+    }
+}
+
+/// Everything the simulation needs to know about an item.
+#[derive(Clone, Debug, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct ItemDefinition {
+    /// The item's ID
+    pub id: ItemId,
+    /// The key the item is defined under in its manifest
+    pub key: String,
+    /// The cargo volume taken up by a single unit
+    pub volume_per_unit: f32,
+    /// Whether the item must be kept in fluid storage
+    pub fluid: bool,
+    /// The category the item is listed under
+    pub category: String,
+    /// The price markets start trading the item at
+    pub base_price: f32,
+}
+
+/// Every item and recipe known to the game, built from the loaded item manifests.
+#[derive(Resource, Default, Debug, Clone)]
+pub struct ItemRegistry {
+    /// The items, keyed by their ID
+    items: HashMap<ItemId, ItemDefinition>,
+    /// The factory recipes, keyed by their recipe key
+    recipes: HashMap<String, Recipe>,
+}
+
+impl ItemRegistry {
+    /// Adds an item, replacing any existing item with the same key.
+    ///
+    /// Fails if a different item already has the same ID.
+    pub fn insert(&mut self, item: ItemDefinition) -> Result<(), ManifestError> {
+        if let Some(existing) = self.items.get(&item.id) {
+            if existing.key != item.key {
+                return Err(ManifestError::IdCollision {
+                    first: existing.key.clone(),
+                    second: item.key,
+                });
+            }
+        }
+
+        self.items.insert(item.id, item);
+        Ok(())
+    }
+
+    /// Get an item by its ID
+    pub fn get(&self, id: ItemId) -> Option<&ItemDefinition> {
+        self.items.get(&id)
+    }
+
+    /// Get the ID of the item with the given key, if it is registered
+    pub fn id(&self, key: &str) -> Option<ItemId> {
+        let id = ItemId::from_key(key);
+        self.items.contains_key(&id).then_some(id)
+    }
+
+    /// Whether the item is registered
+    pub fn contains(&self, id: ItemId) -> bool {
+        self.items.contains_key(&id)
+    }
+
+    /// Iterate over every registered item
+    pub fn iter(&self) -> impl Iterator<Item = &ItemDefinition> {
+        self.items.values()
+    }
+
+    /// Get a recipe by its key
+    pub fn recipe(&self, key: &str) -> Option<&Recipe> {
+        self.recipes.get(key)
+    }
+
+    /// Iterate over every registered recipe
+    pub fn recipes(&self) -> impl Iterator<Item = &Recipe> {
+        self.recipes.values()
+    }
+
+    /// The number of registered items
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Whether no items are registered
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
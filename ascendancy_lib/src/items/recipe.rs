@@ -0,0 +1,134 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::inventory::{InventoryTransaction, ItemStack};
+use super::manifest::ManifestError;
+use super::{ItemId, ItemRegistry};
+
+/// How a factory turns input items and energy into output items.
+#[derive(Clone, Debug, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct Recipe {
+    /// The key the recipe is defined under in its manifest
+    pub key: String,
+    /// The items used up by each cycle
+    pub inputs: Vec<(ItemId, u32)>,
+    /// The items made by each cycle, along with how they are stored
+    pub outputs: Vec<(ItemId, ItemStack)>,
+    /// The station energy used up by each cycle
+    pub energy: f32,
+    /// How long a cycle takes, in seconds
+    pub cycle_seconds: f32,
+}
+
+impl Recipe {
+    /// The inventory change made by one cycle, removing the inputs and adding the outputs
+    pub fn transaction(&self) -> InventoryTransaction {
+        let transaction = self.inputs.iter().fold(
+            InventoryTransaction::new(),
+            |transaction, (item, quantity)| transaction.remove(*item, *quantity),
+        );
+        self.outputs
+            .iter()
+            .fold(transaction, |transaction, (item, stack)| {
+                transaction.add_stack(*item, *stack)
+            })
+    }
+}
+
+/// A single recipe in a manifest, with items referred to by their keys
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct RecipeManifestEntry {
+    /// The units of each item used up by each cycle
+    #[serde(default)]
+    pub inputs: BTreeMap<String, u32>,
+    /// The units of each item made by each cycle
+    pub outputs: BTreeMap<String, u32>,
+    /// The station energy used up by each cycle
+    #[serde(default)]
+    pub energy: f32,
+    /// How long a cycle takes, in seconds
+    pub cycle_seconds: f32,
+}
+
+impl RecipeManifestEntry {
+    /// Checks the recipe against the schema's rules and looks up its items.
+    ///
+    /// Like [`super::manifest::ItemManifest::validate`], these checks are the source of truth the schema is tested against.
+    /// Items are looked up once every manifest has been read, so recipes can use items from other manifests.
+    pub fn resolve(
+        self,
+        path: &Path,
+        key: String,
+        items: &ItemRegistry,
+    ) -> Result<Recipe, ManifestError> {
+        let invalid = |key: String, reason: String| ManifestError::InvalidRecipe {
+            path: path.into(),
+            key,
+            reason,
+        };
+
+        if key.is_empty()
+            || !key
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        {
+            return Err(invalid(
+                key,
+                "must have a key made of lowercase letters, digits and underscores".to_string(),
+            ));
+        }
+        if self.outputs.is_empty() {
+            return Err(invalid(key, "must have at least one output".to_string()));
+        }
+        if !(self.cycle_seconds.is_finite() && self.cycle_seconds > 0.0) {
+            return Err(invalid(
+                key,
+                "must have a cycle_seconds greater than 0".to_string(),
+            ));
+        }
+        if !(self.energy.is_finite() && self.energy >= 0.0) {
+            return Err(invalid(key, "must not have negative energy".to_string()));
+        }
+
+        let mut inputs = Vec::new();
+        for (item_key, quantity) in self.inputs {
+            match items.id(&item_key) {
+                Some(_) if quantity == 0 => {
+                    return Err(invalid(key, format!("uses 0 of input '{}'", item_key)))
+                }
+                Some(id) => inputs.push((id, quantity)),
+                None => return Err(invalid(key, format!("uses unknown item '{}'", item_key))),
+            }
+        }
+
+        let mut outputs = Vec::new();
+        for (item_key, quantity) in self.outputs {
+            match items.id(&item_key).and_then(|id| items.get(id)) {
+                Some(_) if quantity == 0 => {
+                    return Err(invalid(key, format!("makes 0 of output '{}'", item_key)))
+                }
+                Some(item) => outputs.push((
+                    item.id,
+                    ItemStack {
+                        quantity,
+                        volume_per_unit: item.volume_per_unit,
+                        fluid: item.fluid,
+                    },
+                )),
+                None => return Err(invalid(key, format!("makes unknown item '{}'", item_key))),
+            }
+        }
+
+        Ok(Recipe {
+            key,
+            inputs,
+            outputs,
+            energy: self.energy,
+            cycle_seconds: self.cycle_seconds,
+        })
+    }
+}
@@ -17,12 +17,18 @@ pub mod enum_iter;
 pub mod faction;
 /// Graphics module
 pub mod graphics;
+/// Item catalogue module
+pub mod items;
 ///Asset loading
 pub mod loading;
 /// Menu manager
 pub mod menu;
 /// Player interactions module
 pub mod player_interactions;
+/// Save game module
+pub mod save;
+/// Headless simulation plugins
+pub mod simulation;
 /// Solar system module
 pub mod solar_system;
 /// structures
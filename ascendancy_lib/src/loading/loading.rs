@@ -1,6 +1,8 @@
+use crate::faction::definitions::FactionDefinitions;
 use crate::GameState;
 use bevy::prelude::*;
 use bevy_asset_loader::prelude::*;
+use bevy_common_assets::ron::RonAssetPlugin;
 
 ///Asset loading plugin
 pub struct LoadingPlugin;
@@ -10,11 +12,16 @@ pub struct LoadingPlugin;
 /// If interested, take a look at <https://bevy-cheatbook.github.io/features/assets.html>
 impl Plugin for LoadingPlugin {
     fn build(&self, app: &mut App) {
-        app.add_loading_state(
-            LoadingState::new(GameState::Loading)
-                .continue_to_state(GameState::Menu)
-                .load_collection::<TextureAssets>(),
-        );
+        app.add_plugins(RonAssetPlugin::<FactionDefinitions>::new(&["factions.ron"]))
+            .add_loading_state(
+                LoadingState::new(GameState::Loading)
+                    .continue_to_state(GameState::Menu)
+                    // A broken data file is reported by load_factions rather than leaving the game stuck loading
+                    .on_failure_continue_to_state(GameState::Menu)
+                    .load_collection::<TextureAssets>()
+                    .load_collection::<FactionAssets>(),
+            )
+            .add_systems(OnExit(GameState::Loading), load_factions);
     }
 }
 
@@ -34,3 +41,27 @@ pub struct TextureAssets {
     #[asset(path = "sprites/icons/ships/small-trader.png")]
     pub small_trader: Handle<Image>,
 }
+
+/// Holds the data files that define the factions
+#[derive(AssetCollection, Resource)]
+pub struct FactionAssets {
+    /// The factions in the galaxy
+    #[asset(path = "ron/factions.ron")]
+    pub factions: Handle<FactionDefinitions>,
+}
+
+/// Replaces the built in factions with the ones loaded from `ron/factions.ron`.
+///
+/// The built in factions are kept if the file failed to load.
+fn load_factions(
+    mut commands: Commands,
+    faction_assets: Option<Res<FactionAssets>>,
+    definitions: Res<Assets<FactionDefinitions>>,
+) {
+    match faction_assets.and_then(|assets| definitions.get(&assets.factions)) {
+        Some(factions) => {
+            commands.insert_resource(factions.clone().into_resource("ron/factions.ron"));
+        }
+        None => error!("ron/factions.ron was not loaded, using the built in factions"),
+    }
+}
@@ -38,7 +38,9 @@ impl Default for ButtonColors {
 struct Menu;
 
 ///Setup the menu
-fn setup_menu(mut commands: Commands, textures: Res<TextureAssets>) {
+///
+/// The icons are left blank if the textures failed to load.
+fn setup_menu(mut commands: Commands, textures: Option<Res<TextureAssets>>) {
     info!("menu");
 
     //commands.spawn(Camera2dBundle::default());
@@ -131,7 +133,11 @@ fn setup_menu(mut commands: Commands, textures: Res<TextureAssets>) {
                         },
                     ));
                     parent.spawn(ImageBundle {
-                        image: textures.bevy.clone().into(),
+                        image: textures
+                            .as_ref()
+                            .map(|textures| textures.bevy.clone())
+                            .unwrap_or_default()
+                            .into(),
                         style: Style {
                             width: Val::Px(32.),
                             ..default()
@@ -168,7 +174,11 @@ fn setup_menu(mut commands: Commands, textures: Res<TextureAssets>) {
                         },
                     ));
                     parent.spawn(ImageBundle {
-                        image: textures.github.clone().into(),
+                        image: textures
+                            .as_ref()
+                            .map(|textures| textures.github.clone())
+                            .unwrap_or_default()
+                            .into(),
                         style: Style {
                             width: Val::Px(32.),
                             ..default()
@@ -2,6 +2,7 @@ use bevy::prelude::*;
 use leafwing_input_manager::prelude::*;
 
 use self::selection::{listen_for_clicked_event, UpdateSelectedItemEvent};
+use crate::save::{LoadGameEvent, SaveGameEvent, QUICK_SAVE_PATH};
 use leafwing_input_manager::Actionlike;
 
 pub(crate) mod camera;
@@ -19,7 +20,7 @@ impl Plugin for InteractionPlugin {
             .insert_resource(PlayerAction::default_input_map())
             .insert_resource(selection::Selection::new())
             .add_plugins(camera::CameraPlugin)
-            .add_systems(Update, listen_for_clicked_event);
+            .add_systems(Update, (listen_for_clicked_event, quick_save_and_load));
     }
 }
 
@@ -63,6 +64,10 @@ pub enum PlayerAction {
     RotateCameraRight,
     ///Reset the camera position back to 0,0,0 and 0 rotation
     ResetCameraPosition,
+    /// Save the game to the quick save file
+    QuickSave,
+    /// Load the game from the quick save file
+    QuickLoad,
 }
 
 impl PlayerAction {
@@ -87,6 +92,8 @@ impl PlayerAction {
             Self::RotateCameraLeft => KeyCode::KeyQ.into(),
             Self::RotateCameraRight => KeyCode::KeyE.into(),
             Self::ResetCameraPosition => KeyCode::KeyR.into(),
+            Self::QuickSave => KeyCode::F5.into(),
+            Self::QuickLoad => KeyCode::F9.into(),
         }
     }
 
@@ -116,7 +123,30 @@ impl PlayerAction {
             Self::ResetCameraPosition,
             Self::ResetCameraPosition.kbm_binding(),
         );
+        input_map.insert(Self::QuickSave, Self::QuickSave.kbm_binding());
+        input_map.insert(Self::QuickLoad, Self::QuickLoad.kbm_binding());
         // Return the input_map
         input_map
     }
 }
+
+/// Saves or loads the quick save when the player presses the keybinding.
+fn quick_save_and_load(
+    query: Query<&ActionState<PlayerAction>, With<Camera2d>>,
+    mut save_events: EventWriter<SaveGameEvent>,
+    mut load_events: EventWriter<LoadGameEvent>,
+) {
+    let Ok(action_state) = query.get_single() else {
+        return;
+    };
+
+    if action_state.just_pressed(&PlayerAction::QuickSave) {
+        save_events.send(SaveGameEvent {
+            path: QUICK_SAVE_PATH.into(),
+        });
+    } else if action_state.just_pressed(&PlayerAction::QuickLoad) {
+        load_events.send(LoadGameEvent {
+            path: QUICK_SAVE_PATH.into(),
+        });
+    }
+}
@@ -0,0 +1,473 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use hexx::{Hex, HexLayout};
+use serde::{Deserialize, Serialize};
+
+use crate::agent::agent::{Agent, AgentRole};
+use crate::agent::cargo::CargoHold;
+use crate::agent::combat::Wreck;
+use crate::agent::pathfinding::SystemGraph;
+use crate::faction::attributes::Attributes;
+use crate::faction::bank::Bank;
+use crate::faction::claims::SystemControl;
+use crate::faction::diplomoacy::Diplomacy;
+use crate::faction::fleets::{Fleet, FleetMember};
+use crate::faction::goals::FactionGoals;
+use crate::faction::infrastructure::ConstructionSite;
+use crate::faction::taxes::TaxPolicy;
+use crate::faction::{FactionBundle, FactionResourse};
+use crate::items::ItemRegistry;
+use crate::simulation::SimulationClock;
+use crate::solar_system::resources::ResourceFields;
+use crate::solar_system::SolarSystem;
+use crate::structures::services::dock::DockedAt;
+use crate::structures::stargate::Stargate;
+use crate::structures::station::Station;
+use crate::world_gen::npc_generation::agent_bundle;
+use crate::world_gen::{GalaxySeed, Map};
+use crate::GameState;
+
+/// A snapshot of the whole simulation, as written to a save file.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SaveGame {
+    /// The seed the galaxy was generated from
+    pub galaxy_seed: Option<u64>,
+    /// How long the simulation had been played for
+    #[serde(default)]
+    pub clock: SimulationClock,
+    /// The hex map the solar systems are laid out on
+    pub map: SavedMap,
+    /// Every solar system in the galaxy
+    pub solar_systems: Vec<SavedEntity<SolarSystem>>,
+    /// The resource fields in each solar system that has any
+    #[serde(default)]
+    pub resource_fields: Vec<SavedResourceFields>,
+    /// The control points factions hold in each solar system
+    #[serde(default)]
+    pub system_control: Vec<SavedSystemControl>,
+    /// Every stargate in the galaxy
+    pub stargates: Vec<SavedEntity<Stargate>>,
+    /// Every station, including its services and their timers
+    pub stations: Vec<SavedEntity<Station>>,
+    /// Every agent, including its path, wallet and cargo
+    pub agents: Vec<SavedAgent>,
+    /// Every faction and its bank
+    pub factions: Vec<SavedFaction>,
+    /// The relations and treaties between factions
+    #[serde(default)]
+    pub diplomacy: Diplomacy,
+    /// Every faction fleet and its members
+    #[serde(default)]
+    pub fleets: Vec<SavedFleet>,
+    /// Every station and upgrade under construction
+    #[serde(default)]
+    pub construction_sites: Vec<SavedEntity<ConstructionSite>>,
+    /// The wrecks left by destroyed ships, with their cargo
+    #[serde(default)]
+    pub wrecks: Vec<SavedEntity<Wreck>>,
+}
+
+/// A saved component along with where its entity was in the world.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SavedEntity<T> {
+    /// The entity in the world the save was taken from, used to fix up references between entities
+    pub entity: Entity,
+    /// The saved component
+    pub component: T,
+    /// The entity's transform
+    pub transform: Transform,
+}
+
+/// A saved agent.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SavedAgent {
+    /// The entity in the world the save was taken from, used to fix up references between entities
+    pub entity: Entity,
+    /// The agent
+    pub agent: Agent,
+    /// The agent's line of work
+    #[serde(default)]
+    pub role: AgentRole,
+    /// The goods the agent is carrying
+    pub cargo: CargoHold,
+    /// The agent's transform
+    pub transform: Transform,
+}
+
+/// A saved fleet.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SavedFleet {
+    /// The entity in the world the save was taken from
+    pub entity: Entity,
+    /// The fleet, whose members are the agents' entities in the world the save was taken from
+    pub fleet: Fleet,
+}
+
+/// The resource fields in a solar system.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SavedResourceFields {
+    /// The ID of the solar system the fields are in
+    pub system_id: u32,
+    /// The fields
+    pub fields: ResourceFields,
+}
+
+/// The control points factions hold in a solar system.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SavedSystemControl {
+    /// The ID of the solar system
+    pub system_id: u32,
+    /// The control points
+    pub control: SystemControl,
+}
+
+/// The saved hex map.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SavedMap {
+    /// The size of the hexagons
+    pub hex_size: Vec2,
+    /// The hexes that contain a solar system
+    pub hexes: Vec<SavedHex>,
+}
+
+/// A hex on the map and the solar system in it.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SavedHex {
+    /// The x coordinate of the hex
+    pub x: i32,
+    /// The y coordinate of the hex
+    pub y: i32,
+    /// The ID of the solar system in the hex
+    pub system_id: u32,
+}
+
+/// A saved faction.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SavedFaction {
+    /// The factions attributes
+    pub attributes: Attributes,
+    /// The factions bank
+    pub bank: Bank,
+    /// The taxes the faction levies
+    #[serde(default)]
+    pub taxes: TaxPolicy,
+    /// How much weight the faction gives to each of its goals
+    #[serde(default)]
+    pub goals: FactionGoals,
+}
+
+impl SaveGame {
+    /// Takes a snapshot of the simulation in the given world.
+    pub fn capture(world: &mut World) -> Self {
+        let galaxy_seed = world.get_resource::<GalaxySeed>().map(GalaxySeed::seed);
+        let clock = world
+            .get_resource::<SimulationClock>()
+            .copied()
+            .unwrap_or_default();
+
+        let mut solar_systems = world.query::<(Entity, &SolarSystem, &Transform)>();
+        let system_ids: HashMap<Entity, u32> = solar_systems
+            .iter(world)
+            .map(|(entity, system, _)| (entity, system.attributes.id))
+            .collect();
+        let solar_systems = solar_systems
+            .iter(world)
+            .map(|(entity, system, transform)| SavedEntity {
+                entity,
+                component: system.clone(),
+                transform: *transform,
+            })
+            .collect();
+
+        let resource_fields = world
+            .query::<(&SolarSystem, &ResourceFields)>()
+            .iter(world)
+            .map(|(system, fields)| SavedResourceFields {
+                system_id: system.attributes.id,
+                fields: fields.clone(),
+            })
+            .collect();
+
+        let system_control = world
+            .query::<(&SolarSystem, &SystemControl)>()
+            .iter(world)
+            .map(|(system, control)| SavedSystemControl {
+                system_id: system.attributes.id,
+                control: control.clone(),
+            })
+            .collect();
+
+        let map = match world.get_resource::<Map>() {
+            Some(map) => SavedMap {
+                hex_size: map.layout.hex_size,
+                hexes: map
+                    .entities
+                    .iter()
+                    .filter_map(|(hex, entity)| {
+                        system_ids.get(entity).map(|system_id| SavedHex {
+                            x: hex.x,
+                            y: hex.y,
+                            system_id: *system_id,
+                        })
+                    })
+                    .collect(),
+            },
+            None => SavedMap {
+                hex_size: Vec2::ZERO,
+                hexes: Vec::new(),
+            },
+        };
+
+        Self {
+            galaxy_seed,
+            clock,
+            map,
+            solar_systems,
+            resource_fields,
+            system_control,
+            stargates: capture_entities::<Stargate>(world),
+            stations: capture_entities::<Station>(world),
+            agents: world
+                .query::<(Entity, &Agent, Option<&AgentRole>, &CargoHold, &Transform)>()
+                .iter(world)
+                .map(|(entity, agent, role, cargo, transform)| SavedAgent {
+                    entity,
+                    agent: agent.clone(),
+                    role: role.copied().unwrap_or_default(),
+                    cargo: cargo.clone(),
+                    transform: *transform,
+                })
+                .collect(),
+            factions: world
+                .query::<(
+                    &Attributes,
+                    &Bank,
+                    Option<&TaxPolicy>,
+                    Option<&FactionGoals>,
+                )>()
+                .iter(world)
+                .map(|(attributes, bank, taxes, goals)| SavedFaction {
+                    attributes: attributes.clone(),
+                    bank: bank.clone(),
+                    taxes: taxes.copied().unwrap_or_default(),
+                    goals: goals.copied().unwrap_or_default(),
+                })
+                .collect(),
+            diplomacy: world
+                .get_resource::<Diplomacy>()
+                .cloned()
+                .unwrap_or_default(),
+            fleets: world
+                .query::<(Entity, &Fleet)>()
+                .iter(world)
+                .map(|(entity, fleet)| SavedFleet {
+                    entity,
+                    fleet: fleet.clone(),
+                })
+                .collect(),
+            construction_sites: capture_entities::<ConstructionSite>(world),
+            wrecks: capture_entities::<Wreck>(world),
+        }
+    }
+
+    /// Replaces the simulation in the given world with this snapshot.
+    ///
+    /// The `SystemGraph` and `Map` are rebuilt from the restored entities, and the game moves to the `Playing` state.
+    pub fn restore(self, world: &mut World) {
+        despawn_simulation(world);
+
+        if let Some(seed) = self.galaxy_seed {
+            world.insert_resource(GalaxySeed::new(seed));
+        }
+        world.insert_resource(self.clock);
+        world.insert_resource(self.diplomacy);
+
+        let factions: Vec<FactionBundle> = self
+            .factions
+            .into_iter()
+            .map(|faction| FactionBundle {
+                faction_attributes: faction.attributes,
+                faction_bank: faction.bank,
+                faction_taxes: faction.taxes,
+                faction_goals: faction.goals,
+            })
+            .collect();
+        for faction in factions.iter() {
+            world.spawn(faction.clone());
+        }
+        world.insert_resource(FactionResourse { factions });
+
+        let hexes: HashMap<u32, Hex> = self
+            .map
+            .hexes
+            .iter()
+            .map(|saved| (saved.system_id, Hex::new(saved.x, saved.y)))
+            .collect();
+        let mut resource_fields: HashMap<u32, ResourceFields> = self
+            .resource_fields
+            .into_iter()
+            .map(|saved| (saved.system_id, saved.fields))
+            .collect();
+        // Systems from saves without control points are handed to their owners when the game resumes
+        let mut system_control: HashMap<u32, SystemControl> = self
+            .system_control
+            .into_iter()
+            .map(|saved| (saved.system_id, saved.control))
+            .collect();
+        let mut map_entities = HashMap::new();
+        for saved in self.solar_systems.iter() {
+            let name = match hexes.get(&saved.component.attributes.id) {
+                Some(hex) => format!("System - {},{}", hex.x, hex.y),
+                None => format!("System - {}", saved.component.attributes.id),
+            };
+            let entity = world
+                .spawn((
+                    saved.component.clone(),
+                    TransformBundle::from_transform(saved.transform),
+                    Name::new(name),
+                    resource_fields
+                        .remove(&saved.component.attributes.id)
+                        .unwrap_or_default(),
+                ))
+                .id();
+            if let Some(control) = system_control.remove(&saved.component.attributes.id) {
+                world.entity_mut(entity).insert(control);
+            }
+
+            if let Some(hex) = hexes.get(&saved.component.attributes.id) {
+                map_entities.insert(*hex, entity);
+            }
+        }
+        let layout = HexLayout {
+            hex_size: self.map.hex_size,
+            ..default()
+        };
+        let positions: Vec<(u32, Vec2)> = hexes
+            .iter()
+            .map(|(system_id, hex)| (*system_id, layout.hex_to_world_pos(*hex)))
+            .collect();
+        world.insert_resource(Map {
+            layout,
+            entities: map_entities,
+        });
+
+        for saved in self.stargates.iter() {
+            world.spawn((
+                saved.component.clone(),
+                TransformBundle::from_transform(saved.transform),
+                Name::new(format!("Stargate {}", saved.component.id)),
+            ));
+        }
+
+        let mut system_graph = SystemGraph::from_systems_and_gates(
+            self.solar_systems.iter().map(|saved| &saved.component),
+            self.stargates.iter().map(|saved| &saved.component),
+        );
+        system_graph.locate_systems(positions);
+        world.insert_resource(system_graph);
+
+        let mut entity_map = HashMap::new();
+        for saved in self.agents {
+            let entity = world
+                .spawn(agent_bundle(saved.agent, saved.role, saved.transform))
+                .insert(saved.cargo)
+                .id();
+            entity_map.insert(saved.entity, entity);
+        }
+
+        for mut saved in self.fleets {
+            saved.fleet.map_entities(&entity_map);
+            let members = saved.fleet.members().to_vec();
+            let name = Name::new(saved.fleet.name.clone());
+            let fleet = world.spawn((saved.fleet, name)).id();
+            for agent in members {
+                world.entity_mut(agent).insert(FleetMember(fleet));
+            }
+        }
+
+        // Markets from older saves have no listings, and the manifests may have gained items since
+        let items = world
+            .get_resource::<ItemRegistry>()
+            .cloned()
+            .unwrap_or_default();
+        for mut saved in self.stations {
+            saved.component.map_entities(&entity_map);
+            if let Some(market) = saved.component.market_mut() {
+                market.list_items(&items);
+            }
+            let docked = saved
+                .component
+                .dock()
+                .map(|dock| dock.docked_ships.clone())
+                .unwrap_or_default();
+            let name = Name::new(saved.component.name.clone());
+            let station = world
+                .spawn((
+                    saved.component,
+                    TransformBundle::from_transform(saved.transform),
+                    name,
+                ))
+                .id();
+
+            for agent in docked {
+                world.entity_mut(agent).insert(DockedAt(station));
+            }
+        }
+
+        for saved in self.construction_sites {
+            let name = Name::new(saved.component.name());
+            world.spawn((
+                saved.component,
+                TransformBundle::from_transform(saved.transform),
+                name,
+            ));
+        }
+
+        for saved in self.wrecks {
+            world.spawn((
+                saved.component,
+                TransformBundle::from_transform(saved.transform),
+                Name::new("Wreck"),
+            ));
+        }
+
+        world
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::Playing);
+    }
+}
+
+/// Saves every entity with the given component along with its transform.
+fn capture_entities<T: Component + Clone>(world: &mut World) -> Vec<SavedEntity<T>> {
+    world
+        .query::<(Entity, &T, &Transform)>()
+        .iter(world)
+        .map(|(entity, component, transform)| SavedEntity {
+            entity,
+            component: component.clone(),
+            transform: *transform,
+        })
+        .collect()
+}
+
+/// Removes every simulation entity from the world, so a save can be restored into it.
+fn despawn_simulation(world: &mut World) {
+    let entities: Vec<Entity> = world
+        .query_filtered::<Entity, Or<(
+            With<SolarSystem>,
+            With<Stargate>,
+            With<Station>,
+            With<Agent>,
+            With<Attributes>,
+            With<Fleet>,
+            With<ConstructionSite>,
+            With<Wreck>,
+        )>>()
+        .iter(world)
+        .collect();
+
+    for entity in entities {
+        world.entity_mut(entity).despawn_recursive();
+    }
+}
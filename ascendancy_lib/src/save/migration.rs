@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+
+use bevy::prelude::Entity;
+use serde_json::Value;
+
+use super::SaveError;
+use crate::agent::agent::{AGENT_FLUID_CARGO, AGENT_SOLID_CARGO};
+use crate::items::inventory::Inventory;
+use crate::structures::services::dock::DEFAULT_DOCKING_FEE;
+use crate::structures::station::{STATION_FLUID_CAPACITY, STATION_SOLID_CAPACITY};
+
+/// The current version of the save file format.
+///
+/// Bump this whenever the layout of [`super::SaveGame`] changes, and add a migration for the previous version.
+pub const SAVE_FORMAT_VERSION: u32 = 6;
+
+/// Upgrades the contents of a save file from one format version to the next.
+pub type Migration = fn(Value) -> Result<Value, SaveError>;
+
+/// The migrations for every old format version.
+///
+/// `MIGRATIONS[0]` upgrades a version 1 save to version 2, `MIGRATIONS[1]` upgrades version 2 to version 3 and so on.
+const MIGRATIONS: &[Migration] = &[
+    give_stations_inventories,
+    give_agents_cargo,
+    dock_agents_by_entity,
+    wrap_agent_cargo_in_holds,
+    give_agents_factions,
+];
+
+// Every old version needs a migration to the next one
+const _: () = assert!(MIGRATIONS.len() as u32 == SAVE_FORMAT_VERSION - 1);
+
+/// Upgrades the contents of a save file written with the given format version to the current version.
+pub fn migrate(version: u32, mut game: Value) -> Result<Value, SaveError> {
+    if version == 0 || version > SAVE_FORMAT_VERSION {
+        return Err(SaveError::UnsupportedVersion(version));
+    }
+
+    for migration in &MIGRATIONS[(version - 1) as usize..] {
+        game = migration(game)?;
+    }
+
+    Ok(game)
+}
+
+/// Version 2 gives every station empty cargo bays.
+fn give_stations_inventories(mut game: Value) -> Result<Value, SaveError> {
+    let empty_bays = serde_json::to_value(Inventory::new(
+        STATION_SOLID_CAPACITY,
+        STATION_FLUID_CAPACITY,
+    ))?;
+    let resource_managers = game
+        .get_mut("stations")
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+        .filter_map(|saved| saved.pointer_mut("/component/resource_manager"))
+        .filter_map(Value::as_object_mut);
+    for resource_manager in resource_managers {
+        resource_manager.insert("inventory".to_string(), empty_bays.clone());
+    }
+    Ok(game)
+}
+
+/// Records the entity each saved component came from.
+///
+/// Older saves have no references between entities, so any distinct entity will do.
+fn record_saved_entities(game: &mut Value) {
+    let mut next_index = 0;
+    for list in ["solar_systems", "stargates", "stations", "agents"] {
+        let Some(saved) = game.get_mut(list).and_then(Value::as_array_mut) else {
+            continue;
+        };
+        for saved in saved.iter_mut().filter_map(Value::as_object_mut) {
+            saved.insert(
+                "entity".to_string(),
+                Entity::from_raw(next_index).to_bits().into(),
+            );
+            next_index += 1;
+        }
+    }
+}
+
+/// Version 3 saves the entity of every component, and an agent alongside the goods it is carrying.
+///
+/// Agents in older saves were carrying nothing.
+fn give_agents_cargo(mut game: Value) -> Result<Value, SaveError> {
+    record_saved_entities(&mut game);
+
+    let empty_cargo = serde_json::to_value(Inventory::new(AGENT_SOLID_CARGO, AGENT_FLUID_CARGO))?;
+    if let Some(agents) = game.get_mut("agents").and_then(Value::as_array_mut) {
+        for saved in agents.iter_mut().filter_map(Value::as_object_mut) {
+            if let Some(agent) = saved.remove("component") {
+                saved.insert("agent".to_string(), agent);
+            }
+            saved.insert("cargo".to_string(), empty_cargo.clone());
+        }
+    }
+    Ok(game)
+}
+
+/// Version 4 keeps the entities of docked agents rather than copies of them, and adds a queue and fees.
+///
+/// Copies are swapped for the entity of the saved agent with the same ID, and dropped if there isn't one.
+fn dock_agents_by_entity(mut game: Value) -> Result<Value, SaveError> {
+    let agent_entities: HashMap<u64, Value> = game
+        .get("agents")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|saved| {
+            let id = saved.pointer("/agent/id")?.as_u64()?;
+            Some((id, saved.get("entity")?.clone()))
+        })
+        .collect();
+
+    let Some(stations) = game.get_mut("stations").and_then(Value::as_array_mut) else {
+        return Ok(game);
+    };
+    let docks = stations
+        .iter_mut()
+        .filter_map(|saved| saved.pointer_mut("/component/services"))
+        .filter_map(Value::as_array_mut)
+        .flatten()
+        .filter_map(|service| service.get_mut("Dock"))
+        .filter_map(Value::as_object_mut);
+    for dock in docks {
+        if let Some(docked) = dock.get_mut("docked_ships").and_then(Value::as_array_mut) {
+            let ships = std::mem::take(docked);
+            *docked = ships
+                .into_iter()
+                .filter_map(|agent| {
+                    let id = agent.get("id")?.as_u64()?;
+                    agent_entities.get(&id).cloned()
+                })
+                .collect();
+        }
+        dock.insert("queue".to_string(), Value::Array(Vec::new()));
+        dock.insert("docking_fee".to_string(), DEFAULT_DOCKING_FEE.into());
+        dock.insert("forced_undocks".to_string(), Value::Array(Vec::new()));
+    }
+    Ok(game)
+}
+
+/// Version 5 keeps an agent's cargo in a hold rather than a bare inventory.
+fn wrap_agent_cargo_in_holds(mut game: Value) -> Result<Value, SaveError> {
+    if let Some(agents) = game.get_mut("agents").and_then(Value::as_array_mut) {
+        for agent in agents {
+            if let Some(cargo) = agent.get_mut("cargo") {
+                let contents = cargo.take();
+                *cargo = serde_json::json!({ "contents": contents });
+            }
+        }
+    }
+    Ok(game)
+}
+
+/// Version 6 records the faction an agent works for, which was the owner of its home system.
+fn give_agents_factions(mut game: Value) -> Result<Value, SaveError> {
+    if let Some(agents) = game.get_mut("agents").and_then(Value::as_array_mut) {
+        for saved in agents {
+            let Some(agent) = saved.get_mut("agent").and_then(Value::as_object_mut) else {
+                continue;
+            };
+            let owner = agent
+                .get("home_system")
+                .and_then(|system| system.pointer("/attributes/owner"))
+                .cloned()
+                .unwrap_or_else(|| serde_json::json!({ "id": 0 }));
+            agent.insert("faction".to_string(), owner);
+        }
+    }
+    Ok(game)
+}
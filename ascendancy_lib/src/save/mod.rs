@@ -0,0 +1,148 @@
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub use self::game_state::{SaveGame, SavedEntity, SavedFaction, SavedFleet, SavedHex, SavedMap};
+pub use self::migration::{Migration, SAVE_FORMAT_VERSION};
+
+/// The saved simulation state
+pub mod game_state;
+/// Upgrading save files written by older versions of the game
+pub mod migration;
+
+/// The file used for quick saves and quick loads.
+pub const QUICK_SAVE_PATH: &str = "saves/quicksave.json";
+
+/// Saving and loading of the full game state
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SaveGameEvent>()
+            .add_event::<LoadGameEvent>()
+            .add_systems(Update, process_save_requests);
+    }
+}
+
+/// Request that the game is saved to the given file.
+#[derive(Event, Debug, Clone)]
+pub struct SaveGameEvent {
+    /// The file to write the save to
+    pub path: PathBuf,
+}
+
+/// Request that the game is replaced with the save in the given file.
+#[derive(Event, Debug, Clone)]
+pub struct LoadGameEvent {
+    /// The file to read the save from
+    pub path: PathBuf,
+}
+
+/// Errors that can occur when saving or loading the game
+#[derive(Debug)]
+pub enum SaveError {
+    /// The save file could not be read or written
+    Io(std::io::Error),
+    /// The save file could not be serialized or deserialized
+    Serialization(serde_json::Error),
+    /// The save file was written with a format version this build can not read
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Io(error) => write!(f, "could not access save file: {}", error),
+            SaveError::Serialization(error) => write!(f, "malformed save file: {}", error),
+            SaveError::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported save format version {} (current version is {})",
+                version, SAVE_FORMAT_VERSION
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl From<std::io::Error> for SaveError {
+    fn from(error: std::io::Error) -> Self {
+        SaveError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for SaveError {
+    fn from(error: serde_json::Error) -> Self {
+        SaveError::Serialization(error)
+    }
+}
+
+/// The layout of a save file on disk, the game is kept as raw JSON until it has been migrated.
+#[derive(Serialize, Deserialize)]
+struct SaveFile<T> {
+    /// The format version the save was written with
+    version: u32,
+    /// The saved game
+    game: T,
+}
+
+/// Writes a snapshot of the simulation in the world to the given file.
+pub fn save_to_file(world: &mut World, path: &Path) -> Result<(), SaveError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let save_file = SaveFile {
+        version: SAVE_FORMAT_VERSION,
+        game: SaveGame::capture(world),
+    };
+    serde_json::to_writer(BufWriter::new(File::create(path)?), &save_file)?;
+    Ok(())
+}
+
+/// Reads a save file, upgrading it to the current format version if needed.
+pub fn read_save_file(path: &Path) -> Result<SaveGame, SaveError> {
+    let save_file: SaveFile<serde_json::Value> =
+        serde_json::from_reader(BufReader::new(File::open(path)?))?;
+    let game = migration::migrate(save_file.version, save_file.game)?;
+    Ok(serde_json::from_value(game)?)
+}
+
+/// Replaces the simulation in the world with the save in the given file.
+pub fn load_from_file(world: &mut World, path: &Path) -> Result<(), SaveError> {
+    read_save_file(path)?.restore(world);
+    Ok(())
+}
+
+/// Handles any pending save and load requests.
+fn process_save_requests(world: &mut World) {
+    let saves: Vec<SaveGameEvent> = world
+        .resource_mut::<Events<SaveGameEvent>>()
+        .drain()
+        .collect();
+    for event in saves {
+        match save_to_file(world, &event.path) {
+            Ok(()) => info!("Saved game to {}", event.path.display()),
+            Err(error) => error!("Failed to save game to {}: {}", event.path.display(), error),
+        }
+    }
+
+    let loads: Vec<LoadGameEvent> = world
+        .resource_mut::<Events<LoadGameEvent>>()
+        .drain()
+        .collect();
+    for event in loads {
+        match load_from_file(world, &event.path) {
+            Ok(()) => info!("Loaded game from {}", event.path.display()),
+            Err(error) => error!(
+                "Failed to load game from {}: {}",
+                event.path.display(),
+                error
+            ),
+        }
+    }
+}
@@ -0,0 +1,121 @@
+//! Plugins needed to run the game simulation, with or without rendering.
+
+use bevy::app::{AppExit, PluginGroupBuilder};
+use bevy::core::FrameCount;
+use bevy::prelude::*;
+use big_brain::BigBrainPlugin;
+use serde::{Deserialize, Serialize};
+
+use crate::agent::UnitPlugin;
+use crate::faction::FactionPlugin;
+use crate::items::ItemPlugin;
+use crate::save::SavePlugin;
+use crate::solar_system::SolarSystemPlugin;
+use crate::structures::StructurePlugin;
+use crate::world_gen::WorldGenPlugin;
+use crate::GameState;
+
+/// All of the plugins that make up the game simulation.
+///
+/// None of these plugins depend on rendering, a window or loaded assets,
+/// so they can run under [`MinimalPlugins`] as well as [`DefaultPlugins`].
+pub struct SimulationPlugins;
+
+impl PluginGroup for SimulationPlugins {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::start::<Self>()
+            .add(SimulationClockPlugin)
+            .add(ItemPlugin)
+            .add(WorldGenPlugin)
+            .add(SolarSystemPlugin)
+            .add(FactionPlugin)
+            .add(UnitPlugin)
+            .add(StructurePlugin)
+            .add(SavePlugin)
+            .add(BigBrainPlugin::new(PreUpdate))
+    }
+}
+
+/// Keeps the [`SimulationClock`] running while the game is being played.
+pub struct SimulationClockPlugin;
+
+impl Plugin for SimulationClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SimulationClock>().add_systems(
+            FixedUpdate,
+            advance_simulation_clock.run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+/// The time the simulation has been played for, in seconds.
+///
+/// Unlike [`Time`] this is kept in save games, so it can be used to timestamp things that outlive a session.
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SimulationClock {
+    /// The seconds played so far
+    seconds: f64,
+}
+
+impl SimulationClock {
+    /// Creates a clock that has been running for the given number of seconds
+    pub fn from_seconds(seconds: f64) -> Self {
+        Self { seconds }
+    }
+
+    /// The seconds played so far
+    pub fn now(&self) -> f64 {
+        self.seconds
+    }
+
+    /// Moves the clock on by the given number of seconds
+    pub fn advance(&mut self, seconds: f64) {
+        self.seconds += seconds;
+    }
+}
+
+/// Moves the simulation clock on by the length of the fixed tick.
+fn advance_simulation_clock(time: Res<Time>, mut clock: ResMut<SimulationClock>) {
+    clock.advance(time.delta_seconds_f64());
+}
+
+/// Runs the simulation without the loading screen and menu.
+///
+/// World generation starts straight away, and the app can optionally exit after a number of frames.
+#[derive(Default)]
+pub struct HeadlessPlugin {
+    /// The number of frames to run before exiting, runs forever when `None`.
+    pub frame_limit: Option<u32>,
+}
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, start_world_generation);
+
+        if let Some(frame_limit) = self.frame_limit {
+            app.insert_resource(FrameLimit(frame_limit))
+                .add_systems(Last, exit_after_frame_limit);
+        }
+    }
+}
+
+/// The number of frames a headless simulation runs for.
+#[derive(Resource)]
+struct FrameLimit(u32);
+
+/// Skips straight to world generation, as there are no assets to load or menu to show.
+fn start_world_generation(mut next_state: ResMut<NextState<GameState>>) {
+    next_state.set(GameState::WorldGenerating);
+}
+
+/// Exits the app once the frame limit has been reached.
+fn exit_after_frame_limit(
+    frame_count: Res<FrameCount>,
+    frame_limit: Res<FrameLimit>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if frame_count.0 >= frame_limit.0 {
+        info!("Reached frame limit of {}, exiting", frame_limit.0);
+        exit.send(AppExit);
+    }
+}
@@ -1,9 +1,12 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::faction::attributes::FactionID;
 
 /// System Gates
-#[derive(Component, Reflect, Clone, Debug, PartialEq, PartialOrd, Eq, Hash)]
+#[derive(
+    Component, Reflect, Serialize, Deserialize, Clone, Debug, PartialEq, PartialOrd, Eq, Hash,
+)]
 #[reflect(Component)]
 pub struct SystemAttributes {
     /// The ID of the system
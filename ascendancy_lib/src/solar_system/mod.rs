@@ -1,8 +1,10 @@
 use bevy::prelude::*;
 use bevy::utils::Uuid;
+use serde::{Deserialize, Serialize};
 
 use self::attributes::SystemAttributes;
 use self::events::update_solar_systems_on_entity_movement;
+use self::resources::{regenerate_resource_fields, ResourceFields};
 use crate::faction::attributes::FactionID;
 use crate::GameState;
 
@@ -10,6 +12,8 @@ use crate::GameState;
 pub mod attributes;
 /// Solar system events
 pub mod events;
+/// Natural resources found in solar systems
+pub mod resources;
 
 /// Set the game state to align systems with their respective runtimes
 pub struct SolarSystemPlugin;
@@ -20,9 +24,14 @@ impl Plugin for SolarSystemPlugin {
             .add_event::<events::EntityMovedSystemEvent>()
             .register_type::<Uuid>()
             .register_type::<SolarSystem>()
+            .register_type::<ResourceFields>()
             .add_systems(
                 FixedUpdate,
-                update_solar_systems_on_entity_movement.run_if(in_state(GameState::Playing)),
+                (
+                    update_solar_systems_on_entity_movement,
+                    regenerate_resource_fields,
+                )
+                    .run_if(in_state(GameState::Playing)),
             );
     }
 }
@@ -32,12 +41,25 @@ impl Plugin for SolarSystemPlugin {
 pub struct EntityList(pub Vec<Entity>);
 
 /// The solar system
-#[derive(Component, Default, Reflect, Clone, Debug, PartialEq, PartialOrd, Eq, Hash)]
+#[derive(
+    Component,
+    Default,
+    Reflect,
+    Serialize,
+    Deserialize,
+    Clone,
+    Debug,
+    PartialEq,
+    PartialOrd,
+    Eq,
+    Hash,
+)]
 pub struct SolarSystem {
     /// The solar systems attributes
     pub attributes: SystemAttributes,
 
     /// a Vec of jumpgates in the system
+    #[serde(skip)]
     pub entities: EntityList,
 }
 
@@ -0,0 +1,101 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::items::ItemId;
+
+/// The kinds of natural resource found in solar systems
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub enum ResourceKind {
+    /// An asteroid belt yielding raw ore
+    Ore,
+    /// A gas cloud yielding hydrogen
+    Gas,
+}
+
+impl ResourceKind {
+    /// The item the resource yields
+    pub fn item(&self) -> ItemId {
+        match self {
+            Self::Ore => ItemId::RAW_ORE,
+            Self::Gas => ItemId::HYDROGEN,
+        }
+    }
+}
+
+/// A finite deposit of a natural resource that slowly regenerates as it is mined
+#[derive(Clone, Debug, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct ResourceField {
+    /// The kind of resource in the field
+    pub kind: ResourceKind,
+    /// Where the field is, relative to the centre of its system
+    pub offset: Vec2,
+    /// The units left to extract
+    pub remaining: f32,
+    /// The most units the field can hold
+    pub max_yield: f32,
+    /// The units the field regains each second, up to its maximum
+    pub regeneration_rate: f32,
+}
+
+impl ResourceField {
+    /// Creates a full field
+    pub fn new(kind: ResourceKind, offset: Vec2, max_yield: f32, regeneration_rate: f32) -> Self {
+        Self {
+            kind,
+            offset,
+            remaining: max_yield,
+            max_yield,
+            regeneration_rate,
+        }
+    }
+
+    /// The item the field yields
+    pub fn item(&self) -> ItemId {
+        self.kind.item()
+    }
+
+    /// The whole units that can be extracted right now
+    pub fn available(&self) -> u32 {
+        self.remaining.max(0.0).floor() as u32
+    }
+
+    /// Extracts up to the given number of units, returning how many were taken
+    pub fn extract(&mut self, quantity: u32) -> u32 {
+        let extracted = quantity.min(self.available());
+        self.remaining -= extracted as f32;
+        extracted
+    }
+
+    /// Regrows the field over the given number of seconds
+    pub fn regenerate(&mut self, seconds: f32) {
+        self.remaining = (self.remaining + self.regeneration_rate * seconds).min(self.max_yield);
+    }
+}
+
+/// The natural resources in a solar system
+#[derive(Component, Clone, Debug, Default, PartialEq, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
+pub struct ResourceFields(pub Vec<ResourceField>);
+
+impl ResourceFields {
+    /// The field of the given kind with the most units available, and its index
+    pub fn richest(&mut self, kind: ResourceKind) -> Option<(usize, &mut ResourceField)> {
+        self.0
+            .iter_mut()
+            .enumerate()
+            .filter(|(_, field)| field.kind == kind)
+            .max_by_key(|(_, field)| field.available())
+    }
+}
+
+/// Regrows every resource field
+pub fn regenerate_resource_fields(time: Res<Time>, mut systems: Query<&mut ResourceFields>) {
+    let seconds = time.delta_seconds();
+    for mut fields in systems.iter_mut() {
+        for field in fields.0.iter_mut() {
+            if field.remaining < field.max_yield {
+                field.regenerate(seconds);
+            }
+        }
+    }
+}
@@ -2,6 +2,13 @@ use bevy::prelude::*;
 
 use crate::GameState;
 
+use self::services::dock::{
+    process_docking, DockDeniedEvent, DockGrantedEvent, DockRequestEvent, UndockRequestEvent,
+    UndockedEvent,
+};
+use self::services::factory::{report_factory_stalls, FactoryStalledEvent};
+use self::services::market::{deliver_market_fills, sweep_market_funds, MarketTradeEvent};
+use self::services::mining::run_mining;
 use self::station::run_active_services;
 /// Station services
 pub mod services;
@@ -15,9 +22,25 @@ pub struct StructurePlugin;
 
 impl Plugin for StructurePlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            FixedUpdate,
-            run_active_services.run_if(in_state(GameState::Playing)),
-        );
+        app.add_event::<MarketTradeEvent>()
+            .add_event::<DockRequestEvent>()
+            .add_event::<DockGrantedEvent>()
+            .add_event::<DockDeniedEvent>()
+            .add_event::<UndockRequestEvent>()
+            .add_event::<UndockedEvent>()
+            .add_event::<FactoryStalledEvent>()
+            .add_systems(
+                FixedUpdate,
+                (
+                    run_active_services,
+                    run_mining,
+                    report_factory_stalls,
+                    process_docking,
+                    deliver_market_fills,
+                    sweep_market_funds,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            );
     }
 }
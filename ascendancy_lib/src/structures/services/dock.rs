@@ -1,13 +1,26 @@
+use std::collections::{HashMap, VecDeque};
+
 use bevy::prelude::*;
-use bevy::{reflect::Reflect, utils::uuid};
+use bevy::reflect::Reflect;
+use serde::{Deserialize, Serialize};
 
-use crate::{agent::agent::Agent, structures::station::ResourceManager};
+use crate::agent::agent::Agent;
+use crate::faction::attributes::{Attributes, FactionID};
+use crate::faction::bank::{Bank, TransactionKind};
+use crate::faction::diplomoacy::Diplomacy;
+use crate::faction::taxes::TaxPolicy;
+use crate::simulation::SimulationClock;
+use crate::solar_system::SolarSystem;
+use crate::structures::station::{ResourceManager, Station};
 
 // structures/services/Dock.rs
 use super::StationServiceTrait;
 
+/// The credits a new dock charges an agent to dock
+pub const DEFAULT_DOCKING_FEE: f32 = 25.0;
+
 /// The `Dock` struct represents the Dock service
-#[derive(Debug, Clone, PartialEq, Reflect)]
+#[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize)]
 pub struct Dock {
     /// The ID of the dock
     pub id: u32,
@@ -15,8 +28,12 @@ pub struct Dock {
     pub name: String,
     /// The amount of copacity the dock has
     pub capacity: u32,
-    /// A list of ships currently docked
-    pub docked_ships: Vec<Agent>,
+    /// The agents currently docked, one per berth
+    pub docked_ships: Vec<Entity>,
+    /// Agents waiting for a free berth, in the order they asked
+    pub queue: VecDeque<Entity>,
+    /// The credits an agent pays the station's owner to dock
+    pub docking_fee: f32,
     ///flat rate of energy consumption
     pub base_energy_consumption: f32,
     /// Whether the dock is active or not
@@ -25,40 +42,79 @@ pub struct Dock {
     energy_fluctuation: f32,
     /// Energy consumnption timer
     consumption_timer: Timer, // Add a Timer for consumption logic
+    /// Agents that were undocked because the dock lost power, waiting to be told
+    forced_undocks: Vec<Entity>,
 }
 
 impl Dock {
     /// Creates a new Dock service
-    pub fn new(name: String, capacity: u32) -> Self {
+    pub fn new(id: u32, name: String, capacity: u32) -> Self {
         Dock {
-            id: uuid::Uuid::new_v4().as_u128() as u32,
+            id,
             name: name,
             capacity: capacity,
             docked_ships: Vec::with_capacity(capacity as usize),
+            queue: VecDeque::new(),
+            docking_fee: DEFAULT_DOCKING_FEE,
             base_energy_consumption: 400.0,
             is_active: true,
             energy_fluctuation: 0.2,
             consumption_timer: Timer::from_seconds(5.0, bevy::time::TimerMode::Repeating), // Initialize the timer
+            forced_undocks: Vec::new(),
         }
     }
+
+    /// Whether an agent is docked here
+    pub fn is_docked(&self, agent: Entity) -> bool {
+        self.docked_ships.contains(&agent)
+    }
+
+    /// Whether an agent is docked or waiting to dock here
+    pub fn is_queued_or_docked(&self, agent: Entity) -> bool {
+        self.is_docked(agent) || self.queue.contains(&agent)
+    }
+
+    /// The number of free berths
+    pub fn free_berths(&self) -> u32 {
+        self.capacity.saturating_sub(self.docked_ships.len() as u32)
+    }
+
+    /// Removes an agent from its berth or the queue, returning whether it was docked
+    pub fn release(&mut self, agent: Entity) -> bool {
+        self.queue.retain(|queued| *queued != agent);
+        let docked = self.is_docked(agent);
+        self.docked_ships.retain(|docked| *docked != agent);
+        docked
+    }
+
+    /// Swaps the agent entities the dock refers to, dropping agents that no longer exist.
+    pub fn map_entities(&mut self, entity_map: &HashMap<Entity, Entity>) {
+        let map = |agent: &mut Entity| match entity_map.get(agent) {
+            Some(mapped) => {
+                *agent = *mapped;
+                true
+            }
+            None => false,
+        };
+        self.docked_ships.retain_mut(map);
+        self.queue.retain_mut(map);
+        self.forced_undocks.retain_mut(map);
+    }
 }
 
 impl StationServiceTrait for Dock {
     fn id(&self) -> u32 {
-        // Dock-specific ID logic
-        1
+        self.id
     }
 
     fn enable(&mut self) {
         self.is_active = true;
-        // Dock-specific enable logic
-        println!("Dock enabled");
     }
 
     fn disable(&mut self) {
         self.is_active = false;
-        // Dock-specific disable logic
-        println!("Dock disabled");
+        // Ships can't stay docked without power
+        self.forced_undocks.append(&mut self.docked_ships);
     }
 
     // Separate method for energy consumption
@@ -99,3 +155,248 @@ impl PartialOrd for Dock {
         Some(self.capacity.cmp(&other.capacity))
     }
 }
+
+/// Marks an agent as docked at a station
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DockedAt(pub Entity);
+
+/// Sent by an agent to ask for a berth at a station
+#[derive(Event, Debug, Clone, Copy)]
+pub struct DockRequestEvent {
+    /// The agent asking to dock
+    pub agent: Entity,
+    /// The station to dock at
+    pub station: Entity,
+}
+
+/// Sent when an agent has been given a berth and paid the docking fee
+#[derive(Event, Debug, Clone, Copy)]
+pub struct DockGrantedEvent {
+    /// The agent that docked
+    pub agent: Entity,
+    /// The station it docked at
+    pub station: Entity,
+}
+
+/// Why a docking request was turned down
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DockDenial {
+    /// The station has no dock
+    NoDock,
+    /// The dock has no power
+    Inactive,
+    /// The agent can't pay the docking fee
+    InsufficientFunds,
+    /// The station's owner is at war with the agent's faction
+    Embargoed,
+}
+
+/// Sent when a docking request is turned down
+#[derive(Event, Debug, Clone, Copy)]
+pub struct DockDeniedEvent {
+    /// The agent that asked to dock
+    pub agent: Entity,
+    /// The station it asked to dock at
+    pub station: Entity,
+    /// Why it was turned down
+    pub reason: DockDenial,
+}
+
+/// Sent by an agent to leave its berth, or the queue for one
+#[derive(Event, Debug, Clone, Copy)]
+pub struct UndockRequestEvent {
+    /// The agent leaving
+    pub agent: Entity,
+    /// The station it is leaving
+    pub station: Entity,
+}
+
+/// Why an agent left its berth
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UndockReason {
+    /// The agent asked to leave
+    Requested,
+    /// The dock ran out of energy
+    EnergyShortfall,
+}
+
+/// Sent when an agent leaves its berth
+#[derive(Event, Debug, Clone, Copy)]
+pub struct UndockedEvent {
+    /// The agent that left
+    pub agent: Entity,
+    /// The station it left
+    pub station: Entity,
+    /// Why it left
+    pub reason: UndockReason,
+}
+
+/// The events docking systems send
+#[derive(bevy::ecs::system::SystemParam)]
+pub struct DockEventWriters<'w> {
+    /// Berths given to agents
+    granted: EventWriter<'w, DockGrantedEvent>,
+    /// Requests turned down
+    denied: EventWriter<'w, DockDeniedEvent>,
+    /// Agents leaving their berths
+    undocked: EventWriter<'w, UndockedEvent>,
+}
+
+/// Handles docking and undocking requests, and gives queued agents berths as they free up.
+///
+/// Agents pay the docking fee into the bank of the faction that owns the station's system when they are given a berth,
+/// along with that faction's docking tax. Agents whose faction the owner won't trade with are turned away.
+#[allow(clippy::too_many_arguments)]
+pub fn process_docking(
+    mut commands: Commands,
+    mut dock_requests: EventReader<DockRequestEvent>,
+    mut undock_requests: EventReader<UndockRequestEvent>,
+    mut events: DockEventWriters,
+    mut stations: Query<(Entity, &mut Station)>,
+    mut agents: Query<&mut Agent>,
+    systems: Query<&SolarSystem>,
+    mut factions: Query<(&Attributes, &mut Bank, Option<&TaxPolicy>)>,
+    clock: Res<SimulationClock>,
+    diplomacy: Res<Diplomacy>,
+) {
+    let owners: HashMap<u32, FactionID> = systems
+        .iter()
+        .map(|system| (system.attributes.id, system.attributes.owner))
+        .collect();
+    let docking_taxes: HashMap<u32, TaxPolicy> = systems
+        .iter()
+        .filter_map(|system| {
+            factions
+                .iter()
+                .find(|(attributes, _, _)| attributes.id == system.attributes.owner)
+                .and_then(|(_, _, taxes)| taxes.copied())
+                .map(|taxes| (system.attributes.id, taxes))
+        })
+        .collect();
+    // The fee plus the owner's tax on it, which is what an agent pays to dock
+    let docking_cost = |system_id: u32, fee: f32| {
+        fee + docking_taxes
+            .get(&system_id)
+            .map_or(0.0, |taxes| taxes.docking_tax(fee))
+    };
+
+    for request in undock_requests.read() {
+        let Ok((_, mut station)) = stations.get_mut(request.station) else {
+            continue;
+        };
+        let Some(dock) = station.dock_mut() else {
+            continue;
+        };
+        if dock.release(request.agent) {
+            commands.entity(request.agent).remove::<DockedAt>();
+            events.undocked.send(UndockedEvent {
+                agent: request.agent,
+                station: request.station,
+                reason: UndockReason::Requested,
+            });
+        }
+    }
+
+    for request in dock_requests.read() {
+        let denial = match stations.get_mut(request.station) {
+            Ok((_, mut station)) => {
+                let system_id = station.system_id;
+                match station.dock_mut() {
+                    None => Some(DockDenial::NoDock),
+                    Some(dock) if !dock.is_active => Some(DockDenial::Inactive),
+                    Some(dock) if dock.is_queued_or_docked(request.agent) => None,
+                    Some(dock) => match agents.get(request.agent) {
+                        Ok(agent)
+                            if owners.get(&system_id).is_some_and(|owner| {
+                                !diplomacy.can_trade(agent.faction, *owner)
+                            }) =>
+                        {
+                            Some(DockDenial::Embargoed)
+                        }
+                        Ok(agent)
+                            if agent.wallet.money < docking_cost(system_id, dock.docking_fee) =>
+                        {
+                            Some(DockDenial::InsufficientFunds)
+                        }
+                        Ok(_) => {
+                            dock.queue.push_back(request.agent);
+                            None
+                        }
+                        Err(_) => None,
+                    },
+                }
+            }
+            Err(_) => Some(DockDenial::NoDock),
+        };
+
+        if let Some(reason) = denial {
+            events.denied.send(DockDeniedEvent {
+                agent: request.agent,
+                station: request.station,
+                reason,
+            });
+        }
+    }
+
+    for (station_entity, mut station) in stations.iter_mut() {
+        let owner = owners.get(&station.system_id).copied();
+        let system_id = station.system_id;
+        let Some(dock) = station.dock_mut() else {
+            continue;
+        };
+
+        for agent in dock.forced_undocks.drain(..) {
+            if let Some(mut entity) = commands.get_entity(agent) {
+                entity.remove::<DockedAt>();
+            }
+            events.undocked.send(UndockedEvent {
+                agent,
+                station: station_entity,
+                reason: UndockReason::EnergyShortfall,
+            });
+        }
+
+        while dock.is_active && dock.free_berths() > 0 {
+            let Some(agent_entity) = dock.queue.pop_front() else {
+                break;
+            };
+            let Ok(mut agent) = agents.get_mut(agent_entity) else {
+                continue;
+            };
+            let cost = docking_cost(system_id, dock.docking_fee);
+            if agent.wallet.money < cost {
+                events.denied.send(DockDeniedEvent {
+                    agent: agent_entity,
+                    station: station_entity,
+                    reason: DockDenial::InsufficientFunds,
+                });
+                continue;
+            }
+
+            agent.wallet.money -= cost;
+            if let Some((_, mut bank, _)) = factions
+                .iter_mut()
+                .find(|(attributes, _, _)| Some(attributes.id) == owner)
+            {
+                let tax = cost - dock.docking_fee;
+                let mut paid =
+                    bank.deposit(TransactionKind::Fee, dock.docking_fee as u32, clock.now());
+                if paid.is_ok() && tax > 0.0 {
+                    paid = bank.deposit(TransactionKind::Tax, tax as u32, clock.now());
+                }
+                if let Err(error) = paid {
+                    warn!("Docking fee for {:?} was lost: {}", agent_entity, error);
+                }
+            }
+
+            dock.docked_ships.push(agent_entity);
+            commands
+                .entity(agent_entity)
+                .insert(DockedAt(station_entity));
+            events.granted.send(DockGrantedEvent {
+                agent: agent_entity,
+                station: station_entity,
+            });
+        }
+    }
+}
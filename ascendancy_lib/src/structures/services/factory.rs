@@ -0,0 +1,244 @@
+use std::fmt;
+
+use bevy::prelude::*;
+use bevy::reflect::Reflect;
+use serde::{Deserialize, Serialize};
+
+use crate::items::inventory::InventoryError;
+use crate::items::recipe::Recipe;
+use crate::items::ItemId;
+use crate::structures::station::{ResourceManager, Station};
+
+// structures/services/Factory.rs
+use super::StationServiceTrait;
+
+/// Why a factory couldn't finish its cycle
+#[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize)]
+pub enum FactoryStall {
+    /// The station doesn't have enough of an input
+    MissingInput {
+        /// The input that ran out
+        item: ItemId,
+        /// The number of units each cycle uses
+        required: u32,
+        /// The number of units the station holds
+        available: u32,
+    },
+    /// The station doesn't have enough energy for a cycle
+    MissingEnergy {
+        /// The energy each cycle uses
+        required: f32,
+        /// The energy the station has
+        available: f32,
+    },
+    /// The station has no room to store the outputs
+    OutputStorageFull {
+        /// Whether the fluid storage is the one that is full
+        fluid: bool,
+    },
+}
+
+impl fmt::Display for FactoryStall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingInput {
+                item,
+                required,
+                available,
+            } => write!(
+                f,
+                "missing input {}, {} needed but only {} held",
+                item, required, available
+            ),
+            Self::MissingEnergy {
+                required,
+                available,
+            } => write!(
+                f,
+                "missing energy, {} needed but only {} stored",
+                required, available
+            ),
+            Self::OutputStorageFull { fluid } => write!(
+                f,
+                "no room for outputs in {} storage",
+                if *fluid { "fluid" } else { "solid" }
+            ),
+        }
+    }
+}
+
+/// The `Factory` struct represents the Factory service
+///
+/// Each cycle uses up the recipe's inputs and energy from the station and adds its outputs to the station's stores.
+/// A cycle that can't finish stalls, and is retried until it can.
+#[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct Factory {
+    /// The ID of the factory
+    pub id: u32,
+    /// The name of the factory
+    pub name: String,
+    /// The recipe the factory makes
+    pub recipe: Recipe,
+    /// Whether the factory is active or not
+    pub is_active: bool,
+    /// The number of cycles finished
+    pub cycles_completed: u64,
+    /// Why the current cycle can't finish, if it can't
+    stall: Option<FactoryStall>,
+    /// Whether the current stall has been reported
+    stall_reported: bool,
+    /// Times each production cycle
+    cycle_timer: Timer,
+}
+
+impl Factory {
+    /// Creates a new Factory service making the given recipe
+    pub fn new(id: u32, name: String, recipe: &Recipe) -> Self {
+        Factory {
+            id,
+            name,
+            recipe: recipe.clone(),
+            is_active: true,
+            cycles_completed: 0,
+            stall: None,
+            stall_reported: false,
+            cycle_timer: Timer::from_seconds(recipe.cycle_seconds, TimerMode::Once),
+        }
+    }
+
+    /// Why the factory is stalled, if it is
+    pub fn stall(&self) -> Option<&FactoryStall> {
+        self.stall.as_ref()
+    }
+
+    /// Returns the factory's stall the first time it is asked, so each stall is only reported once
+    pub fn take_unreported_stall(&mut self) -> Option<FactoryStall> {
+        if self.stall_reported {
+            return None;
+        }
+        self.stall_reported = true;
+        self.stall.clone()
+    }
+
+    /// Uses up the inputs and energy for one cycle and stores the outputs, changing nothing if it can't
+    fn produce(&self, resources: &mut ResourceManager) -> Result<(), FactoryStall> {
+        if resources.energy < self.recipe.energy {
+            return Err(FactoryStall::MissingEnergy {
+                required: self.recipe.energy,
+                available: resources.energy,
+            });
+        }
+
+        resources
+            .inventory
+            .apply(self.recipe.transaction())
+            .map_err(|error| match error {
+                InventoryError::InsufficientStock {
+                    item,
+                    required,
+                    available,
+                } => FactoryStall::MissingInput {
+                    item,
+                    required,
+                    available,
+                },
+                InventoryError::InsufficientSpace { fluid, .. } => {
+                    FactoryStall::OutputStorageFull { fluid }
+                }
+                // A stack too big to count can't take any more outputs either
+                InventoryError::QuantityOverflow { item, .. } => FactoryStall::OutputStorageFull {
+                    fluid: self
+                        .recipe
+                        .outputs
+                        .iter()
+                        .any(|(output, stack)| *output == item && stack.fluid),
+                },
+            })?;
+        resources.energy -= self.recipe.energy;
+        Ok(())
+    }
+}
+
+impl StationServiceTrait for Factory {
+    fn id(&self) -> u32 {
+        self.id
+    }
+
+    fn enable(&mut self) {
+        self.is_active = true;
+    }
+
+    fn disable(&mut self) {
+        self.is_active = false;
+    }
+
+    /// Factories draw their energy a cycle at a time in `run`, so there is nothing to consume here
+    fn consume_energy(&mut self, _: &mut ResourceManager, _: &Res<Time>) -> bool {
+        true
+    }
+
+    fn run(&mut self, resources: &mut ResourceManager, time: &Res<Time>) {
+        if !self.is_active {
+            return;
+        }
+
+        self.cycle_timer.tick(time.delta());
+        if !self.cycle_timer.finished() {
+            return;
+        }
+
+        match self.produce(resources) {
+            Ok(()) => {
+                self.cycles_completed += 1;
+                self.stall = None;
+                self.stall_reported = false;
+                self.cycle_timer.reset();
+            }
+            Err(stall) => {
+                if self.stall.as_ref() != Some(&stall) {
+                    self.stall_reported = false;
+                }
+                self.stall = Some(stall);
+            }
+        }
+    }
+}
+
+impl PartialOrd for Factory {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        self.cycles_completed.partial_cmp(&other.cycles_completed)
+    }
+}
+
+/// Sent when a factory stalls, and again if the reason it is stalled changes
+#[derive(Event, Debug, Clone)]
+pub struct FactoryStalledEvent {
+    /// The station the factory is on
+    pub station: Entity,
+    /// The name of the factory
+    pub factory: String,
+    /// The recipe the factory makes
+    pub recipe: String,
+    /// Why it stalled
+    pub stall: FactoryStall,
+}
+
+/// Reports factories that have stalled since the last check
+pub fn report_factory_stalls(
+    mut stations: Query<(Entity, &mut Station)>,
+    mut stalls: EventWriter<FactoryStalledEvent>,
+) {
+    for (station_entity, mut station) in stations.iter_mut() {
+        for factory in station.factories_mut() {
+            if let Some(stall) = factory.take_unreported_stall() {
+                debug!("{} stalled: {}", factory.name, stall);
+                stalls.send(FactoryStalledEvent {
+                    station: station_entity,
+                    factory: factory.name.clone(),
+                    recipe: factory.recipe.key.clone(),
+                    stall,
+                });
+            }
+        }
+    }
+}
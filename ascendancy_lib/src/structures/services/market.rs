@@ -1,14 +1,179 @@
+use std::collections::HashMap;
+use std::fmt;
+
 use bevy::prelude::*;
-use bevy::{reflect::Reflect, utils::uuid};
+use bevy::reflect::Reflect;
+use serde::{Deserialize, Serialize};
 
-use crate::structures::station::ResourceManager;
+use crate::agent::agent::{Agent, Wallet};
+use crate::agent::cargo::CargoHold;
+use crate::faction::attributes::{Attributes, FactionID};
+use crate::faction::bank::{Bank, TransactionKind};
+use crate::faction::taxes::TaxPolicy;
+use crate::items::inventory::{Inventory, InventoryError, InventoryTransaction, ItemStack};
+use crate::items::{ItemId, ItemRegistry};
+use crate::simulation::SimulationClock;
+use crate::solar_system::SolarSystem;
+use crate::structures::station::{ResourceManager, Station};
 
 // structures/services/market.rs
 use super::StationServiceTrait;
 
+/// The gap between the station's bid and ask, as a fraction of the item's price
+const STATION_SPREAD: f32 = 0.1;
+/// How strongly prices react to the balance of supply and demand
+const PRICE_ELASTICITY: f32 = 0.5;
+/// The lowest price an item can reach, as a multiple of its base price
+const MIN_PRICE_MULTIPLIER: f32 = 0.2;
+/// The highest price an item can reach, as a multiple of its base price
+const MAX_PRICE_MULTIPLIER: f32 = 5.0;
+/// The stock of each item a station aims to keep
+const DEFAULT_TARGET_STOCK: u32 = 1000;
+
+/// Identifies an order on a market
+pub type OrderId = u64;
+
+/// Whether an order is buying or selling
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub enum OrderSide {
+    /// The order is buying goods
+    Buy,
+    /// The order is selling goods
+    Sell,
+}
+
+/// Who placed an order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub enum Trader {
+    /// The station the market is on, trading from its own inventory
+    Station,
+    /// An agent
+    Agent(Entity),
+}
+
+/// A standing offer to buy or sell an item at a limit price.
+///
+/// Agent orders are backed by escrow: credits for buy orders and goods for sell orders are taken when the order is placed.
+#[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct MarketOrder {
+    /// The order's ID, lower IDs were placed first
+    pub id: OrderId,
+    /// Who placed the order
+    pub trader: Trader,
+    /// Whether the order is buying or selling
+    pub side: OrderSide,
+    /// The item being traded
+    pub item: ItemId,
+    /// The number of units still to be traded
+    pub quantity: u32,
+    /// The limit price per unit
+    pub price: f32,
+}
+
+/// The best price on one side of the order book
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quote {
+    /// The price per unit
+    pub price: f32,
+    /// The number of units available at that price
+    pub quantity: u32,
+}
+
+/// A completed trade between two orders
+#[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct MarketTrade {
+    /// The item traded
+    pub item: ItemId,
+    /// The number of units traded
+    pub quantity: u32,
+    /// The price paid per unit
+    pub price: f32,
+    /// Who bought the goods
+    pub buyer: Trader,
+    /// Who sold the goods
+    pub seller: Trader,
+}
+
+/// Credits and goods owed to an agent, waiting to be delivered.
+#[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct MarketFill {
+    /// The agent being paid
+    pub agent: Entity,
+    /// Credits owed to the agent
+    pub credits: f32,
+    /// Goods owed to the agent
+    pub goods: Option<(ItemId, u32)>,
+}
+
+/// An item the market trades, and its current price
+#[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct Listing {
+    /// The price the item started at
+    pub base_price: f32,
+    /// The current price, set from supply and demand
+    pub price: f32,
+    /// The stock the station aims to keep
+    pub target_stock: u32,
+    /// The volume taken up by a single unit
+    pub volume_per_unit: f32,
+    /// Whether the item is kept in fluid storage
+    pub fluid: bool,
+}
+
+impl Listing {
+    /// A stack of the listed item
+    fn stack(&self, quantity: u32) -> ItemStack {
+        ItemStack {
+            quantity,
+            volume_per_unit: self.volume_per_unit,
+            fluid: self.fluid,
+        }
+    }
+}
+
+/// Why an order was rejected
+#[derive(Debug, Clone, PartialEq)]
+pub enum MarketError {
+    /// The market doesn't trade the item
+    NotListed(ItemId),
+    /// The order has no quantity or an invalid price
+    InvalidOrder,
+    /// The agent can't afford to escrow the order
+    InsufficientFunds {
+        /// The credits needed
+        required: f32,
+        /// The credits the agent has
+        available: f32,
+    },
+    /// The agent doesn't have the goods to sell
+    Inventory(InventoryError),
+    /// The market is not running
+    Inactive,
+}
+
+impl fmt::Display for MarketError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotListed(item) => write!(f, "{} is not traded on this market", item),
+            Self::InvalidOrder => write!(f, "orders need a quantity and a positive price"),
+            Self::InsufficientFunds {
+                required,
+                available,
+            } => write!(
+                f,
+                "{} credits needed but only {} available",
+                required, available
+            ),
+            Self::Inventory(error) => write!(f, "{}", error),
+            Self::Inactive => write!(f, "the market is not running"),
+        }
+    }
+}
+
+impl std::error::Error for MarketError {}
+
 /// The `Market` struct represents the market service
-/// The `Market` struct represents the market service
-#[derive(Debug, Clone, PartialEq, Reflect)]
+#[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize)]
 pub struct Market {
     /// The ID of the dock
     pub id: u32,
@@ -18,30 +183,461 @@ pub struct Market {
     pub base_energy_consumption: f32,
     /// Whether the dock is active or not
     pub is_active: bool,
+    /// The credits the station trades with, kept topped up from the owning faction's bank
+    #[serde(default)]
+    pub funds: f32,
+    /// The share of every sale withheld from the seller as tax, set from the owning faction's policy
+    #[serde(default)]
+    pub tax_rate: f32,
+    /// Tax withheld but not yet paid to the owning faction
+    #[serde(default)]
+    taxes_owed: f32,
     /// fulctuation of energy consumption as a percentage
     energy_fluctuation: f32,
     /// Energy consumnption timer
     consumption_timer: Timer, // Add a Timer for consumption logic
+    /// The items traded on the market
+    #[serde(default)]
+    listings: HashMap<ItemId, Listing>,
+    /// The open orders
+    #[serde(default)]
+    orders: Vec<MarketOrder>,
+    /// The ID given to the next order
+    #[serde(default)]
+    next_order_id: OrderId,
+    /// Credits and goods waiting to be delivered to agents
+    #[serde(default)]
+    fills: Vec<MarketFill>,
+    /// Trades made since they were last collected
+    #[serde(default)]
+    trades: Vec<MarketTrade>,
 }
 
 impl Market {
-    /// Creates a new market service
-    pub fn new() -> Self {
-        Market {
-            id: uuid::Uuid::new_v4().as_u128() as u32,
+    /// Creates a new market service that trades every item in the registry
+    pub fn new(id: u32, items: &ItemRegistry) -> Self {
+        let mut market = Market {
+            id,
             name: "Market".to_string(),
             base_energy_consumption: 400.0,
             is_active: true,
+            funds: 0.0,
+            tax_rate: 0.0,
+            taxes_owed: 0.0,
             energy_fluctuation: 0.2,
             consumption_timer: Timer::from_seconds(5.0, bevy::time::TimerMode::Repeating), // Initialize the timer
+            listings: HashMap::new(),
+            orders: Vec::new(),
+            next_order_id: 0,
+            fills: Vec::new(),
+            trades: Vec::new(),
+        };
+        market.list_items(items);
+        market
+    }
+
+    /// Lists every item in the registry the market doesn't trade yet, at its base price
+    pub fn list_items(&mut self, items: &ItemRegistry) {
+        for item in items.iter() {
+            self.listings.entry(item.id).or_insert_with(|| Listing {
+                base_price: item.base_price,
+                price: item.base_price,
+                target_stock: DEFAULT_TARGET_STOCK,
+                volume_per_unit: item.volume_per_unit,
+                fluid: item.fluid,
+            });
+        }
+    }
+
+    /// Get the listing for an item
+    pub fn listing(&self, item: ItemId) -> Option<&Listing> {
+        self.listings.get(&item)
+    }
+
+    /// Iterate over every item traded on the market
+    pub fn listings(&self) -> impl Iterator<Item = (ItemId, &Listing)> {
+        self.listings.iter().map(|(id, listing)| (*id, listing))
+    }
+
+    /// The current price of an item, set from supply and demand
+    pub fn price(&self, item: ItemId) -> Option<f32> {
+        self.listings.get(&item).map(|listing| listing.price)
+    }
+
+    /// The highest price anyone is paying for an item
+    pub fn best_bid(&self, item: ItemId) -> Option<Quote> {
+        self.best_quote(item, OrderSide::Buy)
+    }
+
+    /// The lowest price anyone is selling an item for
+    pub fn best_ask(&self, item: ItemId) -> Option<Quote> {
+        self.best_quote(item, OrderSide::Sell)
+    }
+
+    /// The best price on one side of the book, along with the quantity available at it
+    fn best_quote(&self, item: ItemId, side: OrderSide) -> Option<Quote> {
+        let orders = self
+            .orders
+            .iter()
+            .filter(|order| order.item == item && order.side == side && order.quantity > 0);
+        let price = match side {
+            OrderSide::Buy => orders.clone().map(|order| order.price).reduce(f32::max),
+            OrderSide::Sell => orders.clone().map(|order| order.price).reduce(f32::min),
+        }?;
+
+        Some(Quote {
+            price,
+            quantity: orders
+                .filter(|order| order.price == price)
+                .map(|order| order.quantity)
+                .sum(),
+        })
+    }
+
+    /// Iterate over the open orders
+    pub fn orders(&self) -> impl Iterator<Item = &MarketOrder> {
+        self.orders.iter()
+    }
+
+    /// Places an order to buy goods, taking the full cost from the wallet as escrow.
+    ///
+    /// Any difference between the limit price and the price paid is refunded when the order fills.
+    pub fn place_buy_order(
+        &mut self,
+        agent: Entity,
+        item: ItemId,
+        quantity: u32,
+        price: f32,
+        wallet: &mut Wallet,
+    ) -> Result<OrderId, MarketError> {
+        self.check_order(item, quantity, price)?;
+
+        let required = quantity as f32 * price;
+        if wallet.money < required {
+            return Err(MarketError::InsufficientFunds {
+                required,
+                available: wallet.money,
+            });
+        }
+        wallet.money -= required;
+
+        Ok(self.push_order(Trader::Agent(agent), OrderSide::Buy, item, quantity, price))
+    }
+
+    /// Places an order to sell goods, unloading them from the agent's hold as escrow.
+    pub fn place_sell_order(
+        &mut self,
+        agent: Entity,
+        item: ItemId,
+        quantity: u32,
+        price: f32,
+        hold: &mut CargoHold,
+    ) -> Result<OrderId, MarketError> {
+        self.check_order(item, quantity, price)?;
+        hold.unload(item, quantity)
+            .map_err(MarketError::Inventory)?;
+
+        Ok(self.push_order(Trader::Agent(agent), OrderSide::Sell, item, quantity, price))
+    }
+
+    /// Cancels an agent's order, returning its escrow as a fill.
+    ///
+    /// Returns false if there is no such order.
+    pub fn cancel_order(&mut self, id: OrderId) -> bool {
+        let Some(index) = self.orders.iter().position(|order| order.id == id) else {
+            return false;
+        };
+        let order = self.orders.remove(index);
+        self.refund(&order);
+        true
+    }
+
+    /// Cancels every order placed by an agent, returning their escrow as fills.
+    pub fn cancel_orders_for(&mut self, agent: Entity) {
+        let (cancelled, kept): (Vec<MarketOrder>, Vec<MarketOrder>) =
+            std::mem::take(&mut self.orders)
+                .into_iter()
+                .partition(|order| order.trader == Trader::Agent(agent));
+        self.orders = kept;
+        for order in cancelled.iter() {
+            self.refund(order);
+        }
+    }
+
+    /// Takes every trade made since the last call
+    pub fn take_trades(&mut self) -> Vec<MarketTrade> {
+        std::mem::take(&mut self.trades)
+    }
+
+    /// Takes the whole credits of tax withheld so far, leaving any fraction owed
+    pub fn take_taxes(&mut self) -> u32 {
+        let taxes = self.taxes_owed.max(0.0).floor();
+        self.taxes_owed -= taxes;
+        taxes as u32
+    }
+
+    /// Takes every fill owed to agents
+    pub fn take_fills(&mut self) -> Vec<MarketFill> {
+        std::mem::take(&mut self.fills)
+    }
+
+    /// Puts back fills that could not be delivered yet
+    pub fn return_fills(&mut self, fills: impl IntoIterator<Item = MarketFill>) {
+        self.fills.extend(fills);
+    }
+
+    /// Swaps the agent entities the market refers to, dropping anything owned by agents that no longer exist.
+    pub fn map_entities(&mut self, entity_map: &HashMap<Entity, Entity>) {
+        self.orders.retain_mut(|order| match order.trader {
+            Trader::Station => true,
+            Trader::Agent(agent) => match entity_map.get(&agent) {
+                Some(mapped) => {
+                    order.trader = Trader::Agent(*mapped);
+                    true
+                }
+                None => false,
+            },
+        });
+        self.fills
+            .retain_mut(|fill| match entity_map.get(&fill.agent) {
+                Some(mapped) => {
+                    fill.agent = *mapped;
+                    true
+                }
+                None => false,
+            });
+    }
+
+    /// Checks that an order can be placed
+    fn check_order(&self, item: ItemId, quantity: u32, price: f32) -> Result<(), MarketError> {
+        if !self.is_active {
+            return Err(MarketError::Inactive);
+        }
+        if !self.listings.contains_key(&item) {
+            return Err(MarketError::NotListed(item));
+        }
+        if quantity == 0 || !(price.is_finite() && price > 0.0) {
+            return Err(MarketError::InvalidOrder);
+        }
+        Ok(())
+    }
+
+    /// Adds an order to the book
+    fn push_order(
+        &mut self,
+        trader: Trader,
+        side: OrderSide,
+        item: ItemId,
+        quantity: u32,
+        price: f32,
+    ) -> OrderId {
+        let id = self.next_order_id;
+        self.next_order_id += 1;
+        self.orders.push(MarketOrder {
+            id,
+            trader,
+            side,
+            item,
+            quantity,
+            price,
+        });
+        id
+    }
+
+    /// Returns the escrow of a removed order to the agent that placed it
+    fn refund(&mut self, order: &MarketOrder) {
+        let Trader::Agent(agent) = order.trader else {
+            return;
+        };
+        self.fills.push(match order.side {
+            OrderSide::Buy => MarketFill {
+                agent,
+                credits: order.quantity as f32 * order.price,
+                goods: None,
+            },
+            OrderSide::Sell => MarketFill {
+                agent,
+                credits: 0.0,
+                goods: Some((order.item, order.quantity)),
+            },
+        });
+    }
+
+    /// Reprices every item and matches any orders that cross, trading with the station's inventory.
+    pub fn trade(&mut self, inventory: &mut Inventory) {
+        self.update_prices(inventory);
+        self.match_orders(inventory);
+    }
+
+    /// Sets prices from supply and demand, and replaces the station's own orders.
+    ///
+    /// Supply is the station's stock plus goods agents are selling, demand is the station's target stock plus goods agents are buying.
+    fn update_prices(&mut self, inventory: &Inventory) {
+        self.orders.retain(|order| order.trader != Trader::Station);
+
+        let mut quotes = Vec::new();
+        for (item, listing) in self.listings.iter_mut() {
+            let stock = inventory.quantity(*item);
+            let (mut supply, mut demand) = (stock as f32, listing.target_stock as f32);
+            for order in self.orders.iter().filter(|order| order.item == *item) {
+                match order.side {
+                    OrderSide::Buy => demand += order.quantity as f32,
+                    OrderSide::Sell => supply += order.quantity as f32,
+                }
+            }
+
+            let multiplier = (demand / supply.max(1.0))
+                .powf(PRICE_ELASTICITY)
+                .clamp(MIN_PRICE_MULTIPLIER, MAX_PRICE_MULTIPLIER);
+            listing.price = listing.base_price * multiplier;
+
+            let ask = listing.price * (1.0 + STATION_SPREAD / 2.0);
+            let bid = listing.price * (1.0 - STATION_SPREAD / 2.0);
+            let room = (inventory.free_volume(listing.fluid) / listing.volume_per_unit) as u32;
+            let wanted = (listing.target_stock * 2)
+                .saturating_sub(stock)
+                .min(room)
+                .min((self.funds.max(0.0) / bid) as u32);
+
+            if stock > 0 {
+                quotes.push((*item, OrderSide::Sell, stock, ask));
+            }
+            if wanted > 0 {
+                quotes.push((*item, OrderSide::Buy, wanted, bid));
+            }
+        }
+
+        for (item, side, quantity, price) in quotes {
+            self.push_order(Trader::Station, side, item, quantity, price);
+        }
+    }
+
+    /// Matches crossing orders and settles the trades.
+    ///
+    /// Orders are filled best price first, then oldest first.
+    /// Trades with the station happen at the station's price, otherwise at the price of the older order.
+    fn match_orders(&mut self, inventory: &mut Inventory) {
+        let items: Vec<ItemId> = self.listings.keys().copied().collect();
+        for item in items {
+            while let Some((buy, sell)) = self.best_match(item) {
+                if !self.settle(buy, sell, inventory) {
+                    break;
+                }
+            }
         }
+        self.orders.retain(|order| order.quantity > 0);
+    }
+
+    /// Finds the indices of the best crossing buy and sell orders for an item
+    ///
+    /// A trader's orders never fill each other, so those pairs are passed over for the next-best order.
+    fn best_match(&self, item: ItemId) -> Option<(usize, usize)> {
+        let candidates = |side: OrderSide| {
+            let mut orders: Vec<_> = self
+                .orders
+                .iter()
+                .enumerate()
+                .filter(|(_, order)| order.item == item && order.side == side && order.quantity > 0)
+                .collect();
+            orders.sort_by(|(_, a), (_, b)| {
+                let by_price = match side {
+                    OrderSide::Buy => b.price.total_cmp(&a.price),
+                    OrderSide::Sell => a.price.total_cmp(&b.price),
+                };
+                by_price.then(a.id.cmp(&b.id))
+            });
+            orders
+        };
+
+        let sells = candidates(OrderSide::Sell);
+        candidates(OrderSide::Buy)
+            .into_iter()
+            .find_map(|(buy, best_buy)| {
+                sells
+                    .iter()
+                    .take_while(|(_, sell)| best_buy.price >= sell.price)
+                    .find(|(_, sell)| sell.trader != best_buy.trader)
+                    .map(|(sell, _)| (buy, *sell))
+            })
+    }
+
+    /// Trades as much as possible between two orders, returning false if nothing could be traded
+    fn settle(&mut self, buy: usize, sell: usize, inventory: &mut Inventory) -> bool {
+        let (buy_order, sell_order) = (self.orders[buy].clone(), self.orders[sell].clone());
+        let Some(listing) = self.listings.get(&buy_order.item).cloned() else {
+            return false;
+        };
+
+        let price = match (buy_order.trader, sell_order.trader) {
+            (Trader::Station, _) => buy_order.price,
+            (_, Trader::Station) => sell_order.price,
+            _ if buy_order.id < sell_order.id => buy_order.price,
+            _ => sell_order.price,
+        };
+        let mut quantity = buy_order.quantity.min(sell_order.quantity);
+        if buy_order.trader == Trader::Station {
+            quantity = quantity.min((self.funds.max(0.0) / price) as u32);
+        }
+        if quantity == 0 {
+            self.orders[buy].quantity = 0;
+            return true;
+        }
+
+        let transaction = match (buy_order.trader, sell_order.trader) {
+            (Trader::Station, _) => {
+                InventoryTransaction::new().add_stack(buy_order.item, listing.stack(quantity))
+            }
+            (_, Trader::Station) => InventoryTransaction::new().remove(buy_order.item, quantity),
+            _ => InventoryTransaction::new(),
+        };
+        if inventory.apply(transaction).is_err() {
+            // The station's quote no longer matches its inventory, drop it until the next refresh
+            let station_order = if buy_order.trader == Trader::Station {
+                buy
+            } else {
+                sell
+            };
+            self.orders[station_order].quantity = 0;
+            return true;
+        }
+
+        let total = quantity as f32 * price;
+        // Sales tax is withheld from whoever is selling
+        let tax = total * self.tax_rate;
+        self.taxes_owed += tax;
+        match buy_order.trader {
+            Trader::Station => self.funds -= total,
+            Trader::Agent(agent) => self.fills.push(MarketFill {
+                agent,
+                credits: quantity as f32 * (buy_order.price - price),
+                goods: Some((buy_order.item, quantity)),
+            }),
+        }
+        match sell_order.trader {
+            Trader::Station => self.funds += total - tax,
+            Trader::Agent(agent) => self.fills.push(MarketFill {
+                agent,
+                credits: total - tax,
+                goods: None,
+            }),
+        }
+
+        self.orders[buy].quantity -= quantity;
+        self.orders[sell].quantity -= quantity;
+        self.trades.push(MarketTrade {
+            item: buy_order.item,
+            quantity,
+            price,
+            buyer: buy_order.trader,
+            seller: sell_order.trader,
+        });
+        true
     }
 }
 
 impl StationServiceTrait for Market {
     fn id(&self) -> u32 {
-        // Market-specific ID logic
-        1
+        self.id
     }
 
     fn enable(&mut self) {
@@ -76,9 +672,9 @@ impl StationServiceTrait for Market {
         }
     }
 
-    fn run(&mut self, _: &mut ResourceManager, _: &Res<Time>) {
+    fn run(&mut self, resources: &mut ResourceManager, _: &Res<Time>) {
         if self.is_active {
-        } else {
+            self.trade(&mut resources.inventory);
         }
     }
 }
@@ -89,3 +685,115 @@ impl PartialOrd for Market {
             .partial_cmp(&other.base_energy_consumption)
     }
 }
+
+/// Sent whenever two orders trade on a market
+#[derive(Event, Debug, Clone)]
+pub struct MarketTradeEvent {
+    /// The station the market is on
+    pub station: Entity,
+    /// The trade that was made
+    pub trade: MarketTrade,
+}
+
+/// The credits a market aims to hold, anything well above this is paid into the owning faction's bank
+const MARKET_FLOAT: f32 = 50000.0;
+
+/// Delivers the credits and goods owed to agents, and reports every trade made on a market.
+///
+/// Goods that don't fit in an agent's hold stay owed until there is room.
+pub fn deliver_market_fills(
+    mut stations: Query<(Entity, &mut Station)>,
+    mut agents: Query<(&mut Agent, &mut CargoHold)>,
+    mut trade_events: EventWriter<MarketTradeEvent>,
+) {
+    for (station_entity, mut station) in stations.iter_mut() {
+        let Some(market) = station.market_mut() else {
+            continue;
+        };
+
+        for trade in market.take_trades() {
+            trade_events.send(MarketTradeEvent {
+                station: station_entity,
+                trade,
+            });
+        }
+
+        let mut undelivered = Vec::new();
+        for mut fill in market.take_fills() {
+            let Ok((mut agent, mut hold)) = agents.get_mut(fill.agent) else {
+                continue;
+            };
+
+            agent.wallet.money += fill.credits;
+            fill.credits = 0.0;
+
+            if let Some((item, quantity)) = fill.goods {
+                let delivered = market
+                    .listing(item)
+                    .is_some_and(|listing| hold.load_stack(item, listing.stack(quantity)).is_ok());
+                if !delivered {
+                    undelivered.push(fill);
+                }
+            }
+        }
+        market.return_fills(undelivered);
+    }
+}
+
+/// Keeps every market's funds near [`MARKET_FLOAT`], paying surplus into and drawing shortfalls from the owning faction's bank.
+///
+/// Markets also take their tax rate from the owning faction's policy and pay it the taxes they have withheld.
+pub fn sweep_market_funds(
+    mut stations: Query<&mut Station>,
+    systems: Query<&SolarSystem>,
+    mut factions: Query<(&Attributes, &mut Bank, Option<&TaxPolicy>)>,
+    clock: Res<SimulationClock>,
+) {
+    let owners: HashMap<u32, FactionID> = systems
+        .iter()
+        .map(|system| (system.attributes.id, system.attributes.owner))
+        .collect();
+
+    for mut station in stations.iter_mut() {
+        let Some(owner) = owners.get(&station.system_id).copied() else {
+            continue;
+        };
+        let Some(market) = station.market_mut() else {
+            continue;
+        };
+        let Some((_, mut bank, taxes)) = factions
+            .iter_mut()
+            .find(|(attributes, _, _)| attributes.id == owner)
+        else {
+            market.tax_rate = 0.0;
+            continue;
+        };
+
+        market.tax_rate = taxes.map_or(0.0, |taxes| taxes.trade_rate);
+        let taxes = market.take_taxes();
+        if taxes > 0 {
+            if let Err(error) = bank.deposit(TransactionKind::Tax, taxes, clock.now()) {
+                warn!("Market taxes for faction {} were lost: {}", owner.id, error);
+            }
+        }
+
+        if market.funds > MARKET_FLOAT * 2.0 {
+            let surplus = (market.funds - MARKET_FLOAT) as u32;
+            // A bank too full to take the surplus leaves it with the market
+            if bank
+                .deposit(TransactionKind::Trade, surplus, clock.now())
+                .is_ok()
+            {
+                market.funds -= surplus as f32;
+            }
+        } else if market.funds < MARKET_FLOAT / 2.0 {
+            let shortfall = ((MARKET_FLOAT - market.funds) as u32).min(bank.bank_balance());
+            if bank
+                .withdraw(TransactionKind::Trade, shortfall, clock.now())
+                .is_ok()
+            {
+                market.funds += shortfall as f32;
+            }
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy::reflect::Reflect;
+use serde::{Deserialize, Serialize};
+
+use crate::items::ItemRegistry;
+use crate::solar_system::resources::{ResourceFields, ResourceKind};
+use crate::solar_system::SolarSystem;
+use crate::structures::station::{ResourceManager, Station};
+
+// structures/services/Mining.rs
+use super::{StationServiceTrait, StationServices};
+
+/// The `Mining` struct represents the Mining service
+///
+/// A mining rig extracts a resource from the fields in its station's system into the station's stores.
+/// The extraction itself happens in [`run_mining`], as it needs the system's fields.
+#[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct Mining {
+    /// The ID of the mining rig
+    pub id: u32,
+    /// The name of the mining rig
+    pub name: String,
+    /// The kind of resource the rig extracts
+    pub resource: ResourceKind,
+    /// The units extracted each cycle
+    pub extraction_rate: u32,
+    /// Flat rate of energy consumption
+    pub base_energy_consumption: f32,
+    /// Whether the rig is active or not
+    pub is_active: bool,
+    /// Fluctuation of energy consumption as a percentage
+    energy_fluctuation: f32,
+    /// Energy consumption timer
+    consumption_timer: Timer,
+    /// Times each extraction cycle
+    extraction_timer: Timer,
+    /// Cycles finished but not yet extracted
+    ready_cycles: u32,
+}
+
+impl Mining {
+    /// Creates a new Mining service extracting the given resource
+    pub fn new(id: u32, name: String, resource: ResourceKind) -> Self {
+        Mining {
+            id,
+            name,
+            resource,
+            extraction_rate: 20,
+            base_energy_consumption: 300.0,
+            is_active: true,
+            energy_fluctuation: 0.2,
+            consumption_timer: Timer::from_seconds(5.0, TimerMode::Repeating),
+            extraction_timer: Timer::from_seconds(5.0, TimerMode::Repeating),
+            ready_cycles: 0,
+        }
+    }
+
+    /// Takes the number of units the rig is due to extract
+    pub fn take_due_units(&mut self) -> u32 {
+        std::mem::take(&mut self.ready_cycles) * self.extraction_rate
+    }
+}
+
+impl StationServiceTrait for Mining {
+    fn id(&self) -> u32 {
+        self.id
+    }
+
+    fn enable(&mut self) {
+        self.is_active = true;
+    }
+
+    fn disable(&mut self) {
+        self.is_active = false;
+    }
+
+    fn consume_energy(&mut self, resources: &mut ResourceManager, time: &Res<Time>) -> bool {
+        self.consumption_timer.tick(time.delta());
+        if !self.consumption_timer.finished() {
+            return false;
+        }
+
+        let energy_consumption = self.base_energy_consumption * (1.0 + self.energy_fluctuation);
+        if resources.consume_energy(energy_consumption) {
+            self.enable();
+            true
+        } else {
+            self.disable();
+            false
+        }
+    }
+
+    fn run(&mut self, _: &mut ResourceManager, time: &Res<Time>) {
+        if self.is_active {
+            self.extraction_timer.tick(time.delta());
+            self.ready_cycles += self.extraction_timer.times_finished_this_tick();
+        }
+    }
+}
+
+impl PartialOrd for Mining {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        self.extraction_rate.partial_cmp(&other.extraction_rate)
+    }
+}
+
+/// Moves the units mining rigs are due from their system's richest field into their station's stores.
+///
+/// Rigs stop extracting when the station has no room, leaving the rest of the field in place.
+pub fn run_mining(
+    mut stations: Query<&mut Station>,
+    mut systems: Query<(&SolarSystem, &mut ResourceFields)>,
+    items: Res<ItemRegistry>,
+) {
+    let mut fields: HashMap<u32, Mut<ResourceFields>> = systems
+        .iter_mut()
+        .map(|(system, fields)| (system.attributes.id, fields))
+        .collect();
+
+    for mut station in stations.iter_mut() {
+        let system_id = station.system_id;
+        let Station {
+            services,
+            resource_manager,
+            ..
+        } = &mut *station;
+
+        for service in services.iter_mut() {
+            let StationServices::Mining(rig) = service else {
+                continue;
+            };
+            let due = rig.take_due_units();
+            if due == 0 {
+                continue;
+            }
+
+            let Some((_, field)) = fields
+                .get_mut(&system_id)
+                .and_then(|fields| fields.richest(rig.resource))
+            else {
+                continue;
+            };
+            let Some(item) = items.get(field.item()) else {
+                continue;
+            };
+
+            let room = resource_manager.inventory.room_for(item);
+            let extracted = field.extract(due.min(room));
+            if extracted > 0 {
+                resource_manager
+                    .inventory
+                    .add(item, extracted)
+                    .expect("extraction is limited to the room in the station");
+            }
+        }
+    }
+}
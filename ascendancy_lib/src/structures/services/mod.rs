@@ -1,13 +1,20 @@
-use self::{dock::Dock, market::Market, solar_generator::SolarGenerator};
+use self::{
+    dock::Dock, factory::Factory, market::Market, mining::Mining, solar_generator::SolarGenerator,
+};
 use bevy::prelude::*;
 use bevy::reflect::Reflect;
+use serde::{Deserialize, Serialize};
 
 use super::station::ResourceManager;
 
 /// The `Dock` struct represents the Dock service
 pub mod dock;
+/// The `Factory` struct represents the factory service
+pub mod factory;
 /// The `Market` struct represents the market service
 pub mod market;
+/// The `Mining` struct represents the mining service
+pub mod mining;
 /// The `solar_generator` struct represents the Solar Generator service
 pub mod solar_generator;
 
@@ -26,7 +33,7 @@ pub trait StationServiceTrait: std::fmt::Debug + Send + Sync + PartialEq {
 }
 
 /// The `StationServices` enum represents all the services that can be run on a station
-#[derive(Debug, Clone, PartialEq, PartialOrd, Reflect)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Reflect, Serialize, Deserialize)]
 pub enum StationServices {
     /// Dock service
     Dock(Dock),
@@ -34,6 +41,10 @@ pub enum StationServices {
     Market(Market),
     /// Solar Generator service
     SolarGenerator(SolarGenerator),
+    /// Factory service
+    Factory(Factory),
+    /// Mining service
+    Mining(Mining),
 }
 
 impl StationServiceTrait for StationServices {
@@ -43,6 +54,8 @@ impl StationServiceTrait for StationServices {
             StationServices::Dock(dock) => dock.id(),
             StationServices::Market(market) => market.id(),
             StationServices::SolarGenerator(solar_generator) => solar_generator.id(),
+            StationServices::Factory(factory) => factory.id(),
+            StationServices::Mining(mining) => mining.id(),
         }
     }
 
@@ -52,6 +65,8 @@ impl StationServiceTrait for StationServices {
             StationServices::Dock(dock) => dock.enable(),
             StationServices::Market(market) => market.enable(),
             StationServices::SolarGenerator(solar_generator) => solar_generator.enable(),
+            StationServices::Factory(factory) => factory.enable(),
+            StationServices::Mining(mining) => mining.enable(),
         }
     }
 
@@ -61,6 +76,8 @@ impl StationServiceTrait for StationServices {
             StationServices::Dock(dock) => dock.disable(),
             StationServices::Market(market) => market.disable(),
             StationServices::SolarGenerator(solar_generator) => solar_generator.disable(),
+            StationServices::Factory(factory) => factory.disable(),
+            StationServices::Mining(mining) => mining.disable(),
         }
     }
 
@@ -73,6 +90,8 @@ impl StationServiceTrait for StationServices {
             StationServices::SolarGenerator(solar_generator) => {
                 solar_generator.consume_energy(resources, time)
             }
+            StationServices::Factory(factory) => factory.consume_energy(resources, time),
+            StationServices::Mining(mining) => mining.consume_energy(resources, time),
         }
     }
 
@@ -84,6 +103,8 @@ impl StationServiceTrait for StationServices {
             StationServices::SolarGenerator(solar_generator) => {
                 solar_generator.run(resources, time)
             }
+            StationServices::Factory(factory) => factory.run(resources, time),
+            StationServices::Mining(mining) => mining.run(resources, time),
         }
     }
 }
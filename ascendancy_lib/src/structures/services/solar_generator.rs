@@ -1,4 +1,5 @@
-use bevy::{prelude::*, utils::uuid};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::structures::station::ResourceManager;
 
@@ -6,7 +7,7 @@ use crate::structures::station::ResourceManager;
 use super::StationServiceTrait;
 
 /// The `solar_generator` struct represents the Solar Generator service
-#[derive(Debug, Clone, PartialEq, Reflect)]
+#[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize)]
 pub struct SolarGenerator {
     /// The ID of the SolarGenerator
     pub id: u32,
@@ -30,9 +31,9 @@ pub struct SolarGenerator {
 
 impl SolarGenerator {
     /// Creates a new SolarGenerator service
-    pub fn new(name: String) -> Self {
+    pub fn new(id: u32, name: String) -> Self {
         SolarGenerator {
-            id: uuid::Uuid::new_v4().as_u128() as u32,
+            id,
             name: name,
             energy_production: 1000.0,
             energy_storage: 10000.0,
@@ -45,8 +46,7 @@ impl SolarGenerator {
 
 impl StationServiceTrait for SolarGenerator {
     fn id(&self) -> u32 {
-        // SolarGenerator-specific ID logic
-        1
+        self.id
     }
 
     fn enable(&mut self) {}
@@ -1,7 +1,10 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
 /// A stargate is a device within the game world that allows agents to travel between solar systems.
-#[derive(Component, Reflect, Clone, Debug, PartialEq, PartialOrd, Hash, Eq)]
+#[derive(
+    Component, Reflect, Serialize, Deserialize, Clone, Debug, PartialEq, PartialOrd, Hash, Eq,
+)]
 #[reflect(Component)]
 pub struct Stargate {
     /// Stargate ID
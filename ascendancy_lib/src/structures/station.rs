@@ -1,9 +1,24 @@
+use crate::agent::cargo::{CargoError, CargoHold};
+use crate::items::inventory::Inventory;
+use crate::items::ItemDefinition;
+use crate::structures::services::dock::Dock;
+use crate::structures::services::factory::Factory;
+use crate::structures::services::market::Market;
 use crate::structures::services::{StationServiceTrait, StationServices};
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fmt;
 
+/// The volume of solid goods a station can store
+pub const STATION_SOLID_CAPACITY: f32 = 20000.0;
+/// The volume of fluid goods a station can store
+pub const STATION_FLUID_CAPACITY: f32 = 10000.0;
+/// The most services a station can run
+pub const MAX_SERVICES: usize = 5;
+
 /// A station is a location within the game world that provides services to agents.
-#[derive(Component, PartialEq, PartialOrd, Reflect)]
+#[derive(Component, Clone, PartialEq, Reflect, Serialize, Deserialize)]
 #[reflect(Component)]
 pub struct Station {
     /// Station ID
@@ -21,12 +36,14 @@ pub struct Station {
 }
 
 /// The `ResourceManager` struct represents the resource manager for a station
-#[derive(Component, PartialEq, PartialOrd, Reflect, Debug)]
+#[derive(Component, Clone, PartialEq, Reflect, Serialize, Deserialize, Debug)]
 pub struct ResourceManager {
     /// The amount of energy the station has
     pub energy: f32,
     /// The maximum amount of energy the station can have
     pub max_energy: f32,
+    /// The goods stored in the station's cargo bays
+    pub inventory: Inventory,
 }
 
 impl ResourceManager {
@@ -44,8 +61,6 @@ impl ResourceManager {
     pub fn produce_energy(&mut self, amount: f32) {
         self.energy += amount;
     }
-
-    // Methods for managing materials...
 }
 
 impl Station {
@@ -58,15 +73,16 @@ impl Station {
             resource_manager: ResourceManager {
                 energy: 0.,
                 max_energy: 10000.0,
+                inventory: Inventory::new(STATION_SOLID_CAPACITY, STATION_FLUID_CAPACITY),
             },
-            services: Vec::with_capacity(5), // Initialize with capacity for 5 services
+            services: Vec::with_capacity(MAX_SERVICES),
             is_active: true,
         }
     }
 
     /// Add service to the station
     pub fn add_service(&mut self, service: StationServices) -> Result<(), String> {
-        if self.services.len() < 5 {
+        if self.services.len() < MAX_SERVICES {
             if !self.services.contains(&service) {
                 self.services.push(service);
                 Ok(())
@@ -87,6 +103,97 @@ impl Station {
             Err("Service not found.".to_string())
         }
     }
+    /// Get the station's market, if it has one
+    pub fn market(&self) -> Option<&Market> {
+        self.services.iter().find_map(|service| match service {
+            StationServices::Market(market) => Some(market),
+            _ => None,
+        })
+    }
+
+    /// Get the station's market mutably, if it has one
+    pub fn market_mut(&mut self) -> Option<&mut Market> {
+        self.services.iter_mut().find_map(|service| match service {
+            StationServices::Market(market) => Some(market),
+            _ => None,
+        })
+    }
+
+    /// Get the station's dock, if it has one
+    pub fn dock(&self) -> Option<&Dock> {
+        self.services.iter().find_map(|service| match service {
+            StationServices::Dock(dock) => Some(dock),
+            _ => None,
+        })
+    }
+
+    /// Get the station's dock mutably, if it has one
+    pub fn dock_mut(&mut self) -> Option<&mut Dock> {
+        self.services.iter_mut().find_map(|service| match service {
+            StationServices::Dock(dock) => Some(dock),
+            _ => None,
+        })
+    }
+
+    /// Iterate over the station's factories
+    pub fn factories(&self) -> impl Iterator<Item = &Factory> {
+        self.services.iter().filter_map(|service| match service {
+            StationServices::Factory(factory) => Some(factory),
+            _ => None,
+        })
+    }
+
+    /// Iterate over the station's factories mutably
+    pub fn factories_mut(&mut self) -> impl Iterator<Item = &mut Factory> {
+        self.services
+            .iter_mut()
+            .filter_map(|service| match service {
+                StationServices::Factory(factory) => Some(factory),
+                _ => None,
+            })
+    }
+
+    /// Loads goods from the station's stores into the hold of an agent docked here
+    pub fn load_cargo(
+        &mut self,
+        agent: Entity,
+        hold: &mut CargoHold,
+        item: &ItemDefinition,
+        quantity: u32,
+    ) -> Result<(), CargoError> {
+        if !self.dock().is_some_and(|dock| dock.is_docked(agent)) {
+            return Err(CargoError::NotDocked);
+        }
+        Ok(hold.load_from(&mut self.resource_manager.inventory, item, quantity)?)
+    }
+
+    /// Unloads goods from the hold of an agent docked here into the station's stores
+    pub fn unload_cargo(
+        &mut self,
+        agent: Entity,
+        hold: &mut CargoHold,
+        item: &ItemDefinition,
+        quantity: u32,
+    ) -> Result<(), CargoError> {
+        if !self.dock().is_some_and(|dock| dock.is_docked(agent)) {
+            return Err(CargoError::NotDocked);
+        }
+        Ok(hold.unload_to(&mut self.resource_manager.inventory, item, quantity)?)
+    }
+
+    /// Swaps the entities the station's services refer to, used when a saved game is restored
+    pub fn map_entities(&mut self, entity_map: &HashMap<Entity, Entity>) {
+        for service in self.services.iter_mut() {
+            match service {
+                StationServices::Dock(dock) => dock.map_entities(entity_map),
+                StationServices::Market(market) => market.map_entities(entity_map),
+                StationServices::SolarGenerator(_)
+                | StationServices::Factory(_)
+                | StationServices::Mining(_) => {}
+            }
+        }
+    }
+
     /// Placeholder method to disable a service
     pub fn disable_service(&self, service: &StationServices) {
         // Implementation depends on your game logic
@@ -128,6 +235,14 @@ pub fn run_active_services(mut query: Query<&mut Station>, time: Res<Time>) {
                 StationServices::SolarGenerator(solar_generator) => {
                     solar_generator.run(&mut station.resource_manager, &time);
                 }
+                StationServices::Factory(factory) => {
+                    factory.consume_energy(&mut station.resource_manager, &time);
+                    factory.run(&mut station.resource_manager, &time);
+                }
+                StationServices::Mining(mining) => {
+                    mining.consume_energy(&mut station.resource_manager, &time);
+                    mining.run(&mut station.resource_manager, &time);
+                }
             }
         }
         station.services = services; // Update the station's services
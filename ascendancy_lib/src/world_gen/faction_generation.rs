@@ -2,6 +2,8 @@ use crate::{faction::FactionResourse, solar_system::SolarSystem};
 use bevy::prelude::*;
 use rand::Rng;
 
+use super::galaxy_seed::GalaxySeed;
+
 /// Creates a faction entity for each faction
 pub fn create_faction_entities(mut commands: Commands, factions: Res<FactionResourse>) {
     for faction in factions.factions.iter() {
@@ -13,8 +15,9 @@ pub fn create_faction_entities(mut commands: Commands, factions: Res<FactionReso
 pub fn assign_systems_to_factions(
     mut query: Query<(Entity, &mut SolarSystem), With<SolarSystem>>,
     factions: Res<FactionResourse>,
+    mut seed: ResMut<GalaxySeed>,
 ) {
-    let mut rng = rand::thread_rng();
+    let rng = seed.rng();
     for (_, mut solar_system) in query.iter_mut() {
         let faction_id = rng.gen_range(0..factions.factions.len());
         let faction = factions.factions.get(faction_id).unwrap();
@@ -0,0 +1,44 @@
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use super::solar_system_generation::GalaxyConfig;
+
+/// The seed and random number generator that drive world generation.
+///
+/// Every generation step draws from this single stream in a fixed order,
+/// so the same seed always reproduces the same galaxy.
+#[derive(Resource)]
+pub struct GalaxySeed {
+    /// The seed the generator was created from.
+    seed: u64,
+    /// The seeded random number generator.
+    rng: StdRng,
+}
+
+impl GalaxySeed {
+    /// Creates a new `GalaxySeed` from the given seed.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// Get the seed the galaxy was generated from
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Get the random number generator used for world generation
+    pub fn rng(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+}
+
+/// Creates the `GalaxySeed` resource from the `GalaxyConfig`, picking a random seed if none is set.
+pub fn seed_galaxy(mut commands: Commands, config: Res<GalaxyConfig>) {
+    let seed = config.seed.unwrap_or_else(|| rand::thread_rng().gen());
+    info!("Generating galaxy with seed {}", seed);
+    commands.insert_resource(GalaxySeed::new(seed));
+}
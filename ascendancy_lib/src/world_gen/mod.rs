@@ -2,21 +2,32 @@ use bevy::prelude::*;
 
 use crate::world_gen::faction_generation::{assign_systems_to_factions, create_faction_entities};
 use crate::world_gen::generate_system_path::create_system_graph;
-use crate::world_gen::npc_generation::spawn_agent;
+use crate::world_gen::npc_generation::{spawn_agent, spawn_fleets};
+use crate::world_gen::pirate_generation::spawn_pirates;
 use crate::world_gen::solar_system_generation::create_galaxy_solar_systems;
 use crate::GameState;
 
-use self::solar_system_generation::{spawn_space_station, GalaxyConfig};
-use self::stargate_generation::spawn_stargates;
+use self::galaxy_seed::seed_galaxy;
+use self::solar_system_generation::spawn_space_station;
+use self::stargate_generation::{report_gate_network, spawn_stargates};
+
+pub use self::galaxy_seed::GalaxySeed;
+pub use self::solar_system_generation::{GalaxyConfig, Map};
+pub use self::stargate_generation::GateNetworkReport;
+
 /// Set the game state to align systems with their respective runtimes
 pub struct WorldGenPlugin;
 
 /// The plugin that handles Factions generation.
 pub(crate) mod faction_generation;
+/// The seeded random number generator used by world generation.
+pub(crate) mod galaxy_seed;
 /// The plugin that handles `SystemPaths` generation.
 pub(crate) mod generate_system_path;
 /// The plugin that handles NPC generation.
 pub(crate) mod npc_generation;
+/// The plugin that handles pirate generation.
+pub(crate) mod pirate_generation;
 /// The plugin that handles solar system generation.
 pub(crate) mod solar_system_generation;
 /// The plugin that handles stargate generation.
@@ -25,19 +36,25 @@ pub(crate) mod stargate_generation;
 impl Plugin for WorldGenPlugin {
     fn build(&self, app: &mut App) {
         app.init_state::<GameState>()
-            .insert_resource(GalaxyConfig::default())
+            .init_resource::<GalaxyConfig>()
             .add_systems(
                 OnEnter(GameState::WorldGenerating),
                 (
+                    seed_galaxy,
+                    apply_deferred,
                     create_galaxy_solar_systems,
                     apply_deferred,
                     spawn_stargates,
                     create_system_graph,
+                    report_gate_network,
                     create_faction_entities,
                     assign_systems_to_factions,
                     apply_deferred,
                     spawn_agent,
+                    spawn_fleets,
                     spawn_space_station,
+                    apply_deferred,
+                    spawn_pirates,
                 )
                     .chain(),
             );
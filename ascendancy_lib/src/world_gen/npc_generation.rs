@@ -1,96 +1,85 @@
-use crate::player_interactions::selection::UpdateSelectedItemEvent;
 use crate::GameState;
 use crate::{
     agent::{
-        agent::Agent,
+        agent::{Agent, AgentRole, AGENT_FLUID_CARGO, AGENT_SOLID_CARGO},
+        cargo::CargoHold,
+        combat::{Shield, Weapon},
         fly_to_system_action::{
             FlyToSystem,
             //fly_to_system, jump_stargate_system, move_to_stargate_system,
             WantToFlyToSystem,
         },
         idle::{Idle, WantToWander},
+        mining::{FindMiningSite, FlyToMiningSite, MineResources, WantToMine},
+        pathfinding::SystemGraph,
+        piracy::{Flee, InterceptTrader, ScoutChokepoint, WantToFlee, WantToRaid},
+        trade::{BuyGoods, DockAtStation, FindTrade, FlyToStation, SellGoods, WantToTrade},
+    },
+    faction::{
+        fleets::{Fleet, FleetMember, FleetOrder, FollowFleet, WantToFollowFleet},
+        FactionResourse,
     },
-    loading::loading::TextureAssets,
     solar_system::SolarSystem,
 };
 use bevy::prelude::*;
-use bevy_mod_picking::events::{Down, Pointer};
-use bevy_mod_picking::prelude::On;
-use bevy_mod_picking::PickableBundle;
 use big_brain::prelude::*;
-use fakeit::name;
 use rand::seq::SliceRandom;
 use rand::Rng;
 
+use super::galaxy_seed::GalaxySeed;
+
 /// The number of agents to spawn
 const AGENTS_TO_SPAWN: u32 = 1000;
+/// The chance of an agent being spawned as a miner rather than a trader
+const MINER_CHANCE: f64 = 0.2;
+/// The number of fleets each faction starts with
+const FLEETS_PER_FACTION: u32 = 2;
+/// The number of agents in each starting fleet
+const STARTING_FLEET_SIZE: u32 = 5;
+/// The most systems on a starting fleet's patrol route
+const PATROL_ROUTE_LENGTH: usize = 4;
+/// The first names agents are given
+const FIRST_NAMES: &[&str] = &[
+    "Ada", "Amara", "Anton", "Astrid", "Bram", "Cassius", "Dara", "Elio", "Freya", "Hiro", "Ilse",
+    "Jonah", "Kaito", "Lena", "Mateo", "Mira", "Nadia", "Orin", "Priya", "Quinn", "Rosa", "Soren",
+    "Talia", "Uma", "Viktor", "Wren", "Yara", "Zane",
+];
+/// The family names agents are given
+const LAST_NAMES: &[&str] = &[
+    "Abara", "Bennett", "Castillo", "Dubois", "Eriksen", "Fontaine", "Garcia", "Hale", "Ishikawa",
+    "Jansen", "Kowalski", "Lind", "Mensah", "Novak", "Okafor", "Petrov", "Quist", "Reyes", "Sato",
+    "Thorne", "Umarov", "Vance", "Whitlock", "Xu", "Yilmaz", "Zeller",
+];
 
 /// Spawns a new agents `AGENTS_TO_SPAWN` number of times
 pub fn spawn_agent(
     mut commands: Commands,
     query: Query<(Entity, &SolarSystem, &Transform)>,
     mut state: ResMut<NextState<GameState>>,
-    textures: Res<TextureAssets>,
+    mut seed: ResMut<GalaxySeed>,
 ) {
-    // Build the thinker
-
     // Collect all solar systems and their positions into a vector
     let systems_with_positions: Vec<_> = query.iter().collect();
 
-    // Get a thread RNG (Random Number Generator)
-    let mut rng = rand::thread_rng();
+    // Use the galaxy RNG so agent spawns are reproducible from the seed
+    let rng = seed.rng();
 
     // Choose a random solar system and its position
     for _ in 0..AGENTS_TO_SPAWN {
-        //let find_and_execute_trade = Steps::build()
-        //.label("FindAndExecuteTrade")
-        //// ...move to the water source...
-        //.step(FindTrade)
-        //.step(FlyToTarget)
-        //.step(ExecuteTrade);
-
-        let thinker = Thinker::build()
-            .label("WandererThinker")
-            .picker(Highest {})
-            .when(WantToWander, Idle { target: None }) // Always wander as we have set the score high.
-            .when(
-                WantToFlyToSystem,
-                FlyToSystem {
-                    target: None,
-                    desire: 0.0,
-                }
-            )
-            //.when(
-            //    WantToTrade,
-            //    find_and_execute_trade
-            //)
-            ;
-
-        if let Some((_, solar_system, position)) = systems_with_positions.choose(&mut rng) {
+        if let Some((_, solar_system, position)) = systems_with_positions.choose(rng) {
             let mut spawn_position =
-                random_position_in_system(Vec2::splat(512.0), position.translation);
+                random_position_in_system(rng, Vec2::splat(512.0), position.translation);
             spawn_position.z = 0.1;
+            let role = if rng.gen_bool(MINER_CHANCE) {
+                AgentRole::Miner
+            } else {
+                AgentRole::Trader
+            };
             let _e = commands
-                .spawn((
-                    SpriteBundle {
-                        texture: textures.small_trader.clone().into(),
-                        transform: Transform {
-                            translation: spawn_position,
-                            scale: Vec3::splat(1.0),
-                            ..Default::default()
-                        },
-                        ..Default::default()
-                    },
-                    PickableBundle::default(),
-                    On::<Pointer<Down>>::send_event::<UpdateSelectedItemEvent>(),
-                    Agent::new(0, String::from(name::full()), solar_system),
-                    Idle::new(),
-                    FlyToSystem {
-                        target: None,
-                        desire: 1.0,
-                    },
-                    thinker,
-                    Name::new("Agent"),
+                .spawn(agent_bundle(
+                    Agent::new(0, agent_name(rng), solar_system),
+                    role,
+                    Transform::from_translation(spawn_position),
                 ))
                 .id();
             // Assuming the Agent component has an `id` field that you want to update
@@ -102,13 +91,166 @@ pub fn spawn_agent(
     state.set(GameState::Playing);
 }
 
+/// Spawns each faction's starting fleets, each patrolling the systems its faction holds around where it starts.
+pub fn spawn_fleets(
+    mut commands: Commands,
+    query: Query<(&SolarSystem, &Transform)>,
+    factions: Res<FactionResourse>,
+    system_graph: Res<SystemGraph>,
+    mut seed: ResMut<GalaxySeed>,
+) {
+    let rng = seed.rng();
+    let mut fleet_id = 0;
+
+    for faction in factions.factions.iter() {
+        let owner = faction.faction_attributes.id;
+        let owned: Vec<_> = query
+            .iter()
+            .filter(|(system, _)| system.attributes.owner == owner)
+            .collect();
+
+        for number in 0..FLEETS_PER_FACTION {
+            let Some((home, position)) = owned.choose(rng) else {
+                break;
+            };
+
+            // Patrol the nearest systems the faction holds, starting from home
+            let jumps = system_graph.jumps_from(home);
+            let mut route: Vec<(usize, u32)> = owned
+                .iter()
+                .filter_map(|(system, _)| {
+                    jumps
+                        .get(&system.attributes.id)
+                        .map(|jumps| (*jumps, system.attributes.id))
+                })
+                .collect();
+            route.sort();
+            let route = route
+                .into_iter()
+                .take(PATROL_ROUTE_LENGTH)
+                .map(|(_, system_id)| system_id)
+                .collect();
+
+            let fleet_entity = commands.spawn_empty().id();
+            let mut fleet = Fleet::new(
+                fleet_id,
+                format!("{} Fleet {}", faction.faction_attributes.name, number + 1),
+                owner,
+            );
+            fleet.set_order(FleetOrder::Patrol { route, next: 0 });
+            fleet_id += 1;
+
+            for _ in 0..STARTING_FLEET_SIZE {
+                let mut spawn_position =
+                    random_position_in_system(rng, Vec2::splat(512.0), position.translation);
+                spawn_position.z = 0.1;
+                let agent = commands
+                    .spawn(agent_bundle(
+                        Agent::new(0, agent_name(rng), home),
+                        AgentRole::Defender,
+                        Transform::from_translation(spawn_position),
+                    ))
+                    .insert(FleetMember(fleet_entity))
+                    .id();
+                fleet.add_member(agent);
+            }
+
+            let name = Name::new(fleet.name.clone());
+            commands.entity(fleet_entity).insert((fleet, name));
+        }
+    }
+}
+
+/// The components every agent is spawned with, including the thinker that drives its behaviour.
+///
+/// The agent's role decides whether its thinker trades or mines. Every agent follows its fleet above all else while it is in one.
+pub(crate) fn agent_bundle(agent: Agent, role: AgentRole, transform: Transform) -> impl Bundle {
+    let thinker = Thinker::build()
+        .label("WandererThinker")
+        .picker(Highest {})
+        .when(WantToFollowFleet, FollowFleet)
+        .when(WantToWander, Idle { target: None }) // Always wander as we have set the score high.
+        .when(
+            WantToFlyToSystem,
+            FlyToSystem {
+                target: None,
+                desire: 0.0,
+            },
+        );
+
+    let thinker = match role {
+        AgentRole::Trader => {
+            let find_and_execute_trade = Steps::build()
+                .label("FindAndExecuteTrade")
+                .step(FindTrade)
+                .step(FlyToStation)
+                .step(DockAtStation)
+                .step(BuyGoods::default())
+                // ...haul the goods to the buyer...
+                .step(FlyToStation)
+                .step(DockAtStation)
+                .step(SellGoods::default());
+            thinker.when(WantToTrade, find_and_execute_trade)
+        }
+        AgentRole::Miner => {
+            let mine_and_sell = Steps::build()
+                .label("MineAndSell")
+                .step(FindMiningSite)
+                .step(FlyToMiningSite)
+                .step(MineResources::default())
+                // ...then sell the haul like a trader selling goods it already holds
+                .step(FindTrade)
+                .step(FlyToStation)
+                .step(DockAtStation)
+                .step(SellGoods::default());
+            thinker.when(WantToMine, mine_and_sell)
+        }
+        // Defenders only wander while they wait for a fleet
+        AgentRole::Defender => thinker,
+        AgentRole::Pirate => {
+            let raid = Steps::build()
+                .label("Raid")
+                .step(ScoutChokepoint::default())
+                .step(InterceptTrader::default());
+            thinker.when(WantToFlee, Flee).when(WantToRaid, raid)
+        }
+    };
+
+    (
+        TransformBundle::from_transform(transform),
+        agent,
+        role,
+        CargoHold::new(AGENT_SOLID_CARGO, AGENT_FLUID_CARGO),
+        Weapon::for_role(role),
+        Shield::for_role(role),
+        Idle::new(),
+        FlyToSystem {
+            target: None,
+            desire: 1.0,
+        },
+        thinker,
+        Name::new("Agent"),
+    )
+}
+
+/// Picks a name for an agent, drawn from the given generator so the same seed always names agents the same.
+pub(crate) fn agent_name(rng: &mut impl Rng) -> String {
+    let first = FIRST_NAMES.choose(rng).copied().unwrap_or_default();
+    let last = LAST_NAMES.choose(rng).copied().unwrap_or_default();
+    format!("{} {}", first, last)
+}
+
 /// Returns a random position in the system.
-pub fn random_position_in_system(hex_size: Vec2, system_position: Vec3) -> Vec3 {
+pub fn random_position_in_system(
+    rng: &mut impl Rng,
+    hex_size: Vec2,
+    system_position: Vec3,
+) -> Vec3 {
     let buffer = hex_size.x * 0.5; // Using 1/4 of the hex size as buffer
-    let random_x = rand::thread_rng().gen_range(
+    let random_x = rng.gen_range(
         (system_position.x - hex_size.x + buffer)..(system_position.x + hex_size.x - buffer),
     );
-    let random_y = rand::thread_rng().gen_range(
+    let random_y = rng.gen_range(
         (system_position.y - hex_size.y + buffer)..(system_position.y + hex_size.y - buffer),
     );
     Vec3::new(random_x, random_y, system_position.z)
@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{
+    agent::agent::{Agent, AgentRole},
+    faction::{
+        attributes::FactionID,
+        claims::measure_presence,
+        diplomoacy::Diplomacy,
+        fleets::FleetMember,
+        pirates::{pirate_faction, PIRATES},
+    },
+    solar_system::SolarSystem,
+    structures::station::Station,
+};
+
+use super::galaxy_seed::GalaxySeed;
+use super::npc_generation::{agent_bundle, agent_name, random_position_in_system};
+
+/// The number of systems the pirates set up hideouts in
+const PIRATE_HIDEOUTS: usize = 3;
+/// The number of pirates spawned at each hideout
+const PIRATES_PER_HIDEOUT: u32 = 4;
+
+/// Spawns the pirate faction and outlaws it, then spawns its ships in the systems their owners have the least hold on.
+///
+/// An owner's hold on a system is the presence its stations and ships give it there.
+pub fn spawn_pirates(
+    mut commands: Commands,
+    mut diplomacy: ResMut<Diplomacy>,
+    mut seed: ResMut<GalaxySeed>,
+    systems: Query<(&SolarSystem, &Transform)>,
+    stations: Query<&Station>,
+    agents: Query<(&Agent, Option<&AgentRole>, Option<&FleetMember>)>,
+) {
+    commands.spawn(pirate_faction());
+    diplomacy.declare_outlaw(PIRATES);
+
+    let owners: HashMap<u32, FactionID> = systems
+        .iter()
+        .map(|(system, _)| (system.attributes.id, system.attributes.owner))
+        .collect();
+    let presence = measure_presence(&owners, stations.iter(), std::iter::empty(), agents.iter());
+
+    let mut hideouts: Vec<(f32, &SolarSystem, &Transform)> = systems
+        .iter()
+        .map(|(system, transform)| {
+            let hold = presence
+                .get(&system.attributes.id)
+                .and_then(|presence| presence.get(&system.attributes.owner))
+                .copied()
+                .unwrap_or_default();
+            (hold, system, transform)
+        })
+        .collect();
+    // Ties are broken by id so the same seed always picks the same hideouts
+    hideouts.sort_by(|a, b| {
+        a.0.total_cmp(&b.0)
+            .then(a.1.attributes.id.cmp(&b.1.attributes.id))
+    });
+
+    let rng = seed.rng();
+    for (_, system, transform) in hideouts.into_iter().take(PIRATE_HIDEOUTS) {
+        info!(
+            "Pirates have set up a hideout in {}",
+            system.attributes.name
+        );
+        for _ in 0..PIRATES_PER_HIDEOUT {
+            let mut agent = Agent::new(0, agent_name(rng), system);
+            agent.faction = PIRATES;
+            let mut spawn_position =
+                random_position_in_system(rng, Vec2::splat(512.0), transform.translation);
+            spawn_position.z = 0.1;
+            commands.spawn(agent_bundle(
+                agent,
+                AgentRole::Pirate,
+                Transform::from_translation(spawn_position),
+            ));
+        }
+    }
+}
@@ -3,30 +3,48 @@ use std::collections::HashSet;
 
 use bevy::math::vec2;
 use bevy::prelude::*;
-use bevy::render::render_asset::RenderAssetUsages;
-use bevy::render::{mesh::Indices, render_resource::PrimitiveTopology};
-use bevy::utils::uuid;
 use hexx::*;
 use rand::Rng; // Bring the trait into scope
 
 use crate::faction::attributes::FactionID;
-use crate::player_interactions::selection::UpdateSelectedItemEvent;
+use crate::items::ItemRegistry;
 use crate::solar_system::attributes::SystemAttributes;
+use crate::solar_system::resources::{ResourceField, ResourceFields, ResourceKind};
 use crate::solar_system::EntityList;
 use crate::solar_system::SolarSystem;
 use crate::structures::services::dock::Dock;
+use crate::structures::services::factory::Factory;
 use crate::structures::services::market::Market;
+use crate::structures::services::mining::Mining;
 use crate::structures::services::solar_generator::SolarGenerator;
 use crate::structures::services::StationServices;
 use crate::structures::stargate::Stargate;
 use crate::structures::station::Station;
 
-use bevy_mod_picking::prelude::*;
+use super::galaxy_seed::GalaxySeed;
 
 /// World size of the hexagons (outer radius)
 const HEX_SIZE: f32 = 512.0;
 /// The radius of the map.
 const MAP_RADIUS: i32 = 10;
+/// The average number of stargates in each system
+const AVERAGE_GATE_DEGREE: f32 = 2.5;
+/// The longest a stargate added to make a loop may be, a little over two hexes
+const MAX_GATE_LENGTH: f32 = HEX_SIZE * 4.0;
+/// The number of stargates that are the only way between two parts of the galaxy
+const CHOKEPOINTS: usize = 2;
+/// The chance of a station being built with a factory
+const FACTORY_CHANCE: f64 = 0.3;
+/// The chance of a station being built with a mining rig, if its system has resource fields
+const MINING_CHANCE: f64 = 0.5;
+/// The chance of a system having an asteroid belt
+const ORE_FIELD_CHANCE: f64 = 0.6;
+/// The chance of a system having a gas cloud
+const GAS_FIELD_CHANCE: f64 = 0.3;
+/// The range of units a new resource field holds
+const FIELD_YIELD: std::ops::Range<f32> = 2000.0..10000.0;
+/// The range of units a resource field regains each second
+const FIELD_REGENERATION: std::ops::Range<f32> = 0.5..3.0;
 
 /// The map resource.
 #[derive(Debug, Resource)]
@@ -44,19 +62,39 @@ pub struct GalaxyConfig {
     pub hex_size: f32,
     /// The radius of the map.
     pub map_radius: i32,
+    /// The seed used for world generation, a random seed is picked when `None`.
+    pub seed: Option<u64>,
+    /// The average number of stargates in each system, never fewer than it takes to connect them all.
+    pub average_gate_degree: f32,
+    /// The longest a stargate may be, unless it is needed to connect the galaxy.
+    pub max_gate_length: f32,
+    /// The number of stargates that are the only way between two parts of the galaxy.
+    pub chokepoints: usize,
     proximity_threshold: i32,
     clump_centers: Vec<Hex>,
 }
 
-impl GalaxyConfig {
-    pub fn default() -> Self {
+impl Default for GalaxyConfig {
+    fn default() -> Self {
         GalaxyConfig {
             hex_size: HEX_SIZE,
             map_radius: MAP_RADIUS,
+            seed: None,
+            average_gate_degree: AVERAGE_GATE_DEGREE,
+            max_gate_length: MAX_GATE_LENGTH,
+            chokepoints: CHOKEPOINTS,
             proximity_threshold: 3,
             clump_centers: vec![Hex::new(0, 0)],
         }
     }
+}
+
+impl GalaxyConfig {
+    /// Set the seed used for world generation
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
 
     // A method to determine spawn chance based on hex proximity to clump centers
     fn spawn_chance_for_hex(&self, hex: Hex) -> f64 {
@@ -75,20 +113,40 @@ impl GalaxyConfig {
     }
 }
 
+/// Rolls the asteroid belts and gas clouds for a new solar system.
+fn generate_resource_fields(rng: &mut impl Rng, hex_size: f32) -> ResourceFields {
+    let mut fields = Vec::new();
+    for (kind, chance) in [
+        (ResourceKind::Ore, ORE_FIELD_CHANCE),
+        (ResourceKind::Gas, GAS_FIELD_CHANCE),
+    ] {
+        if rng.gen_bool(chance) {
+            // Keep fields clear of the station at the centre of the system
+            let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+            let distance = rng.gen_range(hex_size * 0.2..hex_size * 0.5);
+            fields.push(ResourceField::new(
+                kind,
+                Vec2::new(angle.cos(), angle.sin()) * distance,
+                rng.gen_range(FIELD_YIELD),
+                rng.gen_range(FIELD_REGENERATION),
+            ));
+        }
+    }
+    ResourceFields(fields)
+}
+
 /// Creates all the solar systems in the galaxy.
 pub fn create_galaxy_solar_systems(
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
     config: Res<GalaxyConfig>, // Use GalaxyConfig as a resource
+    mut seed: ResMut<GalaxySeed>,
 ) {
     let layout = HexLayout {
         hex_size: vec2(config.hex_size, config.hex_size),
         ..default()
     };
 
-    let mesh_handle = meshes.add(hexagonal_plane(&layout));
-    let mut rng = rand::thread_rng();
+    let rng = seed.rng();
 
     // Use the configuration to adjust galaxy generation logic
     let entities = Hex::ZERO
@@ -98,11 +156,14 @@ pub fn create_galaxy_solar_systems(
             let spawn_chance = config.spawn_chance_for_hex(hex);
 
             if rng.gen_bool(spawn_chance) {
+                let id = rng.gen();
+                let fields = generate_resource_fields(rng, config.hex_size);
                 Some(spawn_solar_system_entity(
                     &mut commands,
-                    &mesh_handle,
+                    id,
                     pos,
                     hex,
+                    fields,
                 ))
             } else {
                 None
@@ -114,54 +175,53 @@ pub fn create_galaxy_solar_systems(
 }
 
 /// Function to encapsulate solar system entity spawning logic.
+///
+/// Only the simulation components are spawned here, the mesh and label are attached by the graphics layer.
 fn spawn_solar_system_entity(
     commands: &mut Commands,
-    mesh_handle: &Handle<Mesh>,
+    id: u32,
     pos: Vec2,
     hex: Hex,
+    fields: ResourceFields,
 ) -> (Hex, Entity) {
     let entity_id = commands
         .spawn((
             SolarSystem {
                 attributes: SystemAttributes {
-                    id: uuid::Uuid::new_v4().as_u128() as u32,
+                    id,
                     name: "Placeholder".to_string(),
                     owner: FactionID { id: 0 },
                 },
                 entities: EntityList::default(),
             },
-            PickableBundle::default(),
-            On::<Pointer<Down>>::send_event::<UpdateSelectedItemEvent>(),
-            ColorMesh2dBundle {
-                transform: Transform::from_xyz(pos.x, pos.y, -1.0).with_scale(Vec3::splat(0.9)),
-                mesh: mesh_handle.clone().into(),
-                ..default()
-            },
+            TransformBundle::from_transform(
+                Transform::from_xyz(pos.x, pos.y, -1.0).with_scale(Vec3::splat(0.9)),
+            ),
+            fields,
             Name::new(format!("System - {},{}", hex.x, hex.y)),
         ))
-        .with_children(|parent| {
-            parent.spawn(Text2dBundle {
-                text: Text::from_section(
-                    format!("{},{}", hex.x, hex.y),
-                    TextStyle {
-                        font_size: 64.0,
-                        color: Color::BLACK,
-                        ..Default::default()
-                    },
-                ),
-                transform: Transform::from_xyz(0.0, 390.0, 10.0),
-                ..Default::default()
-            });
-        })
         .id();
     (hex, entity_id)
 }
+/// Spawns a space station with the default services in every solar system.
 pub fn spawn_space_station(
     mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    solar_systems: Query<(&Transform, &SolarSystem)>,
+    solar_systems: Query<(&Transform, &SolarSystem, Option<&ResourceFields>)>,
+    items: Res<ItemRegistry>,
+    mut seed: ResMut<GalaxySeed>,
 ) {
-    for (system_transform, solar_system) in solar_systems.iter() {
+    let rng = seed.rng();
+    // Sorted so the same seed always builds the same factories
+    let mut recipes: Vec<_> = items.recipes().collect();
+    recipes.sort_by(|a, b| a.key.cmp(&b.key));
+    // Numbered in the order they are built so the same seed always gives the same IDs
+    let mut next_service_id = 0;
+    let mut service_id = || {
+        next_service_id += 1;
+        next_service_id - 1
+    };
+
+    for (system_transform, solar_system, fields) in solar_systems.iter() {
         let system_attributes = &solar_system.attributes;
         let mut station = Station::new(
             system_attributes.id as u32,
@@ -169,63 +229,65 @@ pub fn spawn_space_station(
             system_attributes.id,
         );
 
+        let market = Market::new(service_id(), &items);
+        // Stock each station unevenly so prices differ from system to system,
+        // sorted so the same seed always draws the same quantities
+        let mut listings: Vec<_> = market.listings().collect();
+        listings.sort_by_key(|(item, _)| *item);
+        for (item, listing) in listings {
+            if let Some(definition) = items.get(item) {
+                let quantity = rng.gen_range(0..=listing.target_stock * 2);
+                let _ = station.resource_manager.inventory.add(definition, quantity);
+            }
+        }
         station
-            .add_service(StationServices::Market(Market::new()))
+            .add_service(StationServices::Market(market))
             .unwrap();
         station
             .add_service(StationServices::Dock(Dock::new(
+                service_id(),
                 String::from("Docking Bay 1"),
                 20,
             )))
             .unwrap();
         station
             .add_service(StationServices::SolarGenerator(SolarGenerator::new(
+                service_id(),
                 String::from("Solar Generator 1"),
             )))
             .unwrap();
+        let kinds: Vec<ResourceKind> = fields
+            .map(|fields| fields.0.iter().map(|field| field.kind).collect())
+            .unwrap_or_default();
+        if !kinds.is_empty() && rng.gen_bool(MINING_CHANCE) {
+            let kind = kinds[rng.gen_range(0..kinds.len())];
+            station
+                .add_service(StationServices::Mining(Mining::new(
+                    service_id(),
+                    format!("{:?} Mining Rig", kind),
+                    kind,
+                )))
+                .unwrap();
+        }
+        if !recipes.is_empty() && rng.gen_bool(FACTORY_CHANCE) {
+            let recipe = recipes[rng.gen_range(0..recipes.len())];
+            station
+                .add_service(StationServices::Factory(Factory::new(
+                    service_id(),
+                    format!("{} Factory", recipe.key),
+                    recipe,
+                )))
+                .unwrap();
+        }
 
         commands.spawn((
             station,
-            PickableBundle::default(),
-            On::<Pointer<Down>>::send_event::<UpdateSelectedItemEvent>(),
-            SpriteBundle {
-                texture: asset_server.load("sprites/icons/structures/station_1.png"),
-                transform: Transform {
-                    translation: Vec3::new(
-                        system_transform.translation.x,
-                        system_transform.translation.y,
-                        system_transform.translation.z.max(1.0),
-                    ),
-                    ..Default::default()
-                },
-                ..Default::default()
-            },
+            TransformBundle::from_transform(Transform::from_xyz(
+                system_transform.translation.x,
+                system_transform.translation.y,
+                system_transform.translation.z.max(1.0),
+            )),
             Name::new(format!("Station {}", system_attributes.id)),
         ));
     }
 }
-/// Returns a mesh for a hexagonal plane.
-fn hexagonal_plane(hex_layout: &HexLayout) -> Mesh {
-    let mesh_info = PlaneMeshBuilder::new(hex_layout).facing(Vec3::Z).build();
-    let mut mesh = Mesh::new(
-        PrimitiveTopology::TriangleList,
-        RenderAssetUsages::RENDER_WORLD,
-    );
-    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, mesh_info.vertices);
-    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, mesh_info.normals);
-    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, mesh_info.uvs);
-    mesh.insert_indices(Indices::U16(mesh_info.indices));
-    mesh
-}
-
-/// Returns a random position within a system when provided with the system's position and size.
-fn random_stargate_position(hex_size: Vec2, system_position: Vec3) -> Vec3 {
-    let buffer = hex_size.x * 0.5; // Using 1/4 of the hex size as buffer
-    let random_x = rand::thread_rng().gen_range(
-        (system_position.x - hex_size.x + buffer)..(system_position.x + hex_size.x - buffer),
-    );
-    let random_y = rand::thread_rng().gen_range(
-        (system_position.y - hex_size.y + buffer)..(system_position.y + hex_size.y - buffer),
-    );
-    Vec3::new(random_x, random_y, system_position.z) // Keeping the z-coordinate the same
-}
@@ -1,100 +1,209 @@
-use std::collections::HashSet;
-
 use bevy::prelude::*;
-use bevy::utils::uuid;
+use petgraph::unionfind::UnionFind;
 use rand::Rng; // Bring the trait into scope
 
+use crate::agent::pathfinding::SystemGraph;
 use crate::solar_system::SolarSystem;
 use crate::structures::stargate::Stargate;
 
-use bevy_mod_picking::prelude::*;
-
+use super::galaxy_seed::GalaxySeed;
 use super::solar_system_generation::GalaxyConfig;
 
+/// A summary of the stargate network world generation built, to check every system can be reached
+#[derive(Resource, Debug, Clone, Default, PartialEq)]
+pub struct GateNetworkReport {
+    /// The number of solar systems
+    pub systems: usize,
+    /// The number of stargate pairs
+    pub gates: usize,
+    /// The number of systems in each group that can reach one another, largest first
+    pub components: Vec<usize>,
+    /// The distance covered by the longest jump
+    pub longest_gate: u32,
+}
+
+impl GateNetworkReport {
+    /// Check if every system can reach every other
+    pub fn is_connected(&self) -> bool {
+        self.components.len() <= 1
+    }
+
+    /// The average number of stargates in each system
+    pub fn average_degree(&self) -> f32 {
+        if self.systems == 0 {
+            return 0.0;
+        }
+        (self.gates * 2) as f32 / self.systems as f32
+    }
+}
+
 /// Spawns stargates between solar systems.
+///
+/// The systems are first joined by the shortest gates that connect them all, so every system can reach every other.
+/// Loops are then made with the shortest gates left, up to the average degree and gate length the `GalaxyConfig` allows.
+/// No loop crosses a chokepoint, which stays the only way between the two parts of the galaxy either side of it.
+/// Each gate is only logged at debug level, [`report_gate_network`] sums up the finished network.
 pub fn spawn_stargates(
     mut commands: Commands,
-    asset_server: Res<AssetServer>,
     solar_systems: Query<(Entity, &Transform, &SolarSystem)>,
     config: Res<GalaxyConfig>,
+    mut seed: ResMut<GalaxySeed>,
 ) {
-    println!("Spawning stargates");
-
-    let all_systems = collect_all_solar_systems(&solar_systems);
-    let mut established_connections: HashSet<(Entity, Entity)> = HashSet::new();
-
-
-    for (system_entity, system_transform, solar_system) in solar_systems.iter() {
-        println!("Spawning stargates for system: {:?}", solar_system.attributes.name);
-        let num_stargates: usize = rand::thread_rng().gen_range(0..=2);
-        for _ in 0..num_stargates {
-            if let Some(destination_system) = select_destination_system(
-                system_entity,
-                &all_systems,
-                &mut established_connections,
-                system_transform,
-            ) {
-                spawn_stargate_pair(
-                    &mut commands,
-                    &asset_server,
-                    (system_entity, system_transform, &solar_system),
-                    destination_system,
-                    &solar_systems,
-                    &config,
-                );
-            }
+    let rng = seed.rng();
+
+    // Sorted by id so the same seed always builds the same network
+    let mut systems: Vec<(Entity, &Transform, &SolarSystem)> = solar_systems.iter().collect();
+    systems.sort_by_key(|(_, _, system)| system.attributes.id);
+    let positions: Vec<Vec2> = systems
+        .iter()
+        .map(|(_, transform, _)| transform.translation.truncate())
+        .collect();
+
+    for (origin, destination) in plan_gate_network(&positions, &config) {
+        debug!(
+            "Spawning stargate between {:?} and {:?}",
+            systems[origin].2.attributes.name, systems[destination].2.attributes.name
+        );
+        spawn_stargate_pair(
+            &mut commands,
+            rng,
+            systems[origin],
+            systems[destination].0,
+            &solar_systems,
+            &config,
+        );
+    }
+}
+
+/// Picks the pairs of systems to join with stargates, by their index in `positions`.
+fn plan_gate_network(positions: &[Vec2], config: &GalaxyConfig) -> Vec<(usize, usize)> {
+    let count = positions.len();
+
+    // Every pair of systems, shortest first
+    let mut pairs: Vec<(f32, usize, usize)> = (0..count)
+        .flat_map(|a| (a + 1..count).map(move |b| (positions[a].distance(positions[b]), a, b)))
+        .collect();
+    pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    // The shortest gates that connect every system
+    let mut connected = UnionFind::new(count);
+    let mut tree = Vec::new();
+    let mut loops = Vec::new();
+    for (length, a, b) in pairs {
+        if connected.union(a, b) {
+            tree.push((a, b));
+        } else if length <= config.max_gate_length {
+            loops.push((a, b));
         }
     }
+
+    // Loops may only join systems on the same side of every chokepoint
+    let chokepoints = pick_chokepoints(count, &tree, config.chokepoints);
+    let mut regions = UnionFind::new(count);
+    for (index, (a, b)) in tree.iter().enumerate() {
+        if !chokepoints.contains(&index) {
+            regions.union(*a, *b);
+        }
+    }
+
+    let wanted = (config.average_gate_degree * count as f32 / 2.0).round() as usize;
+    let extra = wanted.saturating_sub(tree.len());
+    let loops = loops
+        .into_iter()
+        .filter(|(a, b)| regions.equiv(*a, *b))
+        .take(extra);
+
+    tree.into_iter().chain(loops).collect()
 }
 
-/// Collect all solar systems into a Vec for potential destinations.
-fn collect_all_solar_systems(
-    solar_systems: &Query<(Entity, &Transform, &SolarSystem)>,
-) -> Vec<(Entity, Vec3)> {
-    solar_systems
-        .iter()
-        .map(|(entity, transform, _)| (entity, transform.translation))
+/// Picks the gates of a tree that split it most evenly, returning their indices.
+///
+/// A gate to a lone system at the edge of the galaxy is never a chokepoint, as nothing passes through it.
+fn pick_chokepoints(count: usize, tree: &[(usize, usize)], chokepoints: usize) -> Vec<usize> {
+    if count == 0 {
+        return Vec::new();
+    }
+    let mut neighbours: Vec<Vec<(usize, usize)>> = vec![Vec::new(); count];
+    for (index, (a, b)) in tree.iter().enumerate() {
+        neighbours[*a].push((*b, index));
+        neighbours[*b].push((*a, index));
+    }
+
+    // Walk the tree from the first system, so each gate leads down to the systems beyond it
+    let mut order = vec![(0, None)];
+    let mut visited = vec![false; count];
+    visited[0] = true;
+    let mut next = 0;
+    while next < order.len() {
+        let (system, _) = order[next];
+        for (neighbour, gate) in &neighbours[system] {
+            if !visited[*neighbour] {
+                visited[*neighbour] = true;
+                order.push((*neighbour, Some((*gate, system))));
+            }
+        }
+        next += 1;
+    }
+
+    // The number of systems beyond each gate, counted from the far end of the walk back
+    let mut beyond = vec![1; count];
+    let mut splits = Vec::new();
+    for (system, via) in order.iter().rev() {
+        if let Some((gate, parent)) = via {
+            beyond[*parent] += beyond[*system];
+            splits.push((beyond[*system].min(count - beyond[*system]), *gate));
+        }
+    }
+
+    splits.sort_by(|a, b| b.cmp(a));
+    splits
+        .into_iter()
+        .filter(|(smaller_side, _)| *smaller_side > 1)
+        .take(chokepoints)
+        .map(|(_, gate)| gate)
         .collect()
 }
 
-/// Select a destination system for the stargate.
-fn select_destination_system(
-    system_entity: Entity,
-    all_systems: &[(Entity, Vec3)],
-    established_connections: &mut HashSet<(Entity, Entity)>,
-    system_transform: &Transform,
-) -> Option<Entity> {
-    let mut rng = rand::thread_rng();
-    let mut possible_destinations: Vec<(Entity, Vec3)> = all_systems
+/// Summarises the stargate network in the `SystemGraph` into a [`GateNetworkReport`], warning if any system is cut off.
+pub fn report_gate_network(mut commands: Commands, system_graph: Res<SystemGraph>) {
+    let mut components: Vec<usize> = system_graph
+        .components()
         .iter()
-        .cloned()
-        .filter(|&(entity, _)| {
-            entity != system_entity
-                && !established_connections.contains(&(system_entity, entity))
-                && !established_connections.contains(&(entity, system_entity))
-        })
+        .map(|component| component.len())
         .collect();
+    components.sort_by(|a, b| b.cmp(a));
+    let report = GateNetworkReport {
+        systems: components.iter().sum(),
+        gates: system_graph.gates().count() / 2,
+        components,
+        longest_gate: system_graph
+            .gates()
+            .map(|gate| gate.distance)
+            .max()
+            .unwrap_or_default(),
+    };
 
-    if possible_destinations.is_empty() {
-        return None;
+    if report.is_connected() {
+        info!(
+            "Stargate network joins {} systems with {} gates, {:.1} per system",
+            report.systems,
+            report.gates,
+            report.average_degree()
+        );
+    } else {
+        warn!(
+            "Stargate network is split into {} parts of {:?} systems",
+            report.components.len(),
+            report.components
+        );
     }
-
-    possible_destinations.sort_by(|&(_, a), &(_, b)| {
-        a.distance_squared(system_transform.translation)
-            .partial_cmp(&b.distance_squared(system_transform.translation))
-            .unwrap()
-    });
-
-    let destination_system = possible_destinations[0].0;
-    established_connections.insert((system_entity, destination_system));
-
-    Some(destination_system)
+    commands.insert_resource(report);
 }
 
 /// Spawn a pair of stargates: one in the origin system and another in the destination system.
 fn spawn_stargate_pair(
     commands: &mut Commands,
-    asset_server: &Res<AssetServer>,
+    rng: &mut impl Rng,
     origin_data: (Entity, &Transform, &SolarSystem),
     destination_system: Entity,
     solar_systems: &Query<(Entity, &Transform, &SolarSystem)>,
@@ -107,51 +216,56 @@ fn spawn_stargate_pair(
 
     // Generate properties for origin and destination stargates
     let (origin_stargate, destination_stargate) = generate_stargate_properties(
+        rng,
         origin_solar_system.attributes.id,
         destination_solar_system.attributes.id,
+        jump_distance(origin_system_transform, destination_system_transform),
     );
 
     let origin_relative_stargate_position =
-        get_relative_stargate_position(origin_system_transform, config);
+        get_relative_stargate_position(rng, origin_system_transform, config);
 
     // Spawn origin stargate
-    let origin_stargate_entity = spawn_stargate(
+    spawn_stargate(
         commands,
-        asset_server,
         &origin_relative_stargate_position,
         &origin_stargate,
     );
 
     let destination_relative_stargate_position =
-        get_relative_stargate_position(destination_system_transform, config);
+        get_relative_stargate_position(rng, destination_system_transform, config);
 
     // Spawn destination stargate
     spawn_stargate(
         commands,
-        asset_server,
         &destination_relative_stargate_position,
         &destination_stargate,
     );
+}
 
-    // Optionally, draw a line between the stargates for visual representation
-    create_line_between_stargates(
-        commands,
-        asset_server,
-        &origin_relative_stargate_position,
-        &destination_relative_stargate_position,
-        origin_stargate_entity,
-    );
+/// The distance a jump between two systems covers, rounded up so no route is shorter than the straight line between its ends.
+fn jump_distance(
+    origin_system_transform: &Transform,
+    destination_system_transform: &Transform,
+) -> u32 {
+    origin_system_transform
+        .translation
+        .truncate()
+        .distance(destination_system_transform.translation.truncate())
+        .ceil() as u32
 }
 
 /// Generate properties for a stargate.
 fn generate_stargate_properties(
+    rng: &mut impl Rng,
     origin_system_id: u32,
     destination_system_id: u32,
+    distance: u32,
 ) -> (Stargate, Stargate) {
     let mut origin_stargate = Stargate {
-        id: uuid::Uuid::new_v4().as_u128() as u32,
+        id: rng.gen(),
         name: "placeholder".to_string(), // "Stargate 1"
-        distance: 100,
+        distance,
         destination_gate_id: 0,
         origin_system_id: origin_system_id,
         destination_system_id: destination_system_id,
@@ -159,9 +273,9 @@ fn generate_stargate_properties(
     };
 
     let mut destination_stargate = Stargate {
-        id: uuid::Uuid::new_v4().as_u128() as u32,
+        id: rng.gen(),
         name: "placeholder".to_string(), // "Stargate 2"
-        distance: 100,
+        distance,
         destination_gate_id: origin_stargate.id,
         origin_system_id: destination_system_id,
         destination_system_id: origin_system_id,
@@ -176,32 +290,21 @@ fn generate_stargate_properties(
 }
 
 /// Spawns a stargate entity with given properties and transform.
-fn spawn_stargate(
-    commands: &mut Commands,
-    asset_server: &Res<AssetServer>,
-    system_transform: &Transform,
-    stargate: &Stargate,
-) -> Entity {
-    let texture_handle = asset_server.load("sprites/icons/systemGate.png");
-
-    commands
-        .spawn(SpriteBundle {
-            texture: texture_handle,
-            transform: *system_transform,
-            ..Default::default()
-        })
-        .insert(stargate.clone()) // Assuming Stargate is cloneable. Otherwise, create a new instance.
-        .insert(PickableBundle::default()) // Optional, for interactivity.
-        .insert(Name::new(format!("Stargate {}", stargate.id))) // Optional, for debugging.
-        .id()
+fn spawn_stargate(commands: &mut Commands, system_transform: &Transform, stargate: &Stargate) {
+    commands.spawn((
+        stargate.clone(),
+        TransformBundle::from_transform(*system_transform),
+        Name::new(format!("Stargate {}", stargate.id)), // Optional, for debugging.
+    ));
 }
 
 fn get_relative_stargate_position(
+    rng: &mut impl Rng,
     system_transform: &Transform,
     config: &Res<GalaxyConfig>,
 ) -> Transform {
     let relative_stargate_position =
-        random_stargate_position(Vec2::splat(config.hex_size), Vec3::ZERO);
+        random_stargate_position(rng, Vec2::splat(config.hex_size), Vec3::ZERO);
 
     let transform = Transform::from_xyz(
         system_transform.translation.x + relative_stargate_position.x,
@@ -212,64 +315,13 @@ fn get_relative_stargate_position(
     transform
 }
 
-/// Creates a visual line between two stargates.
-fn create_line_between_stargates(
-    commands: &mut Commands,
-    asset_server: &Res<AssetServer>,
-    origin_transform: &Transform,
-    destination_transform: &Transform,
-    parent_entity: Entity,
-) {
-    // Draw a line between the origin stargate and the destination stargate
-    let midpoint = (origin_transform.translation + destination_transform.translation) * 0.5;
-    let distance = origin_transform
-        .translation
-        .distance(destination_transform.translation);
-    let angle = (destination_transform.translation.y - origin_transform.translation.y)
-        .atan2(destination_transform.translation.x - origin_transform.translation.x);
-
-    let _parent_global_transform = origin_transform; // Since origin_transform already appears to be global.
-    let child_intended_global_transform = Transform {
-        translation: midpoint,
-        rotation: Quat::from_rotation_z(angle),
-        scale: Vec3::new(distance, 1.0, 1.0),
-    };
-    // Inverse rotation
-    let inverse_rotation = origin_transform.rotation.conjugate();
-
-    // Inverse translation rotated by the inverse rotation
-    let inverse_translation = inverse_rotation * -origin_transform.translation;
-
-    let parent_inverse_transform = Transform {
-        translation: inverse_translation,
-        rotation: inverse_rotation,
-        ..Default::default() // Assuming the scale is just 1, 1, 1
-    };
-
-    let local_transform = parent_inverse_transform * child_intended_global_transform;
-
-    commands.entity(parent_entity).with_children(|parent| {
-        parent
-            .spawn(SpriteBundle {
-                texture: asset_server.load("sprites/icons/line.png"),
-                transform: Transform {
-                    translation: local_transform.translation,
-                    rotation: Quat::from_rotation_z(angle),
-                    scale: Vec3::new(distance, 1.0, 1.0),
-                },
-                ..Default::default()
-            })
-            .insert(Name::new("Stargate Connection Line")); // Optional, for debugging.
-    });
-}
-
 /// Returns a random position within a system when provided with the system's position and size.
-fn random_stargate_position(hex_size: Vec2, system_position: Vec3) -> Vec3 {
+fn random_stargate_position(rng: &mut impl Rng, hex_size: Vec2, system_position: Vec3) -> Vec3 {
     let buffer = hex_size.x * 0.5; // Using 1/4 of the hex size as buffer
-    let random_x = rand::thread_rng().gen_range(
+    let random_x = rng.gen_range(
         (system_position.x - hex_size.x + buffer)..(system_position.x + hex_size.x - buffer),
     );
-    let random_y = rand::thread_rng().gen_range(
+    let random_y = rng.gen_range(
         (system_position.y - hex_size.y + buffer)..(system_position.y + hex_size.y - buffer),
     );
     Vec3::new(random_x, random_y, system_position.z) // Keeping the z-coordinate the same
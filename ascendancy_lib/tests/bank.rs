@@ -0,0 +1,138 @@
+use ascendancy_lib::faction::bank::{
+    Bank, BankError, LoanId, LoanOutcome, LoanTerms, TransactionKind,
+};
+
+#[test]
+fn overdrafts_are_refused() {
+    let mut bank = Bank::new(100);
+
+    assert_eq!(
+        bank.withdraw(TransactionKind::Withdrawal, 150, 0.0),
+        Err(BankError::InsufficientFunds {
+            required: 150,
+            available: 100
+        })
+    );
+    assert_eq!(bank.balance, 100);
+    assert_eq!(bank.transactions().count(), 0);
+
+    bank.withdraw(TransactionKind::Withdrawal, 100, 0.0)
+        .unwrap();
+    assert_eq!(bank.balance, 0);
+    assert_eq!(bank.total_withdrawals, 100);
+}
+
+#[test]
+fn income_and_expenses_are_queried_by_window() {
+    let mut bank = Bank::new(0);
+    bank.deposit(TransactionKind::Fee, 25, 1.0).unwrap();
+    bank.deposit(TransactionKind::Trade, 500, 5.0).unwrap();
+    bank.withdraw(TransactionKind::Trade, 200, 6.0).unwrap();
+    bank.deposit(TransactionKind::Tax, 40, 12.0).unwrap();
+
+    assert_eq!(bank.income_between(0.0, 10.0), 525);
+    assert_eq!(bank.expenses_between(0.0, 10.0), 200);
+    assert_eq!(bank.income_between(10.0, 20.0), 40);
+    assert_eq!(bank.net_between(TransactionKind::Trade, 0.0, 20.0), 300);
+    assert_eq!(bank.transactions_between(5.0, 6.0).count(), 1);
+}
+
+#[test]
+fn loans_are_repaid_with_interest_on_schedule() {
+    let mut bank = Bank::new(500);
+    let terms = LoanTerms {
+        interest_rate: 0.1,
+        repayments: 2,
+        period: 10.0,
+    };
+    let loan = bank.take_loan(1000, terms, 0.0).unwrap();
+    assert_eq!(bank.balance, 1500);
+    assert_eq!(bank.loans()[0].repayment, 577);
+
+    // Nothing is due before the first period is up
+    assert!(bank.service_loans(5.0).is_empty());
+
+    assert!(bank.service_loans(10.0).is_empty());
+    assert_eq!(bank.outstanding_debt(), 523);
+
+    assert_eq!(bank.service_loans(20.0), vec![LoanOutcome::Repaid(loan)]);
+    assert!(bank.loans().is_empty());
+    assert_eq!(bank.total_loans_repaid, 1152);
+    assert_eq!(bank.balance, 348);
+}
+
+#[test]
+fn early_repayments_close_loans() {
+    let mut bank = Bank::new(0);
+    let terms = LoanTerms {
+        interest_rate: 0.05,
+        repayments: 10,
+        period: 10.0,
+    };
+    let loan = bank.take_loan(300, terms, 0.0).unwrap();
+
+    assert_eq!(bank.repay(loan, 1000, 1.0), Ok(300));
+    assert!(bank.loans().is_empty());
+    assert_eq!(
+        bank.repay(loan, 1, 2.0),
+        Err(BankError::UnknownLoan(LoanId(0)))
+    );
+}
+
+#[test]
+fn missed_repayments_lead_to_default() {
+    let mut bank = Bank::new(0);
+    let terms = LoanTerms {
+        interest_rate: 0.0,
+        repayments: 1,
+        period: 10.0,
+    };
+    let loan = bank.take_loan(1000, terms, 0.0).unwrap();
+    bank.withdraw(TransactionKind::Withdrawal, 900, 1.0)
+        .unwrap();
+
+    // Servicing late catches up on every repayment that fell due
+    assert_eq!(
+        bank.service_loans(30.0),
+        vec![
+            LoanOutcome::Missed { loan, missed: 1 },
+            LoanOutcome::Missed { loan, missed: 2 },
+            LoanOutcome::Defaulted {
+                loan,
+                seized: 100,
+                written_off: 900
+            },
+        ]
+    );
+    assert_eq!(bank.balance, 0);
+    assert!(bank.loans().is_empty());
+    assert!(matches!(
+        bank.take_loan(100, terms, 40.0),
+        Err(BankError::CreditRefused { .. })
+    ));
+}
+
+#[test]
+fn deposits_that_would_overflow_are_refused() {
+    let mut bank = Bank::new(u32::MAX - 10);
+
+    assert_eq!(
+        bank.deposit(TransactionKind::Trade, 20, 0.0),
+        Err(BankError::Overflow {
+            amount: 20,
+            balance: u32::MAX - 10
+        })
+    );
+    let terms = LoanTerms {
+        interest_rate: 0.0,
+        repayments: 1,
+        period: 10.0,
+    };
+    assert!(bank.take_loan(20, terms, 0.0).is_err());
+    assert_eq!(bank.balance, u32::MAX - 10);
+    assert!(bank.loans().is_empty());
+    assert_eq!(bank.transactions().count(), 0);
+
+    bank.deposit(TransactionKind::Trade, 10, 1.0).unwrap();
+    assert_eq!(bank.balance, u32::MAX);
+}
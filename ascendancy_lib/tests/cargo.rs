@@ -0,0 +1,60 @@
+use ascendancy_lib::agent::cargo::{CargoError, CargoHold, FULL_HOLD_SPEED_PENALTY};
+use ascendancy_lib::items::{ItemId, ItemRegistry};
+use ascendancy_lib::structures::services::dock::Dock;
+use ascendancy_lib::structures::services::StationServices;
+use ascendancy_lib::structures::station::Station;
+use bevy::prelude::Entity;
+
+#[test]
+fn fuller_holds_fly_slower() {
+    let items = ItemRegistry::built_in();
+    let ore = items.get(ItemId::RAW_ORE).unwrap();
+    let mut hold = CargoHold::new(10.0, 0.0);
+
+    assert_eq!(hold.speed_multiplier(), 1.0);
+
+    hold.load(ore, 5).unwrap();
+    assert!((hold.load_fraction() - 0.5).abs() < 0.001);
+
+    hold.load(ore, 5).unwrap();
+    assert!((hold.speed_multiplier() - (1.0 - FULL_HOLD_SPEED_PENALTY)).abs() < 0.001);
+    assert!(hold.load(ore, 1).is_err());
+
+    hold.unload(ItemId::RAW_ORE, 10).unwrap();
+    assert!(hold.is_empty());
+}
+
+#[test]
+fn only_docked_agents_move_cargo() {
+    let items = ItemRegistry::built_in();
+    let ore = items.get(ItemId::RAW_ORE).unwrap();
+    let agent = Entity::from_raw(1);
+    let mut hold = CargoHold::new(10.0, 0.0);
+
+    let mut station = Station::new(1, "Station".to_string(), 1);
+    station
+        .add_service(StationServices::Dock(Dock::new(0, "Dock".to_string(), 1)))
+        .unwrap();
+    station.resource_manager.inventory.add(ore, 20).unwrap();
+
+    assert_eq!(
+        station.load_cargo(agent, &mut hold, ore, 5),
+        Err(CargoError::NotDocked)
+    );
+
+    station.dock_mut().unwrap().docked_ships.push(agent);
+    assert!(station.load_cargo(agent, &mut hold, ore, 20).is_err());
+    station.load_cargo(agent, &mut hold, ore, 8).unwrap();
+    assert_eq!(hold.quantity(ItemId::RAW_ORE), 8);
+    assert_eq!(
+        station.resource_manager.inventory.quantity(ItemId::RAW_ORE),
+        12
+    );
+
+    station.unload_cargo(agent, &mut hold, ore, 3).unwrap();
+    assert_eq!(hold.quantity(ItemId::RAW_ORE), 5);
+    assert_eq!(
+        station.resource_manager.inventory.quantity(ItemId::RAW_ORE),
+        15
+    );
+}
@@ -0,0 +1,186 @@
+use std::time::Duration;
+
+use ascendancy_lib::agent::agent::{Agent, AgentRole};
+use ascendancy_lib::agent::cargo::CargoHold;
+use ascendancy_lib::agent::combat::{
+    acquire_targets, clear_wrecks, resolve_combat, wreck_destroyed_ships, CombatTimer, Shield,
+    ShipDamagedEvent, ShipDestroyedEvent, ShipEngagedEvent, Target, Weapon, Wreck, COMBAT_TICK,
+};
+use ascendancy_lib::faction::attributes::FactionID;
+use ascendancy_lib::faction::diplomoacy::{Diplomacy, DiplomaticIncidentEvent, IncidentKind};
+use ascendancy_lib::items::{ItemId, ItemRegistry};
+use ascendancy_lib::simulation::SimulationClock;
+use ascendancy_lib::solar_system::attributes::SystemAttributes;
+use ascendancy_lib::solar_system::SolarSystem;
+use ascendancy_lib::structures::services::dock::Dock;
+use ascendancy_lib::structures::services::StationServices;
+use ascendancy_lib::structures::station::Station;
+use bevy::prelude::*;
+
+const EMPIRE: FactionID = FactionID { id: 0 };
+const REBELS: FactionID = FactionID { id: 1 };
+
+struct Battle {
+    app: App,
+    system: Entity,
+    station: Entity,
+    defender: Entity,
+    trader: Entity,
+}
+
+/// Sets up a rebel defender a short way from an empire trader carrying some metal
+fn battle(diplomacy: Diplomacy) -> Battle {
+    let mut app = App::new();
+    app.init_resource::<Time>()
+        .init_resource::<SimulationClock>()
+        .init_resource::<CombatTimer>()
+        .insert_resource(diplomacy)
+        .add_event::<ShipEngagedEvent>()
+        .add_event::<ShipDamagedEvent>()
+        .add_event::<ShipDestroyedEvent>()
+        .add_event::<DiplomaticIncidentEvent>()
+        .add_systems(
+            Update,
+            (
+                acquire_targets,
+                resolve_combat,
+                wreck_destroyed_ships,
+                clear_wrecks,
+            )
+                .chain(),
+        );
+
+    let mut system = SolarSystem {
+        attributes: SystemAttributes {
+            id: 1,
+            name: "Frontier".to_string(),
+            owner: EMPIRE,
+        },
+        ..default()
+    };
+
+    let mut raider = Agent::new(0, "Raider".to_string(), &system);
+    raider.faction = REBELS;
+    let defender = app
+        .world
+        .spawn((
+            raider,
+            AgentRole::Defender,
+            Weapon::for_role(AgentRole::Defender),
+            Shield::for_role(AgentRole::Defender),
+            Transform::from_xyz(30.0, 0.0, 0.0),
+        ))
+        .id();
+
+    let items = ItemRegistry::built_in();
+    let mut hold = CargoHold::new(100.0, 100.0);
+    hold.load(items.get(ItemId::REFINED_METAL).unwrap(), 10)
+        .unwrap();
+    let trader = app
+        .world
+        .spawn((
+            Agent::new(1, "Hauler".to_string(), &system),
+            AgentRole::Trader,
+            hold,
+            Weapon::for_role(AgentRole::Trader),
+            Shield::for_role(AgentRole::Trader),
+            Transform::default(),
+        ))
+        .id();
+
+    // The trader is waiting for a berth when the raider arrives
+    let mut station = Station::new(0, "Capital".to_string(), 1);
+    let mut dock = Dock::new(0, "Capital Dock".to_string(), 1);
+    dock.queue.push_back(trader);
+    station.add_service(StationServices::Dock(dock)).unwrap();
+    let station = app.world.spawn(station).id();
+
+    system.add_entity(defender);
+    system.add_entity(trader);
+    let system = app.world.spawn(system).id();
+
+    Battle {
+        app,
+        system,
+        station,
+        defender,
+        trader,
+    }
+}
+
+fn at_war() -> Diplomacy {
+    let mut diplomacy = Diplomacy::default();
+    diplomacy.declare_war(EMPIRE, REBELS);
+    diplomacy
+}
+
+/// Runs combat ticks until the trader is destroyed, returning how many it took
+fn fight(battle: &mut Battle, ticks: u32) -> Option<u32> {
+    for tick in 1..=ticks {
+        battle
+            .app
+            .world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs_f32(COMBAT_TICK));
+        battle.app.update();
+        if battle.app.world.get_entity(battle.trader).is_none() {
+            return Some(tick);
+        }
+    }
+    None
+}
+
+#[test]
+fn shields_absorb_damage_and_recharge_once_left_alone() {
+    let mut shield = Shield::new(40.0, 4.0, 5.0);
+    assert_eq!(shield.absorb(30.0), 30.0);
+    assert_eq!(shield.absorb(30.0), 10.0);
+    assert_eq!(shield.current, 0.0);
+
+    shield.recharge(4.0);
+    assert_eq!(shield.current, 0.0);
+    shield.recharge(1.0);
+    assert_eq!(shield.current, 4.0);
+    shield.recharge(100.0);
+    assert_eq!(shield.current, 40.0);
+}
+
+#[test]
+fn defenders_destroy_enemy_ships_and_leave_wrecks() {
+    let mut battle = battle(at_war());
+
+    // The trader's shield and hull take 140 damage, at 7.5 a tick
+    assert_eq!(fight(&mut battle, 30), Some(19));
+
+    let mut wrecks = battle.app.world.query::<&Wreck>();
+    let wreck = wrecks.single(&battle.app.world);
+    assert_eq!(wreck.system_id, 1);
+    assert_eq!(wreck.cargo.quantity(ItemId::REFINED_METAL), 10);
+
+    let system = battle.app.world.get::<SolarSystem>(battle.system).unwrap();
+    assert_eq!(system.entities.0, vec![battle.defender]);
+    let station = battle.app.world.get::<Station>(battle.station).unwrap();
+    assert!(!station.dock().unwrap().is_queued_or_docked(battle.trader));
+
+    // The trader shot back, and with nothing left to shoot at the defender stands down
+    let defender = battle.app.world.get::<Shield>(battle.defender).unwrap();
+    assert!(defender.current < defender.max);
+    fight(&mut battle, 1);
+    assert!(battle.app.world.get::<Target>(battle.defender).is_none());
+
+    let events = battle
+        .app
+        .world
+        .resource::<Events<DiplomaticIncidentEvent>>();
+    let incident = events.get_reader().read(events).next().unwrap();
+    assert_eq!(incident.instigator, REBELS);
+    assert_eq!(incident.target, EMPIRE);
+    assert_eq!(incident.kind, IncidentKind::Attack);
+}
+
+#[test]
+fn factions_at_peace_hold_their_fire() {
+    let mut battle = battle(Diplomacy::default());
+    assert_eq!(fight(&mut battle, 30), None);
+    assert!(battle.app.world.get::<Target>(battle.defender).is_none());
+}
@@ -0,0 +1,181 @@
+use ascendancy_lib::faction::attributes::FactionID;
+use ascendancy_lib::faction::diplomoacy::{
+    apply_diplomatic_incidents, Diplomacy, DiplomacyError, DiplomaticIncidentEvent,
+    DiplomaticState, DiplomaticStateChangedEvent, IncidentKind, TreatyKind, MAX_RELATION,
+};
+use ascendancy_lib::save::{load_from_file, save_to_file};
+use ascendancy_lib::simulation::{HeadlessPlugin, SimulationClock, SimulationPlugins};
+use ascendancy_lib::world_gen::GalaxyConfig;
+use bevy::prelude::*;
+
+const EMPIRE: FactionID = FactionID { id: 0 };
+const REBELS: FactionID = FactionID { id: 1 };
+
+#[test]
+fn relations_are_shared_by_both_factions() {
+    let mut diplomacy = Diplomacy::default();
+    assert_eq!(diplomacy.state(EMPIRE, REBELS), DiplomaticState::Neutral);
+    assert_eq!(diplomacy.state(EMPIRE, EMPIRE), DiplomaticState::Alliance);
+
+    diplomacy.shift(REBELS, EMPIRE, 15.0);
+    assert_eq!(diplomacy.score(EMPIRE, REBELS), 15.0);
+    assert_eq!(diplomacy.relations().len(), 1);
+
+    // Scores never leave their bounds
+    diplomacy.shift(EMPIRE, REBELS, 500.0);
+    assert_eq!(diplomacy.score(REBELS, EMPIRE), MAX_RELATION);
+}
+
+#[test]
+fn falling_relations_lead_to_war_and_recovering_ones_to_peace() {
+    let mut diplomacy = Diplomacy::default();
+
+    assert_eq!(
+        diplomacy.shift(EMPIRE, REBELS, -30.0),
+        Some((DiplomaticState::Neutral, DiplomaticState::Hostile))
+    );
+    assert_eq!(
+        diplomacy.shift(EMPIRE, REBELS, -50.0),
+        Some((DiplomaticState::Hostile, DiplomaticState::War))
+    );
+    assert!(!diplomacy.can_use_stargates(REBELS, EMPIRE));
+    assert!(!diplomacy.can_trade(EMPIRE, REBELS));
+
+    // Peace needs relations to recover well past the point war broke out
+    assert_eq!(diplomacy.shift(EMPIRE, REBELS, 20.0), None);
+    assert_eq!(
+        diplomacy.shift(EMPIRE, REBELS, 30.0),
+        Some((DiplomaticState::War, DiplomaticState::Hostile))
+    );
+    assert!(diplomacy.can_trade(EMPIRE, REBELS));
+}
+
+#[test]
+fn treaties_need_good_relations_and_expire() {
+    let mut diplomacy = Diplomacy::default();
+    assert_eq!(
+        diplomacy.sign_treaty(EMPIRE, REBELS, TreatyKind::TradePact, 100.0, 0.0),
+        Err(DiplomacyError::RelationsTooLow {
+            required: 10.0,
+            score: 0.0
+        })
+    );
+
+    diplomacy.shift(EMPIRE, REBELS, 20.0);
+    diplomacy
+        .sign_treaty(EMPIRE, REBELS, TreatyKind::TradePact, 100.0, 0.0)
+        .unwrap();
+    assert_eq!(diplomacy.state(REBELS, EMPIRE), DiplomaticState::TradePact);
+
+    assert!(diplomacy.update(0.0, 50.0).is_empty());
+    assert_eq!(
+        diplomacy.update(0.0, 100.0),
+        vec![(
+            EMPIRE,
+            REBELS,
+            DiplomaticState::TradePact,
+            DiplomaticState::Neutral
+        )]
+    );
+}
+
+#[test]
+fn treaties_break_when_relations_sour() {
+    let mut diplomacy = Diplomacy::default();
+    diplomacy.shift(EMPIRE, REBELS, 60.0);
+    diplomacy
+        .sign_treaty(EMPIRE, REBELS, TreatyKind::Alliance, 1000.0, 0.0)
+        .unwrap();
+
+    assert_eq!(
+        diplomacy.shift(EMPIRE, REBELS, -20.0),
+        Some((DiplomaticState::Alliance, DiplomaticState::Neutral))
+    );
+    assert_eq!(diplomacy.relation(EMPIRE, REBELS).treaty, None);
+}
+
+#[test]
+fn relations_fade_back_toward_neutral() {
+    let mut diplomacy = Diplomacy::default();
+    diplomacy.shift(EMPIRE, REBELS, -50.0);
+
+    diplomacy.update(100.0, 100.0);
+    assert!(diplomacy.score(EMPIRE, REBELS) > -50.0);
+    assert!(diplomacy.score(EMPIRE, REBELS) < 0.0);
+}
+
+#[test]
+fn outlaws_are_at_war_with_everyone() {
+    let mut diplomacy = Diplomacy::default();
+    diplomacy.shift(EMPIRE, REBELS, 60.0);
+    diplomacy.declare_outlaw(REBELS);
+
+    assert_eq!(diplomacy.state(EMPIRE, REBELS), DiplomaticState::War);
+    assert_eq!(diplomacy.state(REBELS, REBELS), DiplomaticState::Alliance);
+    assert_eq!(
+        diplomacy.sign_treaty(EMPIRE, REBELS, TreatyKind::TradePact, 100.0, 0.0),
+        Err(DiplomacyError::AtWar)
+    );
+
+    // Good relations never bring an outlaw to peace
+    diplomacy.update(10.0, 10.0);
+    assert_eq!(diplomacy.state(EMPIRE, REBELS), DiplomaticState::War);
+}
+
+#[test]
+fn attacks_are_applied_as_incidents() {
+    let mut app = App::new();
+    app.init_resource::<Diplomacy>()
+        .add_event::<DiplomaticIncidentEvent>()
+        .add_event::<DiplomaticStateChangedEvent>()
+        .add_systems(Update, apply_diplomatic_incidents);
+
+    for _ in 0..2 {
+        app.world.send_event(DiplomaticIncidentEvent {
+            instigator: REBELS,
+            target: EMPIRE,
+            kind: IncidentKind::Attack,
+        });
+    }
+    app.update();
+
+    let diplomacy = app.world.resource::<Diplomacy>();
+    assert_eq!(diplomacy.score(EMPIRE, REBELS), -40.0);
+    let changes = app.world.resource::<Events<DiplomaticStateChangedEvent>>();
+    let change = changes.get_reader().read(changes).next().unwrap();
+    assert_eq!(change.new, DiplomaticState::Hostile);
+}
+
+#[test]
+fn relations_survive_loading_a_save() {
+    let path = std::env::temp_dir().join("ascendancy_relations_survive_loading.json");
+    let new_app = || {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(GalaxyConfig::default().with_seed(7))
+            .add_plugins((SimulationPlugins, HeadlessPlugin::default()));
+        for _ in 0..10 {
+            app.update();
+        }
+        app
+    };
+
+    let mut app = new_app();
+    app.world
+        .resource_mut::<Diplomacy>()
+        .shift(EMPIRE, REBELS, -80.0);
+    // Long enough into the game that fading by the whole time played would wipe every score
+    app.world
+        .insert_resource(SimulationClock::from_seconds(1000.0));
+    save_to_file(&mut app.world, &path).unwrap();
+
+    let mut app = new_app();
+    load_from_file(&mut app.world, &path).unwrap();
+    app.update();
+    app.world.run_schedule(FixedUpdate);
+    std::fs::remove_file(&path).unwrap();
+
+    let diplomacy = app.world.resource::<Diplomacy>();
+    assert!(diplomacy.score(EMPIRE, REBELS) < -79.0);
+    assert_eq!(diplomacy.state(EMPIRE, REBELS), DiplomaticState::War);
+}
@@ -0,0 +1,165 @@
+use ascendancy_lib::agent::agent::Agent;
+use ascendancy_lib::faction::attributes::{Attributes, FactionID};
+use ascendancy_lib::faction::bank::{Bank, TransactionKind};
+use ascendancy_lib::faction::diplomoacy::Diplomacy;
+use ascendancy_lib::faction::taxes::TaxPolicy;
+use ascendancy_lib::simulation::SimulationClock;
+use ascendancy_lib::solar_system::attributes::SystemAttributes;
+use ascendancy_lib::solar_system::SolarSystem;
+use ascendancy_lib::structures::services::dock::{
+    process_docking, Dock, DockDenial, DockDeniedEvent, DockGrantedEvent, DockRequestEvent,
+    DockedAt, UndockRequestEvent, UndockedEvent,
+};
+use ascendancy_lib::structures::services::StationServices;
+use ascendancy_lib::structures::station::Station;
+use bevy::prelude::*;
+
+fn docking_app() -> App {
+    let mut app = App::new();
+    app.init_resource::<SimulationClock>()
+        .init_resource::<Diplomacy>()
+        .add_event::<DockRequestEvent>()
+        .add_event::<DockGrantedEvent>()
+        .add_event::<DockDeniedEvent>()
+        .add_event::<UndockRequestEvent>()
+        .add_event::<UndockedEvent>()
+        .add_systems(Update, process_docking);
+    app
+}
+
+#[test]
+fn agents_queue_for_berths_and_pay_fees() {
+    let mut app = docking_app();
+
+    let system = SolarSystem {
+        attributes: SystemAttributes {
+            id: 1,
+            name: "Test".to_string(),
+            owner: FactionID { id: 0 },
+        },
+        ..default()
+    };
+    let faction = app.world.spawn((Attributes::default(), Bank::new(0))).id();
+    let mut station = Station::new(1, "Station".to_string(), 1);
+    station
+        .add_service(StationServices::Dock(Dock::new(0, "Dock".to_string(), 1)))
+        .unwrap();
+    let station = app.world.spawn(station).id();
+    let first = app
+        .world
+        .spawn(Agent::new(1, "First".to_string(), &system))
+        .id();
+    let second = app
+        .world
+        .spawn(Agent::new(2, "Second".to_string(), &system))
+        .id();
+    app.world.spawn(system);
+
+    for agent in [first, second] {
+        app.world.send_event(DockRequestEvent { agent, station });
+    }
+    app.update();
+
+    assert_eq!(app.world.get::<DockedAt>(first), Some(&DockedAt(station)));
+    assert!(app.world.get::<DockedAt>(second).is_none());
+    assert_eq!(app.world.get::<Agent>(first).unwrap().wallet.money, 75.0);
+    assert_eq!(app.world.get::<Bank>(faction).unwrap().balance, 25);
+    let dock = app.world.get::<Station>(station).unwrap().dock().unwrap();
+    assert_eq!(dock.queue.front(), Some(&second));
+
+    app.world.send_event(UndockRequestEvent {
+        agent: first,
+        station,
+    });
+    app.update();
+
+    assert!(app.world.get::<DockedAt>(first).is_none());
+    assert_eq!(app.world.get::<DockedAt>(second), Some(&DockedAt(station)));
+}
+
+#[test]
+fn owners_tax_docking_fees() {
+    let mut app = docking_app();
+
+    let system = SolarSystem {
+        attributes: SystemAttributes {
+            id: 1,
+            name: "Test".to_string(),
+            owner: FactionID { id: 0 },
+        },
+        ..default()
+    };
+    let faction = app
+        .world
+        .spawn((
+            Attributes::default(),
+            Bank::new(0),
+            TaxPolicy {
+                trade_rate: 0.0,
+                docking_rate: 0.2,
+            },
+        ))
+        .id();
+    let mut station = Station::new(1, "Station".to_string(), 1);
+    station
+        .add_service(StationServices::Dock(Dock::new(0, "Dock".to_string(), 1)))
+        .unwrap();
+    let station = app.world.spawn(station).id();
+    let agent = app
+        .world
+        .spawn(Agent::new(1, "Agent".to_string(), &system))
+        .id();
+    app.world.spawn(system);
+
+    app.world.send_event(DockRequestEvent { agent, station });
+    app.update();
+
+    assert_eq!(app.world.get::<Agent>(agent).unwrap().wallet.money, 70.0);
+    let bank = app.world.get::<Bank>(faction).unwrap();
+    assert_eq!(bank.balance, 30);
+    assert_eq!(bank.net_between(TransactionKind::Fee, 0.0, 1.0), 25);
+    assert_eq!(bank.net_between(TransactionKind::Tax, 0.0, 1.0), 5);
+}
+
+#[test]
+fn factions_at_war_are_turned_away() {
+    let mut app = docking_app();
+
+    let home = SolarSystem {
+        attributes: SystemAttributes {
+            id: 2,
+            name: "Home".to_string(),
+            owner: FactionID { id: 1 },
+        },
+        ..default()
+    };
+    let system = SolarSystem {
+        attributes: SystemAttributes {
+            id: 1,
+            name: "Test".to_string(),
+            owner: FactionID { id: 0 },
+        },
+        ..default()
+    };
+    app.world
+        .resource_mut::<Diplomacy>()
+        .declare_war(FactionID { id: 0 }, FactionID { id: 1 });
+    let mut station = Station::new(1, "Station".to_string(), 1);
+    station
+        .add_service(StationServices::Dock(Dock::new(0, "Dock".to_string(), 1)))
+        .unwrap();
+    let station = app.world.spawn(station).id();
+    let agent = app
+        .world
+        .spawn(Agent::new(1, "Agent".to_string(), &home))
+        .id();
+    app.world.spawn(system);
+
+    app.world.send_event(DockRequestEvent { agent, station });
+    app.update();
+
+    assert!(app.world.get::<DockedAt>(agent).is_none());
+    let denials = app.world.resource::<Events<DockDeniedEvent>>();
+    let denial = denials.get_reader().read(denials).next().unwrap();
+    assert_eq!(denial.reason, DockDenial::Embargoed);
+}
@@ -0,0 +1,87 @@
+use ascendancy_lib::faction::definitions::{FactionDefinitionError, FactionDefinitions};
+
+#[test]
+fn built_in_factions_are_valid() {
+    let (factions, errors) = FactionDefinitions::built_in().validate();
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert!(!factions.is_empty());
+}
+
+#[test]
+fn clashing_factions_are_skipped() {
+    let faction = |id: u8, name: &str, red: f32| {
+        format!(
+            "(faction_attributes: (id: (id: {id}), name: \"{name}\", colors: Rgba(red: {red}, green: 0.1, blue: 0.1, alpha: 1.0)), \
+             faction_bank: (balance: 0, total_deposits: 0, total_withdrawals: 0, total_loans: 0, total_loans_repaid: 0))"
+        )
+    };
+    let source = format!(
+        "([{}, {}, {}, {}])",
+        faction(0, "A", 0.1),
+        faction(0, "B", 0.2),
+        faction(1, "C", 0.1),
+        faction(2, "D", 2.0),
+    );
+
+    let (factions, errors) = FactionDefinitions::from_ron(&source).unwrap().validate();
+    assert_eq!(factions.len(), 1);
+    assert!(matches!(
+        errors[0],
+        FactionDefinitionError::DuplicateId { .. }
+    ));
+    assert!(matches!(
+        errors[1],
+        FactionDefinitionError::DuplicateColor { .. }
+    ));
+    assert!(matches!(
+        errors[2],
+        FactionDefinitionError::InvalidColor { .. }
+    ));
+}
+
+#[test]
+fn malformed_factions_are_reported() {
+    let error = FactionDefinitions::from_ron("([(faction_attributes: ())])").unwrap_err();
+    assert!(matches!(error, FactionDefinitionError::Malformed(_)));
+}
+
+#[test]
+fn excessive_taxes_are_rejected() {
+    let source = "([(faction_attributes: (id: (id: 0), name: \"Greedy\", colors: Rgba(red: 0.1, green: 0.1, blue: 0.1, alpha: 1.0)), \
+         faction_bank: (balance: 0, total_deposits: 0, total_withdrawals: 0, total_loans: 0, total_loans_repaid: 0), \
+         faction_taxes: (trade_rate: 0.9))])";
+
+    let (factions, errors) = FactionDefinitions::from_ron(source).unwrap().validate();
+    assert!(factions.is_empty());
+    assert!(matches!(
+        errors[0],
+        FactionDefinitionError::InvalidTaxRate { .. }
+    ));
+}
+
+#[test]
+fn the_pirate_id_is_reserved() {
+    let source = "([(faction_attributes: (id: (id: 255), name: \"Corsairs\", colors: Rgba(red: 0.1, green: 0.1, blue: 0.1, alpha: 1.0)), \
+         faction_bank: (balance: 0, total_deposits: 0, total_withdrawals: 0, total_loans: 0, total_loans_repaid: 0))])";
+
+    let (factions, errors) = FactionDefinitions::from_ron(source).unwrap().validate();
+    assert!(factions.is_empty());
+    assert_eq!(
+        errors[0],
+        FactionDefinitionError::ReservedId {
+            name: "Corsairs".to_string()
+        }
+    );
+}
+
+#[test]
+fn files_without_valid_factions_fall_back_to_the_built_in_ones() {
+    let source = "([(faction_attributes: (id: (id: 255), name: \"Corsairs\", colors: Rgba(red: 0.1, green: 0.1, blue: 0.1, alpha: 1.0)), \
+         faction_bank: (balance: 0, total_deposits: 0, total_withdrawals: 0, total_loans: 0, total_loans_repaid: 0))])";
+
+    let resource = FactionDefinitions::from_ron(source)
+        .unwrap()
+        .into_resource("test.factions.ron");
+    let (built_in, _) = FactionDefinitions::built_in().validate();
+    assert_eq!(resource.factions.len(), built_in.len());
+}
@@ -0,0 +1,215 @@
+use ascendancy_lib::faction::attributes::{Attributes, FactionID};
+use ascendancy_lib::faction::bank::Bank;
+use ascendancy_lib::faction::diplomoacy::{
+    Diplomacy, DiplomaticState, DiplomaticStateChangedEvent, TreatyKind,
+};
+use ascendancy_lib::faction::fleets::{Fleet, FleetOrder};
+use ascendancy_lib::faction::goals::{
+    assess_faction_strategy, carry_out_faction_orders, FactionGoals, FactionOrder,
+    FactionOrderEvent, FactionStrategy,
+};
+use ascendancy_lib::faction::infrastructure::{
+    ConstructionProject, ConstructionRequestEvent, ConstructionSite,
+};
+use ascendancy_lib::faction::taxes::TaxPolicy;
+use ascendancy_lib::simulation::SimulationClock;
+use ascendancy_lib::solar_system::attributes::SystemAttributes;
+use ascendancy_lib::solar_system::SolarSystem;
+use ascendancy_lib::structures::stargate::Stargate;
+use ascendancy_lib::structures::station::Station;
+use bevy::prelude::*;
+
+const EMPIRE: FactionID = FactionID { id: 0 };
+const REBELS: FactionID = FactionID { id: 1 };
+
+fn goals_app() -> App {
+    let mut app = App::new();
+    app.init_resource::<SimulationClock>()
+        .init_resource::<Diplomacy>()
+        .add_event::<FactionOrderEvent>()
+        .add_event::<ConstructionRequestEvent>()
+        .add_event::<DiplomaticStateChangedEvent>()
+        .add_systems(
+            Update,
+            (assess_faction_strategy, carry_out_faction_orders).chain(),
+        );
+    app
+}
+
+fn spawn_faction(app: &mut App, id: FactionID, balance: u32) -> Entity {
+    app.world
+        .spawn((
+            Attributes {
+                id,
+                name: format!("Faction {}", id.id),
+                ..default()
+            },
+            Bank::new(balance),
+            TaxPolicy::default(),
+            FactionStrategy::default(),
+        ))
+        .id()
+}
+
+fn spawn_system(app: &mut App, id: u32, owner: FactionID) {
+    app.world.spawn((
+        SolarSystem {
+            attributes: SystemAttributes {
+                id,
+                name: format!("System {}", id),
+                owner,
+            },
+            ..default()
+        },
+        Transform::from_xyz(id as f32 * 100.0, 0.0, 0.0),
+    ));
+}
+
+fn link(app: &mut App, id: u32, origin: u32, destination: u32) {
+    app.world.spawn(Stargate {
+        id,
+        origin_system_id: origin,
+        destination_system_id: destination,
+        ..default()
+    });
+}
+
+#[test]
+fn default_goal_weights_are_valid() {
+    assert!(FactionGoals::default().is_valid());
+    assert!(!FactionGoals {
+        trade: -1.0,
+        ..default()
+    }
+    .is_valid());
+}
+
+#[test]
+fn assessments_find_borders_threats_and_treaty_partners() {
+    let mut app = goals_app();
+    let empire = spawn_faction(&mut app, EMPIRE, 500000);
+    spawn_faction(&mut app, REBELS, 0);
+    spawn_system(&mut app, 1, EMPIRE);
+    spawn_system(&mut app, 2, EMPIRE);
+    spawn_system(&mut app, 3, REBELS);
+    link(&mut app, 1, 1, 2);
+    link(&mut app, 2, 2, 3);
+    app.world.spawn(Station::new(0, "Capital".to_string(), 1));
+    app.world
+        .resource_mut::<Diplomacy>()
+        .shift(EMPIRE, REBELS, -30.0);
+
+    app.update();
+
+    let strategy = app.world.get::<FactionStrategy>(empire).unwrap();
+    let assessment = strategy.assessment.clone().unwrap();
+    assert_eq!(assessment.owned_systems, 2);
+    assert_eq!(assessment.border_systems, vec![2]);
+    // The empty border system is built up before the developed capital
+    assert_eq!(assessment.build_site, Some(2));
+    let threat = assessment.threat.unwrap();
+    assert_eq!(threat.faction, REBELS);
+    assert_eq!(threat.state, DiplomaticState::Hostile);
+    assert_eq!(assessment.treaty_candidate, None);
+    assert!(strategy.next_review > 0.0);
+}
+
+#[test]
+fn stations_under_construction_count_toward_a_system() {
+    let mut app = goals_app();
+    let empire = spawn_faction(&mut app, EMPIRE, 500000);
+    spawn_system(&mut app, 1, EMPIRE);
+    spawn_system(&mut app, 2, EMPIRE);
+    app.world.spawn(Station::new(0, "Capital".to_string(), 1));
+    app.world.spawn(ConstructionSite::new(
+        EMPIRE,
+        2,
+        ConstructionProject::outpost("Outpost".to_string()),
+    ));
+    app.world.spawn(ConstructionSite::new(
+        EMPIRE,
+        2,
+        ConstructionProject::outpost("Outpost".to_string()),
+    ));
+
+    app.update();
+
+    let strategy = app.world.get::<FactionStrategy>(empire).unwrap();
+    assert_eq!(strategy.assessment.as_ref().unwrap().build_site, Some(1));
+}
+
+#[test]
+fn build_orders_commission_outposts() {
+    let mut app = goals_app();
+    let empire = spawn_faction(&mut app, EMPIRE, 0);
+    app.world.send_event(FactionOrderEvent {
+        faction: empire,
+        order: FactionOrder::BuildStation { system_id: 4 },
+    });
+    app.update();
+
+    let requests = app.world.resource::<Events<ConstructionRequestEvent>>();
+    let request = requests.get_reader().read(requests).next().unwrap();
+    assert_eq!(request.faction, EMPIRE);
+    assert_eq!(request.system_id, 4);
+    assert_eq!(
+        request.project,
+        ConstructionProject::outpost("Faction 0 Outpost 4".to_string())
+    );
+}
+
+#[test]
+fn orders_change_taxes_fleets_and_treaties() {
+    let mut app = goals_app();
+    let empire = spawn_faction(&mut app, EMPIRE, 0);
+    let fleet = app
+        .world
+        .spawn(Fleet::new(0, "Home Guard".to_string(), EMPIRE))
+        .id();
+    let foreign_fleet = app
+        .world
+        .spawn(Fleet::new(1, "Raiders".to_string(), REBELS))
+        .id();
+    app.world
+        .resource_mut::<Diplomacy>()
+        .shift(EMPIRE, REBELS, 20.0);
+
+    let taxes = TaxPolicy {
+        trade_rate: 0.1,
+        docking_rate: 0.2,
+    };
+    let blockade = FleetOrder::Blockade { system_id: 3 };
+    for order in [
+        FactionOrder::SetTaxes(taxes),
+        FactionOrder::DispatchFleet {
+            fleet,
+            order: blockade.clone(),
+        },
+        FactionOrder::DispatchFleet {
+            fleet: foreign_fleet,
+            order: blockade.clone(),
+        },
+        FactionOrder::ProposeTreaty {
+            with: REBELS,
+            kind: TreatyKind::TradePact,
+        },
+    ] {
+        app.world.send_event(FactionOrderEvent {
+            faction: empire,
+            order,
+        });
+    }
+    app.update();
+
+    assert_eq!(app.world.get::<TaxPolicy>(empire), Some(&taxes));
+    assert_eq!(app.world.get::<Fleet>(fleet).unwrap().order, blockade);
+    // Factions can't give orders to each other's fleets
+    assert_eq!(
+        app.world.get::<Fleet>(foreign_fleet).unwrap().order,
+        FleetOrder::Hold
+    );
+    assert_eq!(
+        app.world.resource::<Diplomacy>().state(EMPIRE, REBELS),
+        DiplomaticState::TradePact
+    );
+}
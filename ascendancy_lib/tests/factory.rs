@@ -0,0 +1,111 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use ascendancy_lib::items::manifest::{ItemManifest, ManifestError};
+use ascendancy_lib::items::{ItemId, ItemRegistry};
+use ascendancy_lib::structures::services::factory::{
+    report_factory_stalls, Factory, FactoryStall, FactoryStalledEvent,
+};
+use ascendancy_lib::structures::services::StationServices;
+use ascendancy_lib::structures::station::{run_active_services, Station};
+use bevy::prelude::*;
+
+fn factory_app(ore: u32, cells: u32) -> (App, Entity) {
+    let items = ItemRegistry::built_in();
+    let recipe = items.recipe("refine_metal").unwrap();
+
+    let mut station = Station::new(1, "Refinery".to_string(), 1);
+    station
+        .add_service(StationServices::Factory(Factory::new(
+            0,
+            "Refinery".to_string(),
+            recipe,
+        )))
+        .unwrap();
+    let inventory = &mut station.resource_manager.inventory;
+    inventory
+        .add(items.get(ItemId::RAW_ORE).unwrap(), ore)
+        .unwrap();
+    inventory
+        .add(items.get(ItemId::ENERGY_CELLS).unwrap(), cells)
+        .unwrap();
+    station.resource_manager.energy = 10000.0;
+
+    let mut app = App::new();
+    app.init_resource::<Time>()
+        .add_event::<FactoryStalledEvent>()
+        .add_systems(Update, (run_active_services, report_factory_stalls).chain());
+    let entity = app.world.spawn(station).id();
+    (app, entity)
+}
+
+fn run_cycle(app: &mut App) {
+    app.world
+        .resource_mut::<Time>()
+        .advance_by(Duration::from_secs(10));
+    app.update();
+}
+
+#[test]
+fn built_in_recipes_resolve() {
+    let items = ItemRegistry::built_in();
+    let recipe = items.recipe("refine_metal").unwrap();
+    assert!(recipe.inputs.contains(&(ItemId::RAW_ORE, 4)));
+    assert_eq!(recipe.outputs[0].0, ItemId::REFINED_METAL);
+}
+
+#[test]
+fn recipes_need_known_items() {
+    let path = PathBuf::from("mod.item_manifest.json");
+    let manifest = ItemManifest::from_json(
+        &path,
+        r#"{"items": {}, "recipes": {
+            "alchemy": {"inputs": {"lead": 1}, "outputs": {"gold": 1}, "cycle_seconds": 1}
+        }}"#,
+    );
+    let (registry, errors) = ItemRegistry::from_manifests([(path, manifest)]);
+
+    assert!(registry.recipe("alchemy").is_none());
+    assert!(matches!(errors[0], ManifestError::InvalidRecipe { .. }));
+}
+
+#[test]
+fn factories_turn_inputs_into_outputs() {
+    let (mut app, station) = factory_app(8, 2);
+
+    run_cycle(&mut app);
+    run_cycle(&mut app);
+
+    let station = app.world.get::<Station>(station).unwrap();
+    let inventory = &station.resource_manager.inventory;
+    assert_eq!(inventory.quantity(ItemId::REFINED_METAL), 2);
+    assert_eq!(inventory.quantity(ItemId::RAW_ORE), 0);
+    assert_eq!(station.resource_manager.energy, 9600.0);
+    assert_eq!(station.factories().next().unwrap().cycles_completed, 2);
+}
+
+#[test]
+fn missing_inputs_stall_the_factory() {
+    let (mut app, station) = factory_app(2, 1);
+
+    run_cycle(&mut app);
+    run_cycle(&mut app);
+
+    let factory = app
+        .world
+        .get::<Station>(station)
+        .unwrap()
+        .factories()
+        .next()
+        .unwrap()
+        .clone();
+    assert_eq!(factory.cycles_completed, 0);
+    assert!(matches!(
+        factory.stall(),
+        Some(FactoryStall::MissingInput { item, .. }) if *item == ItemId::RAW_ORE
+    ));
+
+    // The stall is only reported once
+    let events = app.world.resource::<Events<FactoryStalledEvent>>();
+    assert_eq!(events.get_reader().read(events).count(), 1);
+}
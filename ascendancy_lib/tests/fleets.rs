@@ -0,0 +1,135 @@
+use std::time::Duration;
+
+use ascendancy_lib::agent::agent::Agent;
+use ascendancy_lib::agent::cargo::CargoHold;
+use ascendancy_lib::agent::pathfinding::SystemGraph;
+use ascendancy_lib::faction::attributes::FactionID;
+use ascendancy_lib::faction::diplomoacy::Diplomacy;
+use ascendancy_lib::faction::fleets::{
+    move_fleets, process_fleet_membership, Fleet, FleetDisbandedEvent, FleetJoinDenial,
+    FleetJoinDeniedEvent, FleetJoinedEvent, FleetLeaderChangedEvent, FleetLeftEvent, FleetMember,
+    Formation, JoinFleetRequestEvent, LeaveFleetRequestEvent,
+};
+use ascendancy_lib::solar_system::attributes::SystemAttributes;
+use ascendancy_lib::solar_system::SolarSystem;
+use bevy::prelude::*;
+
+fn system_owned_by(id: u32, owner: u8) -> SolarSystem {
+    SolarSystem {
+        attributes: SystemAttributes {
+            id,
+            name: format!("System {}", id),
+            owner: FactionID { id: owner },
+        },
+        ..default()
+    }
+}
+
+fn fleet_app() -> App {
+    let mut app = App::new();
+    app.init_resource::<Time>()
+        .init_resource::<SystemGraph>()
+        .init_resource::<Diplomacy>()
+        .add_event::<JoinFleetRequestEvent>()
+        .add_event::<LeaveFleetRequestEvent>()
+        .add_event::<FleetJoinedEvent>()
+        .add_event::<FleetJoinDeniedEvent>()
+        .add_event::<FleetLeftEvent>()
+        .add_event::<FleetLeaderChangedEvent>()
+        .add_event::<FleetDisbandedEvent>()
+        .add_systems(Update, (process_fleet_membership, move_fleets).chain());
+    app
+}
+
+fn spawn_agent(app: &mut App, home: &SolarSystem, position: Vec3) -> Entity {
+    app.world
+        .spawn((
+            Agent::new(0, "Agent".to_string(), home),
+            CargoHold::new(100.0, 50.0),
+            Transform::from_translation(position),
+        ))
+        .id()
+}
+
+#[test]
+fn formations_place_members_around_the_leader() {
+    assert_eq!(Formation::Wedge.slot_offset(0, 6.0), Vec2::ZERO);
+    assert_eq!(Formation::Wedge.slot_offset(1, 6.0), Vec2::new(-6.0, 6.0));
+    assert_eq!(Formation::Wedge.slot_offset(2, 6.0), Vec2::new(-6.0, -6.0));
+    assert_eq!(Formation::Line.slot_offset(3, 6.0), Vec2::new(-18.0, 0.0));
+    assert!(Formation::Ring
+        .slot_offset(1, 6.0)
+        .abs_diff_eq(Vec2::new(6.0, 0.0), 0.001));
+}
+
+#[test]
+fn agents_join_fleets_of_their_own_faction() {
+    let mut app = fleet_app();
+    let home = system_owned_by(1, 0);
+    let fleet = app
+        .world
+        .spawn(Fleet::new(0, "Home Guard".to_string(), FactionID { id: 0 }))
+        .id();
+    let leader = spawn_agent(&mut app, &home, Vec3::ZERO);
+    let wingman = spawn_agent(&mut app, &home, Vec3::ZERO);
+    let foreigner = spawn_agent(&mut app, &system_owned_by(2, 1), Vec3::ZERO);
+
+    for agent in [leader, wingman, foreigner] {
+        app.world.send_event(JoinFleetRequestEvent { fleet, agent });
+    }
+    app.update();
+
+    let members = app.world.get::<Fleet>(fleet).unwrap().members().to_vec();
+    assert_eq!(members, vec![leader, wingman]);
+    assert_eq!(
+        app.world.get::<FleetMember>(wingman),
+        Some(&FleetMember(fleet))
+    );
+    assert!(app.world.get::<FleetMember>(foreigner).is_none());
+    let denials = app.world.resource::<Events<FleetJoinDeniedEvent>>();
+    let denial = denials.get_reader().read(denials).next().unwrap();
+    assert_eq!(denial.reason, FleetJoinDenial::WrongFaction);
+
+    // The next member takes over when the leader leaves
+    app.world.send_event(LeaveFleetRequestEvent {
+        fleet,
+        agent: leader,
+    });
+    app.update();
+    assert_eq!(
+        app.world.get::<Fleet>(fleet).unwrap().leader(),
+        Some(wingman)
+    );
+    assert!(app.world.get::<FleetMember>(leader).is_none());
+
+    // Losing the last member disbands the fleet
+    app.world.despawn(wingman);
+    app.update();
+    assert!(app.world.get_entity(fleet).is_none());
+}
+
+#[test]
+fn members_close_up_on_their_slots() {
+    let mut app = fleet_app();
+    let home = system_owned_by(1, 0);
+    let leader = spawn_agent(&mut app, &home, Vec3::new(100.0, 100.0, 0.0));
+    let wingman = spawn_agent(&mut app, &home, Vec3::new(100.0, 80.0, 0.0));
+    let mut fleet = Fleet::new(0, "Home Guard".to_string(), FactionID { id: 0 });
+    fleet.formation = Formation::Line;
+    fleet.add_member(leader);
+    fleet.add_member(wingman);
+    app.world.spawn(fleet);
+
+    app.world
+        .resource_mut::<Time>()
+        .advance_by(Duration::from_secs(1));
+    app.update();
+
+    let transform = app.world.get::<Transform>(wingman).unwrap();
+    assert!(transform
+        .translation
+        .abs_diff_eq(Vec3::new(94.0, 100.0, 0.0), 0.001));
+    // A fleet holding position leaves its leader where it is
+    let transform = app.world.get::<Transform>(leader).unwrap();
+    assert_eq!(transform.translation, Vec3::new(100.0, 100.0, 0.0));
+}
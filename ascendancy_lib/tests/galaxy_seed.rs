@@ -0,0 +1,117 @@
+use ascendancy_lib::agent::agent::{Agent, AgentRole};
+use ascendancy_lib::faction::attributes::FactionID;
+use ascendancy_lib::items::inventory::Inventory;
+use ascendancy_lib::simulation::{HeadlessPlugin, SimulationPlugins};
+use ascendancy_lib::solar_system::SolarSystem;
+use ascendancy_lib::structures::services::StationServiceTrait;
+use ascendancy_lib::structures::stargate::Stargate;
+use ascendancy_lib::structures::station::Station;
+use ascendancy_lib::world_gen::GalaxyConfig;
+use bevy::prelude::*;
+
+/// The parts of a generated galaxy that should only depend on its seed
+#[derive(Debug, PartialEq)]
+struct Galaxy {
+    systems: Vec<(u32, String, FactionID)>,
+    gates: Vec<(u32, u32, u32)>,
+    stations: Vec<(u32, Vec<u32>, Inventory)>,
+    agents: Vec<(String, u32, FactionID, AgentRole)>,
+}
+
+fn generate(seed: u64) -> Galaxy {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .insert_resource(GalaxyConfig::default().with_seed(seed))
+        .add_plugins((SimulationPlugins, HeadlessPlugin::default()));
+    for _ in 0..10 {
+        app.update();
+    }
+
+    let world = &mut app.world;
+    let mut systems: Vec<_> = world
+        .query::<&SolarSystem>()
+        .iter(world)
+        .map(|system| {
+            let attributes = &system.attributes;
+            (attributes.id, attributes.name.clone(), attributes.owner)
+        })
+        .collect();
+    systems.sort_by_key(|(id, _, _)| *id);
+
+    let mut gates: Vec<_> = world
+        .query::<&Stargate>()
+        .iter(world)
+        .map(|gate| (gate.id, gate.origin_system_id, gate.destination_system_id))
+        .collect();
+    gates.sort();
+
+    let mut stations: Vec<_> = world
+        .query::<&Station>()
+        .iter(world)
+        .map(|station| {
+            let services: Vec<u32> = station
+                .services
+                .iter()
+                .map(|service| service.id())
+                .collect();
+            (
+                station.id,
+                services,
+                station.resource_manager.inventory.clone(),
+            )
+        })
+        .collect();
+    stations.sort_by_key(|(id, _, _)| *id);
+
+    let mut agents: Vec<_> = world
+        .query::<(&Agent, &AgentRole)>()
+        .iter(world)
+        .map(|(agent, role)| {
+            (
+                agent.name.clone(),
+                agent.home_system.attributes.id,
+                agent.faction,
+                *role,
+            )
+        })
+        .collect();
+    agents.sort_by(|a, b| (&a.0, a.1).cmp(&(&b.0, b.1)));
+
+    Galaxy {
+        systems,
+        gates,
+        stations,
+        agents,
+    }
+}
+
+#[test]
+fn same_seed_generates_the_same_galaxy() {
+    let first = generate(42);
+    let second = generate(42);
+
+    assert!(!first.systems.is_empty());
+    assert!(!first.agents.is_empty());
+    assert!(first
+        .stations
+        .iter()
+        .any(|(_, _, inventory)| !inventory.is_empty()));
+    assert_eq!(first, second);
+
+    // Every service in the galaxy gets its own number
+    let mut services: Vec<u32> = first
+        .stations
+        .iter()
+        .flat_map(|(_, services, _)| services.iter().copied())
+        .collect();
+    let count = services.len();
+    services.sort();
+    services.dedup();
+    assert_eq!(services.len(), count);
+    assert_eq!(services.last(), Some(&(count as u32 - 1)));
+}
+
+#[test]
+fn different_seeds_generate_different_galaxies() {
+    assert_ne!(generate(1), generate(2));
+}
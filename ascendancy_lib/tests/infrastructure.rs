@@ -0,0 +1,280 @@
+use std::time::Duration;
+
+use ascendancy_lib::faction::attributes::{Attributes, FactionID};
+use ascendancy_lib::faction::bank::Bank;
+use ascendancy_lib::faction::infrastructure::{
+    advance_construction, commission_construction, supply_construction_sites,
+    ConstructionCompletedEvent, ConstructionDenial, ConstructionDeniedEvent, ConstructionProject,
+    ConstructionRequestEvent, ConstructionSite, ConstructionStall, ConstructionStartedEvent,
+    ServicePlan,
+};
+use ascendancy_lib::items::{ItemId, ItemRegistry};
+use ascendancy_lib::simulation::SimulationClock;
+use ascendancy_lib::solar_system::attributes::SystemAttributes;
+use ascendancy_lib::solar_system::resources::ResourceKind;
+use ascendancy_lib::solar_system::SolarSystem;
+use ascendancy_lib::structures::services::dock::Dock;
+use ascendancy_lib::structures::services::market::Market;
+use ascendancy_lib::structures::services::{StationServiceTrait, StationServices};
+use ascendancy_lib::structures::station::Station;
+use bevy::prelude::*;
+
+const EMPIRE: FactionID = FactionID { id: 0 };
+const REBELS: FactionID = FactionID { id: 1 };
+
+fn generator_upgrade() -> ConstructionProject {
+    ConstructionProject::Upgrade {
+        station_id: 0,
+        services: vec![ServicePlan::SolarGenerator],
+    }
+}
+
+fn construction_app() -> App {
+    let mut app = App::new();
+    app.init_resource::<Time>()
+        .init_resource::<SimulationClock>()
+        .insert_resource(ItemRegistry::built_in())
+        .add_event::<ConstructionRequestEvent>()
+        .add_event::<ConstructionStartedEvent>()
+        .add_event::<ConstructionDeniedEvent>()
+        .add_event::<ConstructionCompletedEvent>()
+        .add_systems(
+            Update,
+            (
+                commission_construction,
+                supply_construction_sites,
+                advance_construction,
+            )
+                .chain(),
+        );
+
+    app.world.spawn((
+        Attributes {
+            id: EMPIRE,
+            ..default()
+        },
+        Bank::new(1000000),
+    ));
+    for (id, owner) in [(1, EMPIRE), (2, REBELS)] {
+        app.world.spawn((
+            SolarSystem {
+                attributes: SystemAttributes {
+                    id,
+                    name: format!("System {}", id),
+                    owner,
+                },
+                ..default()
+            },
+            Transform::default(),
+        ));
+    }
+    app
+}
+
+/// Spawns a station with a market stocked with construction materials
+fn spawn_market_station(app: &mut App) -> Entity {
+    let items = ItemRegistry::built_in();
+    let mut station = Station::new(0, "Capital".to_string(), 1);
+    station
+        .add_service(StationServices::Market(Market::new(0, &items)))
+        .unwrap();
+    for item in [ItemId::REFINED_METAL, ItemId::ENERGY_CELLS] {
+        station
+            .resource_manager
+            .inventory
+            .add(items.get(item).unwrap(), 1000)
+            .unwrap();
+    }
+    app.world.spawn((station, Transform::default())).id()
+}
+
+fn request(app: &mut App, faction: FactionID, system_id: u32, project: ConstructionProject) {
+    app.world.send_event(ConstructionRequestEvent {
+        faction,
+        system_id,
+        project,
+    });
+}
+
+fn denials(app: &App) -> Vec<ConstructionDenial> {
+    let events = app.world.resource::<Events<ConstructionDeniedEvent>>();
+    events
+        .get_reader()
+        .read(events)
+        .map(|event| event.reason.clone())
+        .collect()
+}
+
+#[test]
+fn work_uses_materials_and_credits_as_it_goes() {
+    let mut site = ConstructionSite::new(EMPIRE, 1, generator_upgrade());
+    assert_eq!(site.credits_required, 15000);
+    let mut bank = Bank::new(100000);
+
+    site.work(5.0, &mut bank, 0.0);
+    assert_eq!(site.progress, 0.0);
+    assert_eq!(
+        site.stall,
+        Some(ConstructionStall::AwaitingMaterials(ItemId::REFINED_METAL))
+    );
+
+    // Sites only take what they still need
+    assert_eq!(site.deliver(ItemId::REFINED_METAL, 500), 50);
+    site.work(5.0, &mut bank, 0.0);
+    assert_eq!(site.progress, 0.25);
+    assert_eq!(site.credits_paid, 3750);
+    assert_eq!(site.materials[0].consumed, 13);
+    assert_eq!(site.stall, None);
+
+    site.work(100.0, &mut bank, 0.0);
+    assert!(site.is_complete());
+    assert_eq!(site.materials[0].consumed, 50);
+    assert_eq!(bank.balance, 85000);
+}
+
+#[test]
+fn work_stalls_when_the_faction_cannot_pay() {
+    let mut site = ConstructionSite::new(EMPIRE, 1, generator_upgrade());
+    site.deliver(ItemId::REFINED_METAL, 50);
+    let mut bank = Bank::new(0);
+
+    site.work(5.0, &mut bank, 0.0);
+    assert_eq!(site.progress, 0.0);
+    assert_eq!(site.stall, Some(ConstructionStall::AwaitingCredits));
+}
+
+#[test]
+fn upgrades_respect_the_service_cap() {
+    let mut app = construction_app();
+    spawn_market_station(&mut app);
+
+    request(&mut app, REBELS, 1, generator_upgrade());
+    request(&mut app, EMPIRE, 1, generator_upgrade());
+    request(&mut app, EMPIRE, 1, generator_upgrade());
+    request(
+        &mut app,
+        EMPIRE,
+        1,
+        ConstructionProject::Upgrade {
+            station_id: 0,
+            services: vec![
+                ServicePlan::Dock,
+                ServicePlan::Mining {
+                    resource: ResourceKind::Ore,
+                },
+                ServicePlan::Factory {
+                    recipe: "refine_metal".to_string(),
+                },
+            ],
+        },
+    );
+    request(
+        &mut app,
+        EMPIRE,
+        1,
+        ConstructionProject::Upgrade {
+            station_id: 0,
+            services: vec![ServicePlan::Mining {
+                resource: ResourceKind::Gas,
+            }],
+        },
+    );
+    app.update();
+
+    assert_eq!(
+        denials(&app),
+        vec![
+            ConstructionDenial::NotOwner,
+            ConstructionDenial::DuplicateService(ServicePlan::SolarGenerator),
+            ConstructionDenial::TooManyServices,
+        ]
+    );
+    let mut sites = app.world.query::<&ConstructionSite>();
+    assert_eq!(sites.iter(&app.world).count(), 2);
+}
+
+#[test]
+fn finished_sites_become_stations() {
+    let mut app = construction_app();
+    let capital = spawn_market_station(&mut app);
+    request(
+        &mut app,
+        EMPIRE,
+        1,
+        ConstructionProject::outpost("Outpost".to_string()),
+    );
+    app.update();
+
+    // Materials are bought from the market in the system
+    let mut sites = app.world.query::<&ConstructionSite>();
+    let site = sites.single(&app.world).clone();
+    assert!(site
+        .materials
+        .iter()
+        .all(|material| material.outstanding() == 0));
+    let station = app.world.get::<Station>(capital).unwrap();
+    assert_eq!(
+        station
+            .resource_manager
+            .inventory
+            .quantity(ItemId::REFINED_METAL),
+        1000 - 350
+    );
+
+    app.world
+        .resource_mut::<Time>()
+        .advance_by(Duration::from_secs_f32(site.build_seconds));
+    app.update();
+
+    assert_eq!(sites.iter(&app.world).count(), 0);
+    let events = app.world.resource::<Events<ConstructionCompletedEvent>>();
+    let completed = events.get_reader().read(events).next().unwrap();
+    let outpost = app.world.get::<Station>(completed.station).unwrap();
+    assert_eq!(outpost.id, 1);
+    assert_eq!(outpost.name, "Outpost");
+    assert_eq!(outpost.services.len(), 3);
+}
+
+#[test]
+fn finished_sites_number_services_after_existing_ones() {
+    let mut app = construction_app();
+    let capital = spawn_market_station(&mut app);
+    app.world
+        .get_mut::<Station>(capital)
+        .unwrap()
+        .add_service(StationServices::Dock(Dock::new(
+            3,
+            "Docking Bay 1".to_string(),
+            20,
+        )))
+        .unwrap();
+    request(
+        &mut app,
+        EMPIRE,
+        1,
+        ConstructionProject::outpost("Outpost".to_string()),
+    );
+    app.update();
+
+    let mut sites = app.world.query::<&ConstructionSite>();
+    let build_seconds = sites.single(&app.world).build_seconds;
+    app.world
+        .resource_mut::<Time>()
+        .advance_by(Duration::from_secs_f32(build_seconds));
+    app.update();
+
+    let ids = |station: &Station| -> Vec<u32> {
+        station
+            .services
+            .iter()
+            .map(|service| service.id())
+            .collect()
+    };
+    let in_use = ids(app.world.get::<Station>(capital).unwrap());
+    assert_eq!(in_use, vec![0, 3]);
+
+    let events = app.world.resource::<Events<ConstructionCompletedEvent>>();
+    let completed = events.get_reader().read(events).next().unwrap();
+    let built = ids(app.world.get::<Station>(completed.station).unwrap());
+    assert_eq!(built, vec![4, 5, 6]);
+}
@@ -0,0 +1,92 @@
+use ascendancy_lib::items::inventory::{Inventory, InventoryError, InventoryTransaction};
+use ascendancy_lib::items::{ItemDefinition, ItemId};
+
+fn item(key: &str, volume_per_unit: f32, fluid: bool) -> ItemDefinition {
+    ItemDefinition {
+        id: ItemId::from_key(key),
+        key: key.to_string(),
+        volume_per_unit,
+        fluid,
+        category: "Test".to_string(),
+        base_price: 10.0,
+    }
+}
+
+#[test]
+fn solid_and_fluid_storage_are_separate() {
+    let ore = item("raw_ore", 2.0, false);
+    let water = item("water", 1.0, true);
+    let mut inventory = Inventory::new(10.0, 5.0);
+
+    inventory.add(&ore, 5).unwrap();
+    inventory.add(&water, 5).unwrap();
+    assert_eq!(inventory.room_for(&ore), 0);
+    assert_eq!(inventory.room_for(&water), 0);
+
+    assert!(matches!(
+        inventory.add(&ore, 1),
+        Err(InventoryError::InsufficientSpace { fluid: false, .. })
+    ));
+    assert_eq!(inventory.quantity(ore.id), 5);
+}
+
+#[test]
+fn failed_transactions_change_nothing() {
+    let ore = item("raw_ore", 1.0, false);
+    let cells = item("energy_cells", 1.0, false);
+    let mut inventory = Inventory::new(10.0, 0.0);
+    inventory.add(&ore, 4).unwrap();
+
+    let result = inventory.apply(InventoryTransaction::new().add(&cells, 2).remove(ore.id, 5));
+    assert!(matches!(
+        result,
+        Err(InventoryError::InsufficientStock {
+            required: 5,
+            available: 4,
+            ..
+        })
+    ));
+    assert_eq!(inventory.quantity(ore.id), 4);
+    assert_eq!(inventory.quantity(cells.id), 0);
+
+    inventory
+        .apply(InventoryTransaction::new().add(&cells, 2).remove(ore.id, 4))
+        .unwrap();
+    assert_eq!(inventory.quantity(ore.id), 0);
+    assert_eq!(inventory.quantity(cells.id), 2);
+}
+
+#[test]
+fn overflowing_a_stack_changes_nothing() {
+    let data = item("data_cores", 0.000001, false);
+    let cells = item("energy_cells", 1.0, false);
+    let mut inventory = Inventory::new(10000.0, 0.0);
+    inventory.add(&data, u32::MAX - 1).unwrap();
+
+    let result = inventory.apply(InventoryTransaction::new().add(&cells, 2).add(&data, 2));
+    assert_eq!(
+        result,
+        Err(InventoryError::QuantityOverflow {
+            item: data.id,
+            held: u32::MAX - 1,
+            added: 2,
+        })
+    );
+    assert_eq!(inventory.quantity(data.id), u32::MAX - 1);
+    assert_eq!(inventory.quantity(cells.id), 0);
+}
+
+#[test]
+fn transfers_are_atomic() {
+    let ore = item("raw_ore", 1.0, false);
+    let mut station = Inventory::new(100.0, 0.0);
+    let mut ship = Inventory::new(3.0, 0.0);
+    station.add(&ore, 10).unwrap();
+
+    assert!(Inventory::transfer(&mut station, &mut ship, &ore, 5).is_err());
+    assert_eq!(station.quantity(ore.id), 10);
+
+    Inventory::transfer(&mut station, &mut ship, &ore, 3).unwrap();
+    assert_eq!(station.quantity(ore.id), 7);
+    assert_eq!(ship.quantity(ore.id), 3);
+}
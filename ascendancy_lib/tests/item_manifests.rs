@@ -0,0 +1,251 @@
+use std::path::PathBuf;
+
+use ascendancy_lib::items::manifest::{ItemManifest, ManifestError};
+use ascendancy_lib::items::{ItemId, ItemRegistry};
+use serde_json::{json, Value};
+
+fn manifest(name: &str, json: &str) -> (PathBuf, Result<ItemManifest, ManifestError>) {
+    let path = PathBuf::from(name);
+    let manifest = ItemManifest::from_json(&path, json);
+    (path, manifest)
+}
+
+#[test]
+fn built_in_manifest_defines_base_items() {
+    let registry = ItemRegistry::built_in();
+    assert!(registry.contains(ItemId::RAW_ORE));
+    assert!(registry.contains(ItemId::ENERGY_CELLS));
+    assert_eq!(registry.id("raw_ore"), Some(ItemId::RAW_ORE));
+}
+
+#[test]
+fn later_manifests_extend_and_override() {
+    let (registry, errors) = ItemRegistry::from_manifests([
+        manifest(
+            "base.item_manifest.json",
+            r#"{"items": {"raw_ore": {"volume_per_unit": 1, "fluid": false, "category": "Ore"}}}"#,
+        ),
+        manifest(
+            "mod.item_manifest.json",
+            r#"{"items": {
+                "raw_ore": {"volume_per_unit": 2, "fluid": false, "category": "Ore"},
+                "hydrogen": {"volume_per_unit": 0.5, "fluid": true, "category": "Gas"}
+            }}"#,
+        ),
+    ]);
+
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(registry.len(), 2);
+    assert_eq!(registry.get(ItemId::RAW_ORE).unwrap().volume_per_unit, 2.0);
+    assert!(registry.get(ItemId::from_key("hydrogen")).unwrap().fluid);
+}
+
+#[test]
+fn schema_violations_are_reported() {
+    let (registry, errors) = ItemRegistry::from_manifests([
+        manifest(
+            "bad.item_manifest.json",
+            r#"{"items": {"raw_ore": {"volume_per_unit": 1, "fluid": false}}}"#,
+        ),
+        manifest(
+            "invalid.item_manifest.json",
+            r#"{"items": {
+                "Bad Key": {"volume_per_unit": 1, "fluid": false, "category": "Ore"},
+                "weightless": {"volume_per_unit": 0, "fluid": false, "category": "Ore"}
+            }}"#,
+        ),
+    ]);
+
+    assert!(registry.is_empty());
+    assert!(matches!(errors[0], ManifestError::Malformed(..)));
+    assert!(matches!(errors[1], ManifestError::InvalidItem { .. }));
+    assert!(matches!(errors[2], ManifestError::InvalidItem { .. }));
+}
+
+/// The schema editors check manifests against, which must agree with the rules the game enforces
+fn schema() -> Value {
+    serde_json::from_str(include_str!(
+        "../../ascendancy_game/manifests/schema/items.schema.json"
+    ))
+    .unwrap()
+}
+
+/// Whether a manifest holding just the given item is accepted
+fn item_accepted(key: &str, item: &Value) -> bool {
+    let json = json!({ "items": { key: item } }).to_string();
+    let (registry, errors) =
+        ItemRegistry::from_manifests([manifest("schema.item_manifest.json", &json)]);
+    errors.is_empty() && registry.len() == 1
+}
+
+/// Whether a manifest holding raw ore and the given recipe is accepted
+fn recipe_accepted(key: &str, recipe: &Value) -> bool {
+    let json = json!({
+        "items": { "raw_ore": { "volume_per_unit": 1, "fluid": false, "category": "Ore" } },
+        "recipes": { key: recipe },
+    })
+    .to_string();
+    let (registry, errors) =
+        ItemRegistry::from_manifests([manifest("schema.item_manifest.json", &json)]);
+    errors.is_empty() && registry.recipe(key).is_some()
+}
+
+/// Checks `accepted` against the properties an object schema lists, starting from a valid object that sets every one.
+///
+/// Only required properties can be left out, unknown properties are refused, and numbers must respect their bounds.
+fn check_object_rules(schema: &Value, valid: &Value, accepted: impl Fn(&Value) -> bool) {
+    let properties = schema["properties"].as_object().unwrap();
+    let required: Vec<&str> = schema["required"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|property| property.as_str().unwrap())
+        .collect();
+    assert_eq!(
+        properties.keys().collect::<Vec<_>>(),
+        valid.as_object().unwrap().keys().collect::<Vec<_>>(),
+        "the valid object should set every property the schema lists"
+    );
+    assert!(accepted(valid));
+
+    for (property, rules) in properties {
+        let mut without = valid.clone();
+        without.as_object_mut().unwrap().remove(property);
+        assert_eq!(
+            accepted(&without),
+            !required.contains(&property.as_str()),
+            "leaving out {}",
+            property
+        );
+
+        let with = |value: Value| {
+            let mut object = valid.clone();
+            object[property] = value;
+            accepted(&object)
+        };
+        let number = |value: f64| match rules["type"].as_str() {
+            Some("integer") => json!(value as i64),
+            _ => json!(value),
+        };
+        if let Some(minimum) = rules["exclusiveMinimum"].as_f64() {
+            assert!(
+                !with(number(minimum)),
+                "{} at its exclusive minimum",
+                property
+            );
+            assert!(
+                with(number(minimum + 1.0)),
+                "{} above its minimum",
+                property
+            );
+        }
+        if let Some(minimum) = rules["minimum"].as_f64() {
+            assert!(with(number(minimum)), "{} at its minimum", property);
+            assert!(
+                !with(number(minimum - 1.0)),
+                "{} below its minimum",
+                property
+            );
+        }
+    }
+
+    assert_eq!(schema["additionalProperties"], false);
+    let mut unknown = valid.clone();
+    unknown["unknown_property"] = json!(1);
+    assert!(!accepted(&unknown), "unknown properties should be refused");
+}
+
+/// Checks `accepted` against the key pattern the schema gives
+fn check_key_rules(pattern: &Value, accepted: impl Fn(&str) -> bool) {
+    assert_eq!(*pattern, "^[a-z0-9_]+$");
+    for key in ["ore", "ore_2", "_"] {
+        assert!(accepted(key), "key {:?}", key);
+    }
+    for key in ["", "Ore", "ore-2", "ore 2", "öre"] {
+        assert!(!accepted(key), "key {:?}", key);
+    }
+}
+
+#[test]
+fn item_rules_match_the_schema() {
+    let schema = schema();
+    let items = &schema["properties"]["items"];
+    let entry = &items["additionalProperties"];
+    let valid = json!({
+        "volume_per_unit": 1.0,
+        "fluid": false,
+        "category": "Ore",
+        "base_price": 5.0,
+    });
+
+    check_object_rules(entry, &valid, |item| item_accepted("raw_ore", item));
+    check_key_rules(&items["propertyNames"]["pattern"], |key| {
+        item_accepted(key, &valid)
+    });
+
+    // A category needs more than whitespace
+    let category = &entry["properties"]["category"];
+    assert_eq!(category["minLength"], 1);
+    assert_eq!(category["pattern"], "\\S");
+    for (name, expected) in [("", false), ("  ", false), ("Ore", true)] {
+        let mut item = valid.clone();
+        item["category"] = json!(name);
+        assert_eq!(
+            item_accepted("raw_ore", &item),
+            expected,
+            "category {:?}",
+            name
+        );
+    }
+}
+
+#[test]
+fn recipe_rules_match_the_schema() {
+    let schema = schema();
+    let recipes = &schema["properties"]["recipes"];
+    let entry = &recipes["additionalProperties"];
+    let valid = json!({
+        "inputs": { "raw_ore": 2 },
+        "outputs": { "raw_ore": 1 },
+        "energy": 5.0,
+        "cycle_seconds": 10.0,
+    });
+
+    check_object_rules(entry, &valid, |recipe| recipe_accepted("smelting", recipe));
+    check_key_rules(&recipes["propertyNames"]["pattern"], |key| {
+        recipe_accepted(key, &valid)
+    });
+
+    // Every input and output is used or made at least once, and something is always made
+    for side in ["inputs", "outputs"] {
+        assert_eq!(
+            entry["properties"][side]["additionalProperties"]["minimum"],
+            1
+        );
+        let mut recipe = valid.clone();
+        recipe[side] = json!({ "raw_ore": 0 });
+        assert!(!recipe_accepted("smelting", &recipe), "0 {}", side);
+    }
+    assert_eq!(entry["properties"]["outputs"]["minProperties"], 1);
+    let mut recipe = valid.clone();
+    recipe["outputs"] = json!({});
+    assert!(!recipe_accepted("smelting", &recipe));
+}
+
+#[test]
+fn manifest_rules_match_the_schema() {
+    let schema = schema();
+    assert_eq!(schema["required"], json!(["items"]));
+    assert_eq!(schema["additionalProperties"], false);
+
+    let accepted = |json: &str| {
+        let (_, errors) =
+            ItemRegistry::from_manifests([manifest("schema.item_manifest.json", json)]);
+        errors.is_empty()
+    };
+    assert!(accepted(
+        r#"{"$schema": "schema/items.schema.json", "items": {}}"#
+    ));
+    assert!(!accepted(r#"{"recipes": {}}"#));
+    assert!(!accepted(r#"{"items": {}, "unknown_property": 1}"#));
+}
@@ -0,0 +1,166 @@
+use ascendancy_lib::agent::agent::Wallet;
+use ascendancy_lib::agent::cargo::CargoHold;
+use ascendancy_lib::items::inventory::Inventory;
+use ascendancy_lib::items::{ItemId, ItemRegistry};
+use ascendancy_lib::structures::services::market::{Market, MarketError, Trader};
+use bevy::prelude::Entity;
+
+fn stocked_station(items: &ItemRegistry, ore: u32) -> Inventory {
+    let mut inventory = Inventory::new(10000.0, 10000.0);
+    inventory
+        .add(items.get(ItemId::RAW_ORE).unwrap(), ore)
+        .unwrap();
+    inventory
+}
+
+#[test]
+fn scarce_goods_cost_more() {
+    let items = ItemRegistry::built_in();
+    let mut scarce = Market::new(0, &items);
+    let mut plentiful = Market::new(0, &items);
+
+    scarce.trade(&mut stocked_station(&items, 10));
+    plentiful.trade(&mut stocked_station(&items, 5000));
+
+    let scarce_ask = scarce.best_ask(ItemId::RAW_ORE).unwrap();
+    let plentiful_ask = plentiful.best_ask(ItemId::RAW_ORE).unwrap();
+    assert!(scarce_ask.price > plentiful_ask.price);
+    assert_eq!(scarce_ask.quantity, 10);
+}
+
+#[test]
+fn agents_buy_from_the_station() {
+    let items = ItemRegistry::built_in();
+    let mut market = Market::new(0, &items);
+    let mut station = stocked_station(&items, 100);
+    let agent = Entity::from_raw(7);
+    let mut wallet = Wallet { money: 1000.0 };
+
+    market.trade(&mut station);
+    let ask = market.best_ask(ItemId::RAW_ORE).unwrap();
+    market
+        .place_buy_order(agent, ItemId::RAW_ORE, 10, ask.price * 2.0, &mut wallet)
+        .unwrap();
+    market.trade(&mut station);
+
+    assert_eq!(station.quantity(ItemId::RAW_ORE), 90);
+
+    let trades = market.take_trades();
+    assert_eq!(trades.len(), 1);
+    assert_eq!(trades[0].buyer, Trader::Agent(agent));
+    assert_eq!(trades[0].seller, Trader::Station);
+    let cost = trades[0].price * 10.0;
+    assert!((market.funds - cost).abs() < 0.01);
+
+    let fills = market.take_fills();
+    assert_eq!(fills[0].goods, Some((ItemId::RAW_ORE, 10)));
+    // The difference between the limit price and the trade price is refunded
+    let spent = 1000.0 - wallet.money - fills[0].credits;
+    assert!((spent - cost).abs() < 0.01);
+}
+
+#[test]
+fn agent_orders_match_each_other() {
+    let items = ItemRegistry::built_in();
+    let mut market = Market::new(0, &items);
+    let mut station = Inventory::new(0.0, 0.0);
+    let (buyer, seller) = (Entity::from_raw(1), Entity::from_raw(2));
+    let mut wallet = Wallet { money: 100.0 };
+    let mut cargo = CargoHold::new(100.0, 0.0);
+    cargo.load(items.get(ItemId::RAW_ORE).unwrap(), 5).unwrap();
+
+    market
+        .place_sell_order(seller, ItemId::RAW_ORE, 5, 4.0, &mut cargo)
+        .unwrap();
+    assert_eq!(cargo.quantity(ItemId::RAW_ORE), 0);
+    assert_eq!(market.best_ask(ItemId::RAW_ORE).unwrap().price, 4.0);
+
+    market
+        .place_buy_order(buyer, ItemId::RAW_ORE, 5, 6.0, &mut wallet)
+        .unwrap();
+    market.trade(&mut station);
+
+    let trades = market.take_trades();
+    assert_eq!(trades[0].price, 4.0);
+    assert!(market.best_ask(ItemId::RAW_ORE).is_none());
+}
+
+#[test]
+fn orders_need_escrow() {
+    let items = ItemRegistry::built_in();
+    let mut market = Market::new(0, &items);
+    let agent = Entity::from_raw(1);
+
+    let result =
+        market.place_buy_order(agent, ItemId::RAW_ORE, 10, 5.0, &mut Wallet { money: 1.0 });
+    assert!(matches!(result, Err(MarketError::InsufficientFunds { .. })));
+
+    let result = market.place_sell_order(
+        agent,
+        ItemId::RAW_ORE,
+        1,
+        5.0,
+        &mut CargoHold::new(1.0, 0.0),
+    );
+    assert!(matches!(result, Err(MarketError::Inventory(_))));
+}
+
+#[test]
+fn sales_tax_is_withheld_from_the_seller() {
+    let items = ItemRegistry::built_in();
+    let mut market = Market::new(0, &items);
+    market.tax_rate = 0.1;
+    let mut station = Inventory::new(0.0, 0.0);
+    let (buyer, seller) = (Entity::from_raw(1), Entity::from_raw(2));
+    let mut cargo = CargoHold::new(100.0, 0.0);
+    cargo.load(items.get(ItemId::RAW_ORE).unwrap(), 5).unwrap();
+
+    market
+        .place_sell_order(seller, ItemId::RAW_ORE, 5, 4.0, &mut cargo)
+        .unwrap();
+    market
+        .place_buy_order(buyer, ItemId::RAW_ORE, 5, 4.0, &mut Wallet { money: 100.0 })
+        .unwrap();
+    market.trade(&mut station);
+
+    let fills = market.take_fills();
+    let proceeds = fills.iter().find(|fill| fill.agent == seller).unwrap();
+    assert!((proceeds.credits - 18.0).abs() < 0.01);
+    assert_eq!(market.take_taxes(), 2);
+    assert_eq!(market.take_taxes(), 0);
+}
+
+#[test]
+fn self_matching_orders_do_not_block_the_book() {
+    let items = ItemRegistry::built_in();
+    let mut market = Market::new(0, &items);
+    let mut station = Inventory::new(0.0, 0.0);
+    let (trader, seller) = (Entity::from_raw(1), Entity::from_raw(2));
+    let mut cargo = CargoHold::new(100.0, 0.0);
+    cargo.load(items.get(ItemId::RAW_ORE).unwrap(), 10).unwrap();
+
+    // The trader's own bid and ask cross, but cannot fill each other
+    market
+        .place_sell_order(trader, ItemId::RAW_ORE, 5, 3.0, &mut cargo)
+        .unwrap();
+    market
+        .place_buy_order(
+            trader,
+            ItemId::RAW_ORE,
+            5,
+            6.0,
+            &mut Wallet { money: 100.0 },
+        )
+        .unwrap();
+    market
+        .place_sell_order(seller, ItemId::RAW_ORE, 5, 4.0, &mut cargo)
+        .unwrap();
+    market.trade(&mut station);
+
+    let trades = market.take_trades();
+    assert_eq!(trades.len(), 1);
+    assert_eq!(trades[0].buyer, Trader::Agent(trader));
+    assert_eq!(trades[0].seller, Trader::Agent(seller));
+    assert_eq!(trades[0].price, 4.0);
+    assert_eq!(market.best_ask(ItemId::RAW_ORE).unwrap().price, 3.0);
+}
@@ -0,0 +1,90 @@
+use std::time::Duration;
+
+use ascendancy_lib::items::{ItemId, ItemRegistry};
+use ascendancy_lib::solar_system::resources::{ResourceField, ResourceFields, ResourceKind};
+use ascendancy_lib::solar_system::SolarSystem;
+use ascendancy_lib::structures::services::mining::{run_mining, Mining};
+use ascendancy_lib::structures::services::StationServices;
+use ascendancy_lib::structures::station::{run_active_services, Station};
+use bevy::prelude::*;
+
+fn mining_app(field_yield: f32) -> (App, Entity, Entity) {
+    let mut station = Station::new(1, "Mining Outpost".to_string(), 7);
+    station
+        .add_service(StationServices::Mining(Mining::new(
+            0,
+            "Ore Mining Rig".to_string(),
+            ResourceKind::Ore,
+        )))
+        .unwrap();
+    station.resource_manager.energy = 10000.0;
+
+    let mut system = SolarSystem::new_placeholder();
+    system.attributes.id = 7;
+    let fields = ResourceFields(vec![
+        ResourceField::new(ResourceKind::Gas, Vec2::ZERO, 5000.0, 0.0),
+        ResourceField::new(ResourceKind::Ore, Vec2::ZERO, field_yield, 0.0),
+    ]);
+
+    let mut app = App::new();
+    app.init_resource::<Time>()
+        .insert_resource(ItemRegistry::built_in())
+        .add_systems(Update, (run_active_services, run_mining).chain());
+    let station = app.world.spawn(station).id();
+    let system = app.world.spawn((system, fields)).id();
+    (app, station, system)
+}
+
+fn run_cycle(app: &mut App) {
+    app.world
+        .resource_mut::<Time>()
+        .advance_by(Duration::from_secs(5));
+    app.update();
+}
+
+#[test]
+fn fields_run_dry_and_regrow() {
+    let mut field = ResourceField::new(ResourceKind::Ore, Vec2::ZERO, 100.0, 2.0);
+    assert_eq!(field.item(), ItemId::RAW_ORE);
+
+    assert_eq!(field.extract(60), 60);
+    assert_eq!(field.extract(60), 40);
+    assert_eq!(field.available(), 0);
+
+    field.regenerate(10.0);
+    assert_eq!(field.available(), 20);
+
+    // Fields never regrow past their maximum
+    field.regenerate(1000.0);
+    assert_eq!(field.available(), 100);
+}
+
+#[test]
+fn richest_field_of_a_kind_is_chosen() {
+    let mut fields = ResourceFields(vec![
+        ResourceField::new(ResourceKind::Ore, Vec2::ZERO, 100.0, 0.0),
+        ResourceField::new(ResourceKind::Gas, Vec2::ZERO, 900.0, 0.0),
+        ResourceField::new(ResourceKind::Ore, Vec2::ZERO, 300.0, 0.0),
+    ]);
+
+    let (index, _) = fields.richest(ResourceKind::Ore).unwrap();
+    assert_eq!(index, 2);
+}
+
+#[test]
+fn mining_rigs_fill_station_stores_from_fields() {
+    let (mut app, station, system) = mining_app(30.0);
+
+    run_cycle(&mut app);
+    run_cycle(&mut app);
+
+    let station = app.world.get::<Station>(station).unwrap();
+    let inventory = &station.resource_manager.inventory;
+    assert_eq!(inventory.quantity(ItemId::RAW_ORE), 30);
+    assert_eq!(inventory.quantity(ItemId::HYDROGEN), 0);
+
+    // The ore field is exhausted and the gas cloud is untouched
+    let fields = app.world.get::<ResourceFields>(system).unwrap();
+    assert_eq!(fields.0[1].available(), 0);
+    assert_eq!(fields.0[0].available(), 5000);
+}
@@ -0,0 +1,113 @@
+use ascendancy_lib::agent::agent::{Agent, AgentRole};
+use ascendancy_lib::agent::combat::ShipDestroyedEvent;
+use ascendancy_lib::agent::pathfinding::SystemGraph;
+use ascendancy_lib::agent::piracy::{update_piracy_risk, PiracyRisk};
+use ascendancy_lib::faction::attributes::FactionID;
+use ascendancy_lib::faction::pirates::PIRATES;
+use ascendancy_lib::simulation::SimulationClock;
+use ascendancy_lib::solar_system::attributes::SystemAttributes;
+use ascendancy_lib::solar_system::SolarSystem;
+use ascendancy_lib::structures::stargate::Stargate;
+use bevy::prelude::*;
+
+const EMPIRE: FactionID = FactionID { id: 0 };
+
+fn system(id: u32) -> SolarSystem {
+    SolarSystem {
+        attributes: SystemAttributes {
+            id,
+            name: format!("System {}", id),
+            owner: EMPIRE,
+        },
+        ..default()
+    }
+}
+
+fn gate(id: u32, origin: u32, destination: u32) -> Stargate {
+    Stargate {
+        id,
+        distance: 1,
+        origin_system_id: origin,
+        destination_system_id: destination,
+        ..default()
+    }
+}
+
+#[test]
+fn risk_fades_and_adds_up_along_a_route() {
+    let mut risk = PiracyRisk::default();
+    risk.report(2, 0.5);
+    risk.report(3, 0.8);
+    risk.report(3, 0.8);
+    assert_eq!(risk.risk(3), 1.0);
+
+    let path = [gate(0, 1, 2), gate(1, 2, 4)];
+    assert_eq!(risk.route_risk(&path), 0.5);
+    let path = [gate(0, 1, 2), gate(1, 2, 3)];
+    assert_eq!(risk.route_risk(&path), 1.0);
+
+    risk.fade(50.0);
+    assert!((risk.risk(2) - 0.25).abs() < 0.001);
+    risk.fade(100.0);
+    assert_eq!(risk.risk(2), 0.0);
+    assert_eq!(risk.route_risk(std::iter::empty()), 0.0);
+}
+
+#[test]
+fn pirate_sightings_and_kills_raise_the_risk() {
+    let mut app = App::new();
+    app.init_resource::<SimulationClock>()
+        .init_resource::<PiracyRisk>()
+        .add_event::<ShipDestroyedEvent>()
+        .add_systems(Update, update_piracy_risk);
+
+    let mut agent = Agent::new(0, "Blackbeard".to_string(), &system(1));
+    agent.faction = PIRATES;
+    let pirate = app.world.spawn((agent, AgentRole::Pirate)).id();
+    app.world.spawn((
+        Agent::new(1, "Hauler".to_string(), &system(2)),
+        AgentRole::Trader,
+    ));
+
+    *app.world.resource_mut::<SimulationClock>() = SimulationClock::from_seconds(10.0);
+    app.update();
+    let risk = app.world.resource::<PiracyRisk>();
+    assert!((risk.risk(1) - 0.05).abs() < 0.001);
+    assert_eq!(risk.risk(2), 0.0);
+
+    app.world.send_event(ShipDestroyedEvent {
+        ship: Entity::PLACEHOLDER,
+        faction: EMPIRE,
+        attacker: pirate,
+        attacker_faction: PIRATES,
+    });
+    app.update();
+    assert!((app.world.resource::<PiracyRisk>().risk(1) - 0.3).abs() < 0.001);
+}
+
+#[test]
+fn chokepoints_carry_the_most_traffic() {
+    // Three systems that only connect through a fourth in the middle
+    let systems = [system(1), system(2), system(3), system(4)];
+    let gates = [
+        gate(0, 1, 2),
+        gate(1, 2, 1),
+        gate(2, 2, 3),
+        gate(3, 3, 2),
+        gate(4, 2, 4),
+        gate(5, 4, 2),
+    ];
+    let mut graph = SystemGraph::from_systems_and_gates(systems.iter(), gates.iter());
+
+    let traffic = graph.through_traffic();
+    // Every route between the three outer systems passes through the middle one
+    assert_eq!(traffic[&2], 6);
+    assert_eq!(traffic[&1], 0);
+    assert_eq!(traffic[&3], 0);
+    assert_eq!(traffic[&4], 0);
+
+    // The cached counts are forgotten once the gates change
+    graph.remove_gate(4);
+    graph.remove_gate(5);
+    assert_eq!(graph.through_traffic()[&2], 2);
+}
@@ -0,0 +1,75 @@
+use std::path::{Path, PathBuf};
+
+use ascendancy_lib::faction::attributes::FactionID;
+use ascendancy_lib::save::{read_save_file, SaveGame};
+use ascendancy_lib::simulation::{HeadlessPlugin, SimulationPlugins};
+use ascendancy_lib::world_gen::GalaxyConfig;
+use ascendancy_lib::GameState;
+use bevy::prelude::*;
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+#[test]
+fn version_1_save_is_migrated() {
+    let save = read_save_file(&fixture("save_v1.json")).unwrap();
+
+    assert_eq!(save.galaxy_seed, Some(42));
+    assert_eq!(save.solar_systems.len(), 2);
+    assert_eq!(save.stargates.len(), 2);
+    assert_eq!(save.stations.len(), 1);
+    assert_eq!(save.agents.len(), 2);
+    assert_eq!(save.factions.len(), 2);
+
+    let ada = save
+        .agents
+        .iter()
+        .find(|saved| saved.agent.id == 0)
+        .unwrap();
+    let bram = save
+        .agents
+        .iter()
+        .find(|saved| saved.agent.id == 1)
+        .unwrap();
+    assert_ne!(ada.entity, bram.entity);
+    assert_eq!(bram.agent.faction, FactionID { id: 2 });
+    assert!(bram.cargo.is_empty());
+
+    // The copy of Ada in the dock becomes a reference to the saved agent
+    let dock = save.stations[0].component.dock().unwrap();
+    assert_eq!(dock.docked_ships, vec![ada.entity]);
+    assert!(dock.queue.is_empty());
+}
+
+#[test]
+fn version_1_save_restores() {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .insert_resource(GalaxyConfig::default().with_seed(7))
+        .add_plugins((SimulationPlugins, HeadlessPlugin::default()));
+
+    for _ in 0..10 {
+        app.update();
+    }
+
+    read_save_file(&fixture("save_v1.json"))
+        .unwrap()
+        .restore(&mut app.world);
+    app.update();
+
+    assert_eq!(
+        app.world.resource::<State<GameState>>().get(),
+        &GameState::Playing
+    );
+    let restored = SaveGame::capture(&mut app.world);
+    assert_eq!(restored.galaxy_seed, Some(42));
+    assert_eq!(restored.solar_systems.len(), 2);
+    assert_eq!(restored.agents.len(), 2);
+
+    // Markets saved before listings existed trade every known item once restored
+    let market = restored.stations[0].component.market().unwrap();
+    assert!(market.listings().next().is_some());
+}
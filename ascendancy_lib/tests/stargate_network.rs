@@ -0,0 +1,40 @@
+use ascendancy_lib::simulation::{HeadlessPlugin, SimulationPlugins};
+use ascendancy_lib::solar_system::SolarSystem;
+use ascendancy_lib::world_gen::{GalaxyConfig, GateNetworkReport};
+use bevy::prelude::*;
+
+fn generate(config: GalaxyConfig) -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .insert_resource(config)
+        .add_plugins((SimulationPlugins, HeadlessPlugin::default()));
+    for _ in 0..10 {
+        app.update();
+    }
+    app
+}
+
+#[test]
+fn every_system_can_reach_every_other() {
+    for seed in [1, 7, 42] {
+        let mut app = generate(GalaxyConfig::default().with_seed(seed));
+        let systems = app.world.query::<&SolarSystem>().iter(&app.world).count();
+
+        let report = app.world.resource::<GateNetworkReport>();
+        assert!(report.is_connected(), "seed {}: {:?}", seed, report);
+        assert_eq!(report.systems, systems);
+        assert!(report.gates >= systems - 1);
+    }
+}
+
+#[test]
+fn without_loops_the_network_is_a_tree() {
+    let mut config = GalaxyConfig::default().with_seed(42);
+    config.average_gate_degree = 4.0;
+    config.max_gate_length = 0.0;
+    let app = generate(config);
+
+    let report = app.world.resource::<GateNetworkReport>();
+    assert!(report.is_connected());
+    assert_eq!(report.gates, report.systems - 1);
+}
@@ -0,0 +1,325 @@
+use ascendancy_lib::agent::agent::Agent;
+use std::collections::HashMap;
+
+use ascendancy_lib::agent::pathfinding::{
+    resolve_path_requests, sync_system_graph, GraphError, PathRequest, RoutePolicy, SystemGraph,
+};
+use ascendancy_lib::agent::piracy::PiracyRisk;
+use ascendancy_lib::faction::attributes::FactionID;
+use ascendancy_lib::solar_system::attributes::SystemAttributes;
+use ascendancy_lib::solar_system::SolarSystem;
+use ascendancy_lib::structures::stargate::Stargate;
+use bevy::prelude::*;
+
+fn system(id: u32) -> SolarSystem {
+    owned_system(id, 0)
+}
+
+fn owned_system(id: u32, owner: u8) -> SolarSystem {
+    SolarSystem {
+        attributes: SystemAttributes {
+            id,
+            name: format!("System {}", id),
+            owner: FactionID { id: owner },
+        },
+        ..default()
+    }
+}
+
+fn gate(id: u32, origin: u32, destination: u32, distance: u32) -> Stargate {
+    Stargate {
+        id,
+        distance,
+        origin_system_id: origin,
+        destination_system_id: destination,
+        ..default()
+    }
+}
+
+fn gate_ids(path: &[Stargate]) -> Vec<u32> {
+    path.iter().map(|gate| gate.id).collect()
+}
+
+/// The gates of the route from system 1 to system 3 under a policy
+fn route(graph: &SystemGraph, policy: RoutePolicy) -> Vec<u32> {
+    gate_ids(
+        &graph
+            .get_pathfinding_between(&system(1), &system(3), policy)
+            .unwrap(),
+    )
+}
+
+/// A short way from system 1 to system 3 through system 2, and a long way round through system 4
+fn two_routes() -> ([SolarSystem; 4], [Stargate; 4]) {
+    (
+        [system(1), system(2), system(3), system(4)],
+        [
+            gate(0, 1, 2, 1),
+            gate(1, 2, 3, 1),
+            gate(2, 1, 4, 5),
+            gate(3, 4, 3, 5),
+        ],
+    )
+}
+
+#[test]
+fn closed_and_removed_gates_are_routed_around() {
+    let (systems, gates) = two_routes();
+    let mut graph = SystemGraph::from_systems_and_gates(systems.iter(), gates.iter());
+    let path = graph
+        .get_pathfinding_between(&system(1), &system(3), RoutePolicy::Shortest)
+        .unwrap();
+    assert_eq!(gate_ids(&path), vec![0, 1]);
+
+    let mut closed = gate(1, 2, 3, 1);
+    closed.deactivate();
+    graph.add_gate(closed);
+    assert!(!graph.is_path_open(&path));
+    let path = graph
+        .get_pathfinding_between(&system(1), &system(3), RoutePolicy::Shortest)
+        .unwrap();
+    assert_eq!(gate_ids(&path), vec![2, 3]);
+
+    graph.remove_gate(3);
+    assert!(!graph.contains_gate(3));
+    assert!(graph.contains_gate(2));
+    assert_eq!(
+        graph.get_pathfinding_between(&system(1), &system(3), RoutePolicy::Shortest),
+        Err(GraphError::NoPath)
+    );
+
+    graph.add_gate(gate(1, 2, 3, 1));
+    assert!(graph.is_path_open(&[gate(0, 1, 2, 1), gate(1, 2, 3, 1)]));
+}
+
+#[test]
+fn removing_a_system_removes_its_gates() {
+    let (systems, gates) = two_routes();
+    let mut graph = SystemGraph::from_systems_and_gates(systems.iter(), gates.iter());
+
+    graph.remove_system(2);
+    assert!(!graph.contains_gate(0));
+    assert!(!graph.contains_gate(1));
+    assert!(graph.gate_by_id(&2).is_some());
+
+    let path = graph
+        .get_pathfinding_between(&system(1), &system(3), RoutePolicy::Shortest)
+        .unwrap();
+    assert_eq!(gate_ids(&path), vec![2, 3]);
+}
+
+#[test]
+fn route_policies_choose_between_the_ways_round() {
+    // The short way runs through system 2, held by another faction, and the long way through system 4
+    let systems = [system(1), owned_system(2, 1), system(3), system(4)];
+    let gates = [
+        gate(0, 1, 2, 2),
+        gate(1, 2, 3, 2),
+        gate(2, 1, 4, 3),
+        gate(3, 4, 3, 3),
+    ];
+    let mut graph = SystemGraph::from_systems_and_gates(systems.iter(), gates.iter());
+    graph.locate_systems([
+        (1, Vec2::new(0.0, 0.0)),
+        (2, Vec2::new(2.0, 0.0)),
+        (3, Vec2::new(4.0, 0.0)),
+        (4, Vec2::new(2.0, 2.0)),
+    ]);
+
+    assert_eq!(route(&graph, RoutePolicy::Shortest), vec![0, 1]);
+
+    let mut risk = PiracyRisk::default();
+    assert_eq!(route(&graph, RoutePolicy::Safest(&risk)), vec![0, 1]);
+    risk.report(2, 1.0);
+    assert_eq!(route(&graph, RoutePolicy::Safest(&risk)), vec![2, 3]);
+
+    let mut tolls = HashMap::new();
+    assert_eq!(
+        route(&graph, RoutePolicy::CheapestTolls(&tolls)),
+        vec![0, 1]
+    );
+    tolls.insert(FactionID { id: 1 }, 1.0);
+    assert_eq!(
+        route(&graph, RoutePolicy::CheapestTolls(&tolls)),
+        vec![2, 3]
+    );
+
+    assert_eq!(
+        route(&graph, RoutePolicy::AvoidFaction(FactionID { id: 1 })),
+        vec![2, 3]
+    );
+    assert_eq!(
+        graph.get_pathfinding_between(
+            &system(1),
+            &system(3),
+            RoutePolicy::AvoidFaction(FactionID { id: 0 })
+        ),
+        Err(GraphError::NoPath)
+    );
+}
+
+#[test]
+fn no_jump_is_shorter_than_the_straight_line() {
+    // The gates through system 2 claim to be short, but the system is far out of the way
+    let systems = [system(1), system(2), system(3), system(4)];
+    let gates = [
+        gate(0, 1, 2, 1),
+        gate(1, 2, 3, 1),
+        gate(2, 1, 4, 3),
+        gate(3, 4, 3, 3),
+    ];
+    let mut graph = SystemGraph::from_systems_and_gates(systems.iter(), gates.iter());
+    graph.locate_systems([
+        (1, Vec2::new(0.0, 0.0)),
+        (2, Vec2::new(0.0, 100.0)),
+        (3, Vec2::new(4.0, 0.0)),
+        (4, Vec2::new(2.0, 2.0)),
+    ]);
+
+    let path = graph
+        .get_pathfinding_between(&system(1), &system(3), RoutePolicy::Shortest)
+        .unwrap();
+    assert_eq!(gate_ids(&path), vec![2, 3]);
+}
+
+#[test]
+fn the_graph_follows_the_world_and_invalidates_paths() {
+    let mut app = App::new();
+    app.init_resource::<SystemGraph>()
+        .add_systems(Update, sync_system_graph);
+
+    let (systems, gates) = two_routes();
+    for system in systems {
+        app.world.spawn(system);
+    }
+    let gate_entities: Vec<Entity> = gates
+        .into_iter()
+        .map(|gate| app.world.spawn(gate).id())
+        .collect();
+    app.update();
+
+    let graph = app.world.resource::<SystemGraph>();
+    let path = graph
+        .get_pathfinding_between(&system(1), &system(3), RoutePolicy::Shortest)
+        .unwrap();
+    assert_eq!(gate_ids(&path), vec![0, 1]);
+
+    let mut agent = Agent::new(0, "Traveller".to_string(), &system(1));
+    agent.set_stargate_path(path);
+    let traveller = app.world.spawn(agent).id();
+    let mut agent = Agent::new(1, "Wanderer".to_string(), &system(1));
+    agent.set_stargate_path(vec![gate(2, 1, 4, 5)]);
+    let wanderer = app.world.spawn(agent).id();
+
+    app.world
+        .get_mut::<Stargate>(gate_entities[1])
+        .unwrap()
+        .deactivate();
+    app.update();
+
+    let agent = app.world.get::<Agent>(traveller).unwrap();
+    assert!(agent.stargate_path.is_invalidated());
+    let agent = app.world.get::<Agent>(wanderer).unwrap();
+    assert!(!agent.stargate_path.is_invalidated());
+    let graph = app.world.resource::<SystemGraph>();
+    let path = graph
+        .get_pathfinding_between(&system(1), &system(3), RoutePolicy::Shortest)
+        .unwrap();
+    assert_eq!(gate_ids(&path), vec![2, 3]);
+
+    app.world.despawn(gate_entities[3]);
+    app.world.spawn(gate(4, 4, 3, 2));
+    app.update();
+
+    let graph = app.world.resource::<SystemGraph>();
+    assert!(!graph.contains_gate(3));
+    let path = graph
+        .get_pathfinding_between(&system(1), &system(3), RoutePolicy::Shortest)
+        .unwrap();
+    assert_eq!(gate_ids(&path), vec![2, 4]);
+}
+
+#[test]
+fn cached_routes_are_forgotten_when_a_gate_changes() {
+    let (systems, gates) = two_routes();
+    let mut graph = SystemGraph::from_systems_and_gates(systems.iter(), gates.iter());
+    let path = graph.shortest_path(&system(1), &system(3)).unwrap();
+    assert_eq!(gate_ids(&path), vec![0, 1]);
+    let path = graph.shortest_path(&system(1), &system(2)).unwrap();
+    assert_eq!(gate_ids(&path), vec![0]);
+
+    let mut closed = gate(1, 2, 3, 1);
+    closed.deactivate();
+    graph.add_gate(closed);
+    let path = graph.shortest_path(&system(1), &system(3)).unwrap();
+    assert_eq!(gate_ids(&path), vec![2, 3]);
+
+    let path = graph.get_pathfinding_to_random_system(&system(1)).unwrap();
+    assert_eq!(path[0].origin_system_id, 1);
+    assert_ne!(path.last().unwrap().destination_system_id, 1);
+}
+
+#[test]
+fn batched_requests_are_answered_in_order() {
+    let (systems, gates) = two_routes();
+    let mut graph = SystemGraph::from_systems_and_gates(systems.iter(), gates.iter());
+    graph.add_node(system(5));
+
+    let paths = graph.resolve_paths(&[
+        (1, PathRequest::To(3)),
+        (4, PathRequest::To(3)),
+        (1, PathRequest::To(1)),
+        (1, PathRequest::To(5)),
+        (9, PathRequest::To(3)),
+        (5, PathRequest::Anywhere),
+    ]);
+    let paths: Vec<Result<Vec<u32>, GraphError>> = paths
+        .iter()
+        .map(|path| path.as_deref().map(gate_ids).map_err(|error| *error))
+        .collect();
+    assert_eq!(
+        paths,
+        vec![
+            Ok(vec![0, 1]),
+            Ok(vec![3]),
+            Ok(vec![]),
+            Err(GraphError::NoPath),
+            Err(GraphError::SystemNotFound),
+            Err(GraphError::NoPath),
+        ]
+    );
+}
+
+#[test]
+fn agents_waiting_on_a_path_are_given_one() {
+    let mut app = App::new();
+    let (systems, gates) = two_routes();
+    app.insert_resource(SystemGraph::from_systems_and_gates(
+        systems.iter(),
+        gates.iter(),
+    ))
+    .add_systems(Update, resolve_path_requests);
+
+    let traveller = app
+        .world
+        .spawn((
+            Agent::new(0, "Traveller".to_string(), &system(1)),
+            PathRequest::To(3),
+        ))
+        .id();
+    let stranded = app
+        .world
+        .spawn((
+            Agent::new(1, "Stranded".to_string(), &system(3)),
+            PathRequest::To(9),
+        ))
+        .id();
+    app.update();
+
+    let agent = app.world.get::<Agent>(traveller).unwrap();
+    assert_eq!(gate_ids(&agent.stargate_path.path), vec![0, 1]);
+    assert!(app.world.get::<PathRequest>(traveller).is_none());
+    let agent = app.world.get::<Agent>(stranded).unwrap();
+    assert!(agent.stargate_path.path.is_empty());
+    assert!(app.world.get::<PathRequest>(stranded).is_none());
+}
@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+
+use ascendancy_lib::agent::agent::{Agent, AgentRole};
+use ascendancy_lib::faction::attributes::FactionID;
+use ascendancy_lib::faction::claims::{
+    update_system_control, OwnershipChangedEvent, SystemControl, MAX_CONTROL,
+};
+use ascendancy_lib::faction::diplomoacy::Diplomacy;
+use ascendancy_lib::simulation::SimulationClock;
+use ascendancy_lib::solar_system::attributes::SystemAttributes;
+use ascendancy_lib::solar_system::SolarSystem;
+use bevy::prelude::*;
+
+const EMPIRE: FactionID = FactionID { id: 0 };
+const REBELS: FactionID = FactionID { id: 1 };
+
+fn at_war() -> Diplomacy {
+    let mut diplomacy = Diplomacy::default();
+    diplomacy.declare_war(EMPIRE, REBELS);
+    diplomacy
+}
+
+/// Runs a system held by the empire with a rebel fleet in it, returning the step it was captured at, if it was
+fn invade(diplomacy: Diplomacy, steps: u32) -> Option<u32> {
+    let mut app = App::new();
+    app.init_resource::<SimulationClock>()
+        .insert_resource(diplomacy)
+        .add_event::<OwnershipChangedEvent>()
+        .add_systems(Update, update_system_control);
+
+    let system = SolarSystem {
+        attributes: SystemAttributes {
+            id: 1,
+            name: "Frontier".to_string(),
+            owner: EMPIRE,
+        },
+        ..default()
+    };
+    let entity = app
+        .world
+        .spawn((system.clone(), SystemControl::held_by(EMPIRE)))
+        .id();
+    for id in 0..5 {
+        let mut agent = Agent::new(id, format!("Raider {}", id), &system);
+        agent.faction = REBELS;
+        app.world.spawn((agent, AgentRole::Defender));
+    }
+
+    for step in 1..=steps {
+        *app.world.resource_mut::<SimulationClock>() =
+            SimulationClock::from_seconds(step as f64 * 10.0);
+        app.update();
+
+        let events = app.world.resource::<Events<OwnershipChangedEvent>>();
+        if let Some(change) = events.get_reader().read(events).next() {
+            assert_eq!(change.old, EMPIRE);
+            assert_eq!(change.new, REBELS);
+            let system = app.world.get::<SolarSystem>(entity).unwrap();
+            assert_eq!(system.attributes.owner, REBELS);
+            return Some(step);
+        }
+    }
+    None
+}
+
+#[test]
+fn presence_builds_control_and_hostile_presence_wears_it_down() {
+    let diplomacy = at_war();
+    let mut control = SystemControl::held_by(EMPIRE);
+    let presence = HashMap::from([(EMPIRE, 2.0), (REBELS, 4.0)]);
+
+    control.update(EMPIRE, &presence, &diplomacy, 10.0);
+    assert!((control.points(EMPIRE) - (MAX_CONTROL - 1.0)).abs() < 0.001);
+    assert!((control.points(REBELS) - 1.0).abs() < 0.001);
+    assert!(control.is_contested(EMPIRE));
+    assert_eq!(control.captor(EMPIRE), None);
+
+    // A challenger that leaves loses its foothold
+    control.update(EMPIRE, &HashMap::new(), &diplomacy, 100.0);
+    assert_eq!(control.points(REBELS), 0.0);
+    assert!(!control.is_contested(EMPIRE));
+    assert!((control.points(EMPIRE) - (MAX_CONTROL - 1.0)).abs() < 0.001);
+}
+
+#[test]
+fn hostile_fleets_capture_systems() {
+    // Five defenders take about four minutes to wear down a system's owner
+    let step = invade(at_war(), 30).unwrap();
+    assert!((24..=25).contains(&step), "captured at step {}", step);
+}
+
+#[test]
+fn neutral_factions_do_not_capture_systems() {
+    assert_eq!(invade(Diplomacy::default(), 30), None);
+}
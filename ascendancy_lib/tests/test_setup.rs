@@ -1,4 +1,49 @@
 #![cfg(test)]
 
+use ascendancy_lib::save::SaveGame;
+use ascendancy_lib::simulation::{HeadlessPlugin, SimulationPlugins};
+use ascendancy_lib::world_gen::GalaxyConfig;
+use ascendancy_lib::GameState;
+use bevy::prelude::*;
+
+#[test]
+fn minimal_app_can_update() {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .insert_resource(GalaxyConfig::default().with_seed(42))
+        .add_plugins((SimulationPlugins, HeadlessPlugin::default()));
+
+    for _ in 0..10 {
+        app.update();
+    }
+
+    assert_eq!(
+        app.world.resource::<State<GameState>>().get(),
+        &GameState::Playing
+    );
+}
+
 #[test]
-fn minimal_app_can_update() {}
+fn save_game_roundtrips() {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .insert_resource(GalaxyConfig::default().with_seed(42))
+        .add_plugins((SimulationPlugins, HeadlessPlugin::default()));
+
+    for _ in 0..10 {
+        app.update();
+    }
+
+    let saved = SaveGame::capture(&mut app.world);
+    let json = serde_json::to_string(&saved).unwrap();
+    let loaded: SaveGame = serde_json::from_str(&json).unwrap();
+    loaded.restore(&mut app.world);
+    app.update();
+
+    let restored = SaveGame::capture(&mut app.world);
+    assert_eq!(restored.solar_systems.len(), saved.solar_systems.len());
+    assert_eq!(restored.stargates.len(), saved.stargates.len());
+    assert_eq!(restored.stations.len(), saved.stations.len());
+    assert_eq!(restored.agents.len(), saved.agents.len());
+    assert_eq!(restored.factions.len(), saved.factions.len());
+}
@@ -0,0 +1,58 @@
+use ascendancy_lib::agent::trade::{KnownPrices, TradePlan, TradeStage, TradeStop};
+use ascendancy_lib::items::inventory::Inventory;
+use ascendancy_lib::items::{ItemId, ItemRegistry};
+use ascendancy_lib::structures::services::market::Market;
+use bevy::prelude::Entity;
+
+fn market_with_ore(items: &ItemRegistry, ore: u32) -> Market {
+    let mut inventory = Inventory::new(10000.0, 10000.0);
+    inventory
+        .add(items.get(ItemId::RAW_ORE).unwrap(), ore)
+        .unwrap();
+    let mut market = Market::new(0, items);
+    market.funds = 10000.0;
+    market.trade(&mut inventory);
+    market
+}
+
+#[test]
+fn spreads_span_markets() {
+    let items = ItemRegistry::built_in();
+    let plentiful = market_with_ore(&items, 1900);
+    let scarce = market_with_ore(&items, 10);
+
+    let mut prices = KnownPrices::default();
+    prices.record(Entity::from_raw(1), 1, &plentiful);
+    prices.record(Entity::from_raw(2), 2, &scarce);
+
+    let (ask, bid) = prices.best_spread(ItemId::RAW_ORE).unwrap();
+    assert_eq!(ask, plentiful.best_ask(ItemId::RAW_ORE).unwrap());
+    assert_eq!(bid, scarce.best_bid(ItemId::RAW_ORE).unwrap());
+    assert!(bid.price > ask.price);
+
+    // Recording a market again replaces what was known about it
+    prices.record(Entity::from_raw(1), 1, &plentiful);
+    assert_eq!(prices.quotes(ItemId::RAW_ORE).len(), 2);
+}
+
+#[test]
+fn plans_head_to_the_seller_then_the_buyer() {
+    let stop = |station, price| TradeStop {
+        station: Entity::from_raw(station),
+        system_id: station,
+        price,
+    };
+    let mut plan = TradePlan {
+        item: ItemId::RAW_ORE,
+        quantity: 10,
+        buy_from: Some(stop(1, 4.0)),
+        sell_to: stop(2, 6.0),
+        stage: TradeStage::Buying,
+    };
+
+    assert_eq!(plan.current_stop().station, Entity::from_raw(1));
+    assert!((plan.expected_profit() - 20.0).abs() < 0.01);
+
+    plan.stage = TradeStage::Selling;
+    assert_eq!(plan.current_stop().station, Entity::from_raw(2));
+}